@@ -0,0 +1,143 @@
+//! Measures `WriteStream` throughput against a simulated peripheral.
+//!
+//! The simulated link accepts writes into a transmit buffer of a fixed number of packets and
+//! drains a fixed number of packets per connection event, which is how CoreBluetooth paces writes
+//! without response. The measured throughput should approach the link's theoretical maximum for
+//! every queue capacity; stalls count how often the stream had to wait for the link.
+//!
+//! Run with `cargo bench -p corebluetooth-async --bench write_stream`.
+
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use corebluetooth_async::error::Result;
+use corebluetooth_async::{WriteStream, WriteTarget};
+use futures_lite::future;
+use futures_sink::Sink;
+
+const MAX_WRITE_LEN: usize = 244;
+const TOTAL_BYTES: usize = 1 << 20;
+
+#[derive(Debug, Clone, Copy)]
+struct Link {
+    connection_interval: Duration,
+    packets_per_event: usize,
+    transmit_buffer: usize,
+}
+
+impl Link {
+    fn max_bytes_per_second(&self) -> f64 {
+        (self.packets_per_event * MAX_WRITE_LEN) as f64 / self.connection_interval.as_secs_f64()
+    }
+}
+
+#[derive(Debug, Default)]
+struct LinkState {
+    buffered: usize,
+    waker: Option<Waker>,
+    closed: bool,
+}
+
+/// A peripheral whose transmit buffer is drained on every connection event by a background thread.
+#[derive(Debug)]
+struct SimulatedPeripheral {
+    link: Link,
+    state: Arc<Mutex<LinkState>>,
+}
+
+impl SimulatedPeripheral {
+    fn new(link: Link) -> Self {
+        let state = Arc::new(Mutex::new(LinkState::default()));
+        let events = state.clone();
+        thread::spawn(move || {
+            let mut next_event = Instant::now();
+            loop {
+                next_event += link.connection_interval;
+                thread::sleep(next_event.saturating_duration_since(Instant::now()));
+                let mut state = events.lock().unwrap();
+                if state.closed {
+                    break;
+                }
+                state.buffered = state.buffered.saturating_sub(link.packets_per_event);
+                if let Some(waker) = state.waker.take() {
+                    waker.wake();
+                }
+            }
+        });
+        Self { link, state }
+    }
+}
+
+impl Drop for SimulatedPeripheral {
+    fn drop(&mut self) {
+        self.state.lock().unwrap().closed = true;
+    }
+}
+
+impl WriteTarget for SimulatedPeripheral {
+    fn max_write_len(&self) -> usize {
+        MAX_WRITE_LEN
+    }
+
+    fn poll_ready_to_send(&mut self, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        let mut state = self.state.lock().unwrap();
+        if state.buffered < self.link.transmit_buffer {
+            Poll::Ready(Ok(()))
+        } else {
+            state.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+
+    fn send(&mut self, _data: Vec<u8>) {
+        self.state.lock().unwrap().buffered += 1;
+    }
+}
+
+async fn send_all(stream: &mut WriteStream<SimulatedPeripheral>) -> Result<()> {
+    let chunk = vec![0x5a; MAX_WRITE_LEN];
+    for _ in 0..TOTAL_BYTES / MAX_WRITE_LEN {
+        std::future::poll_fn(|cx| Pin::new(&mut *stream).poll_ready(cx)).await?;
+        Pin::new(&mut *stream).start_send(chunk.clone())?;
+    }
+    std::future::poll_fn(|cx| Pin::new(&mut *stream).poll_flush(cx)).await
+}
+
+fn main() {
+    let links = [
+        Link {
+            connection_interval: Duration::from_micros(7500),
+            packets_per_event: 4,
+            transmit_buffer: 8,
+        },
+        Link {
+            connection_interval: Duration::from_millis(15),
+            packets_per_event: 6,
+            transmit_buffer: 12,
+        },
+    ];
+
+    println!(
+        "{:>10} {:>8} {:>9} {:>12} {:>12} {:>8}",
+        "interval", "packets", "capacity", "bytes/s", "max bytes/s", "stalls"
+    );
+    for link in links {
+        for capacity in [1, 8, 64] {
+            let mut stream = WriteStream::new(SimulatedPeripheral::new(link), capacity);
+            future::block_on(send_all(&mut stream)).unwrap();
+            let stats = stream.stats();
+            println!(
+                "{:>10?} {:>8} {:>9} {:>12.0} {:>12.0} {:>8}",
+                link.connection_interval,
+                link.packets_per_event,
+                capacity,
+                stats.bytes_per_second(),
+                link.max_bytes_per_second(),
+                stats.stalls,
+            );
+        }
+    }
+}
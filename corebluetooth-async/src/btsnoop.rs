@@ -0,0 +1,593 @@
+//! Export of observed traffic as btsnoop captures that can be opened in Wireshark.
+//!
+//! CoreBluetooth does not expose the packets exchanged with a peripheral, so the packets in a
+//! capture are synthesized from what the delegate callbacks and the values written report:
+//! connections become HCI connection and disconnection events, and MTU exchanges, reads, writes
+//! and notifications become ATT PDUs sent over ACL data packets. Captures use the btsnoop format
+//! with the HCI UART (H4) datalink.
+//!
+//! [`BtsnoopWriter`] writes individual packets. With the `record` feature, [`export()`] converts
+//! an entire [`Recording`][crate::record::Recording]:
+//!
+//! ```no_run
+//! # #[cfg(feature = "record")]
+//! # fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! use std::fs::File;
+//! use std::io::{BufReader, BufWriter};
+//!
+//! use corebluetooth_async::btsnoop;
+//! use corebluetooth_async::record::Recording;
+//!
+//! let recording = Recording::read(BufReader::new(File::open("session.cbrec")?))?;
+//! btsnoop::export(&recording, BufWriter::new(File::create("session.btsnoop")?))?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::io::{self, Write};
+use std::time::SystemTime;
+
+/// The btsnoop datalink type for HCI UART (H4) packets.
+pub const DATALINK_H4: u32 = 1002;
+
+/// Microseconds between midnight, January 1st, 0 AD and the Unix epoch.
+const EPOCH_OFFSET_MICROS: i64 = 0x00dc_ddb3_0f2f_8000;
+
+/// The L2CAP channel identifier of the attribute protocol.
+const ATT_CID: u16 = 0x0004;
+
+/// An attribute protocol PDU.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AttPdu<'a> {
+    /// A request failed.
+    ErrorResponse {
+        /// The opcode of the request that failed.
+        request: u8,
+        /// The handle of the attribute the request was for.
+        handle: u16,
+        /// The ATT error code.
+        error: u8,
+    },
+    /// The client's receive MTU.
+    ExchangeMtuRequest {
+        /// The MTU.
+        mtu: u16,
+    },
+    /// The server's receive MTU.
+    ExchangeMtuResponse {
+        /// The MTU.
+        mtu: u16,
+    },
+    /// A request to read an attribute.
+    ReadRequest {
+        /// The attribute handle.
+        handle: u16,
+    },
+    /// The value of an attribute that was read.
+    ReadResponse {
+        /// The value.
+        value: &'a [u8],
+    },
+    /// A request to write an attribute.
+    WriteRequest {
+        /// The attribute handle.
+        handle: u16,
+        /// The value.
+        value: &'a [u8],
+    },
+    /// An attribute was written.
+    WriteResponse,
+    /// A write of an attribute without response.
+    WriteCommand {
+        /// The attribute handle.
+        handle: u16,
+        /// The value.
+        value: &'a [u8],
+    },
+    /// A notification of an attribute's value.
+    HandleValueNotification {
+        /// The attribute handle.
+        handle: u16,
+        /// The value.
+        value: &'a [u8],
+    },
+}
+
+impl AttPdu<'_> {
+    /// The opcode of the PDU.
+    pub fn opcode(&self) -> u8 {
+        match self {
+            AttPdu::ErrorResponse { .. } => 0x01,
+            AttPdu::ExchangeMtuRequest { .. } => 0x02,
+            AttPdu::ExchangeMtuResponse { .. } => 0x03,
+            AttPdu::ReadRequest { .. } => 0x0a,
+            AttPdu::ReadResponse { .. } => 0x0b,
+            AttPdu::WriteRequest { .. } => 0x12,
+            AttPdu::WriteResponse => 0x13,
+            AttPdu::WriteCommand { .. } => 0x52,
+            AttPdu::HandleValueNotification { .. } => 0x1b,
+        }
+    }
+
+    /// Appends the encoded PDU to `buf`.
+    pub fn encode(&self, buf: &mut Vec<u8>) {
+        buf.push(self.opcode());
+        match *self {
+            AttPdu::ErrorResponse {
+                request,
+                handle,
+                error,
+            } => {
+                buf.push(request);
+                buf.extend_from_slice(&handle.to_le_bytes());
+                buf.push(error);
+            }
+            AttPdu::ExchangeMtuRequest { mtu } | AttPdu::ExchangeMtuResponse { mtu } => {
+                buf.extend_from_slice(&mtu.to_le_bytes());
+            }
+            AttPdu::ReadRequest { handle } => buf.extend_from_slice(&handle.to_le_bytes()),
+            AttPdu::ReadResponse { value } => buf.extend_from_slice(value),
+            AttPdu::WriteRequest { handle, value }
+            | AttPdu::WriteCommand { handle, value }
+            | AttPdu::HandleValueNotification { handle, value } => {
+                buf.extend_from_slice(&handle.to_le_bytes());
+                buf.extend_from_slice(value);
+            }
+            AttPdu::WriteResponse => {}
+        }
+    }
+
+    /// Returns the encoded PDU.
+    pub fn to_vec(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        self.encode(&mut buf);
+        buf
+    }
+}
+
+/// The direction of a packet, relative to the host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    /// Sent by the host to the controller, for example a request to a peripheral.
+    Sent,
+    /// Received by the host from the controller, for example a notification from a peripheral.
+    Received,
+}
+
+/// Encodes an ATT PDU as an H4 ACL data packet on the given connection.
+///
+/// Fails with [`io::ErrorKind::InvalidInput`] if the PDU is too long for a single ACL data
+/// packet.
+pub fn acl_packet(connection: u16, pdu: &AttPdu<'_>) -> io::Result<Vec<u8>> {
+    let pdu = pdu.to_vec();
+    // The ACL data length includes the 4 byte L2CAP header.
+    let l2cap_len = u16::try_from(pdu.len())
+        .ok()
+        .filter(|len| *len <= u16::MAX - 4)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "ATT PDU too long"))?;
+    // Packet boundary flag 0b10: the first, automatically flushable fragment.
+    let handle = (connection & 0x0fff) | 0x2000;
+
+    let mut packet = Vec::with_capacity(9 + pdu.len());
+    packet.push(0x02);
+    packet.extend_from_slice(&handle.to_le_bytes());
+    packet.extend_from_slice(&(l2cap_len + 4).to_le_bytes());
+    packet.extend_from_slice(&l2cap_len.to_le_bytes());
+    packet.extend_from_slice(&ATT_CID.to_le_bytes());
+    packet.extend_from_slice(&pdu);
+    Ok(packet)
+}
+
+/// Encodes an H4 HCI LE Connection Complete event for a connection in the central role.
+///
+/// CoreBluetooth does not reveal peer addresses, so `peer_address` is typically derived from the
+/// peripheral identifier. It is given most significant byte first and encoded as a random
+/// address.
+pub fn connection_complete_event(status: u8, connection: u16, peer_address: [u8; 6]) -> Vec<u8> {
+    let mut event = vec![0x04, 0x3e, 0x13, 0x01, status];
+    event.extend_from_slice(&connection.to_le_bytes());
+    // Central role, random peer address.
+    event.extend_from_slice(&[0x00, 0x01]);
+    event.extend(peer_address.iter().rev());
+    // Connection interval (30 ms), peripheral latency, supervision timeout (720 ms) and clock
+    // accuracy are not reported by CoreBluetooth; typical values are used.
+    event.extend_from_slice(&0x0018u16.to_le_bytes());
+    event.extend_from_slice(&0x0000u16.to_le_bytes());
+    event.extend_from_slice(&0x0048u16.to_le_bytes());
+    event.push(0x00);
+    event
+}
+
+/// Encodes an H4 HCI Disconnection Complete event with the given reason.
+pub fn disconnection_complete_event(connection: u16, reason: u8) -> Vec<u8> {
+    let mut event = vec![0x04, 0x05, 0x04, 0x00];
+    event.extend_from_slice(&connection.to_le_bytes());
+    event.push(reason);
+    event
+}
+
+/// Writes packets to a btsnoop capture with the H4 datalink.
+///
+/// # Example
+///
+/// ```
+/// use std::time::{Duration, SystemTime};
+///
+/// use corebluetooth_async::btsnoop::{AttPdu, BtsnoopWriter, Direction};
+///
+/// let mut writer = BtsnoopWriter::new(Vec::new())?;
+/// let timestamp = SystemTime::UNIX_EPOCH + Duration::from_secs(1);
+/// let pdu = AttPdu::HandleValueNotification { handle: 0x0012, value: &[0x64] };
+/// writer.write_att(timestamp, Direction::Received, 0x0040, &pdu)?;
+///
+/// let capture: Vec<u8> = writer.into_inner();
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct BtsnoopWriter<W> {
+    writer: W,
+}
+
+impl<W: Write> BtsnoopWriter<W> {
+    /// Starts a capture, writing its header to `writer`.
+    pub fn new(mut writer: W) -> io::Result<Self> {
+        writer.write_all(b"btsnoop\0")?;
+        writer.write_all(&1u32.to_be_bytes())?;
+        writer.write_all(&DATALINK_H4.to_be_bytes())?;
+        Ok(Self { writer })
+    }
+
+    /// Writes an H4 packet, including its packet type byte.
+    pub fn write_packet(
+        &mut self,
+        timestamp: SystemTime,
+        direction: Direction,
+        packet: &[u8],
+    ) -> io::Result<()> {
+        let mut flags = match direction {
+            Direction::Sent => 0,
+            Direction::Received => 1,
+        };
+        // Commands and events, as opposed to data.
+        if matches!(packet.first(), Some(0x01 | 0x04)) {
+            flags |= 2;
+        }
+
+        let micros = match timestamp.duration_since(SystemTime::UNIX_EPOCH) {
+            Ok(since) => since.as_micros() as i64,
+            Err(err) => -(err.duration().as_micros() as i64),
+        };
+
+        let len = packet.len() as u32;
+        self.writer.write_all(&len.to_be_bytes())?;
+        self.writer.write_all(&len.to_be_bytes())?;
+        self.writer.write_all(&(flags as u32).to_be_bytes())?;
+        self.writer.write_all(&0u32.to_be_bytes())?;
+        self.writer
+            .write_all(&(micros + EPOCH_OFFSET_MICROS).to_be_bytes())?;
+        self.writer.write_all(packet)
+    }
+
+    /// Writes an ATT PDU on the given connection.
+    pub fn write_att(
+        &mut self,
+        timestamp: SystemTime,
+        direction: Direction,
+        connection: u16,
+        pdu: &AttPdu<'_>,
+    ) -> io::Result<()> {
+        self.write_packet(timestamp, direction, &acl_packet(connection, pdu)?)
+    }
+
+    /// Flushes the underlying writer.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[cfg(feature = "record")]
+pub use self::export::export;
+
+#[cfg(feature = "record")]
+mod export {
+    use std::collections::{HashMap, HashSet};
+    use std::io::{self, Write};
+    use std::time::SystemTime;
+
+    use btuuid::BluetoothUuid;
+    use uuid::Uuid;
+
+    use super::{
+        AttPdu, BtsnoopWriter, Direction, connection_complete_event, disconnection_complete_event,
+    };
+    use crate::error::{CBError, ErrorKind};
+    use crate::record::{Event, RecordedError, Recording};
+
+    /// ATT error code for errors that were not reported as ATT errors ("Unlikely Error").
+    const UNLIKELY_ERROR: u8 = 0x0e;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    enum Attribute {
+        Characteristic(Option<BluetoothUuid>, BluetoothUuid),
+        Descriptor(Option<BluetoothUuid>, BluetoothUuid),
+    }
+
+    #[derive(Default)]
+    struct Connections {
+        handles: HashMap<Uuid, u16>,
+        attributes: HashMap<(Uuid, Attribute), u16>,
+        next_attribute: HashMap<Uuid, u16>,
+        /// Attributes with a write request awaiting its response.
+        pending_writes: HashSet<(Uuid, Attribute)>,
+    }
+
+    impl Connections {
+        fn connection(&mut self, peripheral: Uuid) -> u16 {
+            let next = self.handles.len() as u16 + 1;
+            *self.handles.entry(peripheral).or_insert(next)
+        }
+
+        fn attribute(&mut self, peripheral: Uuid, attribute: Attribute) -> u16 {
+            *self
+                .attributes
+                .entry((peripheral, attribute))
+                .or_insert_with(|| {
+                    let next = self.next_attribute.entry(peripheral).or_insert(1);
+                    let handle = *next;
+                    *next = next.wrapping_add(1);
+                    handle
+                })
+        }
+    }
+
+    fn att_error(error: &RecordedError) -> u8 {
+        match error.kind {
+            ErrorKind::ATT(error) => error.0 as u8,
+            _ => UNLIKELY_ERROR,
+        }
+    }
+
+    fn disconnect_reason(error: Option<&RecordedError>) -> u8 {
+        match error.map(|error| error.kind) {
+            // Connection Terminated By Local Host.
+            None => 0x16,
+            // Connection Timeout.
+            Some(ErrorKind::Bluetooth(CBError::ConnectionTimeout)) => 0x08,
+            // Remote User Terminated Connection.
+            Some(_) => 0x13,
+        }
+    }
+
+    /// Converts a [`Recording`] to a btsnoop capture written to `writer`, returning the writer.
+    ///
+    /// Each peripheral is assigned a connection handle and each characteristic and descriptor an
+    /// attribute handle, in the order in which they first appear. Peer addresses are derived from
+    /// the peripheral identifiers. Values updated while notifications are enabled become
+    /// notifications, and other value updates become read requests and responses. Writes with
+    /// response become write requests and responses, and writes without response become write
+    /// commands; in recordings made before version 2 of the format, which do not include the
+    /// values written, write requests carry no value and writes without response are missing. The
+    /// MTU recorded for a connection becomes an MTU exchange. Errors become error responses, with
+    /// errors other than ATT errors reported as "Unlikely Error". Discovery and other events have
+    /// no packet equivalent and are omitted.
+    pub fn export<W: Write>(recording: &Recording, writer: W) -> io::Result<W> {
+        let mut writer = BtsnoopWriter::new(writer)?;
+        let mut connections = Connections::default();
+
+        for record in recording.records() {
+            let timestamp = recording.started_at() + record.elapsed;
+            match &record.event {
+                Event::Connected { peripheral, mtu } => {
+                    let connection = connections.connection(*peripheral);
+                    let event = connection_complete_event(0x00, connection, address(peripheral));
+                    writer.write_packet(timestamp, Direction::Received, &event)?;
+                    if let Some(mtu) = *mtu {
+                        let request = AttPdu::ExchangeMtuRequest { mtu };
+                        let response = AttPdu::ExchangeMtuResponse { mtu };
+                        exchange(&mut writer, timestamp, connection, request, response, &None)?;
+                    }
+                }
+                Event::FailedToConnect { peripheral, .. } => {
+                    let connection = connections.connection(*peripheral);
+                    // Connection Failed to be Established.
+                    let event = connection_complete_event(0x3e, connection, address(peripheral));
+                    writer.write_packet(timestamp, Direction::Received, &event)?;
+                }
+                Event::Disconnected {
+                    peripheral, error, ..
+                } => {
+                    let connection = connections.connection(*peripheral);
+                    let event =
+                        disconnection_complete_event(connection, disconnect_reason(error.as_ref()));
+                    writer.write_packet(timestamp, Direction::Received, &event)?;
+                }
+                Event::CharacteristicValueUpdated {
+                    peripheral,
+                    service,
+                    characteristic,
+                    is_notifying,
+                    value,
+                    error,
+                } => {
+                    let connection = connections.connection(*peripheral);
+                    let handle = connections.attribute(
+                        *peripheral,
+                        Attribute::Characteristic(*service, *characteristic),
+                    );
+                    if *is_notifying && error.is_none() {
+                        let pdu = AttPdu::HandleValueNotification { handle, value };
+                        writer.write_att(timestamp, Direction::Received, connection, &pdu)?;
+                    } else {
+                        let pdu = AttPdu::ReadRequest { handle };
+                        let response = AttPdu::ReadResponse { value };
+                        exchange(&mut writer, timestamp, connection, pdu, response, error)?;
+                    }
+                }
+                Event::CharacteristicWriteRequested {
+                    peripheral,
+                    service,
+                    characteristic,
+                    with_response,
+                    value,
+                } => {
+                    let connection = connections.connection(*peripheral);
+                    let attribute = Attribute::Characteristic(*service, *characteristic);
+                    let handle = connections.attribute(*peripheral, attribute);
+                    let pdu = if *with_response {
+                        connections.pending_writes.insert((*peripheral, attribute));
+                        AttPdu::WriteRequest { handle, value }
+                    } else {
+                        AttPdu::WriteCommand { handle, value }
+                    };
+                    writer.write_att(timestamp, Direction::Sent, connection, &pdu)?;
+                }
+                Event::CharacteristicValueWritten {
+                    peripheral,
+                    service,
+                    characteristic,
+                    error,
+                } => {
+                    let attribute = Attribute::Characteristic(*service, *characteristic);
+                    write_response(
+                        &mut writer,
+                        &mut connections,
+                        timestamp,
+                        *peripheral,
+                        attribute,
+                        error,
+                    )?;
+                }
+                Event::DescriptorValueUpdated {
+                    peripheral,
+                    characteristic,
+                    descriptor,
+                    value,
+                    error,
+                } => {
+                    let connection = connections.connection(*peripheral);
+                    let handle = connections.attribute(
+                        *peripheral,
+                        Attribute::Descriptor(*characteristic, *descriptor),
+                    );
+                    let pdu = AttPdu::ReadRequest { handle };
+                    let response = AttPdu::ReadResponse { value };
+                    exchange(&mut writer, timestamp, connection, pdu, response, error)?;
+                }
+                Event::DescriptorWriteRequested {
+                    peripheral,
+                    characteristic,
+                    descriptor,
+                    value,
+                } => {
+                    let connection = connections.connection(*peripheral);
+                    let attribute = Attribute::Descriptor(*characteristic, *descriptor);
+                    let handle = connections.attribute(*peripheral, attribute);
+                    connections.pending_writes.insert((*peripheral, attribute));
+                    let pdu = AttPdu::WriteRequest { handle, value };
+                    writer.write_att(timestamp, Direction::Sent, connection, &pdu)?;
+                }
+                Event::DescriptorValueWritten {
+                    peripheral,
+                    characteristic,
+                    descriptor,
+                    error,
+                } => {
+                    let attribute = Attribute::Descriptor(*characteristic, *descriptor);
+                    write_response(
+                        &mut writer,
+                        &mut connections,
+                        timestamp,
+                        *peripheral,
+                        attribute,
+                        error,
+                    )?;
+                }
+                _ => {}
+            }
+        }
+
+        writer.flush()?;
+        Ok(writer.into_inner())
+    }
+
+    /// Writes the response to a write request, preceded by the request itself if the recording
+    /// does not include it.
+    fn write_response<W: Write>(
+        writer: &mut BtsnoopWriter<W>,
+        connections: &mut Connections,
+        timestamp: SystemTime,
+        peripheral: Uuid,
+        attribute: Attribute,
+        error: &Option<RecordedError>,
+    ) -> io::Result<()> {
+        let connection = connections.connection(peripheral);
+        let handle = connections.attribute(peripheral, attribute);
+        let request = AttPdu::WriteRequest { handle, value: &[] };
+        if !connections.pending_writes.remove(&(peripheral, attribute)) {
+            // Recordings made before version 2 of the format do not include write requests.
+            writer.write_att(timestamp, Direction::Sent, connection, &request)?;
+        }
+        respond(
+            writer,
+            timestamp,
+            connection,
+            request,
+            AttPdu::WriteResponse,
+            error,
+        )
+    }
+
+    /// Writes `request` and either `response` or, if the request failed, an error response.
+    fn exchange<W: Write>(
+        writer: &mut BtsnoopWriter<W>,
+        timestamp: SystemTime,
+        connection: u16,
+        request: AttPdu<'_>,
+        response: AttPdu<'_>,
+        error: &Option<RecordedError>,
+    ) -> io::Result<()> {
+        writer.write_att(timestamp, Direction::Sent, connection, &request)?;
+        respond(writer, timestamp, connection, request, response, error)
+    }
+
+    /// Writes `response` to `request` or, if the request failed, an error response.
+    fn respond<W: Write>(
+        writer: &mut BtsnoopWriter<W>,
+        timestamp: SystemTime,
+        connection: u16,
+        request: AttPdu<'_>,
+        response: AttPdu<'_>,
+        error: &Option<RecordedError>,
+    ) -> io::Result<()> {
+        let response = match (error, request) {
+            (None, _) => response,
+            (Some(error), AttPdu::ReadRequest { handle } | AttPdu::WriteRequest { handle, .. }) => {
+                AttPdu::ErrorResponse {
+                    request: request.opcode(),
+                    handle,
+                    error: att_error(error),
+                }
+            }
+            (Some(error), _) => AttPdu::ErrorResponse {
+                request: request.opcode(),
+                handle: 0,
+                error: att_error(error),
+            },
+        };
+        writer.write_att(timestamp, Direction::Received, connection, &response)
+    }
+
+    /// A stable, random static device address derived from a peripheral identifier.
+    fn address(peripheral: &Uuid) -> [u8; 6] {
+        let mut address = [0; 6];
+        address.copy_from_slice(&peripheral.as_bytes()[..6]);
+        // The two most significant bits of a random static address are set.
+        address[0] |= 0xc0;
+        address
+    }
+}
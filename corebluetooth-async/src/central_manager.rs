@@ -2,6 +2,7 @@ use std::any::Any;
 use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 use std::ops::Deref;
+use std::rc::Rc;
 
 use btuuid::BluetoothUuid;
 use corebluetooth::advertisement_data::AdvertisementData;
@@ -14,8 +15,11 @@ use objc2_core_bluetooth::{CBConnectionEvent, CBManagerState, CBPeripheralState}
 use uuid::Uuid;
 
 use crate::error::{Error, Result};
+use crate::metrics::{self, Operation};
 use crate::peripheral::{PeripheralAsync, PeripheralAsyncDelegate};
-use crate::util::{BroadcastReceiver, BroadcastSender, broadcast, defer, watch};
+use crate::trace::{event, instrument};
+use crate::util::{BroadcastReceiver, BroadcastSender, broadcast, defer, record, watch};
+use crate::web_bluetooth::NotificationContexts;
 
 /// An asynchronous wrapper around [`CentralManager`].
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -89,18 +93,25 @@ impl CentralManagerAsync {
         peripheral: &PeripheralAsync,
         options: ConnectPeripheralOptions,
     ) -> Result<()> {
-        self.inner.connect_with_options(peripheral, options);
-
-        let guard = defer(|| {
-            if peripheral.state() == CBPeripheralState::Connecting {
-                self.inner.cancel_peripheral_connection(peripheral);
-            }
-        });
-
-        let receiver = self.delegate().register_connecting(peripheral);
-        let res = receiver.await?;
-        guard.defuse();
-        res
+        let connect = instrument!(
+            async {
+                self.inner.connect_with_options(peripheral, options);
+
+                let guard = defer(|| {
+                    if peripheral.state() == CBPeripheralState::Connecting {
+                        self.inner.cancel_peripheral_connection(peripheral);
+                    }
+                });
+
+                let receiver = self.delegate().register_connecting(peripheral);
+                let res = receiver.await?;
+                guard.defuse();
+                res
+            },
+            "connect",
+            peripheral = %peripheral.identifier(),
+        );
+        metrics::measure(peripheral.identifier(), Operation::Connect, connect).await
     }
 
     /// Cancels an active or pending connection to a peripheral.
@@ -112,6 +123,11 @@ impl CentralManagerAsync {
         peripheral: &PeripheralAsync,
     ) -> Option<DidDisconnect> {
         let state = peripheral.state();
+        event!(
+            peripheral = %peripheral.identifier(),
+            ?state,
+            "cancel_peripheral_connection"
+        );
         if state == CBPeripheralState::Connecting || state == CBPeripheralState::Connected {
             self.inner.cancel_peripheral_connection(peripheral);
         }
@@ -160,6 +176,7 @@ impl CentralManagerAsync {
             panic!("CentralManager::scan called while already scanning")
         }
 
+        event!(?services, allow_duplicates, ?solicited_services, "scan");
         self.inner
             .scan(services, allow_duplicates, solicited_services);
 
@@ -175,6 +192,13 @@ impl CentralManagerAsync {
     pub fn ancs_authorization_updates(&self) -> BroadcastReceiver<PeripheralAsync> {
         self.delegate().ancs_authorization_updates()
     }
+
+    /// The Web Bluetooth notification contexts shared by every `Bluetooth` using this central.
+    pub(crate) fn notification_contexts(
+        &self,
+    ) -> Rc<RefCell<NotificationContexts<corebluetooth::Characteristic>>> {
+        self.delegate().notification_contexts.clone()
+    }
 }
 
 struct CentralManagerAsyncDelegate {
@@ -184,6 +208,7 @@ struct CentralManagerAsyncDelegate {
     discoveries: Cell<Option<mpsc::UnboundedSender<DidDiscover>>>,
     connection_events: BroadcastSender<ConnectionEvent>,
     ancs_authorization_updates: BroadcastSender<PeripheralAsync>,
+    notification_contexts: Rc<RefCell<NotificationContexts<corebluetooth::Characteristic>>>,
 }
 
 impl Default for CentralManagerAsyncDelegate {
@@ -192,13 +217,18 @@ impl Default for CentralManagerAsyncDelegate {
     }
 }
 
+// Some parameters are only read by trace events, which are compiled out without `tracing`.
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
 impl corebluetooth::CentralManagerDelegate for CentralManagerAsyncDelegate {
     fn new_peripheral_delegate(&self) -> Box<dyn corebluetooth::PeripheralDelegate> {
         Box::new(PeripheralAsyncDelegate::default())
     }
 
     fn did_update_state(&self, central: CentralManager) {
-        let _ = self.state_updated.try_broadcast(central.state());
+        let state = central.state();
+        event!(?state, "did_update_state");
+        record!(crate::record::Event::StateUpdated { state: state.0 });
+        let _ = self.state_updated.try_broadcast(state);
     }
 
     fn did_discover(
@@ -208,6 +238,19 @@ impl corebluetooth::CentralManagerDelegate for CentralManagerAsyncDelegate {
         advertisement_data: AdvertisementData,
         rssi: i16,
     ) {
+        event!(
+            peripheral = %peripheral.identifier(),
+            rssi,
+            local_name = ?advertisement_data.local_name,
+            "did_discover"
+        );
+        record!(crate::record::Event::Discovered {
+            peripheral: peripheral.identifier(),
+            name: peripheral.name(),
+            advertisement_data: (&advertisement_data).into(),
+            rssi,
+        });
+        metrics::with_sink(|sink| sink.discovered(peripheral.identifier(), rssi));
         if let Some(sender) = self.discoveries.take() {
             if sender
                 .unbounded_send(DidDiscover {
@@ -226,6 +269,17 @@ impl corebluetooth::CentralManagerDelegate for CentralManagerAsyncDelegate {
 
     fn did_connect(&self, _central: CentralManager, peripheral: corebluetooth::Peripheral) {
         let id = peripheral.identifier();
+        event!(peripheral = %id, "did_connect");
+        record!(crate::record::Event::Connected {
+            peripheral: id,
+            // The maximum length of a write without response is the ATT MTU less its 3 byte header.
+            mtu: u16::try_from(
+                peripheral
+                    .max_write_value_len(corebluetooth::CharacteristicWriteType::WithoutResponse)
+                    + 3
+            )
+            .ok(),
+        });
         if let Some(sender) = self.connecting.borrow_mut().remove(&id) {
             let _ = sender.send(Ok(()));
         }
@@ -238,8 +292,14 @@ impl corebluetooth::CentralManagerDelegate for CentralManagerAsyncDelegate {
         error: corebluetooth::Error,
     ) {
         let id = peripheral.identifier();
+        let error = Error::from(error);
+        event!(peripheral = %id, error = ?error.kind(), "did_fail_to_connect");
+        record!(crate::record::Event::FailedToConnect {
+            peripheral: id,
+            error: (&error).into(),
+        });
         if let Some(sender) = self.connecting.borrow_mut().remove(&id) {
-            let _ = sender.send(Err(error.into()));
+            let _ = sender.send(Err(error));
         }
     }
 
@@ -251,11 +311,29 @@ impl corebluetooth::CentralManagerDelegate for CentralManagerAsyncDelegate {
         is_reconnecting: bool,
         error: Option<corebluetooth::Error>,
     ) {
+        let error = error.map(Error::from);
+        event!(
+            peripheral = %peripheral.identifier(),
+            is_reconnecting,
+            error = error.as_ref().map(|err| tracing::field::debug(err.kind())),
+            "did_disconnect"
+        );
+        record!(crate::record::Event::Disconnected {
+            peripheral: peripheral.identifier(),
+            is_reconnecting,
+            error: error.as_ref().map(Into::into),
+        });
+        metrics::with_sink(|sink| {
+            sink.disconnected(
+                peripheral.identifier(),
+                error.as_ref().map(|err| err.kind()),
+            )
+        });
         let _ = self.disconnects.try_broadcast(DidDisconnect {
             peripheral: PeripheralAsync::new_unchecked(peripheral),
             timestamp,
             is_reconnecting,
-            error: error.map(Error::from),
+            error,
         });
     }
 
@@ -265,6 +343,15 @@ impl corebluetooth::CentralManagerDelegate for CentralManagerAsyncDelegate {
         event: CBConnectionEvent,
         peripheral: corebluetooth::Peripheral,
     ) {
+        event!(
+            peripheral = %peripheral.identifier(),
+            ?event,
+            "on_connection_event"
+        );
+        record!(crate::record::Event::ConnectionEvent {
+            peripheral: peripheral.identifier(),
+            event: event.0,
+        });
         let _ = self.connection_events.try_broadcast(ConnectionEvent {
             peripheral: PeripheralAsync::new_unchecked(peripheral),
             event,
@@ -276,6 +363,13 @@ impl corebluetooth::CentralManagerDelegate for CentralManagerAsyncDelegate {
         _central: CentralManager,
         peripheral: corebluetooth::Peripheral,
     ) {
+        event!(
+            peripheral = %peripheral.identifier(),
+            "did_update_ancs_authorization"
+        );
+        record!(crate::record::Event::AncsAuthorizationUpdated {
+            peripheral: peripheral.identifier(),
+        });
         let _ = self
             .ancs_authorization_updates
             .try_broadcast(PeripheralAsync::new_unchecked(peripheral));
@@ -296,6 +390,7 @@ impl CentralManagerAsyncDelegate {
             discoveries: Cell::new(None),
             connection_events,
             ancs_authorization_updates,
+            notification_contexts: Rc::new(RefCell::new(NotificationContexts::new())),
         }
     }
 
@@ -335,6 +430,11 @@ impl CentralManagerAsyncDelegate {
 
 /// A peripheral disconnection event.
 #[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize),
+    serde(into = "crate::serialize::DidDisconnect")
+)]
 pub struct DidDisconnect {
     /// The peripheral that was disconnected.
     pub peripheral: PeripheralAsync,
@@ -348,6 +448,11 @@ pub struct DidDisconnect {
 
 /// A peripheral discovery event.
 #[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize),
+    serde(into = "crate::serialize::DidDiscover")
+)]
 pub struct DidDiscover {
     /// The peripheral that was discovered.
     pub peripheral: PeripheralAsync,
@@ -359,6 +464,11 @@ pub struct DidDiscover {
 
 /// A connection event.
 #[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize),
+    serde(into = "crate::serialize::ConnectionEvent")
+)]
 pub struct ConnectionEvent {
     /// The peripheral that the event is for.
     pub peripheral: PeripheralAsync,
@@ -0,0 +1,865 @@
+//! Message framing for byte streams such as L2CAP channels.
+//!
+//! A [`Codec`] splits a byte stream into discrete frames. [`Framed`] combines a codec with an
+//! [`AsyncRead`]/[`AsyncWrite`] byte stream (for example an [`L2capStream`][crate::L2capStream])
+//! to produce a [`Stream`] of received frames and a [`Sink`] for frames to send.
+//!
+//! ```
+//! use std::os::unix::net::UnixStream;
+//!
+//! use corebluetooth_async::ChannelStream;
+//! use corebluetooth_async::codec::{Cobs, Framed};
+//!
+//! // On macOS and iOS, the stream returned by `PeripheralAsync::open_l2cap_stream()` is framed in
+//! // the same way.
+//! let (socket, _peer) = UnixStream::pair()?;
+//! let framed = Framed::new(ChannelStream::new((), socket)?, Cobs::new(1024));
+//! # Ok::<(), std::io::Error>(())
+//! ```
+//!
+//! Every codec enforces a maximum frame length. A frame that is too long or malformed is reported
+//! as an error from the stream and discarded; decoding then resumes with the next frame.
+
+use std::fmt::Display;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+use futures_io::{AsyncRead, AsyncWrite};
+use futures_sink::Sink;
+
+/// An error produced while encoding or decoding a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FrameError {
+    /// A frame exceeded the codec's maximum frame length.
+    TooLong {
+        /// The length of the frame, if known.
+        len: Option<usize>,
+        /// The maximum frame length.
+        max: usize,
+    },
+    /// A frame could not be decoded.
+    Malformed,
+    /// A frame contains a byte sequence that cannot be encoded by the codec.
+    Unencodable,
+}
+
+impl Display for FrameError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FrameError::TooLong {
+                len: Some(len),
+                max,
+            } => {
+                write!(f, "frame of {len} bytes exceeds maximum of {max} bytes")
+            }
+            FrameError::TooLong { len: None, max } => {
+                write!(f, "frame exceeds maximum of {max} bytes")
+            }
+            FrameError::Malformed => f.write_str("malformed frame"),
+            FrameError::Unencodable => f.write_str("frame cannot be encoded"),
+        }
+    }
+}
+
+impl std::error::Error for FrameError {}
+
+impl From<FrameError> for io::Error {
+    fn from(error: FrameError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, error)
+    }
+}
+
+/// A message framing scheme.
+pub trait Codec {
+    /// Appends the encoding of `frame` to `dst`.
+    fn encode(&mut self, frame: &[u8], dst: &mut Vec<u8>) -> Result<(), FrameError>;
+
+    /// Decodes the next frame from the start of `src`.
+    ///
+    /// Bytes that have been consumed are removed from `src`. Returns `Ok(None)` if `src` does not
+    /// yet contain a complete frame. After returning an error, the codec must have discarded (or
+    /// be prepared to discard) the offending frame so that decoding can resume with the next one.
+    fn decode(&mut self, src: &mut Vec<u8>) -> Result<Option<Vec<u8>>, FrameError>;
+}
+
+/// The width of the length field used by [`LengthPrefixed`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PrefixWidth {
+    /// A 16-bit little-endian length.
+    U16,
+    /// A 32-bit little-endian length.
+    U32,
+}
+
+impl PrefixWidth {
+    fn len(self) -> usize {
+        match self {
+            PrefixWidth::U16 => 2,
+            PrefixWidth::U32 => 4,
+        }
+    }
+}
+
+/// Frames prefixed by their length as a little-endian integer.
+#[derive(Debug, Clone)]
+pub struct LengthPrefixed {
+    width: PrefixWidth,
+    max_frame_len: usize,
+    discarding: usize,
+}
+
+impl LengthPrefixed {
+    /// Creates a codec with a length field of the given width.
+    ///
+    /// `max_frame_len` is clamped to the largest length that the field can represent.
+    pub fn new(width: PrefixWidth, max_frame_len: usize) -> Self {
+        let max_len = match width {
+            PrefixWidth::U16 => u16::MAX as usize,
+            PrefixWidth::U32 => u32::MAX as usize,
+        };
+        Self {
+            width,
+            max_frame_len: max_frame_len.min(max_len),
+            discarding: 0,
+        }
+    }
+
+    /// Creates a codec with a 16-bit length field.
+    pub fn u16(max_frame_len: usize) -> Self {
+        Self::new(PrefixWidth::U16, max_frame_len)
+    }
+
+    /// Creates a codec with a 32-bit length field.
+    pub fn u32(max_frame_len: usize) -> Self {
+        Self::new(PrefixWidth::U32, max_frame_len)
+    }
+}
+
+impl Codec for LengthPrefixed {
+    fn encode(&mut self, frame: &[u8], dst: &mut Vec<u8>) -> Result<(), FrameError> {
+        if frame.len() > self.max_frame_len {
+            return Err(FrameError::TooLong {
+                len: Some(frame.len()),
+                max: self.max_frame_len,
+            });
+        }
+
+        match self.width {
+            PrefixWidth::U16 => dst.extend_from_slice(&(frame.len() as u16).to_le_bytes()),
+            PrefixWidth::U32 => dst.extend_from_slice(&(frame.len() as u32).to_le_bytes()),
+        }
+        dst.extend_from_slice(frame);
+        Ok(())
+    }
+
+    fn decode(&mut self, src: &mut Vec<u8>) -> Result<Option<Vec<u8>>, FrameError> {
+        if self.discarding > 0 {
+            let n = self.discarding.min(src.len());
+            src.drain(..n);
+            self.discarding -= n;
+            if self.discarding > 0 {
+                return Ok(None);
+            }
+        }
+
+        let header_len = self.width.len();
+        if src.len() < header_len {
+            return Ok(None);
+        }
+
+        let len = match self.width {
+            PrefixWidth::U16 => u16::from_le_bytes([src[0], src[1]]) as usize,
+            PrefixWidth::U32 => u32::from_le_bytes([src[0], src[1], src[2], src[3]]) as usize,
+        };
+
+        if len > self.max_frame_len {
+            src.drain(..header_len);
+            self.discarding = len;
+            return Err(FrameError::TooLong {
+                len: Some(len),
+                max: self.max_frame_len,
+            });
+        }
+
+        if src.len() < header_len + len {
+            return Ok(None);
+        }
+
+        let frame = src[header_len..header_len + len].to_vec();
+        src.drain(..header_len + len);
+        Ok(Some(frame))
+    }
+}
+
+/// Splits delimited frames from `src`, discarding any frame longer than `max_len` bytes.
+fn split_delimited(
+    src: &mut Vec<u8>,
+    delimiter: u8,
+    max_len: usize,
+    discarding: &mut bool,
+) -> Result<Option<Vec<u8>>, FrameError> {
+    loop {
+        match src.iter().position(|&b| b == delimiter) {
+            Some(pos) => {
+                let frame: Vec<u8> = src.drain(..=pos).take(pos).collect();
+                if std::mem::take(discarding) {
+                    continue;
+                }
+                if frame.len() > max_len {
+                    return Err(FrameError::TooLong {
+                        len: Some(frame.len()),
+                        max: max_len,
+                    });
+                }
+                return Ok(Some(frame));
+            }
+            None if src.len() > max_len => {
+                src.clear();
+                if !std::mem::replace(discarding, true) {
+                    return Err(FrameError::TooLong {
+                        len: None,
+                        max: max_len,
+                    });
+                }
+                return Ok(None);
+            }
+            None => {
+                if *discarding {
+                    src.clear();
+                }
+                return Ok(None);
+            }
+        }
+    }
+}
+
+/// Frames encoded with Consistent Overhead Byte Stuffing and terminated by a zero byte.
+#[derive(Debug, Clone)]
+pub struct Cobs {
+    max_frame_len: usize,
+    discarding: bool,
+}
+
+impl Cobs {
+    /// Creates a COBS codec.
+    pub fn new(max_frame_len: usize) -> Self {
+        Self {
+            max_frame_len,
+            discarding: false,
+        }
+    }
+
+    fn max_encoded_len(&self) -> usize {
+        self.max_frame_len + self.max_frame_len / 254 + 1
+    }
+}
+
+impl Codec for Cobs {
+    fn encode(&mut self, frame: &[u8], dst: &mut Vec<u8>) -> Result<(), FrameError> {
+        if frame.len() > self.max_frame_len {
+            return Err(FrameError::TooLong {
+                len: Some(frame.len()),
+                max: self.max_frame_len,
+            });
+        }
+
+        let mut code_idx = dst.len();
+        let mut code = 1u8;
+        dst.push(0);
+        for &byte in frame {
+            if byte != 0 {
+                dst.push(byte);
+                code += 1;
+            }
+            if byte == 0 || code == 0xff {
+                dst[code_idx] = code;
+                code_idx = dst.len();
+                code = 1;
+                dst.push(0);
+            }
+        }
+        dst[code_idx] = code;
+        dst.push(0);
+        Ok(())
+    }
+
+    fn decode(&mut self, src: &mut Vec<u8>) -> Result<Option<Vec<u8>>, FrameError> {
+        loop {
+            let max_len = self.max_encoded_len();
+            let Some(encoded) = split_delimited(src, 0, max_len, &mut self.discarding)? else {
+                return Ok(None);
+            };
+            // Skip empty frames between consecutive delimiters.
+            if encoded.is_empty() {
+                continue;
+            }
+
+            let mut frame = Vec::with_capacity(encoded.len());
+            let mut i = 0;
+            while i < encoded.len() {
+                let code = encoded[i] as usize;
+                let end = i + code;
+                if end > encoded.len() {
+                    return Err(FrameError::Malformed);
+                }
+                frame.extend_from_slice(&encoded[i + 1..end]);
+                i = end;
+                if code < 0xff && i < encoded.len() {
+                    frame.push(0);
+                }
+            }
+
+            if frame.len() > self.max_frame_len {
+                return Err(FrameError::TooLong {
+                    len: Some(frame.len()),
+                    max: self.max_frame_len,
+                });
+            }
+            return Ok(Some(frame));
+        }
+    }
+}
+
+const SLIP_END: u8 = 0xc0;
+const SLIP_ESC: u8 = 0xdb;
+const SLIP_ESC_END: u8 = 0xdc;
+const SLIP_ESC_ESC: u8 = 0xdd;
+
+/// Frames encoded with the Serial Line Internet Protocol ([RFC 1055](https://www.rfc-editor.org/rfc/rfc1055)).
+///
+/// Encoded frames begin and end with an `END` byte, so that any line noise preceding a frame is
+/// flushed as an empty or malformed frame by the receiver. Empty frames are skipped when decoding,
+/// so an empty frame that is sent is never received.
+#[derive(Debug, Clone)]
+pub struct Slip {
+    max_frame_len: usize,
+    discarding: bool,
+}
+
+impl Slip {
+    /// Creates a SLIP codec.
+    pub fn new(max_frame_len: usize) -> Self {
+        Self {
+            max_frame_len,
+            discarding: false,
+        }
+    }
+}
+
+impl Codec for Slip {
+    fn encode(&mut self, frame: &[u8], dst: &mut Vec<u8>) -> Result<(), FrameError> {
+        if frame.len() > self.max_frame_len {
+            return Err(FrameError::TooLong {
+                len: Some(frame.len()),
+                max: self.max_frame_len,
+            });
+        }
+
+        dst.push(SLIP_END);
+        for &byte in frame {
+            match byte {
+                SLIP_END => dst.extend_from_slice(&[SLIP_ESC, SLIP_ESC_END]),
+                SLIP_ESC => dst.extend_from_slice(&[SLIP_ESC, SLIP_ESC_ESC]),
+                _ => dst.push(byte),
+            }
+        }
+        dst.push(SLIP_END);
+        Ok(())
+    }
+
+    fn decode(&mut self, src: &mut Vec<u8>) -> Result<Option<Vec<u8>>, FrameError> {
+        loop {
+            let max_len = self.max_frame_len * 2;
+            let Some(encoded) = split_delimited(src, SLIP_END, max_len, &mut self.discarding)?
+            else {
+                return Ok(None);
+            };
+            if encoded.is_empty() {
+                continue;
+            }
+
+            let mut frame = Vec::with_capacity(encoded.len());
+            let mut bytes = encoded.into_iter();
+            while let Some(byte) = bytes.next() {
+                match byte {
+                    SLIP_ESC => match bytes.next() {
+                        Some(SLIP_ESC_END) => frame.push(SLIP_END),
+                        Some(SLIP_ESC_ESC) => frame.push(SLIP_ESC),
+                        _ => return Err(FrameError::Malformed),
+                    },
+                    _ => frame.push(byte),
+                }
+            }
+
+            if frame.len() > self.max_frame_len {
+                return Err(FrameError::TooLong {
+                    len: Some(frame.len()),
+                    max: self.max_frame_len,
+                });
+            }
+            return Ok(Some(frame));
+        }
+    }
+}
+
+/// Frames terminated by a newline (`\n`).
+///
+/// A carriage return preceding the newline is removed when decoding.
+#[derive(Debug, Clone)]
+pub struct Newline {
+    max_frame_len: usize,
+    discarding: bool,
+}
+
+impl Newline {
+    /// Creates a newline-delimited codec.
+    pub fn new(max_frame_len: usize) -> Self {
+        Self {
+            max_frame_len,
+            discarding: false,
+        }
+    }
+}
+
+impl Codec for Newline {
+    fn encode(&mut self, frame: &[u8], dst: &mut Vec<u8>) -> Result<(), FrameError> {
+        if frame.len() > self.max_frame_len {
+            return Err(FrameError::TooLong {
+                len: Some(frame.len()),
+                max: self.max_frame_len,
+            });
+        }
+        if frame.contains(&b'\n') {
+            return Err(FrameError::Unencodable);
+        }
+
+        dst.extend_from_slice(frame);
+        dst.push(b'\n');
+        Ok(())
+    }
+
+    fn decode(&mut self, src: &mut Vec<u8>) -> Result<Option<Vec<u8>>, FrameError> {
+        // Allow for a trailing carriage return.
+        let max_len = self.max_frame_len + 1;
+        let Some(mut frame) = split_delimited(src, b'\n', max_len, &mut self.discarding)? else {
+            return Ok(None);
+        };
+        if frame.last() == Some(&b'\r') {
+            frame.pop();
+        }
+        // The frame has already been consumed, so decoding resumes with the next one.
+        if frame.len() > self.max_frame_len {
+            return Err(FrameError::TooLong {
+                len: Some(frame.len()),
+                max: self.max_frame_len,
+            });
+        }
+        Ok(Some(frame))
+    }
+}
+
+const READ_CHUNK_LEN: usize = 4096;
+const WRITE_BACKPRESSURE_LEN: usize = 8192;
+
+/// A [`Stream`] and [`Sink`] of frames over a byte stream.
+///
+/// Decoding errors are yielded from the stream without ending it. The stream ends when the
+/// underlying byte stream reaches end-of-file; if a partial frame was buffered at that point, an
+/// [`io::ErrorKind::UnexpectedEof`] error is yielded first.
+#[derive(Debug)]
+pub struct Framed<S, C> {
+    io: S,
+    codec: C,
+    read_buf: Vec<u8>,
+    write_buf: Vec<u8>,
+    eof: bool,
+}
+
+impl<S, C> Framed<S, C> {
+    /// Creates a framed stream from a byte stream and a codec.
+    pub fn new(io: S, codec: C) -> Self {
+        Self {
+            io,
+            codec,
+            read_buf: Vec::new(),
+            write_buf: Vec::new(),
+            eof: false,
+        }
+    }
+
+    /// A reference to the underlying byte stream.
+    pub fn get_ref(&self) -> &S {
+        &self.io
+    }
+
+    /// A mutable reference to the underlying byte stream.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.io
+    }
+
+    /// A reference to the codec.
+    pub fn codec(&self) -> &C {
+        &self.codec
+    }
+
+    /// Returns the underlying byte stream, discarding any buffered data.
+    pub fn into_inner(self) -> S {
+        self.io
+    }
+}
+
+impl<S: AsyncRead + Unpin, C: Codec + Unpin> Stream for Framed<S, C> {
+    type Item = io::Result<Vec<u8>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if !this.read_buf.is_empty() {
+                match this.codec.decode(&mut this.read_buf) {
+                    Ok(Some(frame)) => return Poll::Ready(Some(Ok(frame))),
+                    Ok(None) => (),
+                    Err(err) => return Poll::Ready(Some(Err(err.into()))),
+                }
+            }
+
+            if this.eof {
+                if this.read_buf.is_empty() {
+                    return Poll::Ready(None);
+                }
+                this.read_buf.clear();
+                return Poll::Ready(Some(Err(io::ErrorKind::UnexpectedEof.into())));
+            }
+
+            let len = this.read_buf.len();
+            this.read_buf.resize(len + READ_CHUNK_LEN, 0);
+            let res = Pin::new(&mut this.io).poll_read(cx, &mut this.read_buf[len..]);
+            let n = match res {
+                Poll::Ready(Ok(n)) => n,
+                Poll::Ready(Err(err)) => {
+                    this.read_buf.truncate(len);
+                    return Poll::Ready(Some(Err(err)));
+                }
+                Poll::Pending => {
+                    this.read_buf.truncate(len);
+                    return Poll::Pending;
+                }
+            };
+            this.read_buf.truncate(len + n);
+            if n == 0 {
+                this.eof = true;
+            }
+        }
+    }
+}
+
+impl<S: AsyncWrite + Unpin, C: Codec + Unpin> Framed<S, C> {
+    fn poll_write_buf(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while !self.write_buf.is_empty() {
+            match Pin::new(&mut self.io).poll_write(cx, &self.write_buf) {
+                Poll::Ready(Ok(0)) => return Poll::Ready(Err(io::ErrorKind::WriteZero.into())),
+                Poll::Ready(Ok(n)) => {
+                    self.write_buf.drain(..n);
+                }
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S: AsyncWrite + Unpin, C: Codec + Unpin> Sink<Vec<u8>> for Framed<S, C> {
+    type Error = io::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if this.write_buf.len() >= WRITE_BACKPRESSURE_LEN {
+            this.poll_write_buf(cx)
+        } else {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: Vec<u8>) -> io::Result<()> {
+        let this = self.get_mut();
+        this.codec.encode(&item, &mut this.write_buf)?;
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        match this.poll_write_buf(cx) {
+            Poll::Ready(Ok(())) => Pin::new(&mut this.io).poll_flush(cx),
+            other => other,
+        }
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        match this.poll_write_buf(cx) {
+            Poll::Ready(Ok(())) => Pin::new(&mut this.io).poll_close(cx),
+            other => other,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::net::UnixStream;
+
+    use futures_lite::{AsyncWriteExt, StreamExt, future};
+
+    use super::*;
+    use crate::ChannelStream;
+
+    type Socket = ChannelStream<()>;
+
+    fn pair<C: Codec + Clone>(codec: C) -> (Framed<Socket, C>, Framed<Socket, C>) {
+        let (a, b) = UnixStream::pair().unwrap();
+        (
+            Framed::new(ChannelStream::new((), a).unwrap(), codec.clone()),
+            Framed::new(ChannelStream::new((), b).unwrap(), codec),
+        )
+    }
+
+    async fn send<S: Sink<Vec<u8>> + Unpin>(sink: &mut S, frame: &[u8]) -> Result<(), S::Error> {
+        future::poll_fn(|cx| Pin::new(&mut *sink).poll_ready(cx)).await?;
+        Pin::new(&mut *sink).start_send(frame.to_vec())?;
+        future::poll_fn(|cx| Pin::new(&mut *sink).poll_flush(cx)).await
+    }
+
+    fn decode_all<C: Codec>(codec: &mut C, mut src: Vec<u8>) -> Vec<Result<Vec<u8>, FrameError>> {
+        let mut frames = Vec::new();
+        loop {
+            match codec.decode(&mut src) {
+                Ok(Some(frame)) => frames.push(Ok(frame)),
+                Ok(None) => return frames,
+                Err(err) => frames.push(Err(err)),
+            }
+        }
+    }
+
+    fn frames() -> Vec<Vec<u8>> {
+        vec![
+            b"hello".to_vec(),
+            Vec::new(),
+            vec![0; 3],
+            vec![SLIP_END, SLIP_ESC, SLIP_ESC_END, SLIP_ESC_ESC],
+            (0..=255).cycle().take(600).collect(),
+        ]
+    }
+
+    fn round_trip<C: Codec + Clone + Unpin>(codec: C, frames: &[Vec<u8>]) {
+        let (mut a, mut b) = pair(codec);
+        future::block_on(async {
+            for frame in frames {
+                send(&mut a, frame).await.unwrap();
+            }
+            for frame in frames {
+                assert_eq!(b.next().await.unwrap().unwrap(), *frame);
+            }
+        });
+    }
+
+    #[test]
+    fn length_prefixed_round_trip() {
+        round_trip(LengthPrefixed::u16(1024), &frames());
+        round_trip(LengthPrefixed::u32(1024), &frames());
+    }
+
+    #[test]
+    fn cobs_round_trip() {
+        round_trip(Cobs::new(1024), &frames());
+    }
+
+    #[test]
+    fn slip_round_trip() {
+        let frames: Vec<_> = frames()
+            .into_iter()
+            .filter(|frame| !frame.is_empty())
+            .collect();
+        round_trip(Slip::new(1024), &frames);
+
+        let mut dst = Vec::new();
+        Slip::new(1024).encode(&[], &mut dst).unwrap();
+        assert!(decode_all(&mut Slip::new(1024), dst).is_empty());
+    }
+
+    #[test]
+    fn newline_round_trip() {
+        let frames: Vec<_> = frames()
+            .into_iter()
+            .filter(|frame| !frame.contains(&b'\n'))
+            .collect();
+        round_trip(Newline::new(1024), &frames);
+    }
+
+    #[test]
+    fn length_prefixed_clamps_max_frame_len() {
+        let mut codec = LengthPrefixed::new(PrefixWidth::U16, 100_000);
+        assert_eq!(codec.max_frame_len, u16::MAX as usize);
+
+        let frame = vec![0; u16::MAX as usize + 1];
+        assert_eq!(
+            codec.encode(&frame, &mut Vec::new()),
+            Err(FrameError::TooLong {
+                len: Some(frame.len()),
+                max: u16::MAX as usize
+            })
+        );
+
+        let mut dst = Vec::new();
+        codec.encode(&frame[1..], &mut dst).unwrap();
+        assert_eq!(dst[..2], [0xff, 0xff]);
+    }
+
+    #[test]
+    fn length_prefixed_discards_long_frame() {
+        let mut src = vec![5, 0, 1, 2, 3, 4, 5, 2, 0, 6, 7];
+        let frames = decode_all(&mut LengthPrefixed::u16(4), std::mem::take(&mut src));
+        assert_eq!(
+            frames,
+            [
+                Err(FrameError::TooLong {
+                    len: Some(5),
+                    max: 4
+                }),
+                Ok(vec![6, 7]),
+            ]
+        );
+    }
+
+    #[test]
+    fn cobs_encoding() {
+        let mut dst = Vec::new();
+        Cobs::new(1024)
+            .encode(&[0x11, 0x00, 0x00, 0x22], &mut dst)
+            .unwrap();
+        assert_eq!(dst, [0x02, 0x11, 0x01, 0x02, 0x22, 0x00]);
+
+        // A run of 254 non-zero bytes fills a block without an implicit zero.
+        let frame = vec![0x01; 254];
+        let mut dst = Vec::new();
+        Cobs::new(1024).encode(&frame, &mut dst).unwrap();
+        assert_eq!(dst.len(), 257);
+        assert_eq!((dst[0], dst[255], dst[256]), (0xff, 0x01, 0x00));
+        assert_eq!(decode_all(&mut Cobs::new(1024), dst), [Ok(frame)]);
+    }
+
+    #[test]
+    fn cobs_malformed_frame_is_skipped() {
+        // The code byte points past the end of the frame.
+        let frames = decode_all(&mut Cobs::new(16), vec![0x05, 0x11, 0x00, 0x02, 0x22, 0x00]);
+        assert_eq!(frames, [Err(FrameError::Malformed), Ok(vec![0x22])]);
+    }
+
+    #[test]
+    fn slip_malformed_escape_is_skipped() {
+        let frames = decode_all(
+            &mut Slip::new(16),
+            vec![SLIP_END, SLIP_ESC, 0x01, SLIP_END, SLIP_END, 0x02, SLIP_END],
+        );
+        assert_eq!(frames, [Err(FrameError::Malformed), Ok(vec![0x02])]);
+    }
+
+    #[test]
+    fn newline_enforces_max_frame_len() {
+        let frames = decode_all(&mut Newline::new(4), b"1234\r\n12345\nabc\n".to_vec());
+        assert_eq!(
+            frames,
+            [
+                Ok(b"1234".to_vec()),
+                Err(FrameError::TooLong {
+                    len: Some(5),
+                    max: 4
+                }),
+                Ok(b"abc".to_vec()),
+            ]
+        );
+        assert_eq!(
+            Newline::new(4).encode(b"a\nb", &mut Vec::new()),
+            Err(FrameError::Unencodable)
+        );
+    }
+
+    #[test]
+    fn delimited_codec_discards_unterminated_long_frame() {
+        let mut codec = Newline::new(4);
+        let mut src = b"0123456".to_vec();
+        assert_eq!(
+            codec.decode(&mut src),
+            Err(FrameError::TooLong { len: None, max: 5 })
+        );
+        // The rest of the long frame is discarded until the next delimiter.
+        src.extend_from_slice(b"789\nok\n");
+        assert_eq!(codec.decode(&mut src), Ok(Some(b"ok".to_vec())));
+    }
+
+    #[test]
+    fn stream_recovers_after_error() {
+        let (a, b) = UnixStream::pair().unwrap();
+        let mut a = ChannelStream::new((), a).unwrap();
+        let mut b = Framed::new(ChannelStream::new((), b).unwrap(), Slip::new(4));
+        future::block_on(async {
+            a.write_all(&[SLIP_END, 1, 2, 3, 4, 5, SLIP_END, SLIP_END, 6, SLIP_END])
+                .await
+                .unwrap();
+            let err = b.next().await.unwrap().unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+            assert_eq!(b.next().await.unwrap().unwrap(), [6]);
+        });
+    }
+
+    #[test]
+    fn partial_frame_at_eof() {
+        let (a, b) = UnixStream::pair().unwrap();
+        let mut a = ChannelStream::new((), a).unwrap();
+        let mut b = Framed::new(ChannelStream::new((), b).unwrap(), Newline::new(16));
+        future::block_on(async {
+            a.write_all(b"complete\npartial").await.unwrap();
+            a.close().await.unwrap();
+            assert_eq!(b.next().await.unwrap().unwrap(), b"complete");
+            let err = b.next().await.unwrap().unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+            assert!(b.next().await.is_none());
+        });
+    }
+
+    #[test]
+    fn sink_rejects_long_frame() {
+        let (mut a, mut b) = pair(Cobs::new(4));
+        future::block_on(async {
+            let err = send(&mut a, &[0; 5]).await.unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+            send(&mut a, &[1, 2]).await.unwrap();
+            assert_eq!(b.next().await.unwrap().unwrap(), [1, 2]);
+        });
+    }
+
+    #[test]
+    fn large_transfer_with_backpressure() {
+        let frames: Vec<Vec<u8>> = (0..200u32).map(|i| i.to_le_bytes().repeat(256)).collect();
+        let (mut a, mut b) = pair(LengthPrefixed::u32(4096));
+        future::block_on(future::zip(
+            async {
+                for frame in &frames {
+                    future::poll_fn(|cx| Pin::new(&mut a).poll_ready(cx))
+                        .await
+                        .unwrap();
+                    Pin::new(&mut a).start_send(frame.clone()).unwrap();
+                }
+                future::poll_fn(|cx| Pin::new(&mut a).poll_close(cx))
+                    .await
+                    .unwrap();
+            },
+            async {
+                for frame in &frames {
+                    assert_eq!(b.next().await.unwrap().unwrap(), *frame);
+                }
+                assert!(b.next().await.is_none());
+            },
+        ));
+    }
+}
@@ -1,9 +1,14 @@
 //! Error types for this crate.
 
 use std::fmt::Display;
+use std::sync::Arc;
 
 use futures_channel::oneshot;
-use objc2_core_bluetooth::{CBATTError, CBError};
+
+#[cfg(not(target_vendor = "apple"))]
+pub use crate::portable::{CBATTError, CBError};
+#[cfg(target_vendor = "apple")]
+pub use objc2_core_bluetooth::{CBATTError, CBError};
 
 /// A convenience type alias for a `Result` with an `Error` type.
 pub type Result<T> = std::result::Result<T, Error>;
@@ -15,7 +20,10 @@ pub struct Error {
 }
 
 /// The kind of error that occurred.
+///
+/// New kinds may be added in minor releases, so matches must include a wildcard arm.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
 pub enum ErrorKind {
     /// A Core Bluetooth error.
     Bluetooth(CBError),
@@ -25,27 +33,97 @@ pub enum ErrorKind {
     Canceled,
     /// A broadcast channel lagged.
     Lagged,
+    /// An I/O error occurred on an L2CAP channel stream.
+    Io(std::io::ErrorKind),
+    /// The requested peripheral or attribute was not found.
+    NotFound,
     /// An unknown or other error.
     Other,
 }
 
 #[derive(Debug, Clone)]
 enum ErrorData {
+    #[cfg(target_vendor = "apple")]
     Os(corebluetooth::Error),
+    Io(Arc<std::io::Error>),
     Simple(ErrorKind),
+    #[cfg(feature = "serde")]
+    Described(ErrorKind, Arc<str>),
 }
 
 impl Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match &self.data {
+            #[cfg(target_vendor = "apple")]
             ErrorData::Os(error) => error.fmt(f),
+            ErrorData::Io(error) => error.fmt(f),
             ErrorData::Simple(kind) => kind.fmt(f),
+            #[cfg(feature = "serde")]
+            ErrorData::Described(_, message) => f.write_str(message),
         }
     }
 }
 
 impl std::error::Error for Error {}
 
+/// Errors are serialized as their domain, code and message.
+///
+/// CoreBluetooth errors use the `CBErrorDomain` and `CBATTErrorDomain` domains with numeric
+/// codes; I/O errors and errors originating in this crate use named codes. A deserialized error
+/// has the same [`kind()`][Error::kind] and message as the original.
+///
+/// ```
+/// use corebluetooth_async::error::{CBATTError, Error, ErrorKind};
+///
+/// let error = Error::from(ErrorKind::ATT(CBATTError::ReadNotPermitted));
+/// let json = serde_json::to_value(&error)?;
+/// assert_eq!(json["domain"], "CBATTErrorDomain");
+/// assert_eq!(json["code"], 2);
+///
+/// let deserialized: Error = serde_json::from_value(json)?;
+/// assert_eq!(deserialized.kind(), error.kind());
+/// assert_eq!(deserialized.to_string(), error.to_string());
+/// # Ok::<(), serde_json::Error>(())
+/// ```
+#[cfg(feature = "serde")]
+impl serde::Serialize for Error {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        let (domain, code) = self.kind().domain_and_code();
+        SerializedError {
+            domain: domain.to_owned(),
+            code,
+            message: Some(self.to_string()),
+        }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Error {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        let error = SerializedError::deserialize(deserializer)?;
+        let kind = ErrorKind::from_domain_and_code(&error.domain, &error.code);
+        Ok(match error.message {
+            Some(message) => Error::described(kind, message),
+            None => kind.into(),
+        })
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(error: std::io::Error) -> Self {
+        Error {
+            data: ErrorData::Io(Arc::new(error)),
+        }
+    }
+}
+
+#[cfg(target_vendor = "apple")]
 impl From<corebluetooth::Error> for Error {
     fn from(error: corebluetooth::Error) -> Self {
         Error {
@@ -62,6 +140,7 @@ impl From<ErrorKind> for Error {
     }
 }
 
+#[cfg(target_vendor = "apple")]
 impl From<corebluetooth::error::ErrorKind> for Error {
     fn from(kind: corebluetooth::error::ErrorKind) -> Self {
         Error {
@@ -83,31 +162,54 @@ impl From<async_broadcast::RecvError> for Error {
 }
 
 impl Error {
+    /// Creates an error of the given kind that is displayed as `message`.
+    #[cfg(feature = "serde")]
+    pub(crate) fn described(kind: ErrorKind, message: impl Into<Arc<str>>) -> Self {
+        Error {
+            data: ErrorData::Described(kind, message.into()),
+        }
+    }
+
     /// If this is an `ErrorData::Os` error, returns a reference to the underlying `corebluetooth::Error`.
+    #[cfg(target_vendor = "apple")]
     pub fn get_ref(&self) -> Option<&corebluetooth::Error> {
         match &self.data {
             ErrorData::Os(error) => Some(error),
-            ErrorData::Simple(_) => None,
+            _ => None,
         }
     }
 
     /// If this is an `ErrorData::Os` error, returns the underlying `corebluetooth::Error`.
+    #[cfg(target_vendor = "apple")]
     pub fn into_inner(self) -> Option<corebluetooth::Error> {
         match self.data {
             ErrorData::Os(error) => Some(error),
-            ErrorData::Simple(_) => None,
+            _ => None,
+        }
+    }
+
+    /// If this is an I/O error, returns a reference to the underlying `std::io::Error`.
+    pub fn io_error(&self) -> Option<&std::io::Error> {
+        match &self.data {
+            ErrorData::Io(error) => Some(error),
+            _ => None,
         }
     }
 
     /// Returns the kind of error.
     pub fn kind(&self) -> ErrorKind {
         match &self.data {
+            #[cfg(target_vendor = "apple")]
             ErrorData::Os(error) => error.kind().into(),
+            ErrorData::Io(error) => ErrorKind::Io(error.kind()),
             ErrorData::Simple(kind) => *kind,
+            #[cfg(feature = "serde")]
+            ErrorData::Described(kind, _) => *kind,
         }
     }
 }
 
+#[cfg(target_vendor = "apple")]
 impl From<corebluetooth::error::ErrorKind> for ErrorKind {
     fn from(kind: corebluetooth::error::ErrorKind) -> Self {
         match kind {
@@ -118,6 +220,7 @@ impl From<corebluetooth::error::ErrorKind> for ErrorKind {
     }
 }
 
+#[cfg(target_vendor = "apple")]
 impl TryFrom<ErrorKind> for corebluetooth::error::ErrorKind {
     type Error = ErrorKind;
 
@@ -130,11 +233,151 @@ impl TryFrom<ErrorKind> for corebluetooth::error::ErrorKind {
             ErrorKind::Other => Ok(corebluetooth::error::ErrorKind::Other),
             ErrorKind::Canceled => Err(kind),
             ErrorKind::Lagged => Err(kind),
+            ErrorKind::Io(_) => Err(kind),
+            ErrorKind::NotFound => Err(kind),
         }
     }
 }
 
+#[cfg(feature = "serde")]
+const CB_ERROR_DOMAIN: &str = "CBErrorDomain";
+#[cfg(feature = "serde")]
+const CB_ATT_ERROR_DOMAIN: &str = "CBATTErrorDomain";
+#[cfg(feature = "serde")]
+const IO_ERROR_DOMAIN: &str = "std::io";
+#[cfg(feature = "serde")]
+const CRATE_ERROR_DOMAIN: &str = "corebluetooth_async";
+
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SerializedError {
+    domain: String,
+    code: ErrorCode,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+}
+
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(untagged)]
+enum ErrorCode {
+    Number(isize),
+    Name(String),
+}
+
+#[cfg(feature = "serde")]
+impl ErrorKind {
+    fn domain_and_code(self) -> (&'static str, ErrorCode) {
+        let name = |name: &str| ErrorCode::Name(name.to_owned());
+        match self {
+            ErrorKind::Bluetooth(cberror) => (CB_ERROR_DOMAIN, ErrorCode::Number(cberror.0)),
+            ErrorKind::ATT(cbatterror) => (CB_ATT_ERROR_DOMAIN, ErrorCode::Number(cbatterror.0)),
+            ErrorKind::Io(kind) => (IO_ERROR_DOMAIN, ErrorCode::Name(format!("{kind:?}"))),
+            ErrorKind::Canceled => (CRATE_ERROR_DOMAIN, name("canceled")),
+            ErrorKind::Lagged => (CRATE_ERROR_DOMAIN, name("lagged")),
+            ErrorKind::NotFound => (CRATE_ERROR_DOMAIN, name("not_found")),
+            ErrorKind::Other => (CRATE_ERROR_DOMAIN, name("other")),
+        }
+    }
+
+    /// Unrecognized domains and codes, including the names of I/O error kinds this crate does not
+    /// know, are deserialized as [`ErrorKind::Other`] rather than as an I/O error of a different
+    /// kind.
+    fn from_domain_and_code(domain: &str, code: &ErrorCode) -> Self {
+        match (domain, code) {
+            (CB_ERROR_DOMAIN, ErrorCode::Number(code)) => ErrorKind::Bluetooth(CBError(*code)),
+            (CB_ATT_ERROR_DOMAIN, ErrorCode::Number(code)) => ErrorKind::ATT(CBATTError(*code)),
+            (IO_ERROR_DOMAIN, ErrorCode::Name(name)) => {
+                io_error_kind(name).map_or(ErrorKind::Other, ErrorKind::Io)
+            }
+            (CRATE_ERROR_DOMAIN, ErrorCode::Name(name)) => match name.as_str() {
+                "canceled" => ErrorKind::Canceled,
+                "lagged" => ErrorKind::Lagged,
+                "not_found" => ErrorKind::NotFound,
+                _ => ErrorKind::Other,
+            },
+            _ => ErrorKind::Other,
+        }
+    }
+}
+
+/// Error kinds are serialized as the domain and code of an [`Error`] of that kind.
+///
+/// ```
+/// use corebluetooth_async::error::{CBError, ErrorKind};
+///
+/// for kind in [
+///     ErrorKind::Bluetooth(CBError::ConnectionTimeout),
+///     ErrorKind::Io(std::io::ErrorKind::TimedOut),
+///     ErrorKind::Canceled,
+/// ] {
+///     let json = serde_json::to_string(&kind)?;
+///     assert_eq!(serde_json::from_str::<ErrorKind>(&json)?, kind);
+/// }
+/// # Ok::<(), serde_json::Error>(())
+/// ```
+#[cfg(feature = "serde")]
+impl serde::Serialize for ErrorKind {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        let (domain, code) = self.domain_and_code();
+        SerializedError {
+            domain: domain.to_owned(),
+            code,
+            message: None,
+        }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for ErrorKind {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        let error = SerializedError::deserialize(deserializer)?;
+        Ok(ErrorKind::from_domain_and_code(&error.domain, &error.code))
+    }
+}
+
+/// Parses the `Debug` name of an I/O error kind, such as `"TimedOut"`.
+///
+/// Returns `None` for names that are not recognized, including those of kinds added to the
+/// standard library after this crate was written.
+#[cfg(feature = "serde")]
+pub(crate) fn io_error_kind(name: &str) -> Option<std::io::ErrorKind> {
+    use std::io::ErrorKind::*;
+
+    [
+        NotFound,
+        PermissionDenied,
+        ConnectionRefused,
+        ConnectionReset,
+        ConnectionAborted,
+        NotConnected,
+        AddrInUse,
+        AddrNotAvailable,
+        BrokenPipe,
+        AlreadyExists,
+        WouldBlock,
+        InvalidInput,
+        InvalidData,
+        TimedOut,
+        WriteZero,
+        Interrupted,
+        Unsupported,
+        UnexpectedEof,
+        OutOfMemory,
+        Other,
+    ]
+    .into_iter()
+    .find(|kind| format!("{kind:?}") == name)
+}
+
 impl Display for ErrorKind {
+    #[cfg(target_vendor = "apple")]
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             ErrorKind::Bluetooth(cberror) => {
@@ -144,6 +387,88 @@ impl Display for ErrorKind {
             ErrorKind::Other => corebluetooth::error::ErrorKind::Other.fmt(f),
             ErrorKind::Canceled => f.write_str("canceled"),
             ErrorKind::Lagged => f.write_str("lagged"),
+            ErrorKind::Io(kind) => write!(f, "I/O error ({kind})"),
+            ErrorKind::NotFound => f.write_str("not found"),
         }
     }
+
+    #[cfg(not(target_vendor = "apple"))]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ErrorKind::Bluetooth(cberror) => write!(f, "bluetooth error ({})", cberror.0),
+            ErrorKind::ATT(cbatterror) => write!(f, "bluetooth ATT error ({})", cbatterror.0),
+            ErrorKind::Other => f.write_str("other error"),
+            ErrorKind::Canceled => f.write_str("canceled"),
+            ErrorKind::Lagged => f.write_str("lagged"),
+            ErrorKind::Io(kind) => write!(f, "I/O error ({kind})"),
+            ErrorKind::NotFound => f.write_str("not found"),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::*;
+
+    fn round_trip(error: &Error) -> Error {
+        serde_json::from_str(&serde_json::to_string(error).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn errors_round_trip() {
+        for kind in [
+            ErrorKind::Bluetooth(CBError::ConnectionTimeout),
+            ErrorKind::ATT(CBATTError::InsufficientEncryption),
+            ErrorKind::Canceled,
+            ErrorKind::Lagged,
+            ErrorKind::Io(std::io::ErrorKind::BrokenPipe),
+            ErrorKind::Io(std::io::ErrorKind::Other),
+            ErrorKind::NotFound,
+            ErrorKind::Other,
+        ] {
+            let error = Error::from(kind);
+            let deserialized = round_trip(&error);
+            assert_eq!(deserialized.kind(), kind);
+            assert_eq!(deserialized.to_string(), error.to_string());
+
+            let json = serde_json::to_string(&kind).unwrap();
+            assert_eq!(serde_json::from_str::<ErrorKind>(&json).unwrap(), kind);
+        }
+    }
+
+    #[test]
+    fn io_errors_round_trip() {
+        let error = Error::from(std::io::Error::new(
+            std::io::ErrorKind::ConnectionReset,
+            "reset by peer",
+        ));
+        let deserialized = round_trip(&error);
+        assert_eq!(
+            deserialized.kind(),
+            ErrorKind::Io(std::io::ErrorKind::ConnectionReset)
+        );
+        assert_eq!(deserialized.to_string(), "reset by peer");
+    }
+
+    #[test]
+    fn unknown_io_error_kinds_are_not_io_errors() {
+        assert_eq!(
+            io_error_kind("TimedOut"),
+            Some(std::io::ErrorKind::TimedOut)
+        );
+        assert_eq!(io_error_kind("Other"), Some(std::io::ErrorKind::Other));
+        assert_eq!(io_error_kind("NotARealKind"), None);
+
+        let json = r#"{"domain":"std::io","code":"NotARealKind","message":"failed"}"#;
+        let error: Error = serde_json::from_str(json).unwrap();
+        assert_eq!(error.kind(), ErrorKind::Other);
+        assert_eq!(error.to_string(), "failed");
+    }
+
+    #[test]
+    fn unknown_domains_are_other_errors() {
+        let json = r#"{"domain":"NSURLErrorDomain","code":-1001}"#;
+        let kind: ErrorKind = serde_json::from_str(json).unwrap();
+        assert_eq!(kind, ErrorKind::Other);
+    }
 }
@@ -0,0 +1,300 @@
+use std::io;
+use std::os::unix::net::UnixStream;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use async_io::Async;
+#[cfg(target_vendor = "apple")]
+use corebluetooth::L2capChannel;
+use futures_io::{AsyncRead, AsyncWrite};
+
+/// A non-blocking byte stream over an L2CAP channel.
+///
+/// See [`ChannelStream`].
+#[cfg(target_vendor = "apple")]
+pub type L2capStream<P> = ChannelStream<L2capChannel<P>>;
+
+/// A non-blocking byte stream over an L2CAP channel for use with tokio.
+///
+/// See [`TokioChannelStream`].
+#[cfg(all(target_vendor = "apple", feature = "tokio"))]
+pub type TokioL2capStream<P> = TokioChannelStream<L2capChannel<P>>;
+
+/// A non-blocking byte stream over a Unix socket that owns the channel the socket belongs to.
+///
+/// The stream implements [`futures_io::AsyncRead`] and [`futures_io::AsyncWrite`]. Readiness
+/// is driven by the [`async-io`](https://docs.rs/async-io) reactor, so the stream can be polled
+/// from any executor.
+///
+/// On macOS and iOS, the channel is the `L2capChannel` the socket was opened with (see
+/// `L2capStream`). The channel stays open as long as the stream exists and is released, after the
+/// socket is closed, when the stream is dropped. Closing the stream shuts down its write half;
+/// reads return `Ok(0)` once the peer has closed the channel.
+#[derive(Debug)]
+pub struct ChannelStream<C> {
+    // Declared before `channel` so that the socket is closed before the channel is released.
+    stream: Async<UnixStream>,
+    channel: C,
+}
+
+impl<C> ChannelStream<C> {
+    /// Creates an asynchronous stream from a channel and its socket.
+    ///
+    /// The socket is put into non-blocking mode.
+    pub fn new(channel: C, stream: UnixStream) -> io::Result<Self> {
+        Ok(Self {
+            stream: Async::new(stream)?,
+            channel,
+        })
+    }
+
+    /// The channel that this stream is connected to.
+    pub fn channel(&self) -> &C {
+        &self.channel
+    }
+
+    /// A reference to the underlying socket.
+    pub fn get_ref(&self) -> &UnixStream {
+        self.stream.get_ref()
+    }
+
+    /// Returns the channel and its socket.
+    ///
+    /// The socket is left in non-blocking mode.
+    pub fn into_inner(self) -> io::Result<(C, UnixStream)> {
+        let stream = self.stream.into_inner()?;
+        Ok((self.channel, stream))
+    }
+}
+
+impl<C: Unpin> AsyncRead for ChannelStream<C> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().stream).poll_read(cx, buf)
+    }
+
+    fn poll_read_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &mut [io::IoSliceMut<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().stream).poll_read_vectored(cx, bufs)
+    }
+}
+
+impl<C: Unpin> AsyncWrite for ChannelStream<C> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().stream).poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().stream).poll_write_vectored(cx, bufs)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().stream).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        // `Async::poll_close()` only flushes, so the write half is shut down here.
+        let stream = &mut self.get_mut().stream;
+        std::task::ready!(Pin::new(&mut *stream).poll_close(cx))?;
+        Poll::Ready(stream.get_ref().shutdown(std::net::Shutdown::Write))
+    }
+}
+
+/// A non-blocking byte stream over a Unix socket for use with tokio.
+///
+/// This is the tokio counterpart of [`ChannelStream`], implementing [`tokio::io::AsyncRead`] and
+/// [`tokio::io::AsyncWrite`]. Readiness is driven by the tokio reactor, so it must be created
+/// from within a tokio runtime.
+#[cfg(feature = "tokio")]
+#[derive(Debug)]
+pub struct TokioChannelStream<C> {
+    // Declared before `channel` so that the socket is closed before the channel is released.
+    stream: tokio::net::UnixStream,
+    channel: C,
+}
+
+#[cfg(feature = "tokio")]
+impl<C> TokioChannelStream<C> {
+    /// Creates an asynchronous stream from a channel and its socket.
+    ///
+    /// The socket is put into non-blocking mode.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a tokio runtime.
+    pub fn new(channel: C, stream: UnixStream) -> io::Result<Self> {
+        stream.set_nonblocking(true)?;
+        Ok(Self {
+            stream: tokio::net::UnixStream::from_std(stream)?,
+            channel,
+        })
+    }
+
+    /// The channel that this stream is connected to.
+    pub fn channel(&self) -> &C {
+        &self.channel
+    }
+
+    /// A reference to the underlying socket.
+    pub fn get_ref(&self) -> &tokio::net::UnixStream {
+        &self.stream
+    }
+
+    /// Returns the channel and its socket.
+    ///
+    /// The socket is left in non-blocking mode.
+    pub fn into_inner(self) -> io::Result<(C, UnixStream)> {
+        let stream = self.stream.into_std()?;
+        Ok((self.channel, stream))
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<C: Unpin> tokio::io::AsyncRead for TokioChannelStream<C> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().stream).poll_read(cx, buf)
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<C: Unpin> tokio::io::AsyncWrite for TokioChannelStream<C> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().stream).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().stream).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+    use std::sync::{Arc, Mutex};
+
+    use futures_lite::{AsyncReadExt, AsyncWriteExt, future};
+
+    use super::*;
+
+    /// A channel that records, when it is released, whether its peer had already seen EOF.
+    struct Channel {
+        peer: UnixStream,
+        peer_saw_eof: Arc<Mutex<Option<bool>>>,
+    }
+
+    impl Drop for Channel {
+        fn drop(&mut self) {
+            self.peer.set_nonblocking(true).unwrap();
+            let eof = matches!(self.peer.read(&mut [0; 1]), Ok(0));
+            *self.peer_saw_eof.lock().unwrap() = Some(eof);
+        }
+    }
+
+    fn pair() -> (ChannelStream<()>, ChannelStream<()>) {
+        let (a, b) = UnixStream::pair().unwrap();
+        (
+            ChannelStream::new((), a).unwrap(),
+            ChannelStream::new((), b).unwrap(),
+        )
+    }
+
+    #[test]
+    fn round_trip() {
+        let (mut a, mut b) = pair();
+        future::block_on(async {
+            a.write_all(b"ping").await.unwrap();
+            let mut buf = [0; 4];
+            b.read_exact(&mut buf).await.unwrap();
+            assert_eq!(&buf, b"ping");
+
+            b.write_all(b"pong").await.unwrap();
+            a.read_exact(&mut buf).await.unwrap();
+            assert_eq!(&buf, b"pong");
+        });
+    }
+
+    #[test]
+    fn close_is_eof_for_peer() {
+        let (mut a, mut b) = pair();
+        future::block_on(async {
+            a.write_all(b"last").await.unwrap();
+            a.close().await.unwrap();
+
+            let mut received = Vec::new();
+            b.read_to_end(&mut received).await.unwrap();
+            assert_eq!(received, b"last");
+
+            // Only the write half is shut down.
+            b.write_all(b"reply").await.unwrap();
+            let mut buf = [0; 5];
+            a.read_exact(&mut buf).await.unwrap();
+            assert_eq!(&buf, b"reply");
+        });
+    }
+
+    #[test]
+    fn socket_is_closed_before_channel_is_released() {
+        let (socket, peer) = UnixStream::pair().unwrap();
+        let peer_saw_eof = Arc::new(Mutex::new(None));
+        let channel = Channel {
+            peer,
+            peer_saw_eof: peer_saw_eof.clone(),
+        };
+        drop(ChannelStream::new(channel, socket).unwrap());
+        assert_eq!(*peer_saw_eof.lock().unwrap(), Some(true));
+    }
+
+    #[test]
+    fn into_inner_returns_non_blocking_socket() {
+        let (a, _b) = UnixStream::pair().unwrap();
+        let stream = ChannelStream::new(7, a).unwrap();
+        assert_eq!(*stream.channel(), 7);
+
+        let (channel, mut socket) = stream.into_inner().unwrap();
+        assert_eq!(channel, 7);
+        let err = socket.read(&mut [0; 1]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn tokio_round_trip() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let (a, b) = UnixStream::pair().unwrap();
+        let mut a = TokioChannelStream::new((), a).unwrap();
+        let mut b = TokioChannelStream::new((), b).unwrap();
+
+        a.write_all(b"ping").await.unwrap();
+        a.shutdown().await.unwrap();
+        let mut received = Vec::new();
+        b.read_to_end(&mut received).await.unwrap();
+        assert_eq!(received, b"ping");
+    }
+}
@@ -4,15 +4,60 @@
 //! framework.
 //!
 //! See the `examples` directory for more complete usage examples.
+//!
+//! On platforms other than macOS and iOS, only the platform-independent modules ([`btsnoop`],
+//! [`codec`], [`error`], [`metrics`], [`transfer`] and, with the `record` and `serde` features,
+//! `record` and `serialize`), the socket streams of [`ChannelStream`] and the flow control of
+//! [`WriteStream`], the filters and blocklist of
+//! [`web_bluetooth`], the byte stream and simulated peripheral of [`profiles::nus`], and the value
+//! decoders of [`profiles::dis`] and [`profiles::battery`] are available.
 
+pub mod btsnoop;
+#[cfg(target_vendor = "apple")]
 mod central_manager;
+pub mod codec;
 pub mod error;
+mod l2cap_stream;
+pub mod metrics;
+#[cfg(any(target_vendor = "apple", test))]
+mod notification;
+#[cfg(target_vendor = "apple")]
 mod peripheral;
+#[cfg(not(target_vendor = "apple"))]
+mod portable;
+pub mod profiles;
+#[cfg(feature = "record")]
+pub mod record;
+#[cfg(feature = "serde")]
+pub mod serialize;
+#[cfg(any(target_vendor = "apple", test))]
+mod shared;
+#[cfg(all(target_vendor = "apple", feature = "tracing"))]
+pub mod trace;
+#[cfg(all(target_vendor = "apple", not(feature = "tracing")))]
+mod trace;
+pub mod transfer;
+#[cfg(target_vendor = "apple")]
 mod util;
+pub mod web_bluetooth;
+mod write_stream;
 
+#[cfg(target_vendor = "apple")]
 pub use central_manager::*;
+#[cfg(target_vendor = "apple")]
 pub use corebluetooth::{
     Central, Characteristic, ConnectPeripheralOptions, Descriptor, L2capChannel, Service,
     advertisement_data, dispatch,
 };
+pub use l2cap_stream::*;
+#[cfg(target_vendor = "apple")]
+pub use notification::{DeliveryPolicy, DeliveryStats, NotificationReceiver};
+#[cfg(target_vendor = "apple")]
+pub use objc2_core_bluetooth::{CBConnectionEvent, CBManagerState};
+#[cfg(target_vendor = "apple")]
 pub use peripheral::*;
+#[cfg(not(target_vendor = "apple"))]
+pub use portable::{CBConnectionEvent, CBManagerState};
+#[cfg(target_vendor = "apple")]
+pub use shared::*;
+pub use write_stream::*;
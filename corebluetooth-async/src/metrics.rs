@@ -0,0 +1,822 @@
+//! Operational metrics for Bluetooth operations.
+//!
+//! [`CentralManagerAsync`] and [`PeripheralAsync`] report the outcome and latency of every
+//! operation, every notification received and dropped, and every disconnection to the
+//! process-wide [`MetricsSink`]. No sink is installed by default.
+//!
+//! [`AggregatingSink`] keeps counters and latency histograms for each peripheral in memory. With
+//! the `metrics` feature, [`MetricsCrateSink`] forwards measurements to the
+//! [`metrics`](https://docs.rs/metrics) crate.
+//!
+//! # Example
+//!
+//! ```
+//! use std::sync::Arc;
+//!
+//! use corebluetooth_async::metrics::{self, AggregatingSink, Operation};
+//!
+//! let sink = Arc::new(AggregatingSink::new());
+//! metrics::set_sink(sink.clone());
+//!
+//! // ... use the central manager ...
+//!
+//! for (peripheral, stats) in sink.snapshot() {
+//!     if let Some(reads) = stats.operations.get(&Operation::ReadCharacteristic) {
+//!         println!("{peripheral}: {} reads, mean {:?}", reads.count, reads.latency.mean());
+//!     }
+//! }
+//! ```
+//!
+//! [`CentralManagerAsync`]: crate::CentralManagerAsync
+//! [`PeripheralAsync`]: crate::PeripheralAsync
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+use uuid::Uuid;
+
+use crate::error::ErrorKind;
+
+/// An operation whose outcome and latency are measured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Operation {
+    /// Connecting to a peripheral.
+    Connect,
+    /// Discovering services.
+    DiscoverServices,
+    /// Discovering included services.
+    DiscoverIncludedServices,
+    /// Discovering characteristics.
+    DiscoverCharacteristics,
+    /// Discovering descriptors.
+    DiscoverDescriptors,
+    /// Reading a characteristic value.
+    ReadCharacteristic,
+    /// Reading a descriptor value.
+    ReadDescriptor,
+    /// Writing a characteristic value.
+    WriteCharacteristic,
+    /// Writing a descriptor value.
+    WriteDescriptor,
+    /// Enabling or disabling notifications.
+    SetNotify,
+    /// Reading the RSSI.
+    ReadRssi,
+    /// Opening an L2CAP channel.
+    OpenL2capChannel,
+}
+
+impl Operation {
+    /// A short, stable name for the operation, suitable for use as a metric label.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Operation::Connect => "connect",
+            Operation::DiscoverServices => "discover_services",
+            Operation::DiscoverIncludedServices => "discover_included_services",
+            Operation::DiscoverCharacteristics => "discover_characteristics",
+            Operation::DiscoverDescriptors => "discover_descriptors",
+            Operation::ReadCharacteristic => "read_characteristic",
+            Operation::ReadDescriptor => "read_descriptor",
+            Operation::WriteCharacteristic => "write_characteristic",
+            Operation::WriteDescriptor => "write_descriptor",
+            Operation::SetNotify => "set_notify",
+            Operation::ReadRssi => "read_rssi",
+            Operation::OpenL2capChannel => "open_l2cap_channel",
+        }
+    }
+}
+
+impl Display for Operation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Receives measurements from [`CentralManagerAsync`][crate::CentralManagerAsync] and
+/// [`PeripheralAsync`][crate::PeripheralAsync].
+///
+/// Methods are called from the dispatch queue of the central manager, so they should return
+/// quickly. Every method has an empty default implementation.
+pub trait MetricsSink: Send + Sync {
+    /// Called when an operation completes, with the kind of error if it failed.
+    fn operation(
+        &self,
+        peripheral: Uuid,
+        operation: Operation,
+        latency: Duration,
+        error: Option<ErrorKind>,
+    ) {
+        let _ = (peripheral, operation, latency, error);
+    }
+
+    /// Called when a scan discovers a peripheral.
+    fn discovered(&self, peripheral: Uuid, rssi: i16) {
+        let _ = (peripheral, rssi);
+    }
+
+    /// Called when a value is received for a characteristic that is notifying.
+    fn notification(&self, peripheral: Uuid, len: usize) {
+        let _ = (peripheral, len);
+    }
+
+    /// Called when a subscriber falls behind and a value is discarded.
+    fn notification_dropped(&self, peripheral: Uuid) {
+        let _ = peripheral;
+    }
+
+    /// Called when a peripheral disconnects, with the kind of error if the disconnection was not
+    /// requested.
+    fn disconnected(&self, peripheral: Uuid, reason: Option<ErrorKind>) {
+        let _ = (peripheral, reason);
+    }
+}
+
+static SINK: RwLock<Option<Arc<dyn MetricsSink>>> = RwLock::new(None);
+
+/// Sets the process-wide metrics sink, returning the previous sink.
+pub fn set_sink(sink: Arc<dyn MetricsSink>) -> Option<Arc<dyn MetricsSink>> {
+    let mut current = SINK.write().unwrap_or_else(|err| err.into_inner());
+    current.replace(sink)
+}
+
+/// Removes the process-wide metrics sink, returning it.
+pub fn clear_sink() -> Option<Arc<dyn MetricsSink>> {
+    let mut current = SINK.write().unwrap_or_else(|err| err.into_inner());
+    current.take()
+}
+
+/// Calls `func` with the process-wide metrics sink, if one is installed.
+///
+/// The lock is released before `func` is called, so the sink may replace itself.
+#[cfg(any(target_vendor = "apple", test))]
+pub(crate) fn with_sink(func: impl FnOnce(&dyn MetricsSink)) {
+    let sink = SINK.read().unwrap_or_else(|err| err.into_inner()).clone();
+    if let Some(sink) = sink {
+        func(&*sink);
+    }
+}
+
+/// Awaits `future`, reporting its latency and outcome as `operation` on `peripheral`.
+#[cfg(any(target_vendor = "apple", test))]
+pub(crate) async fn measure<T>(
+    peripheral: Uuid,
+    operation: Operation,
+    future: impl Future<Output = crate::error::Result<T>>,
+) -> crate::error::Result<T> {
+    let start = std::time::Instant::now();
+    let result = future.await;
+    let error = result.as_ref().err().map(|err| err.kind());
+    with_sink(|sink| sink.operation(peripheral, operation, start.elapsed(), error));
+    result
+}
+
+/// A histogram of latencies with exponentially sized buckets.
+///
+/// Bucket `i` counts latencies of less than 2<sup>`i`</sup> milliseconds that did not fit in a
+/// smaller bucket; the last bucket counts everything else.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LatencyHistogram {
+    /// The number of latencies in each bucket.
+    pub buckets: [u64; Self::BUCKETS],
+    /// The number of latencies recorded.
+    pub count: u64,
+    /// The sum of all latencies recorded.
+    pub sum: Duration,
+    /// The smallest latency recorded.
+    pub min: Option<Duration>,
+    /// The largest latency recorded.
+    pub max: Option<Duration>,
+}
+
+impl LatencyHistogram {
+    /// The number of buckets. The last bucket counts latencies of 2<sup>16</sup> ms (about 65
+    /// seconds) or more.
+    pub const BUCKETS: usize = 18;
+
+    /// Records a latency.
+    pub fn record(&mut self, latency: Duration) {
+        let millis = latency.as_millis();
+        let bucket = if millis == 0 {
+            0
+        } else {
+            ((u128::BITS - millis.leading_zeros()) as usize).min(Self::BUCKETS - 1)
+        };
+        self.buckets[bucket] += 1;
+        self.count += 1;
+        self.sum += latency;
+        self.min = Some(self.min.map_or(latency, |min| min.min(latency)));
+        self.max = Some(self.max.map_or(latency, |max| max.max(latency)));
+    }
+
+    /// The mean latency, if any latencies have been recorded.
+    pub fn mean(&self) -> Option<Duration> {
+        (self.count > 0)
+            .then(|| Duration::from_nanos((self.sum.as_nanos() / self.count as u128) as u64))
+    }
+
+    /// The upper bound of the bucket containing the given quantile (between 0 and 1), if any
+    /// latencies have been recorded.
+    ///
+    /// Returns `None` for quantiles in the last bucket, which has no upper bound.
+    pub fn quantile_upper_bound(&self, quantile: f64) -> Option<Duration> {
+        if self.count == 0 {
+            return None;
+        }
+
+        let target = (quantile.clamp(0.0, 1.0) * self.count as f64)
+            .ceil()
+            .max(1.0) as u64;
+        let mut seen = 0;
+        for (i, count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= target {
+                return (i < Self::BUCKETS - 1).then(|| Duration::from_millis(1 << i));
+            }
+        }
+        None
+    }
+}
+
+/// Statistics for one kind of operation on one peripheral.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OperationStats {
+    /// The number of times the operation completed.
+    pub count: u64,
+    /// The number of failures, by kind of error.
+    pub errors: HashMap<ErrorKind, u64>,
+    /// The latency of the operation, including failures.
+    pub latency: LatencyHistogram,
+}
+
+/// Statistics for one peripheral.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PeripheralStats {
+    /// Statistics for each kind of operation.
+    pub operations: HashMap<Operation, OperationStats>,
+    /// The number of times the peripheral was discovered by a scan.
+    pub discoveries: u64,
+    /// The RSSI of the most recent discovery.
+    pub last_rssi: Option<i16>,
+    /// The number of notifications received.
+    pub notifications: u64,
+    /// The total length of the notifications received, in bytes.
+    pub notification_bytes: u64,
+    /// The number of notifications discarded because a subscriber fell behind.
+    pub notifications_dropped: u64,
+    /// The number of disconnections, by kind of error. Requested disconnections are counted
+    /// under `None`.
+    pub disconnects: HashMap<Option<ErrorKind>, u64>,
+}
+
+/// A [`MetricsSink`] that aggregates measurements for each peripheral in memory.
+#[derive(Debug, Default)]
+pub struct AggregatingSink {
+    peripherals: Mutex<HashMap<Uuid, PeripheralStats>>,
+}
+
+impl AggregatingSink {
+    /// Creates an empty sink.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the statistics for each peripheral.
+    pub fn snapshot(&self) -> HashMap<Uuid, PeripheralStats> {
+        self.peripherals.lock().unwrap().clone()
+    }
+
+    /// Returns the statistics for one peripheral.
+    pub fn peripheral(&self, peripheral: Uuid) -> Option<PeripheralStats> {
+        self.peripherals.lock().unwrap().get(&peripheral).cloned()
+    }
+
+    /// Discards all statistics.
+    pub fn reset(&self) {
+        self.peripherals.lock().unwrap().clear();
+    }
+
+    fn update(&self, peripheral: Uuid, func: impl FnOnce(&mut PeripheralStats)) {
+        func(
+            self.peripherals
+                .lock()
+                .unwrap()
+                .entry(peripheral)
+                .or_default(),
+        );
+    }
+}
+
+impl MetricsSink for AggregatingSink {
+    fn operation(
+        &self,
+        peripheral: Uuid,
+        operation: Operation,
+        latency: Duration,
+        error: Option<ErrorKind>,
+    ) {
+        self.update(peripheral, |stats| {
+            let stats = stats.operations.entry(operation).or_default();
+            stats.count += 1;
+            stats.latency.record(latency);
+            if let Some(error) = error {
+                *stats.errors.entry(error).or_default() += 1;
+            }
+        });
+    }
+
+    fn discovered(&self, peripheral: Uuid, rssi: i16) {
+        self.update(peripheral, |stats| {
+            stats.discoveries += 1;
+            stats.last_rssi = Some(rssi);
+        });
+    }
+
+    fn notification(&self, peripheral: Uuid, len: usize) {
+        self.update(peripheral, |stats| {
+            stats.notifications += 1;
+            stats.notification_bytes += len as u64;
+        });
+    }
+
+    fn notification_dropped(&self, peripheral: Uuid) {
+        self.update(peripheral, |stats| stats.notifications_dropped += 1);
+    }
+
+    fn disconnected(&self, peripheral: Uuid, reason: Option<ErrorKind>) {
+        self.update(peripheral, |stats| {
+            *stats.disconnects.entry(reason).or_default() += 1;
+        });
+    }
+}
+
+/// A [`MetricsSink`] that records measurements with the [`metrics`](https://docs.rs/metrics)
+/// crate.
+///
+/// Every metric is labeled with the `peripheral` identifier. The following metrics are recorded:
+///
+/// - `corebluetooth.operations` (counter), labeled with `operation` and `result` (`ok` or the
+///   kind of error)
+/// - `corebluetooth.operation.duration` (histogram, in seconds), labeled with `operation`
+/// - `corebluetooth.discoveries` (counter)
+/// - `corebluetooth.rssi` (gauge)
+/// - `corebluetooth.notifications` (counter)
+/// - `corebluetooth.notification.bytes` (counter)
+/// - `corebluetooth.notifications.dropped` (counter)
+/// - `corebluetooth.disconnects` (counter), labeled with `reason` (`requested` or the kind of
+///   error)
+#[cfg(feature = "metrics")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MetricsCrateSink;
+
+#[cfg(feature = "metrics")]
+impl MetricsSink for MetricsCrateSink {
+    fn operation(
+        &self,
+        peripheral: Uuid,
+        operation: Operation,
+        latency: Duration,
+        error: Option<ErrorKind>,
+    ) {
+        let peripheral = peripheral.to_string();
+        let result = error.map_or_else(|| "ok".to_owned(), |error| format!("{error:?}"));
+        ::metrics::counter!(
+            "corebluetooth.operations",
+            "peripheral" => peripheral.clone(),
+            "operation" => operation.as_str(),
+            "result" => result,
+        )
+        .increment(1);
+        ::metrics::histogram!(
+            "corebluetooth.operation.duration",
+            "peripheral" => peripheral,
+            "operation" => operation.as_str(),
+        )
+        .record(latency);
+    }
+
+    fn discovered(&self, peripheral: Uuid, rssi: i16) {
+        let peripheral = peripheral.to_string();
+        ::metrics::counter!("corebluetooth.discoveries", "peripheral" => peripheral.clone())
+            .increment(1);
+        ::metrics::gauge!("corebluetooth.rssi", "peripheral" => peripheral).set(rssi);
+    }
+
+    fn notification(&self, peripheral: Uuid, len: usize) {
+        let peripheral = peripheral.to_string();
+        ::metrics::counter!("corebluetooth.notifications", "peripheral" => peripheral.clone())
+            .increment(1);
+        ::metrics::counter!("corebluetooth.notification.bytes", "peripheral" => peripheral)
+            .increment(len as u64);
+    }
+
+    fn notification_dropped(&self, peripheral: Uuid) {
+        ::metrics::counter!(
+            "corebluetooth.notifications.dropped",
+            "peripheral" => peripheral.to_string(),
+        )
+        .increment(1);
+    }
+
+    fn disconnected(&self, peripheral: Uuid, reason: Option<ErrorKind>) {
+        let reason = reason.map_or_else(|| "requested".to_owned(), |reason| format!("{reason:?}"));
+        ::metrics::counter!(
+            "corebluetooth.disconnects",
+            "peripheral" => peripheral.to_string(),
+            "reason" => reason,
+        )
+        .increment(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_lite::future;
+
+    use super::*;
+    use crate::error::Error;
+
+    /// Serializes the tests that install the process-wide sink.
+    static GLOBAL: Mutex<()> = Mutex::new(());
+
+    /// A sink that records every call.
+    #[derive(Default)]
+    struct FakeSink {
+        calls: Mutex<Vec<String>>,
+    }
+
+    impl FakeSink {
+        fn calls(&self) -> Vec<String> {
+            self.calls.lock().unwrap().clone()
+        }
+    }
+
+    impl MetricsSink for FakeSink {
+        fn operation(
+            &self,
+            peripheral: Uuid,
+            operation: Operation,
+            _latency: Duration,
+            error: Option<ErrorKind>,
+        ) {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(format!("{peripheral} {operation} {error:?}"));
+        }
+
+        fn notification(&self, peripheral: Uuid, len: usize) {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(format!("{peripheral} notification {len}"));
+        }
+    }
+
+    /// A sink that replaces itself with `next` the first time it is called.
+    struct ReplacingSink {
+        next: Arc<FakeSink>,
+    }
+
+    impl MetricsSink for ReplacingSink {
+        fn notification(&self, _peripheral: Uuid, _len: usize) {
+            set_sink(self.next.clone());
+        }
+    }
+
+    fn with_global<R>(func: impl FnOnce() -> R) -> R {
+        let _guard = GLOBAL.lock().unwrap_or_else(|err| err.into_inner());
+        let previous = clear_sink();
+        let result = func();
+        match previous {
+            Some(previous) => set_sink(previous),
+            None => clear_sink(),
+        };
+        result
+    }
+
+    #[test]
+    fn with_sink_without_sink_does_nothing() {
+        with_global(|| {
+            let mut called = false;
+            with_sink(|_| called = true);
+            assert!(!called);
+        });
+    }
+
+    #[test]
+    fn set_sink_returns_previous_sink() {
+        with_global(|| {
+            let first = Arc::new(FakeSink::default());
+            assert!(set_sink(first.clone()).is_none());
+            let previous = set_sink(Arc::new(FakeSink::default())).unwrap();
+            previous.notification(Uuid::nil(), 1);
+            assert_eq!(first.calls().len(), 1);
+            assert!(clear_sink().is_some());
+            assert!(clear_sink().is_none());
+        });
+    }
+
+    #[test]
+    fn sink_can_replace_itself() {
+        with_global(|| {
+            let next = Arc::new(FakeSink::default());
+            set_sink(Arc::new(ReplacingSink { next: next.clone() }));
+
+            with_sink(|sink| sink.notification(Uuid::nil(), 1));
+            with_sink(|sink| sink.notification(Uuid::nil(), 2));
+            assert_eq!(next.calls(), [format!("{} notification 2", Uuid::nil())]);
+        });
+    }
+
+    #[test]
+    fn measure_reports_outcome() {
+        with_global(|| {
+            let sink = Arc::new(FakeSink::default());
+            set_sink(sink.clone());
+            let peripheral = Uuid::from_u128(1);
+
+            let ok = future::block_on(measure(peripheral, Operation::ReadRssi, async {
+                Ok::<_, Error>(-40)
+            }));
+            assert_eq!(ok.unwrap(), -40);
+
+            let err = future::block_on(measure(peripheral, Operation::Connect, async {
+                Err::<(), _>(Error::from(ErrorKind::Canceled))
+            }));
+            assert_eq!(err.unwrap_err().kind(), ErrorKind::Canceled);
+
+            assert_eq!(
+                sink.calls(),
+                [
+                    format!("{peripheral} read_rssi None"),
+                    format!("{peripheral} connect Some(Canceled)"),
+                ]
+            );
+        });
+    }
+
+    #[test]
+    fn histogram_buckets() {
+        let mut histogram = LatencyHistogram::default();
+        assert_eq!(histogram.mean(), None);
+        assert_eq!(histogram.quantile_upper_bound(0.5), None);
+
+        histogram.record(Duration::from_micros(500));
+        histogram.record(Duration::from_millis(1));
+        histogram.record(Duration::from_millis(3));
+        histogram.record(Duration::from_secs(3600));
+        assert_eq!(histogram.buckets[0], 1);
+        assert_eq!(histogram.buckets[1], 1);
+        assert_eq!(histogram.buckets[2], 1);
+        assert_eq!(histogram.buckets[LatencyHistogram::BUCKETS - 1], 1);
+        assert_eq!(histogram.count, 4);
+        assert_eq!(histogram.min, Some(Duration::from_micros(500)));
+        assert_eq!(histogram.max, Some(Duration::from_secs(3600)));
+
+        assert_eq!(
+            histogram.quantile_upper_bound(0.0),
+            Some(Duration::from_millis(1))
+        );
+        assert_eq!(
+            histogram.quantile_upper_bound(0.5),
+            Some(Duration::from_millis(2))
+        );
+        assert_eq!(
+            histogram.quantile_upper_bound(0.75),
+            Some(Duration::from_millis(4))
+        );
+        assert_eq!(histogram.quantile_upper_bound(1.0), None);
+    }
+
+    #[test]
+    fn histogram_mean() {
+        let mut histogram = LatencyHistogram::default();
+        histogram.record(Duration::from_millis(10));
+        histogram.record(Duration::from_millis(20));
+        assert_eq!(histogram.mean(), Some(Duration::from_millis(15)));
+    }
+
+    #[test]
+    fn aggregating_sink_counts_per_peripheral() {
+        let sink = AggregatingSink::new();
+        let a = Uuid::from_u128(1);
+        let b = Uuid::from_u128(2);
+
+        sink.operation(
+            a,
+            Operation::ReadCharacteristic,
+            Duration::from_millis(5),
+            None,
+        );
+        sink.operation(
+            a,
+            Operation::ReadCharacteristic,
+            Duration::from_millis(7),
+            Some(ErrorKind::Canceled),
+        );
+        sink.discovered(a, -60);
+        sink.discovered(a, -50);
+        sink.notification(a, 20);
+        sink.notification(a, 4);
+        sink.notification_dropped(a);
+        sink.disconnected(a, None);
+        sink.disconnected(b, Some(ErrorKind::Other));
+
+        let stats = sink.peripheral(a).unwrap();
+        let reads = &stats.operations[&Operation::ReadCharacteristic];
+        assert_eq!(reads.count, 2);
+        assert_eq!(reads.errors[&ErrorKind::Canceled], 1);
+        assert_eq!(reads.latency.count, 2);
+        assert_eq!(stats.discoveries, 2);
+        assert_eq!(stats.last_rssi, Some(-50));
+        assert_eq!(stats.notifications, 2);
+        assert_eq!(stats.notification_bytes, 24);
+        assert_eq!(stats.notifications_dropped, 1);
+        assert_eq!(stats.disconnects[&None], 1);
+
+        let snapshot = sink.snapshot();
+        assert_eq!(snapshot.len(), 2);
+        assert_eq!(snapshot[&b].disconnects[&Some(ErrorKind::Other)], 1);
+
+        sink.reset();
+        assert!(sink.snapshot().is_empty());
+        assert!(sink.peripheral(a).is_none());
+    }
+
+    #[cfg(feature = "metrics")]
+    mod metrics_crate {
+        use ::metrics::{
+            Counter, CounterFn, Gauge, GaugeFn, Histogram, HistogramFn, Key, KeyName, Metadata,
+            Recorder, SharedString, Unit,
+        };
+
+        use super::*;
+
+        /// A metric handle that logs every update as `name{labels} action value`.
+        struct Handle {
+            key: String,
+            log: Arc<Mutex<Vec<String>>>,
+        }
+
+        impl Handle {
+            fn push(&self, action: &str, value: impl Display) {
+                let entry = format!("{} {action} {value}", self.key);
+                self.log.lock().unwrap().push(entry);
+            }
+        }
+
+        impl CounterFn for Handle {
+            fn increment(&self, value: u64) {
+                self.push("increment", value);
+            }
+
+            fn absolute(&self, value: u64) {
+                self.push("absolute", value);
+            }
+        }
+
+        impl GaugeFn for Handle {
+            fn increment(&self, value: f64) {
+                self.push("increment", value);
+            }
+
+            fn decrement(&self, value: f64) {
+                self.push("decrement", value);
+            }
+
+            fn set(&self, value: f64) {
+                self.push("set", value);
+            }
+        }
+
+        impl HistogramFn for Handle {
+            fn record(&self, value: f64) {
+                self.push("record", value);
+            }
+        }
+
+        /// A recorder that logs every update to the metrics it registers.
+        #[derive(Default)]
+        struct FakeRecorder {
+            log: Arc<Mutex<Vec<String>>>,
+        }
+
+        impl FakeRecorder {
+            fn handle(&self, key: &Key) -> Arc<Handle> {
+                let labels = key
+                    .labels()
+                    .map(|label| format!("{}={}", label.key(), label.value()))
+                    .collect::<Vec<_>>()
+                    .join(",");
+                Arc::new(Handle {
+                    key: format!("{}{{{labels}}}", key.name()),
+                    log: self.log.clone(),
+                })
+            }
+
+            fn log(&self) -> Vec<String> {
+                self.log.lock().unwrap().clone()
+            }
+        }
+
+        impl Recorder for FakeRecorder {
+            fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+            fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+            fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+            fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+                Counter::from_arc(self.handle(key))
+            }
+
+            fn register_gauge(&self, key: &Key, _: &Metadata<'_>) -> Gauge {
+                Gauge::from_arc(self.handle(key))
+            }
+
+            fn register_histogram(&self, key: &Key, _: &Metadata<'_>) -> Histogram {
+                Histogram::from_arc(self.handle(key))
+            }
+        }
+
+        #[test]
+        fn records_operations() {
+            let recorder = FakeRecorder::default();
+            let peripheral = Uuid::nil();
+            ::metrics::with_local_recorder(&recorder, || {
+                let sink = MetricsCrateSink;
+                sink.operation(
+                    peripheral,
+                    Operation::WriteCharacteristic,
+                    Duration::from_millis(250),
+                    None,
+                );
+                sink.operation(
+                    peripheral,
+                    Operation::Connect,
+                    Duration::from_secs(2),
+                    Some(ErrorKind::Canceled),
+                );
+            });
+            assert_eq!(
+                recorder.log(),
+                [
+                    format!(
+                        "corebluetooth.operations{{peripheral={peripheral},\
+                         operation=write_characteristic,result=ok}} increment 1"
+                    ),
+                    format!(
+                        "corebluetooth.operation.duration{{peripheral={peripheral},\
+                         operation=write_characteristic}} record 0.25"
+                    ),
+                    format!(
+                        "corebluetooth.operations{{peripheral={peripheral},\
+                         operation=connect,result=Canceled}} increment 1"
+                    ),
+                    format!(
+                        "corebluetooth.operation.duration{{peripheral={peripheral},\
+                         operation=connect}} record 2"
+                    ),
+                ]
+            );
+        }
+
+        #[test]
+        fn records_peripheral_events() {
+            let recorder = FakeRecorder::default();
+            let peripheral = Uuid::nil();
+            ::metrics::with_local_recorder(&recorder, || {
+                let sink = MetricsCrateSink;
+                sink.discovered(peripheral, -70);
+                sink.notification(peripheral, 20);
+                sink.notification_dropped(peripheral);
+                sink.disconnected(peripheral, None);
+                sink.disconnected(peripheral, Some(ErrorKind::Other));
+            });
+            assert_eq!(
+                recorder.log(),
+                [
+                    format!("corebluetooth.discoveries{{peripheral={peripheral}}} increment 1"),
+                    format!("corebluetooth.rssi{{peripheral={peripheral}}} set -70"),
+                    format!("corebluetooth.notifications{{peripheral={peripheral}}} increment 1"),
+                    format!(
+                        "corebluetooth.notification.bytes{{peripheral={peripheral}}} increment 20"
+                    ),
+                    format!(
+                        "corebluetooth.notifications.dropped{{peripheral={peripheral}}} increment 1"
+                    ),
+                    format!(
+                        "corebluetooth.disconnects{{peripheral={peripheral},reason=requested}} \
+                         increment 1"
+                    ),
+                    format!(
+                        "corebluetooth.disconnects{{peripheral={peripheral},reason=Other}} \
+                         increment 1"
+                    ),
+                ]
+            );
+        }
+    }
+}
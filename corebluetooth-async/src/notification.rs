@@ -0,0 +1,332 @@
+//! Buffered delivery of characteristic value updates.
+
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+use futures_core::Stream;
+
+/// How undelivered values are buffered for a single subscription.
+///
+/// CoreBluetooth provides no flow control for notifications: values are delivered to the delegate
+/// as fast as the peripheral sends them, and there is no way to make the peripheral wait. The
+/// policy therefore cannot apply backpressure; it only determines which values are kept when a
+/// subscriber falls behind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DeliveryPolicy {
+    /// Buffer at most `n` values. Once the buffer is full, new values are discarded (and counted
+    /// as dropped) until the subscriber catches up, so buffered values are never overwritten.
+    DropNewest(usize),
+    /// Buffer every value until it is received.
+    Unbounded,
+    /// Buffer at most `n` values. Once the buffer is full, the oldest buffered value is discarded
+    /// (and counted as dropped) to make room for each new value.
+    DropOldest(usize),
+    /// Keep only the most recent value.
+    LatestOnly,
+}
+
+impl Default for DeliveryPolicy {
+    fn default() -> Self {
+        DeliveryPolicy::DropOldest(16)
+    }
+}
+
+/// Delivery statistics for a subscription.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DeliveryStats {
+    /// The number of values received from the peripheral.
+    pub received: u64,
+    /// The number of values delivered to the subscriber.
+    pub delivered: u64,
+    /// The number of values discarded because of the delivery policy.
+    pub dropped: u64,
+    /// The number of values currently buffered.
+    pub queued: usize,
+    /// The largest number of values that have been buffered at once.
+    pub high_water_mark: usize,
+}
+
+struct Shared<T> {
+    policy: DeliveryPolicy,
+    queue: VecDeque<T>,
+    waker: Option<Waker>,
+    sender_closed: bool,
+    receiver_closed: bool,
+    stats: DeliveryStats,
+}
+
+/// Creates a new subscription channel with the given delivery policy.
+pub(crate) fn channel<T>(
+    policy: DeliveryPolicy,
+) -> (NotificationSender<T>, NotificationReceiver<T>) {
+    let shared = Arc::new(Mutex::new(Shared {
+        policy,
+        queue: VecDeque::new(),
+        waker: None,
+        sender_closed: false,
+        receiver_closed: false,
+        stats: DeliveryStats::default(),
+    }));
+
+    (
+        NotificationSender {
+            shared: shared.clone(),
+        },
+        NotificationReceiver { shared },
+    )
+}
+
+/// The outcome of [`NotificationSender::send()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SendStatus {
+    /// The value was buffered.
+    Queued,
+    /// The value was buffered, or rejected, and a value was discarded because of the delivery
+    /// policy.
+    Dropped,
+    /// The receiver has been dropped.
+    Closed,
+}
+
+/// The sending half of a subscription channel.
+pub(crate) struct NotificationSender<T> {
+    shared: Arc<Mutex<Shared<T>>>,
+}
+
+impl<T> NotificationSender<T> {
+    /// Enqueues a value according to the channel's delivery policy.
+    pub fn send(&self, value: T) -> SendStatus {
+        let mut shared = self.shared.lock().unwrap();
+        if shared.receiver_closed {
+            return SendStatus::Closed;
+        }
+
+        shared.stats.received += 1;
+        let dropped = shared.stats.dropped;
+        match shared.policy {
+            DeliveryPolicy::DropNewest(cap) => {
+                if shared.queue.len() < cap {
+                    shared.queue.push_back(value);
+                } else {
+                    shared.stats.dropped += 1;
+                }
+            }
+            DeliveryPolicy::Unbounded => shared.queue.push_back(value),
+            DeliveryPolicy::DropOldest(cap) => {
+                if cap == 0 {
+                    shared.stats.dropped += 1;
+                } else {
+                    if shared.queue.len() >= cap {
+                        shared.queue.pop_front();
+                        shared.stats.dropped += 1;
+                    }
+                    shared.queue.push_back(value);
+                }
+            }
+            DeliveryPolicy::LatestOnly => {
+                if shared.queue.pop_front().is_some() {
+                    shared.stats.dropped += 1;
+                }
+                shared.queue.push_back(value);
+            }
+        }
+
+        shared.stats.queued = shared.queue.len();
+        shared.stats.high_water_mark = shared.stats.high_water_mark.max(shared.queue.len());
+
+        let status = if shared.stats.dropped > dropped {
+            SendStatus::Dropped
+        } else {
+            SendStatus::Queued
+        };
+
+        if let Some(waker) = shared.waker.take() {
+            waker.wake();
+        }
+
+        status
+    }
+}
+
+impl<T> Drop for NotificationSender<T> {
+    fn drop(&mut self) {
+        let mut shared = self.shared.lock().unwrap();
+        shared.sender_closed = true;
+        if let Some(waker) = shared.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// A stream of values delivered according to a [`DeliveryPolicy`].
+///
+/// The stream ends when the peripheral that produces the values is dropped.
+pub struct NotificationReceiver<T> {
+    shared: Arc<Mutex<Shared<T>>>,
+}
+
+impl<T> std::fmt::Debug for NotificationReceiver<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let shared = self.shared.lock().unwrap();
+        f.debug_struct("NotificationReceiver")
+            .field("policy", &shared.policy)
+            .field("stats", &shared.stats)
+            .finish()
+    }
+}
+
+impl<T> NotificationReceiver<T> {
+    /// Receives the next value, waiting until one is available.
+    ///
+    /// Returns `None` once the stream has ended.
+    pub async fn recv(&mut self) -> Option<T> {
+        std::future::poll_fn(|cx| Pin::new(&mut *self).poll_next(cx)).await
+    }
+
+    /// Receives the next value if one is immediately available.
+    pub fn try_recv(&mut self) -> Option<T> {
+        let mut shared = self.shared.lock().unwrap();
+        let value = shared.queue.pop_front();
+        if value.is_some() {
+            shared.stats.delivered += 1;
+            shared.stats.queued = shared.queue.len();
+        }
+        value
+    }
+
+    /// Returns the delivery policy of this subscription.
+    pub fn policy(&self) -> DeliveryPolicy {
+        self.shared.lock().unwrap().policy
+    }
+
+    /// Returns the delivery statistics of this subscription.
+    pub fn stats(&self) -> DeliveryStats {
+        self.shared.lock().unwrap().stats
+    }
+}
+
+impl<T> Stream for NotificationReceiver<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut shared = self.shared.lock().unwrap();
+        if let Some(value) = shared.queue.pop_front() {
+            shared.stats.delivered += 1;
+            shared.stats.queued = shared.queue.len();
+            Poll::Ready(Some(value))
+        } else if shared.sender_closed {
+            Poll::Ready(None)
+        } else {
+            shared.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let shared = self.shared.lock().unwrap();
+        if shared.sender_closed {
+            (shared.queue.len(), Some(shared.queue.len()))
+        } else {
+            (shared.queue.len(), None)
+        }
+    }
+}
+
+impl<T> Drop for NotificationReceiver<T> {
+    fn drop(&mut self) {
+        let mut shared = self.shared.lock().unwrap();
+        shared.receiver_closed = true;
+        shared.queue.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_lite::{StreamExt, future};
+
+    use super::*;
+
+    fn send_all(sender: &NotificationSender<u32>, values: impl IntoIterator<Item = u32>) {
+        for value in values {
+            assert_ne!(sender.send(value), SendStatus::Closed);
+        }
+    }
+
+    fn drain(receiver: &mut NotificationReceiver<u32>) -> Vec<u32> {
+        std::iter::from_fn(|| receiver.try_recv()).collect()
+    }
+
+    #[test]
+    fn drop_newest_keeps_oldest_values() {
+        let (sender, mut receiver) = channel(DeliveryPolicy::DropNewest(2));
+        assert_eq!(receiver.policy(), DeliveryPolicy::DropNewest(2));
+        assert_eq!(sender.send(1), SendStatus::Queued);
+        assert_eq!(sender.send(2), SendStatus::Queued);
+        assert_eq!(sender.send(3), SendStatus::Dropped);
+        assert_eq!(drain(&mut receiver), [1, 2]);
+
+        let stats = receiver.stats();
+        assert_eq!(stats.received, 3);
+        assert_eq!(stats.delivered, 2);
+        assert_eq!(stats.dropped, 1);
+        assert_eq!(stats.high_water_mark, 2);
+    }
+
+    #[test]
+    fn drop_oldest_keeps_newest_values() {
+        let (sender, mut receiver) = channel(DeliveryPolicy::DropOldest(2));
+        send_all(&sender, 1..=4);
+        assert_eq!(drain(&mut receiver), [3, 4]);
+        assert_eq!(receiver.stats().dropped, 2);
+    }
+
+    #[test]
+    fn drop_oldest_with_zero_capacity_drops_everything() {
+        let (sender, mut receiver) = channel(DeliveryPolicy::DropOldest(0));
+        assert_eq!(sender.send(1), SendStatus::Dropped);
+        assert!(drain(&mut receiver).is_empty());
+    }
+
+    #[test]
+    fn latest_only_keeps_last_value() {
+        let (sender, mut receiver) = channel(DeliveryPolicy::LatestOnly);
+        send_all(&sender, 1..=3);
+        assert_eq!(drain(&mut receiver), [3]);
+        assert_eq!(receiver.stats().dropped, 2);
+    }
+
+    #[test]
+    fn unbounded_keeps_every_value() {
+        let (sender, mut receiver) = channel(DeliveryPolicy::Unbounded);
+        send_all(&sender, 0..1000);
+        assert_eq!(drain(&mut receiver), (0..1000).collect::<Vec<_>>());
+        assert_eq!(receiver.stats().dropped, 0);
+        assert_eq!(receiver.stats().high_water_mark, 1000);
+    }
+
+    #[test]
+    fn stream_ends_after_sender_is_dropped() {
+        let (sender, receiver) = channel(DeliveryPolicy::Unbounded);
+        send_all(&sender, [1, 2]);
+        drop(sender);
+        assert_eq!(future::block_on(receiver.collect::<Vec<_>>()), [1, 2]);
+    }
+
+    #[test]
+    fn send_wakes_pending_receiver() {
+        let (sender, mut receiver) = channel(DeliveryPolicy::default());
+        let thread = std::thread::spawn(move || future::block_on(receiver.recv()));
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        sender.send(7);
+        assert_eq!(thread.join().unwrap(), Some(7));
+    }
+
+    #[test]
+    fn send_after_receiver_is_dropped_is_closed() {
+        let (sender, receiver) = channel(DeliveryPolicy::default());
+        drop(receiver);
+        assert_eq!(sender.send(1), SendStatus::Closed);
+    }
+}
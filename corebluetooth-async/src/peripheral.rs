@@ -16,7 +16,17 @@ use objc2::rc::Retained;
 use objc2_core_bluetooth::CBPeer;
 
 use crate::error::Result;
-use crate::util::{BroadcastReceiver, BroadcastSender, broadcast, watch};
+use crate::l2cap_stream::L2capStream;
+#[cfg(feature = "tokio")]
+use crate::l2cap_stream::TokioL2capStream;
+use crate::metrics::{self, Operation};
+use crate::notification::{
+    self, DeliveryPolicy, NotificationReceiver, NotificationSender, SendStatus,
+};
+use crate::trace::{event, instrument};
+use crate::transfer::Framing;
+use crate::util::{BroadcastReceiver, BroadcastSender, broadcast, record, watch};
+use crate::write_stream::{CharacteristicWriter, WriteStream};
 
 /// An asynchronous wrapper around a [`Peripheral`].
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -51,6 +61,18 @@ impl Deref for PeripheralAsync {
     }
 }
 
+/// Peripherals are serialized as their identifier and current name (see
+/// [`serialize::Peripheral`][crate::serialize::Peripheral]).
+#[cfg(feature = "serde")]
+impl serde::Serialize for PeripheralAsync {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serde::Serialize::serialize(&crate::serialize::Peripheral::from(self), serializer)
+    }
+}
+
 impl PeripheralAsync {
     /// Creates a new `PeripheralAsync` from a `Peripheral`.
     ///
@@ -81,9 +103,21 @@ impl PeripheralAsync {
     ///
     /// If `services` is provided, only services with those UUIDs will be discovered.
     pub async fn discover_services(&self, services: Option<&[BluetoothUuid]>) -> Result<()> {
-        self.inner.discover_services(services);
-        let mut receiver = self.delegate().service_discovery();
-        receiver.recv().await?
+        metrics::measure(
+            self.identifier(),
+            Operation::DiscoverServices,
+            instrument!(
+                async {
+                    self.inner.discover_services(services);
+                    let mut receiver = self.delegate().service_discovery();
+                    receiver.recv().await?
+                },
+                "discover_services",
+                peripheral = %self.identifier(),
+                services = ?services,
+            ),
+        )
+        .await
     }
 
     /// Returns a stream of service change events.
@@ -103,9 +137,21 @@ impl PeripheralAsync {
         service: &Service,
         services: Option<&[BluetoothUuid]>,
     ) -> Result<()> {
-        self.inner.discover_included_services(service, services);
-        let receiver = self.delegate().included_service_discovery(service.clone());
-        receiver.await?
+        metrics::measure(
+            self.identifier(),
+            Operation::DiscoverIncludedServices,
+            instrument!(
+                async {
+                    self.inner.discover_included_services(service, services);
+                    let receiver = self.delegate().included_service_discovery(service.clone());
+                    receiver.await?
+                },
+                "discover_included_services",
+                peripheral = %self.identifier(),
+                service = %service.uuid(),
+            ),
+        )
+        .await
     }
 
     /// Initiates discovery of the characteristics of a service.
@@ -120,10 +166,22 @@ impl PeripheralAsync {
         service: &Service,
         characteristics: Option<&[BluetoothUuid]>,
     ) -> Result<()> {
-        self.inner
-            .discover_characteristics(service, characteristics);
-        let receiver = self.delegate().characteristic_discovery(service.clone());
-        receiver.await?
+        metrics::measure(
+            self.identifier(),
+            Operation::DiscoverCharacteristics,
+            instrument!(
+                async {
+                    self.inner
+                        .discover_characteristics(service, characteristics);
+                    let receiver = self.delegate().characteristic_discovery(service.clone());
+                    receiver.await?
+                },
+                "discover_characteristics",
+                peripheral = %self.identifier(),
+                service = %service.uuid(),
+            ),
+        )
+        .await
     }
 
     /// Initiates discovery of the descriptors of a characteristic.
@@ -131,9 +189,21 @@ impl PeripheralAsync {
     /// After discovery completes, the characteristics may be retrieved by calling
     /// [`Characteristic::descriptors()`].
     pub async fn discover_descriptors(&self, characteristic: &Characteristic) -> Result<()> {
-        self.inner.discover_descriptors(characteristic);
-        let receiver = self.delegate().descriptor_discovery(characteristic.clone());
-        receiver.await?
+        metrics::measure(
+            self.identifier(),
+            Operation::DiscoverDescriptors,
+            instrument!(
+                async {
+                    self.inner.discover_descriptors(characteristic);
+                    let receiver = self.delegate().descriptor_discovery(characteristic.clone());
+                    receiver.await?
+                },
+                "discover_descriptors",
+                peripheral = %self.identifier(),
+                characteristic = %characteristic.uuid(),
+            ),
+        )
+        .await
     }
 
     /// Reads the value of a characteristic.
@@ -141,33 +211,187 @@ impl PeripheralAsync {
         &self,
         characteristic: &Characteristic,
     ) -> Result<Vec<u8>> {
-        self.inner.read_characteristic_value(characteristic);
-        self.delegate()
-            .characteristic_value_updates(characteristic.clone())
-            .recv()
-            .await?
+        metrics::measure(
+            self.identifier(),
+            Operation::ReadCharacteristic,
+            instrument!(
+                async {
+                    self.inner.read_characteristic_value(characteristic);
+                    self.delegate()
+                        .characteristic_value_updates(characteristic.clone())
+                        .recv()
+                        .await?
+                },
+                "read_characteristic_value",
+                peripheral = %self.identifier(),
+                characteristic = %characteristic.uuid(),
+            ),
+        )
+        .await
     }
 
     /// Reads the value of a descriptor.
     pub async fn read_descriptor_value(&self, descriptor: &Descriptor) -> Result<Vec<u8>> {
-        self.inner.read_descriptor_value(descriptor);
-        self.delegate()
-            .descriptor_value_updates(descriptor.clone())
-            .await?
+        metrics::measure(
+            self.identifier(),
+            Operation::ReadDescriptor,
+            instrument!(
+                async {
+                    self.inner.read_descriptor_value(descriptor);
+                    self.delegate()
+                        .descriptor_value_updates(descriptor.clone())
+                        .await?
+                },
+                "read_descriptor_value",
+                peripheral = %self.identifier(),
+                descriptor = %descriptor.uuid(),
+            ),
+        )
+        .await
     }
 
     /// Writes the value of a characteristic.
+    ///
+    /// Writes with response complete when the peripheral acknowledges the write. Writes without
+    /// response complete as soon as the value has been handed to CoreBluetooth; use
+    /// [`ready_to_send_write_without_response()`][Self::ready_to_send_write_without_response] or a
+    /// [`WriteStream`] for flow control.
     pub async fn write_characteristic_value(
         &self,
         characteristic: &Characteristic,
         data: Vec<u8>,
         write_type: CharacteristicWriteType,
     ) -> Result<()> {
+        metrics::measure(
+            self.identifier(),
+            Operation::WriteCharacteristic,
+            instrument!(
+                async {
+                    self.send_characteristic_value(characteristic, data, write_type);
+                    if write_type == CharacteristicWriteType::WithoutResponse {
+                        // CoreBluetooth does not call `peripheral:didWriteValueForCharacteristic:`
+                        // for writes without response, so there is nothing to wait for.
+                        return Ok(());
+                    }
+                    self.delegate()
+                        .register_characteristic_value_write(characteristic.clone())
+                        .await?
+                },
+                "write_characteristic_value",
+                peripheral = %self.identifier(),
+                characteristic = %characteristic.uuid(),
+                ?write_type,
+                len = data.len(),
+                payload = %crate::trace::Payload(&data),
+            ),
+        )
+        .await
+    }
+
+    /// Creates a [`WriteStream`] that queues writes without response to `characteristic`.
+    ///
+    /// At most `capacity` writes are buffered in the stream before it applies backpressure.
+    pub fn write_stream(
+        &self,
+        characteristic: &Characteristic,
+        capacity: usize,
+    ) -> WriteStream<CharacteristicWriter> {
+        let writer = CharacteristicWriter::new(
+            self.clone(),
+            characteristic.clone(),
+            self.delegate().ready_to_send_write_without_response(),
+        );
+        WriteStream::new(writer, capacity)
+    }
+
+    /// Writes a value of any length to a characteristic.
+    ///
+    /// The value is split into chunks of at most
+    /// [`max_write_value_len()`][Peripheral::max_write_value_len] bytes, which are written in
+    /// order. Writes with response wait for each chunk to be acknowledged; writes without response
+    /// wait until the peripheral is ready to send before each chunk. An empty value is written
+    /// once.
+    ///
+    /// Fails with [`ErrorKind::Io`][crate::error::ErrorKind::Io]`(InvalidInput)` if the maximum
+    /// write length is too small for the framing, or the value is too large for its length prefix.
+    pub async fn write_large(
+        &self,
+        characteristic: &Characteristic,
+        data: &[u8],
+        write_type: CharacteristicWriteType,
+    ) -> Result<()> {
+        self.write_framed(characteristic, data, write_type, Framing::default())
+            .await
+    }
+
+    /// Writes a value of any length to a characteristic using the given application-level framing.
+    ///
+    /// See [`write_large()`][Self::write_large] and [`Framing`].
+    pub async fn write_framed(
+        &self,
+        characteristic: &Characteristic,
+        data: &[u8],
+        write_type: CharacteristicWriteType,
+        framing: Framing,
+    ) -> Result<()> {
+        instrument!(
+            async {
+                let max_len = self.max_write_value_len(write_type);
+                let chunks = framing
+                    .chunks(data, max_len)
+                    .map_err(|error| std::io::Error::new(std::io::ErrorKind::InvalidInput, error))?;
+                for chunk in chunks {
+                    match write_type {
+                        CharacteristicWriteType::WithResponse => {
+                            self.write_characteristic_value(characteristic, chunk, write_type)
+                                .await?;
+                        }
+                        CharacteristicWriteType::WithoutResponse => {
+                            self.ready_to_send_write_without_response().await?;
+                            self.send_characteristic_value(characteristic, chunk, write_type);
+                        }
+                    }
+                }
+                Ok(())
+            },
+            "write_framed",
+            peripheral = %self.identifier(),
+            characteristic = %characteristic.uuid(),
+            ?write_type,
+            len = data.len(),
+        )
+        .await
+    }
+
+    /// Hands a characteristic write to CoreBluetooth, recording the value written.
+    pub(crate) fn send_characteristic_value(
+        &self,
+        characteristic: &Characteristic,
+        data: Vec<u8>,
+        write_type: CharacteristicWriteType,
+    ) {
+        record!(crate::record::Event::CharacteristicWriteRequested {
+            peripheral: self.identifier(),
+            service: characteristic.service().map(|service| service.uuid()),
+            characteristic: characteristic.uuid(),
+            with_response: write_type == CharacteristicWriteType::WithResponse,
+            value: data.clone(),
+        });
         self.inner
             .write_characteristic_value(characteristic, data, write_type);
-        self.delegate()
-            .register_characteristic_value_write(characteristic.clone())
-            .await?
+    }
+
+    /// Hands a descriptor write to CoreBluetooth, recording the value written.
+    fn send_descriptor_value(&self, descriptor: &Descriptor, data: Vec<u8>) {
+        record!(crate::record::Event::DescriptorWriteRequested {
+            peripheral: self.identifier(),
+            characteristic: descriptor
+                .characteristic()
+                .map(|characteristic| characteristic.uuid()),
+            descriptor: descriptor.uuid(),
+            value: data.clone(),
+        });
+        self.inner.write_descriptor_value(descriptor, data);
     }
 
     /// Writes the value of a descriptor.
@@ -176,18 +400,45 @@ impl PeripheralAsync {
         descriptor: &Descriptor,
         data: Vec<u8>,
     ) -> Result<()> {
-        self.inner.write_descriptor_value(descriptor, data);
-        self.delegate()
-            .register_descriptor_value_write(descriptor.clone())
-            .await?
+        metrics::measure(
+            self.identifier(),
+            Operation::WriteDescriptor,
+            instrument!(
+                async {
+                    self.send_descriptor_value(descriptor, data);
+                    self.delegate()
+                        .register_descriptor_value_write(descriptor.clone())
+                        .await?
+                },
+                "write_descriptor_value",
+                peripheral = %self.identifier(),
+                descriptor = %descriptor.uuid(),
+                len = data.len(),
+                payload = %crate::trace::Payload(&data),
+            ),
+        )
+        .await
     }
 
     /// Enables or disables notifications for a characteristic.
     pub async fn set_notify(&self, characteristic: &Characteristic, notify: bool) -> Result<bool> {
-        self.inner.set_notify(characteristic, notify);
-        self.delegate()
-            .register_notification_update(characteristic.clone())
-            .await?
+        metrics::measure(
+            self.identifier(),
+            Operation::SetNotify,
+            instrument!(
+                async {
+                    self.inner.set_notify(characteristic, notify);
+                    self.delegate()
+                        .register_notification_update(characteristic.clone())
+                        .await?
+                },
+                "set_notify",
+                peripheral = %self.identifier(),
+                characteristic = %characteristic.uuid(),
+                notify,
+            ),
+        )
+        .await
     }
 
     /// Returns a stream of value updates for a characteristic.
@@ -203,6 +454,21 @@ impl PeripheralAsync {
             .characteristic_value_updates(characteristic.clone())
     }
 
+    /// Returns a stream of value updates for a characteristic, buffered according to `policy`.
+    ///
+    /// Unlike [`characteristic_value_updates()`][Self::characteristic_value_updates], each
+    /// subscription has its own buffer, so a slow subscriber never causes another to lose
+    /// values. The number of values discarded by the policy is reported by
+    /// [`NotificationReceiver::stats()`].
+    pub fn characteristic_value_updates_with_policy(
+        &self,
+        characteristic: &Characteristic,
+        policy: DeliveryPolicy,
+    ) -> NotificationReceiver<Result<Vec<u8>>> {
+        self.delegate()
+            .subscribe_characteristic_value_updates(characteristic.clone(), policy)
+    }
+
     /// Waits until the peripheral is ready to send a write without response.
     pub async fn ready_to_send_write_without_response(&self) -> Result<()> {
         if !self.can_send_write_without_repsonse() {
@@ -216,20 +482,61 @@ impl PeripheralAsync {
 
     /// Reads the RSSI of the peripheral.
     pub async fn read_rssi(&self) -> Result<i16> {
-        self.inner.read_rssi();
-        let mut receiver = self.delegate().rssi_updates();
-        receiver.recv().await?
+        metrics::measure(
+            self.identifier(),
+            Operation::ReadRssi,
+            instrument!(
+                async {
+                    self.inner.read_rssi();
+                    let mut receiver = self.delegate().rssi_updates();
+                    receiver.recv().await?
+                },
+                "read_rssi",
+                peripheral = %self.identifier(),
+            ),
+        )
+        .await
     }
 
     /// Opens an L2CAP channel to the peripheral.
     pub async fn open_l2cap_channel(&self, psm: u16) -> Result<(L2capChannel<Self>, UnixStream)> {
-        self.inner.open_l2cap_channel(psm);
-        let receiver = self.delegate().register_l2cap_channel_open();
-        receiver.await?
+        metrics::measure(
+            self.identifier(),
+            Operation::OpenL2capChannel,
+            instrument!(
+                async {
+                    self.inner.open_l2cap_channel(psm);
+                    let receiver = self.delegate().register_l2cap_channel_open();
+                    receiver.await?
+                },
+                "open_l2cap_channel",
+                peripheral = %self.identifier(),
+                psm,
+            ),
+        )
+        .await
+    }
+
+    /// Opens an L2CAP channel to the peripheral, returning a non-blocking [`L2capStream`].
+    pub async fn open_l2cap_stream(&self, psm: u16) -> Result<L2capStream<Self>> {
+        let (channel, stream) = self.open_l2cap_channel(psm).await?;
+        Ok(L2capStream::new(channel, stream)?)
+    }
+
+    /// Opens an L2CAP channel to the peripheral, returning a non-blocking [`TokioL2capStream`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a tokio runtime.
+    #[cfg(feature = "tokio")]
+    pub async fn open_l2cap_tokio_stream(&self, psm: u16) -> Result<TokioL2capStream<Self>> {
+        let (channel, stream) = self.open_l2cap_channel(psm).await?;
+        Ok(TokioL2capStream::new(channel, stream)?)
     }
 }
 
 type OneshotMap<K, V> = HashMap<K, oneshot::Sender<Result<V>>>;
+type SubscriptionMap<K, V> = HashMap<K, Vec<NotificationSender<Result<V>>>>;
 type L2capChannelOpenResult = Result<(L2capChannel<PeripheralAsync>, UnixStream)>;
 
 pub(crate) struct PeripheralAsyncDelegate {
@@ -242,6 +549,7 @@ pub(crate) struct PeripheralAsyncDelegate {
     descriptor_discovery: RefCell<OneshotMap<Characteristic, ()>>,
     characteristic_value_updates:
         RefCell<HashMap<Characteristic, BroadcastSender<Result<Vec<u8>>>>>,
+    characteristic_subscriptions: RefCell<SubscriptionMap<Characteristic, Vec<u8>>>,
     notification_updates: RefCell<OneshotMap<Characteristic, bool>>,
     characteristic_writes: RefCell<OneshotMap<Characteristic, ()>>,
     descriptor_value_updates: RefCell<OneshotMap<Descriptor, Vec<u8>>>,
@@ -270,6 +578,7 @@ impl Default for PeripheralAsyncDelegate {
             characteristic_writes: Default::default(),
             descriptor_writes: Default::default(),
             characteristic_value_updates: Default::default(),
+            characteristic_subscriptions: Default::default(),
             descriptor_value_updates: Default::default(),
             ready_to_send_write_without_response,
             l2cap_channel_opened: Default::default(),
@@ -277,165 +586,359 @@ impl Default for PeripheralAsyncDelegate {
     }
 }
 
+// Some parameters are only read by trace events, which are compiled out without `tracing`.
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
 impl PeripheralDelegate for PeripheralAsyncDelegate {
     fn did_update_name(&self, peripheral: Peripheral) {
-        let _ = self.name_updates.try_broadcast(peripheral.name());
+        let name = peripheral.name();
+        event!(peripheral = %peripheral.identifier(), ?name, "did_update_name");
+        record!(crate::record::Event::NameUpdated {
+            peripheral: peripheral.identifier(),
+            name: name.clone(),
+        });
+        let _ = self.name_updates.try_broadcast(name);
     }
 
     fn did_modify_services(
         &self,
-        _peripheral: Peripheral,
+        peripheral: Peripheral,
         invalidated_services: Vec<corebluetooth::Service>,
     ) {
+        event!(
+            peripheral = %peripheral.identifier(),
+            invalidated = invalidated_services.len(),
+            "did_modify_services"
+        );
+        record!(crate::record::Event::ServicesModified {
+            peripheral: peripheral.identifier(),
+            invalidated_services: invalidated_services.iter().map(Service::uuid).collect(),
+        });
         let _ = self.services_changed.try_broadcast(invalidated_services);
     }
 
-    fn did_read_rssi(&self, _peripheral: Peripheral, rssi: CBResult<i16>) {
-        let _ = self.rssi_updates.try_broadcast(rssi.map_err(Into::into));
+    fn did_read_rssi(&self, peripheral: Peripheral, rssi: CBResult<i16>) {
+        let rssi = rssi.map_err(Into::into);
+        event!(
+            peripheral = %peripheral.identifier(),
+            rssi = rssi.as_ref().ok(),
+            error = crate::trace::error_kind(&rssi),
+            "did_read_rssi"
+        );
+        record!(crate::record::Event::RssiRead {
+            peripheral: peripheral.identifier(),
+            rssi: rssi.as_ref().ok().copied(),
+            error: crate::record::error(&rssi),
+        });
+        let _ = self.rssi_updates.try_broadcast(rssi);
     }
 
-    fn did_discover_services(&self, _peripheral: Peripheral, result: CBResult<()>) {
-        let _ = self
-            .service_discovery
-            .try_broadcast(result.map_err(Into::into));
+    fn did_discover_services(&self, peripheral: Peripheral, result: CBResult<()>) {
+        let result = result.map_err(Into::into);
+        event!(
+            peripheral = %peripheral.identifier(),
+            error = crate::trace::error_kind(&result),
+            "did_discover_services"
+        );
+        record!(crate::record::Event::ServicesDiscovered {
+            peripheral: peripheral.identifier(),
+            services: crate::record::uuids(peripheral.services(), Service::uuid),
+            error: crate::record::error(&result),
+        });
+        let _ = self.service_discovery.try_broadcast(result);
     }
 
     fn did_discover_included_services(
         &self,
-        _peripheral: Peripheral,
+        peripheral: Peripheral,
         service: corebluetooth::Service,
         result: CBResult<()>,
     ) {
+        let result = result.map_err(Into::into);
+        event!(
+            peripheral = %peripheral.identifier(),
+            service = %service.uuid(),
+            error = crate::trace::error_kind(&result),
+            "did_discover_included_services"
+        );
+        record!(crate::record::Event::IncludedServicesDiscovered {
+            peripheral: peripheral.identifier(),
+            service: service.uuid(),
+            included_services: crate::record::uuids(service.included_services(), Service::uuid),
+            error: crate::record::error(&result),
+        });
         if let Some(sender) = self
             .included_service_discovery
             .borrow_mut()
             .remove(&service)
         {
-            let _ = sender.send(result.map_err(Into::into));
+            let _ = sender.send(result);
         }
     }
 
     fn did_discover_characteristics(
         &self,
-        _peripheral: Peripheral,
+        peripheral: Peripheral,
         service: corebluetooth::Service,
         result: CBResult<()>,
     ) {
+        let result = result.map_err(Into::into);
+        event!(
+            peripheral = %peripheral.identifier(),
+            service = %service.uuid(),
+            error = crate::trace::error_kind(&result),
+            "did_discover_characteristics"
+        );
+        record!(crate::record::Event::CharacteristicsDiscovered {
+            peripheral: peripheral.identifier(),
+            service: service.uuid(),
+            characteristics: crate::record::uuids(service.characteristics(), Characteristic::uuid),
+            error: crate::record::error(&result),
+        });
         if let Some(sender) = self.characteristic_discovery.borrow_mut().remove(&service) {
-            let _ = sender.send(result.map_err(Into::into));
+            let _ = sender.send(result);
         }
     }
 
     fn did_update_value_for_characteristic(
         &self,
-        _peripheral: Peripheral,
+        peripheral: Peripheral,
         characteristic: corebluetooth::Characteristic,
         result: CBResult<()>,
     ) {
-        if let Some(sender) = self
-            .characteristic_value_updates
-            .borrow()
-            .get(&characteristic)
+        let update: Result<Vec<u8>> = result
+            .map(|_| characteristic.value().unwrap())
+            .map_err(Into::into);
+        event!(
+            peripheral = %peripheral.identifier(),
+            characteristic = %characteristic.uuid(),
+            len = update.as_ref().ok().map(Vec::len),
+            payload = update.as_deref().ok().map(|value| tracing::field::display(crate::trace::Payload(value))),
+            error = crate::trace::error_kind(&update),
+            "did_update_value_for_characteristic"
+        );
+        record!(crate::record::Event::CharacteristicValueUpdated {
+            peripheral: peripheral.identifier(),
+            service: characteristic.service().map(|service| service.uuid()),
+            characteristic: characteristic.uuid(),
+            is_notifying: characteristic.is_notifying(),
+            value: update.clone().unwrap_or_default(),
+            error: crate::record::error(&update),
+        });
+
+        let id = peripheral.identifier();
+        if let Ok(value) = &update
+            && characteristic.is_notifying()
         {
-            let update = result.map(|_| characteristic.value().unwrap());
+            metrics::with_sink(|sink| sink.notification(id, value.len()));
+        }
+
+        let mut updates = self.characteristic_value_updates.borrow_mut();
+        if let Some(sender) = updates.get(&characteristic) {
             if sender.receiver_count() == 0 {
-                self.characteristic_value_updates
-                    .borrow_mut()
-                    .remove(&characteristic);
-            } else {
-                let _ = sender.try_broadcast(update.map_err(Into::into));
+                updates.remove(&characteristic);
+            } else if let Ok(Some(_)) = sender.try_broadcast(update.clone()) {
+                // The oldest value was overwritten because a receiver lagged.
+                metrics::with_sink(|sink| sink.notification_dropped(id));
+            }
+        }
+
+        let mut subscriptions = self.characteristic_subscriptions.borrow_mut();
+        if let Some(senders) = subscriptions.get_mut(&characteristic) {
+            senders.retain(|sender| match sender.send(update.clone()) {
+                SendStatus::Queued => true,
+                SendStatus::Dropped => {
+                    metrics::with_sink(|sink| sink.notification_dropped(id));
+                    true
+                }
+                SendStatus::Closed => false,
+            });
+            if senders.is_empty() {
+                subscriptions.remove(&characteristic);
             }
         }
     }
 
     fn did_write_value_for_characteristic(
         &self,
-        _peripheral: Peripheral,
+        peripheral: Peripheral,
         characteristic: corebluetooth::Characteristic,
         result: CBResult<()>,
     ) {
+        let result = result.map_err(Into::into);
+        event!(
+            peripheral = %peripheral.identifier(),
+            characteristic = %characteristic.uuid(),
+            error = crate::trace::error_kind(&result),
+            "did_write_value_for_characteristic"
+        );
+        record!(crate::record::Event::CharacteristicValueWritten {
+            peripheral: peripheral.identifier(),
+            service: characteristic.service().map(|service| service.uuid()),
+            characteristic: characteristic.uuid(),
+            error: crate::record::error(&result),
+        });
         if let Some(sender) = self
             .characteristic_writes
             .borrow_mut()
             .remove(&characteristic)
         {
-            let _ = sender.send(result.map_err(Into::into));
+            let _ = sender.send(result);
         }
     }
 
     fn did_update_notification_state_for_characteristic(
         &self,
-        _peripheral: Peripheral,
+        peripheral: Peripheral,
         characteristic: corebluetooth::Characteristic,
         result: CBResult<()>,
     ) {
+        let result = result
+            .map(|_| characteristic.is_notifying())
+            .map_err(Into::into);
+        event!(
+            peripheral = %peripheral.identifier(),
+            characteristic = %characteristic.uuid(),
+            notifying = result.as_ref().ok(),
+            error = crate::trace::error_kind(&result),
+            "did_update_notification_state_for_characteristic"
+        );
+        record!(crate::record::Event::NotificationStateUpdated {
+            peripheral: peripheral.identifier(),
+            service: characteristic.service().map(|service| service.uuid()),
+            characteristic: characteristic.uuid(),
+            is_notifying: characteristic.is_notifying(),
+            error: crate::record::error(&result),
+        });
         if let Some(sender) = self
             .notification_updates
             .borrow_mut()
             .remove(&characteristic)
         {
-            let result = result.map(|_| characteristic.is_notifying());
-            let _ = sender.send(result.map_err(Into::into));
+            let _ = sender.send(result);
         }
     }
 
     fn did_discover_descriptors_for_characteristic(
         &self,
-        _peripheral: Peripheral,
+        peripheral: Peripheral,
         characteristic: corebluetooth::Characteristic,
         result: CBResult<()>,
     ) {
+        let result = result.map_err(Into::into);
+        event!(
+            peripheral = %peripheral.identifier(),
+            characteristic = %characteristic.uuid(),
+            error = crate::trace::error_kind(&result),
+            "did_discover_descriptors_for_characteristic"
+        );
+        record!(crate::record::Event::DescriptorsDiscovered {
+            peripheral: peripheral.identifier(),
+            service: characteristic.service().map(|service| service.uuid()),
+            characteristic: characteristic.uuid(),
+            descriptors: crate::record::uuids(characteristic.descriptors(), Descriptor::uuid),
+            error: crate::record::error(&result),
+        });
         if let Some(sender) = self
             .descriptor_discovery
             .borrow_mut()
             .remove(&characteristic)
         {
-            let _ = sender.send(result.map_err(Into::into));
+            let _ = sender.send(result);
         }
     }
 
     fn did_update_value_for_descriptor(
         &self,
-        _peripheral: Peripheral,
+        peripheral: Peripheral,
         descriptor: corebluetooth::Descriptor,
         result: CBResult<()>,
     ) {
+        let update: Result<Vec<u8>> = result
+            .map(|_| descriptor.value().unwrap())
+            .map_err(Into::into);
+        event!(
+            peripheral = %peripheral.identifier(),
+            descriptor = %descriptor.uuid(),
+            len = update.as_ref().ok().map(Vec::len),
+            payload = update.as_deref().ok().map(|value| tracing::field::display(crate::trace::Payload(value))),
+            error = crate::trace::error_kind(&update),
+            "did_update_value_for_descriptor"
+        );
+        record!(crate::record::Event::DescriptorValueUpdated {
+            peripheral: peripheral.identifier(),
+            characteristic: descriptor
+                .characteristic()
+                .map(|characteristic| characteristic.uuid()),
+            descriptor: descriptor.uuid(),
+            value: update.clone().unwrap_or_default(),
+            error: crate::record::error(&update),
+        });
         if let Some(sender) = self
             .descriptor_value_updates
             .borrow_mut()
             .remove(&descriptor)
         {
-            let update = result.map(|_| descriptor.value().unwrap());
-            let _ = sender.send(update.map_err(Into::into));
+            let _ = sender.send(update);
         }
     }
 
     fn did_write_value_for_descriptor(
         &self,
-        _peripheral: Peripheral,
+        peripheral: Peripheral,
         descriptor: corebluetooth::Descriptor,
         result: CBResult<()>,
     ) {
+        let result = result.map_err(Into::into);
+        event!(
+            peripheral = %peripheral.identifier(),
+            descriptor = %descriptor.uuid(),
+            error = crate::trace::error_kind(&result),
+            "did_write_value_for_descriptor"
+        );
+        record!(crate::record::Event::DescriptorValueWritten {
+            peripheral: peripheral.identifier(),
+            characteristic: descriptor
+                .characteristic()
+                .map(|characteristic| characteristic.uuid()),
+            descriptor: descriptor.uuid(),
+            error: crate::record::error(&result),
+        });
         if let Some(sender) = self.descriptor_writes.borrow_mut().remove(&descriptor) {
-            let _ = sender.send(result.map_err(Into::into));
+            let _ = sender.send(result);
         }
     }
 
-    fn is_ready_to_send_write_without_response(&self, _peripheral: Peripheral) {
+    fn is_ready_to_send_write_without_response(&self, peripheral: Peripheral) {
+        event!(
+            peripheral = %peripheral.identifier(),
+            "is_ready_to_send_write_without_response"
+        );
+        record!(crate::record::Event::ReadyToSendWriteWithoutResponse {
+            peripheral: peripheral.identifier(),
+        });
         let _ = self.ready_to_send_write_without_response.try_broadcast(());
     }
 
     fn did_open_l2cap_channel(
         &self,
-        _peripheral: Peripheral,
+        peripheral: Peripheral,
         result: CBResult<(corebluetooth::L2capChannel<Peripheral>, UnixStream)>,
     ) {
+        let result = result
+            .map(|(channel, stream)| (L2capChannel::map(channel), stream))
+            .map_err(Into::into);
+        event!(
+            peripheral = %peripheral.identifier(),
+            error = crate::trace::error_kind(&result),
+            "did_open_l2cap_channel"
+        );
+        record!(crate::record::Event::L2capChannelOpened {
+            peripheral: peripheral.identifier(),
+            psm: result.as_ref().ok().map(|(channel, _)| channel.psm()),
+            error: crate::record::error(&result),
+        });
         if let Some(sender) = self.l2cap_channel_opened.take() {
-            let _ = sender.send(
-                result
-                    .map(|(channel, stream)| (L2capChannel::map(channel), stream))
-                    .map_err(Into::into),
-            );
+            let _ = sender.send(result);
         }
     }
 }
@@ -500,6 +1003,20 @@ impl PeripheralAsyncDelegate {
         }
     }
 
+    pub fn subscribe_characteristic_value_updates(
+        &self,
+        characteristic: Characteristic,
+        policy: DeliveryPolicy,
+    ) -> NotificationReceiver<Result<Vec<u8>>> {
+        let (sender, receiver) = notification::channel(policy);
+        self.characteristic_subscriptions
+            .borrow_mut()
+            .entry(characteristic)
+            .or_default()
+            .push(sender);
+        receiver
+    }
+
     pub fn descriptor_value_updates(
         &self,
         descriptor: Descriptor,
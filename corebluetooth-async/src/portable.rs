@@ -0,0 +1,84 @@
+//! Stand-ins for the CoreBluetooth enumerations used by this crate's platform-independent types.
+//!
+//! On Apple platforms these types are re-exported from `objc2_core_bluetooth`. Elsewhere, the
+//! definitions below mirror them so that errors, metrics and recordings can be used (for example,
+//! to replay a recorded session) without CoreBluetooth.
+
+#![allow(non_upper_case_globals)]
+
+/// CoreBluetooth error codes (`CBError`).
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CBError(pub isize);
+
+impl CBError {
+    pub const Unknown: Self = Self(0);
+    pub const InvalidParameters: Self = Self(1);
+    pub const InvalidHandle: Self = Self(2);
+    pub const NotConnected: Self = Self(3);
+    pub const OutOfSpace: Self = Self(4);
+    pub const OperationCancelled: Self = Self(5);
+    pub const ConnectionTimeout: Self = Self(6);
+    pub const PeripheralDisconnected: Self = Self(7);
+    pub const UUIDNotAllowed: Self = Self(8);
+    pub const AlreadyAdvertising: Self = Self(9);
+    pub const ConnectionFailed: Self = Self(10);
+    pub const ConnectionLimitReached: Self = Self(11);
+    pub const UnknownDevice: Self = Self(12);
+    pub const OperationNotSupported: Self = Self(13);
+    pub const PeerRemovedPairingInformation: Self = Self(14);
+    pub const EncryptionTimedOut: Self = Self(15);
+    pub const TooManyLEPairedDevices: Self = Self(16);
+    pub const LeGattExceededBackgroundNotificationLimit: Self = Self(17);
+    pub const LeGattNearBackgroundNotificationLimit: Self = Self(18);
+}
+
+/// ATT error codes (`CBATTError`).
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CBATTError(pub isize);
+
+impl CBATTError {
+    pub const Success: Self = Self(0x00);
+    pub const InvalidHandle: Self = Self(0x01);
+    pub const ReadNotPermitted: Self = Self(0x02);
+    pub const WriteNotPermitted: Self = Self(0x03);
+    pub const InvalidPdu: Self = Self(0x04);
+    pub const InsufficientAuthentication: Self = Self(0x05);
+    pub const RequestNotSupported: Self = Self(0x06);
+    pub const InvalidOffset: Self = Self(0x07);
+    pub const InsufficientAuthorization: Self = Self(0x08);
+    pub const PrepareQueueFull: Self = Self(0x09);
+    pub const AttributeNotFound: Self = Self(0x0A);
+    pub const AttributeNotLong: Self = Self(0x0B);
+    pub const InsufficientEncryptionKeySize: Self = Self(0x0C);
+    pub const InvalidAttributeValueLength: Self = Self(0x0D);
+    pub const UnlikelyError: Self = Self(0x0E);
+    pub const InsufficientEncryption: Self = Self(0x0F);
+    pub const UnsupportedGroupType: Self = Self(0x10);
+    pub const InsufficientResources: Self = Self(0x11);
+}
+
+/// The state of a central manager (`CBManagerState`).
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CBManagerState(pub isize);
+
+impl CBManagerState {
+    pub const Unknown: Self = Self(0);
+    pub const Resetting: Self = Self(1);
+    pub const Unsupported: Self = Self(2);
+    pub const Unauthorized: Self = Self(3);
+    pub const PoweredOff: Self = Self(4);
+    pub const PoweredOn: Self = Self(5);
+}
+
+/// A peripheral connection event (`CBConnectionEvent`).
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CBConnectionEvent(pub isize);
+
+impl CBConnectionEvent {
+    pub const PeerDisconnected: Self = Self(0);
+    pub const PeerConnected: Self = Self(1);
+}
@@ -0,0 +1,19 @@
+//! Clients for common GATT profiles.
+//!
+//! - [`battery`]: the Battery Service, with the battery level and power state.
+//! - [`dis`]: the Device Information Service.
+//! - [`nus`]: the Nordic UART Service, a byte stream over a pair of characteristics.
+
+pub mod battery;
+pub mod dis;
+pub mod nus;
+
+/// The error for a characteristic value that cannot be decoded.
+#[cfg(target_vendor = "apple")]
+fn invalid_value(name: &str) -> crate::error::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        format!("invalid {name} value"),
+    )
+    .into()
+}
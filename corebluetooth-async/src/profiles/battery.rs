@@ -0,0 +1,419 @@
+//! A client for the Battery Service.
+//!
+//! On macOS and iOS, `Battery` reads the battery level, reads the power state from the Battery
+//! Level Status characteristic, and streams level notifications. The Battery Level Status value
+//! is decoded by [`BatteryLevelStatus`], which is available on all platforms:
+//!
+//! ```
+//! use corebluetooth_async::profiles::battery::{BatteryLevelStatus, ChargeState};
+//!
+//! // Battery level present; battery present, wired power connected, charging.
+//! let status = BatteryLevelStatus::from_bytes(&[0x02, 0x23, 0x00, 80]).unwrap();
+//! assert!(status.power_state.battery_present);
+//! assert_eq!(status.power_state.wired_external_power, Some(true));
+//! assert_eq!(status.power_state.charge_state, ChargeState::Charging);
+//! assert_eq!(status.battery_level, Some(80));
+//! ```
+
+#[cfg(target_vendor = "apple")]
+pub use apple::*;
+
+/// The Battery Level Status characteristic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BatteryLevelStatus {
+    /// The power state of the battery.
+    pub power_state: PowerState,
+    /// The identifier of the battery, for peripherals with more than one Battery Service.
+    pub identifier: Option<u16>,
+    /// The battery level, in percent.
+    pub battery_level: Option<u8>,
+    /// Whether the battery requires service, or `None` if this is unknown or not included.
+    pub service_required: Option<bool>,
+    /// Whether the battery has a fault. `false` if this is not included.
+    pub battery_fault: bool,
+}
+
+impl BatteryLevelStatus {
+    /// Decodes the value of the characteristic.
+    ///
+    /// Returns `None` if the value is shorter than its flags require.
+    pub fn from_bytes(value: &[u8]) -> Option<Self> {
+        let (&flags, value) = value.split_first()?;
+        let (power_state, mut value) = value.split_first_chunk::<2>()?;
+        let mut status = Self {
+            power_state: PowerState::from_bits(u16::from_le_bytes(*power_state)),
+            identifier: None,
+            battery_level: None,
+            service_required: None,
+            battery_fault: false,
+        };
+
+        if flags & 0x01 != 0 {
+            let (identifier, rest) = value.split_first_chunk::<2>()?;
+            status.identifier = Some(u16::from_le_bytes(*identifier));
+            value = rest;
+        }
+        if flags & 0x02 != 0 {
+            let (&battery_level, rest) = value.split_first()?;
+            status.battery_level = Some(battery_level);
+            value = rest;
+        }
+        if flags & 0x04 != 0 {
+            let &additional_status = value.first()?;
+            status.service_required = tristate(additional_status);
+            status.battery_fault = additional_status & 0x04 != 0;
+        }
+        Some(status)
+    }
+}
+
+/// The power state field of the Battery Level Status characteristic.
+///
+/// Two-state fields are `None` if the peripheral reports them as unknown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PowerState {
+    /// Whether a battery is present.
+    pub battery_present: bool,
+    /// Whether a wired external power source is connected.
+    pub wired_external_power: Option<bool>,
+    /// Whether a wireless external power source is connected.
+    pub wireless_external_power: Option<bool>,
+    /// Whether the battery is charging or discharging.
+    pub charge_state: ChargeState,
+    /// The charge level of the battery.
+    pub charge_level: ChargeLevel,
+    /// How the battery is being charged.
+    pub charging_type: ChargingType,
+    /// Why the battery is not charging, if it has a fault.
+    pub charging_fault: ChargingFault,
+}
+
+impl PowerState {
+    /// Decodes the 16-bit power state field.
+    pub fn from_bits(bits: u16) -> Self {
+        let field = |shift: u32, width: u32| ((bits >> shift) & ((1 << width) - 1)) as u8;
+        Self {
+            battery_present: field(0, 1) != 0,
+            wired_external_power: tristate(field(1, 2)),
+            wireless_external_power: tristate(field(3, 2)),
+            charge_state: match field(5, 2) {
+                1 => ChargeState::Charging,
+                2 => ChargeState::DischargingActive,
+                3 => ChargeState::DischargingInactive,
+                _ => ChargeState::Unknown,
+            },
+            charge_level: match field(7, 2) {
+                1 => ChargeLevel::Good,
+                2 => ChargeLevel::Low,
+                3 => ChargeLevel::Critical,
+                _ => ChargeLevel::Unknown,
+            },
+            charging_type: match field(9, 3) {
+                1 => ChargingType::ConstantCurrent,
+                2 => ChargingType::ConstantVoltage,
+                3 => ChargingType::Trickle,
+                4 => ChargingType::Float,
+                _ => ChargingType::Unknown,
+            },
+            charging_fault: ChargingFault {
+                battery: field(12, 1) != 0,
+                external_power: field(13, 1) != 0,
+                other: field(14, 1) != 0,
+            },
+        }
+    }
+}
+
+/// Whether a battery is charging or discharging.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChargeState {
+    /// The charge state is unknown.
+    Unknown,
+    /// The battery is charging.
+    Charging,
+    /// The battery is discharging while the device is in use.
+    DischargingActive,
+    /// The battery is discharging while the device is idle.
+    DischargingInactive,
+}
+
+/// The charge level of a battery.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChargeLevel {
+    /// The charge level is unknown.
+    Unknown,
+    /// The charge level is good.
+    Good,
+    /// The charge level is low.
+    Low,
+    /// The charge level is critically low.
+    Critical,
+}
+
+/// How a battery is being charged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChargingType {
+    /// The charging type is unknown, or the battery is not charging.
+    Unknown,
+    /// Constant current charging.
+    ConstantCurrent,
+    /// Constant voltage charging.
+    ConstantVoltage,
+    /// Trickle charging.
+    Trickle,
+    /// Float charging.
+    Float,
+}
+
+/// The reasons a battery has a charging fault.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ChargingFault {
+    /// The battery has a fault.
+    pub battery: bool,
+    /// The external power source has a fault.
+    pub external_power: bool,
+    /// Some other fault.
+    pub other: bool,
+}
+
+impl ChargingFault {
+    /// Whether any fault is reported.
+    pub fn any(&self) -> bool {
+        self.battery || self.external_power || self.other
+    }
+}
+
+/// Decodes a two-bit field where 0 is no, 1 is yes and other values are unknown.
+fn tristate(bits: u8) -> Option<bool> {
+    match bits & 0x03 {
+        0 => Some(false),
+        1 => Some(true),
+        _ => None,
+    }
+}
+
+#[cfg(target_vendor = "apple")]
+mod apple {
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    use btuuid::{characteristic, service};
+    use corebluetooth::Characteristic;
+    use futures_core::Stream;
+
+    use super::{BatteryLevelStatus, PowerState};
+    use crate::error::{ErrorKind, Result};
+    use crate::profiles::invalid_value;
+    use crate::shared::discover_characteristics;
+    use crate::{DeliveryPolicy, NotificationReceiver, PeripheralAsync};
+
+    /// A client for the Battery Service of a connected peripheral.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use corebluetooth_async::PeripheralAsync;
+    /// # use corebluetooth_async::profiles::battery::Battery;
+    /// # use futures_lite::StreamExt;
+    /// # async fn example(peripheral: &PeripheralAsync) -> corebluetooth_async::error::Result<()> {
+    /// let battery = Battery::open(peripheral).await?;
+    /// println!("{}%", battery.level().await?);
+    ///
+    /// let mut updates = battery.level_updates().await?;
+    /// while let Some(level) = updates.next().await {
+    ///     println!("{}%", level?);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[derive(Debug, Clone)]
+    pub struct Battery {
+        peripheral: PeripheralAsync,
+        level: Characteristic,
+        level_status: Option<Characteristic>,
+    }
+
+    impl Battery {
+        /// Discovers the Battery Service of `peripheral` and its characteristics if necessary.
+        ///
+        /// Fails with [`ErrorKind::NotFound`] if the peripheral does not have the service or its
+        /// Battery Level characteristic.
+        pub async fn open(peripheral: &PeripheralAsync) -> Result<Self> {
+            let characteristics =
+                discover_characteristics(peripheral, service::BATTERY.into()).await?;
+            let find = |uuid: btuuid::BluetoothUuid16| {
+                characteristics
+                    .iter()
+                    .find(|c| c.uuid() == uuid.into())
+                    .cloned()
+            };
+            Ok(Self {
+                peripheral: peripheral.clone(),
+                level: find(characteristic::BATTERY_LEVEL).ok_or(ErrorKind::NotFound)?,
+                level_status: find(characteristic::BATTERY_LEVEL_STATUS),
+            })
+        }
+
+        /// The peripheral.
+        pub fn peripheral(&self) -> &PeripheralAsync {
+            &self.peripheral
+        }
+
+        /// Reads the battery level, in percent.
+        pub async fn level(&self) -> Result<u8> {
+            let value = self
+                .peripheral
+                .read_characteristic_value(&self.level)
+                .await?;
+            decode_level(&value)
+        }
+
+        /// Reads the Battery Level Status characteristic.
+        ///
+        /// Fails with [`ErrorKind::NotFound`] if the peripheral does not have the characteristic,
+        /// which was added in version 1.1 of the service.
+        pub async fn level_status(&self) -> Result<BatteryLevelStatus> {
+            let characteristic = self.level_status.as_ref().ok_or(ErrorKind::NotFound)?;
+            let value = self
+                .peripheral
+                .read_characteristic_value(characteristic)
+                .await?;
+            BatteryLevelStatus::from_bytes(&value)
+                .ok_or_else(|| invalid_value("Battery Level Status"))
+        }
+
+        /// Reads the power state from the Battery Level Status characteristic.
+        ///
+        /// Fails with [`ErrorKind::NotFound`] if the peripheral does not have the characteristic.
+        pub async fn power_state(&self) -> Result<PowerState> {
+            Ok(self.level_status().await?.power_state)
+        }
+
+        /// Enables notifications of the battery level and returns a stream of the levels.
+        ///
+        /// Only the latest level is kept if the stream is not polled in time. Notifications stay
+        /// enabled when the stream is dropped. The stream does not end when the peripheral
+        /// disconnects.
+        pub async fn level_updates(&self) -> Result<LevelUpdates> {
+            let updates = self
+                .peripheral
+                .characteristic_value_updates_with_policy(&self.level, DeliveryPolicy::LatestOnly);
+            self.peripheral.set_notify(&self.level, true).await?;
+            Ok(LevelUpdates { updates })
+        }
+    }
+
+    /// A stream of battery levels, returned by [`Battery::level_updates()`].
+    #[derive(Debug)]
+    pub struct LevelUpdates {
+        updates: NotificationReceiver<Result<Vec<u8>>>,
+    }
+
+    impl Stream for LevelUpdates {
+        type Item = Result<u8>;
+
+        fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            Pin::new(&mut self.updates)
+                .poll_next(cx)
+                .map(|value| value.map(|value| decode_level(&value?)))
+        }
+    }
+
+    fn decode_level(value: &[u8]) -> Result<u8> {
+        match value {
+            &[level] if level <= 100 => Ok(level),
+            _ => Err(invalid_value("Battery Level")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_every_optional_field() {
+        let status =
+            BatteryLevelStatus::from_bytes(&[0x07, 0x01, 0x00, 0x34, 0x12, 42, 0x05]).unwrap();
+        assert!(status.power_state.battery_present);
+        assert_eq!(status.identifier, Some(0x1234));
+        assert_eq!(status.battery_level, Some(42));
+        assert_eq!(status.service_required, Some(true));
+        assert!(status.battery_fault);
+    }
+
+    #[test]
+    fn absent_fields_are_none() {
+        let status = BatteryLevelStatus::from_bytes(&[0x00, 0x00, 0x00]).unwrap();
+        assert_eq!(status.identifier, None);
+        assert_eq!(status.battery_level, None);
+        assert_eq!(status.service_required, None);
+        assert!(!status.battery_fault);
+    }
+
+    #[test]
+    fn truncated_values_are_rejected() {
+        // Missing flags or power state.
+        assert_eq!(BatteryLevelStatus::from_bytes(&[]), None);
+        assert_eq!(BatteryLevelStatus::from_bytes(&[0x00, 0x01]), None);
+        // The flags promise fields that are not there.
+        assert_eq!(
+            BatteryLevelStatus::from_bytes(&[0x01, 0x01, 0x00, 0x34]),
+            None
+        );
+        assert_eq!(BatteryLevelStatus::from_bytes(&[0x02, 0x01, 0x00]), None);
+        assert_eq!(BatteryLevelStatus::from_bytes(&[0x04, 0x01, 0x00]), None);
+        assert_eq!(
+            BatteryLevelStatus::from_bytes(&[0x07, 0x01, 0x00, 0x34, 0x12, 42]),
+            None
+        );
+    }
+
+    #[test]
+    fn reserved_flags_and_trailing_bytes_are_ignored() {
+        let status = BatteryLevelStatus::from_bytes(&[0xfa, 0x01, 0x00, 80, 0xff]).unwrap();
+        assert_eq!(status.identifier, None);
+        assert_eq!(status.battery_level, Some(80));
+        assert_eq!(status.service_required, None);
+    }
+
+    #[test]
+    fn unknown_additional_status_is_none() {
+        let status = BatteryLevelStatus::from_bytes(&[0x04, 0x01, 0x00, 0x02]).unwrap();
+        assert_eq!(status.service_required, None);
+        assert!(!status.battery_fault);
+    }
+
+    #[test]
+    fn decodes_power_state_fields() {
+        // Battery present, no wired power, unknown wireless power, discharging while active,
+        // critical, trickle charging, and every fault.
+        let state = PowerState::from_bits(1 | 2 << 3 | 2 << 5 | 3 << 7 | 3 << 9 | 0b111 << 12);
+        assert!(state.battery_present);
+        assert_eq!(state.wired_external_power, Some(false));
+        assert_eq!(state.wireless_external_power, None);
+        assert_eq!(state.charge_state, ChargeState::DischargingActive);
+        assert_eq!(state.charge_level, ChargeLevel::Critical);
+        assert_eq!(state.charging_type, ChargingType::Trickle);
+        assert_eq!(
+            state.charging_fault,
+            ChargingFault {
+                battery: true,
+                external_power: true,
+                other: true,
+            }
+        );
+    }
+
+    #[test]
+    fn reserved_power_state_values_are_unknown() {
+        // Charging type 5 to 7 are reserved.
+        let state = PowerState::from_bits(0b0000_1110_0000_0000);
+        assert!(!state.battery_present);
+        assert_eq!(state.wired_external_power, Some(false));
+        assert_eq!(state.charge_state, ChargeState::Unknown);
+        assert_eq!(state.charge_level, ChargeLevel::Unknown);
+        assert_eq!(state.charging_type, ChargingType::Unknown);
+        assert!(!state.charging_fault.any());
+    }
+}
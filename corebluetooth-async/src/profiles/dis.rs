@@ -0,0 +1,430 @@
+//! A client for the Device Information Service.
+//!
+//! On macOS and iOS, `read_all()` reads every characteristic of the service that a peripheral
+//! has into a [`DeviceInformation`]. The values of the structured characteristics are decoded by
+//! [`PnpId`], [`SystemId`] and [`RegulatoryCertification`], which are available on all platforms:
+//!
+//! ```
+//! use corebluetooth_async::profiles::dis::{PnpId, VendorIdSource};
+//!
+//! let pnp_id = PnpId::from_bytes(&[0x01, 0x59, 0x00, 0x34, 0x12, 0x10, 0x02]).unwrap();
+//! assert_eq!(pnp_id.vendor_id_source, VendorIdSource::Bluetooth);
+//! assert_eq!(pnp_id.vendor_id, 0x0059);
+//! assert_eq!(pnp_id.product_id, 0x1234);
+//! assert_eq!(pnp_id.version(), (2, 1, 0));
+//! ```
+
+#[cfg(target_vendor = "apple")]
+pub use apple::*;
+
+/// The information read from a Device Information Service.
+///
+/// Each field is `None` if the peripheral does not have the corresponding characteristic.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DeviceInformation {
+    /// The Manufacturer Name String.
+    pub manufacturer_name: Option<String>,
+    /// The Model Number String.
+    pub model_number: Option<String>,
+    /// The Serial Number String.
+    pub serial_number: Option<String>,
+    /// The Hardware Revision String.
+    pub hardware_revision: Option<String>,
+    /// The Firmware Revision String.
+    pub firmware_revision: Option<String>,
+    /// The Software Revision String.
+    pub software_revision: Option<String>,
+    /// The System ID.
+    pub system_id: Option<SystemId>,
+    /// The IEEE 11073-20601 Regulatory Certification Data List.
+    pub regulatory_certifications: Option<Vec<RegulatoryCertification>>,
+    /// The PnP ID.
+    pub pnp_id: Option<PnpId>,
+}
+
+/// Decodes the value of one of the string characteristics of the service.
+///
+/// The value is UTF-8, but invalid sequences are replaced rather than rejected, and trailing NUL
+/// padding, which some peripherals include, is removed.
+pub fn decode_string(value: &[u8]) -> String {
+    let end = value.iter().rposition(|&b| b != 0).map_or(0, |i| i + 1);
+    String::from_utf8_lossy(&value[..end]).into_owned()
+}
+
+/// The organization that assigned the vendor ID of a [`PnpId`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VendorIdSource {
+    /// A Bluetooth SIG assigned company identifier.
+    Bluetooth,
+    /// A USB Implementers Forum assigned vendor ID.
+    Usb,
+    /// A reserved value.
+    Reserved(u8),
+}
+
+impl From<u8> for VendorIdSource {
+    fn from(value: u8) -> Self {
+        match value {
+            1 => VendorIdSource::Bluetooth,
+            2 => VendorIdSource::Usb,
+            value => VendorIdSource::Reserved(value),
+        }
+    }
+}
+
+/// The PnP ID characteristic, which identifies the product in the same way as a USB device
+/// descriptor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PnpId {
+    /// The organization that assigned `vendor_id`.
+    pub vendor_id_source: VendorIdSource,
+    /// The vendor ID.
+    pub vendor_id: u16,
+    /// The vendor-assigned product ID.
+    pub product_id: u16,
+    /// The product version, as a binary-coded decimal `0xJJMN`.
+    pub product_version: u16,
+}
+
+impl PnpId {
+    /// Decodes the 7-byte value of the characteristic.
+    pub fn from_bytes(value: &[u8]) -> Option<Self> {
+        let value: &[u8; 7] = value.try_into().ok()?;
+        let u16_at = |i: usize| u16::from_le_bytes([value[i], value[i + 1]]);
+        Some(Self {
+            vendor_id_source: value[0].into(),
+            vendor_id: u16_at(1),
+            product_id: u16_at(3),
+            product_version: u16_at(5),
+        })
+    }
+
+    /// The major, minor and sub-minor parts of the product version, decoded from binary-coded
+    /// decimal, so that `0x1234` is version 12.3.4.
+    pub fn version(&self) -> (u8, u8, u8) {
+        let version = self.product_version;
+        (
+            (version >> 12 & 0xf) as u8 * 10 + (version >> 8 & 0xf) as u8,
+            (version >> 4 & 0xf) as u8,
+            (version & 0xf) as u8,
+        )
+    }
+}
+
+/// The System ID characteristic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SystemId {
+    /// The 40-bit manufacturer-defined identifier.
+    pub manufacturer_identifier: u64,
+    /// The 24-bit IEEE organizationally unique identifier of the manufacturer.
+    pub organizationally_unique_identifier: u32,
+}
+
+impl SystemId {
+    /// Decodes the 8-byte value of the characteristic.
+    pub fn from_bytes(value: &[u8]) -> Option<Self> {
+        let value: &[u8; 8] = value.try_into().ok()?;
+        let mut manufacturer_identifier = [0; 8];
+        manufacturer_identifier[..5].copy_from_slice(&value[..5]);
+        let mut organizationally_unique_identifier = [0; 4];
+        organizationally_unique_identifier[..3].copy_from_slice(&value[5..]);
+        Some(Self {
+            manufacturer_identifier: u64::from_le_bytes(manufacturer_identifier),
+            organizationally_unique_identifier: u32::from_le_bytes(
+                organizationally_unique_identifier,
+            ),
+        })
+    }
+}
+
+/// An entry of the IEEE 11073-20601 Regulatory Certification Data List characteristic.
+///
+/// The list is encoded with the medical device encoding rules (MDER) of IEEE 11073-20601: a
+/// big-endian count and length, followed by the entries. The structure of each entry's `data`
+/// is defined by its certifying body.
+///
+/// ```
+/// use corebluetooth_async::profiles::dis::RegulatoryCertification;
+///
+/// let value = [
+///     0x00, 0x01, 0x00, 0x06, // one entry of six bytes
+///     0x02, 0x02, 0x00, 0x02, 0x00, 0x01, // Continua regulation status
+/// ];
+/// let list = RegulatoryCertification::decode_list(&value).unwrap();
+/// assert_eq!(list[0].authorizing_body, RegulatoryCertification::CONTINUA);
+/// assert_eq!(list[0].data, [0x00, 0x01]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RegulatoryCertification {
+    /// The certifying body, such as [`CONTINUA`][Self::CONTINUA].
+    pub authorizing_body: u8,
+    /// The type of `data`, defined by the certifying body.
+    pub structure_type: u8,
+    /// The certification data.
+    pub data: Vec<u8>,
+}
+
+impl RegulatoryCertification {
+    /// No certifying body.
+    pub const EMPTY: u8 = 0;
+    /// IEEE 11073.
+    pub const IEEE_11073: u8 = 1;
+    /// The Continua Health Alliance.
+    pub const CONTINUA: u8 = 2;
+    /// An experimental certifying body.
+    pub const EXPERIMENTAL: u8 = 254;
+
+    /// Decodes the value of the characteristic.
+    ///
+    /// Returns `None` if the value is truncated or its count and length do not match its
+    /// entries.
+    pub fn decode_list(value: &[u8]) -> Option<Vec<Self>> {
+        let u16_at = |value: &[u8], i: usize| -> Option<usize> {
+            Some(u16::from_be_bytes(value.get(i..i + 2)?.try_into().ok()?).into())
+        };
+        let count = u16_at(value, 0)?;
+        let len = u16_at(value, 2)?;
+        let mut entries = value.get(4..)?;
+        if entries.len() != len {
+            return None;
+        }
+
+        let mut list = Vec::with_capacity(count);
+        for _ in 0..count {
+            let data_len = u16_at(entries, 2)?;
+            let data = entries.get(4..4 + data_len)?;
+            list.push(Self {
+                authorizing_body: entries[0],
+                structure_type: entries[1],
+                data: data.to_vec(),
+            });
+            entries = &entries[4 + data_len..];
+        }
+        entries.is_empty().then_some(list)
+    }
+}
+
+#[cfg(target_vendor = "apple")]
+mod apple {
+    use btuuid::{BluetoothUuid16, characteristic, service};
+
+    use super::{DeviceInformation, PnpId, RegulatoryCertification, SystemId, decode_string};
+    use crate::PeripheralAsync;
+    use crate::error::Result;
+    use crate::profiles::invalid_value;
+    use crate::shared::discover_characteristics;
+
+    /// Reads the Device Information Service of a connected peripheral.
+    ///
+    /// The service and its characteristics are discovered if necessary. Fails with
+    /// [`ErrorKind::NotFound`][crate::error::ErrorKind::NotFound] if the peripheral does not have
+    /// the service, or with the error of the first characteristic that cannot be read or decoded.
+    pub async fn read_all(peripheral: &PeripheralAsync) -> Result<DeviceInformation> {
+        let characteristics =
+            discover_characteristics(peripheral, service::DEVICE_INFORMATION.into()).await?;
+        let read = async |uuid: BluetoothUuid16| -> Result<Option<Vec<u8>>> {
+            match characteristics.iter().find(|c| c.uuid() == uuid.into()) {
+                Some(characteristic) => Ok(Some(
+                    peripheral.read_characteristic_value(characteristic).await?,
+                )),
+                None => Ok(None),
+            }
+        };
+
+        let mut info = DeviceInformation::default();
+        let strings = [
+            (
+                characteristic::MANUFACTURER_NAME_STRING,
+                &mut info.manufacturer_name,
+            ),
+            (characteristic::MODEL_NUMBER_STRING, &mut info.model_number),
+            (
+                characteristic::SERIAL_NUMBER_STRING,
+                &mut info.serial_number,
+            ),
+            (
+                characteristic::HARDWARE_REVISION_STRING,
+                &mut info.hardware_revision,
+            ),
+            (
+                characteristic::FIRMWARE_REVISION_STRING,
+                &mut info.firmware_revision,
+            ),
+            (
+                characteristic::SOFTWARE_REVISION_STRING,
+                &mut info.software_revision,
+            ),
+        ];
+        for (uuid, field) in strings {
+            *field = read(uuid).await?.map(|value| decode_string(&value));
+        }
+
+        if let Some(value) = read(characteristic::SYSTEM_ID).await? {
+            let system_id =
+                SystemId::from_bytes(&value).ok_or_else(|| invalid_value("System ID"))?;
+            info.system_id = Some(system_id);
+        }
+        let regulatory_list = characteristic::IEEE_11073_20601_REGULATORY_CERTIFICATION_DATA_LIST;
+        if let Some(value) = read(regulatory_list).await? {
+            let list = RegulatoryCertification::decode_list(&value).ok_or_else(|| {
+                invalid_value("IEEE 11073-20601 Regulatory Certification Data List")
+            })?;
+            info.regulatory_certifications = Some(list);
+        }
+        if let Some(value) = read(characteristic::PNP_ID).await? {
+            let pnp_id = PnpId::from_bytes(&value).ok_or_else(|| invalid_value("PnP ID"))?;
+            info.pnp_id = Some(pnp_id);
+        }
+        Ok(info)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strings_lose_nul_padding_and_invalid_utf8() {
+        assert_eq!(decode_string(b"Acme\0\0"), "Acme");
+        assert_eq!(decode_string(b"\0\0"), "");
+        assert_eq!(decode_string(b""), "");
+        assert_eq!(decode_string(b"A\xffB"), "A\u{fffd}B");
+    }
+
+    #[test]
+    fn decodes_pnp_id() {
+        let pnp_id = PnpId::from_bytes(&[0x02, 0xac, 0x05, 0x4c, 0x02, 0x23, 0x01]).unwrap();
+        assert_eq!(pnp_id.vendor_id_source, VendorIdSource::Usb);
+        assert_eq!(pnp_id.vendor_id, 0x05ac);
+        assert_eq!(pnp_id.product_id, 0x024c);
+        assert_eq!(pnp_id.version(), (1, 2, 3));
+    }
+
+    #[test]
+    fn versions_are_binary_coded_decimal() {
+        let pnp_id = |product_version| PnpId {
+            vendor_id_source: VendorIdSource::Bluetooth,
+            vendor_id: 0,
+            product_id: 0,
+            product_version,
+        };
+        assert_eq!(pnp_id(0x1234).version(), (12, 3, 4));
+        assert_eq!(pnp_id(0x9999).version(), (99, 9, 9));
+        assert_eq!(pnp_id(0x0000).version(), (0, 0, 0));
+    }
+
+    #[test]
+    fn pnp_ids_of_the_wrong_length_are_rejected() {
+        assert_eq!(PnpId::from_bytes(&[]), None);
+        assert_eq!(
+            PnpId::from_bytes(&[0x01, 0x4c, 0x00, 0x01, 0x00, 0x10]),
+            None
+        );
+        assert_eq!(
+            PnpId::from_bytes(&[0x01, 0x4c, 0x00, 0x01, 0x00, 0x10, 0x00, 0x00]),
+            None
+        );
+    }
+
+    #[test]
+    fn reserved_vendor_id_sources_are_kept() {
+        let pnp_id = PnpId::from_bytes(&[0x00, 0, 0, 0, 0, 0, 0]).unwrap();
+        assert_eq!(pnp_id.vendor_id_source, VendorIdSource::Reserved(0));
+        let pnp_id = PnpId::from_bytes(&[0xff, 0, 0, 0, 0, 0, 0]).unwrap();
+        assert_eq!(pnp_id.vendor_id_source, VendorIdSource::Reserved(0xff));
+    }
+
+    #[test]
+    fn decodes_system_id() {
+        let system_id =
+            SystemId::from_bytes(&[0x01, 0x02, 0x03, 0x04, 0x05, 0x0a, 0x0b, 0x0c]).unwrap();
+        assert_eq!(system_id.manufacturer_identifier, 0x05_0403_0201);
+        assert_eq!(system_id.organizationally_unique_identifier, 0x0c0b0a);
+        assert_eq!(SystemId::from_bytes(&[0; 7]), None);
+        assert_eq!(SystemId::from_bytes(&[0; 9]), None);
+    }
+
+    #[test]
+    fn decodes_certification_lists() {
+        assert_eq!(
+            RegulatoryCertification::decode_list(&[0x00, 0x00, 0x00, 0x00]),
+            Some(Vec::new())
+        );
+
+        let value = [
+            0x00, 0x02, 0x00, 0x0b, // two entries, eleven bytes
+            0x01, 0x01, 0x00, 0x01, 0xaa, // IEEE 11073
+            0xfe, 0x00, 0x00, 0x02, 0xbb, 0xcc, // experimental
+        ];
+        let list = RegulatoryCertification::decode_list(&value).unwrap();
+        assert_eq!(
+            list,
+            [
+                RegulatoryCertification {
+                    authorizing_body: RegulatoryCertification::IEEE_11073,
+                    structure_type: 1,
+                    data: vec![0xaa],
+                },
+                RegulatoryCertification {
+                    authorizing_body: RegulatoryCertification::EXPERIMENTAL,
+                    structure_type: 0,
+                    data: vec![0xbb, 0xcc],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn truncated_certification_lists_are_rejected() {
+        assert_eq!(RegulatoryCertification::decode_list(&[]), None);
+        assert_eq!(
+            RegulatoryCertification::decode_list(&[0x00, 0x01, 0x00]),
+            None
+        );
+        // The entry header is cut short.
+        assert_eq!(
+            RegulatoryCertification::decode_list(&[0x00, 0x01, 0x00, 0x03, 0x02, 0x02, 0x00]),
+            None
+        );
+        // The entry's data is cut short.
+        assert_eq!(
+            RegulatoryCertification::decode_list(&[
+                0x00, 0x01, 0x00, 0x05, 0x02, 0x02, 0x00, 0x02, 0x00
+            ]),
+            None
+        );
+    }
+
+    #[test]
+    fn certification_lists_with_mismatched_lengths_are_rejected() {
+        let entry = [0x02, 0x02, 0x00, 0x02, 0x00, 0x01];
+        let list = |count: u16, len: u16, entries: &[u8]| {
+            let mut value = Vec::new();
+            value.extend(count.to_be_bytes());
+            value.extend(len.to_be_bytes());
+            value.extend(entries);
+            RegulatoryCertification::decode_list(&value)
+        };
+
+        assert_eq!(list(1, 6, &entry).map(|list| list.len()), Some(1));
+        // The length is shorter or longer than the entries.
+        assert_eq!(list(1, 5, &entry), None);
+        assert_eq!(list(1, 7, &entry), None);
+        // There are more entries than counted.
+        assert_eq!(list(1, 12, &[entry, entry].concat()), None);
+        assert_eq!(list(0, 6, &entry), None);
+        // There are fewer entries than counted.
+        assert_eq!(list(2, 6, &entry), None);
+    }
+
+    #[test]
+    fn certification_entries_cannot_overrun_the_list() {
+        // An entry that claims more data than the list holds.
+        assert_eq!(
+            RegulatoryCertification::decode_list(&[
+                0x00, 0x01, 0x00, 0x06, 0x02, 0x02, 0xff, 0xff, 0x00, 0x01
+            ]),
+            None
+        );
+    }
+}
@@ -0,0 +1,628 @@
+//! A client for the Nordic UART Service (NUS).
+//!
+//! NUS carries a byte stream in each direction over a single service: the client writes to the
+//! [RX characteristic][RX_CHARACTERISTIC] with writes without response, and the peripheral sends
+//! notifications of the [TX characteristic][TX_CHARACTERISTIC]. Message boundaries are not
+//! preserved, so [`NusStream`] exposes the service as a [`futures_io::AsyncRead`] and
+//! [`futures_io::AsyncWrite`] byte stream:
+//!
+//! - Writes are split into chunks of at most the maximum write without response length, which
+//!   depends on the negotiated ATT MTU.
+//! - Chunks are only sent when the peripheral is ready to accept another write without response,
+//!   so a fast writer waits for the link instead of overrunning CoreBluetooth's transmit buffer.
+//! - Notifications are buffered without loss until they are read.
+//!
+//! On macOS and iOS, `open()` finds the service of a connected `PeripheralAsync`, subscribes to
+//! TX and returns a stream. On every platform, [`SimulatedPeripheral`] provides a NUS peripheral
+//! in memory for testing code written against [`NusStream`]:
+//!
+//! ```
+//! use corebluetooth_async::profiles::nus::SimulatedPeripheral;
+//! use futures_lite::{AsyncReadExt, AsyncWriteExt, future};
+//!
+//! // A peripheral with a 20-byte MTU payload that buffers at most two writes.
+//! let peripheral = SimulatedPeripheral::new(20, 2);
+//! let mut stream = peripheral.connect();
+//!
+//! let echo = async {
+//!     let mut chunks = 0;
+//!     while let Some(chunk) = peripheral.receive().await {
+//!         assert!(chunk.len() <= 20);
+//!         chunks += 1;
+//!         peripheral.notify(&chunk);
+//!     }
+//!     chunks
+//! };
+//! let client = async {
+//!     let message = [0x55; 50];
+//!     stream.write_all(&message).await?;
+//!     stream.flush().await?;
+//!
+//!     let mut echoed = [0; 50];
+//!     stream.read_exact(&mut echoed).await?;
+//!     assert_eq!(echoed, message);
+//!
+//!     peripheral.disconnect();
+//!     assert_eq!(stream.read(&mut echoed).await?, 0);
+//!     std::io::Result::Ok(())
+//! };
+//! let (chunks, res) = future::block_on(future::zip(echo, client));
+//! res?;
+//! assert_eq!(chunks, 3);
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+use std::collections::VecDeque;
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker, ready};
+
+use btuuid::BluetoothUuid;
+use futures_core::Stream;
+use futures_io::{AsyncRead, AsyncWrite};
+use futures_sink::Sink;
+
+use crate::error::{CBATTError, CBError, Error, ErrorKind, Result};
+
+#[cfg(target_vendor = "apple")]
+pub use apple::*;
+
+/// The Nordic UART Service.
+pub const SERVICE: BluetoothUuid = BluetoothUuid::from_u128(0x6e400001_b5a3_f393_e0a9_e50e24dcca9e);
+
+/// The RX characteristic, which the client writes to.
+pub const RX_CHARACTERISTIC: BluetoothUuid =
+    BluetoothUuid::from_u128(0x6e400002_b5a3_f393_e0a9_e50e24dcca9e);
+
+/// The TX characteristic, which the peripheral notifies.
+pub const TX_CHARACTERISTIC: BluetoothUuid =
+    BluetoothUuid::from_u128(0x6e400003_b5a3_f393_e0a9_e50e24dcca9e);
+
+/// A byte stream over the RX and TX characteristics of a Nordic UART Service.
+///
+/// `rx` is a [`Sink`] of writes without response to the RX characteristic that applies flow
+/// control in [`poll_ready()`][Sink::poll_ready], and `tx` is a [`Stream`] of the TX
+/// characteristic's notifications. Each write to the stream sends at most one chunk of
+/// `max_write_len` bytes; reads return the bytes of one or more notifications.
+///
+/// Reads return `Ok(0)` once `tx` ends. Errors from either half are returned as [`io::Error`]s
+/// wrapping the [`Error`], with a kind of [`io::ErrorKind::NotConnected`] if the peripheral is not
+/// connected.
+#[derive(Debug)]
+pub struct NusStream<W, R> {
+    rx: W,
+    tx: R,
+    max_write_len: usize,
+    read_buf: Vec<u8>,
+    read_pos: usize,
+}
+
+impl<W, R> NusStream<W, R> {
+    /// Creates a stream from the two halves of a Nordic UART Service.
+    pub fn new(rx: W, tx: R, max_write_len: usize) -> Self {
+        Self {
+            rx,
+            tx,
+            max_write_len: max_write_len.max(1),
+            read_buf: Vec::new(),
+            read_pos: 0,
+        }
+    }
+
+    /// The largest chunk sent in a single write.
+    pub fn max_write_len(&self) -> usize {
+        self.max_write_len
+    }
+
+    /// The sink of writes to the RX characteristic.
+    pub fn rx(&self) -> &W {
+        &self.rx
+    }
+
+    /// The stream of notifications of the TX characteristic.
+    pub fn tx(&self) -> &R {
+        &self.tx
+    }
+
+    /// Returns the two halves of the stream.
+    ///
+    /// Bytes of a notification that have not been read are discarded.
+    pub fn into_inner(self) -> (W, R) {
+        (self.rx, self.tx)
+    }
+}
+
+impl<W, R> AsyncRead for NusStream<W, R>
+where
+    W: Unpin,
+    R: Stream<Item = Result<Vec<u8>>> + Unpin,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+
+        while this.read_pos == this.read_buf.len() {
+            match ready!(Pin::new(&mut this.tx).poll_next(cx)) {
+                Some(Ok(value)) => {
+                    this.read_buf = value;
+                    this.read_pos = 0;
+                }
+                Some(Err(err)) => return Poll::Ready(Err(io_error(err))),
+                None => return Poll::Ready(Ok(0)),
+            }
+        }
+
+        let available = &this.read_buf[this.read_pos..];
+        let len = available.len().min(buf.len());
+        buf[..len].copy_from_slice(&available[..len]);
+        this.read_pos += len;
+        Poll::Ready(Ok(len))
+    }
+}
+
+impl<W, R> AsyncWrite for NusStream<W, R>
+where
+    W: Sink<Vec<u8>, Error = Error> + Unpin,
+    R: Unpin,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+
+        ready!(Pin::new(&mut this.rx).poll_ready(cx)).map_err(io_error)?;
+        let len = buf.len().min(this.max_write_len);
+        Pin::new(&mut this.rx)
+            .start_send(buf[..len].to_vec())
+            .map_err(io_error)?;
+        Poll::Ready(Ok(len))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().rx)
+            .poll_flush(cx)
+            .map_err(io_error)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().rx)
+            .poll_close(cx)
+            .map_err(io_error)
+    }
+}
+
+fn io_error(error: Error) -> io::Error {
+    let kind = match error.kind() {
+        ErrorKind::Io(kind) => kind,
+        ErrorKind::Bluetooth(CBError::NotConnected | CBError::PeripheralDisconnected) => {
+            io::ErrorKind::NotConnected
+        }
+        _ => io::ErrorKind::Other,
+    };
+    io::Error::new(kind, error)
+}
+
+/// A Nordic UART Service peripheral simulated in memory.
+///
+/// The peripheral receives the chunks written by the client of [`connect()`][Self::connect] and
+/// sends notifications to it. It accepts at most `capacity` writes that it has not
+/// [received][Self::receive] yet, after which the client's writes wait, as they would for a
+/// peripheral whose transmit buffer is full. Clones refer to the same peripheral.
+#[derive(Debug, Clone)]
+pub struct SimulatedPeripheral {
+    state: Arc<Mutex<SimulatedState>>,
+}
+
+#[derive(Debug)]
+struct SimulatedState {
+    max_write_len: usize,
+    capacity: usize,
+    connected: bool,
+    writes: VecDeque<Vec<u8>>,
+    notifications: VecDeque<Vec<u8>>,
+    /// The client waiting for room for another write.
+    writer: Option<Waker>,
+    /// The client waiting for a notification.
+    reader: Option<Waker>,
+    /// The peripheral waiting for a write.
+    receiver: Option<Waker>,
+}
+
+impl SimulatedState {
+    fn wake_all(&mut self) {
+        for waker in [self.writer.take(), self.reader.take(), self.receiver.take()]
+            .into_iter()
+            .flatten()
+        {
+            waker.wake();
+        }
+    }
+}
+
+impl SimulatedPeripheral {
+    /// Creates a disconnected peripheral that accepts writes of at most `max_write_len` bytes
+    /// and buffers at most `capacity` writes.
+    pub fn new(max_write_len: usize, capacity: usize) -> Self {
+        let state = SimulatedState {
+            max_write_len: max_write_len.max(1),
+            capacity: capacity.max(1),
+            connected: false,
+            writes: VecDeque::new(),
+            notifications: VecDeque::new(),
+            writer: None,
+            reader: None,
+            receiver: None,
+        };
+        Self {
+            state: Arc::new(Mutex::new(state)),
+        }
+    }
+
+    /// Connects a client to the peripheral and subscribes it to TX.
+    ///
+    /// Writes and notifications left over from a previous connection are discarded.
+    pub fn connect(&self) -> NusStream<SimulatedRx, SimulatedTx> {
+        let mut state = self.state.lock().unwrap();
+        state.connected = true;
+        state.writes.clear();
+        state.notifications.clear();
+        let max_write_len = state.max_write_len;
+        drop(state);
+
+        let rx = SimulatedRx {
+            state: self.state.clone(),
+        };
+        let tx = SimulatedTx {
+            state: self.state.clone(),
+        };
+        NusStream::new(rx, tx, max_write_len)
+    }
+
+    /// Disconnects the client.
+    ///
+    /// The client's writes fail and its reads end once it has read the notifications already
+    /// sent. Writes already accepted can still be received.
+    pub fn disconnect(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.connected = false;
+        state.wake_all();
+    }
+
+    /// Returns whether a client is connected.
+    pub fn is_connected(&self) -> bool {
+        self.state.lock().unwrap().connected
+    }
+
+    /// Sends `data` to the client as notifications of at most `max_write_len` bytes each.
+    ///
+    /// Does nothing if no client is connected.
+    pub fn notify(&self, data: &[u8]) {
+        let mut state = self.state.lock().unwrap();
+        if !state.connected {
+            return;
+        }
+        let max_len = state.max_write_len;
+        state
+            .notifications
+            .extend(data.chunks(max_len).map(<[u8]>::to_vec));
+        if let Some(waker) = state.reader.take() {
+            waker.wake();
+        }
+    }
+
+    /// Polls for the next chunk written by the client.
+    ///
+    /// Returns `None` once the client has disconnected and every accepted write has been
+    /// received.
+    pub fn poll_receive(&self, cx: &mut Context<'_>) -> Poll<Option<Vec<u8>>> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(chunk) = state.writes.pop_front() {
+            if let Some(waker) = state.writer.take() {
+                waker.wake();
+            }
+            Poll::Ready(Some(chunk))
+        } else if !state.connected {
+            Poll::Ready(None)
+        } else {
+            state.receiver = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+
+    /// Waits for the next chunk written by the client.
+    ///
+    /// See [`poll_receive()`][Self::poll_receive].
+    pub async fn receive(&self) -> Option<Vec<u8>> {
+        std::future::poll_fn(|cx| self.poll_receive(cx)).await
+    }
+}
+
+/// The RX half of a client of a [`SimulatedPeripheral`].
+#[derive(Debug)]
+pub struct SimulatedRx {
+    state: Arc<Mutex<SimulatedState>>,
+}
+
+impl Sink<Vec<u8>> for SimulatedRx {
+    type Error = Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        let mut state = self.state.lock().unwrap();
+        if !state.connected {
+            Poll::Ready(Err(ErrorKind::Bluetooth(CBError::NotConnected).into()))
+        } else if state.writes.len() < state.capacity {
+            Poll::Ready(Ok(()))
+        } else {
+            state.writer = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: Vec<u8>) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        if !state.connected {
+            return Err(ErrorKind::Bluetooth(CBError::NotConnected).into());
+        }
+        if item.len() > state.max_write_len {
+            return Err(ErrorKind::ATT(CBATTError::InvalidAttributeValueLength).into());
+        }
+        state.writes.push_back(item);
+        if let Some(waker) = state.receiver.take() {
+            waker.wake();
+        }
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<()>> {
+        // Writes are delivered to the peripheral as soon as they are accepted.
+        if self.state.lock().unwrap().connected {
+            Poll::Ready(Ok(()))
+        } else {
+            Poll::Ready(Err(ErrorKind::Bluetooth(CBError::NotConnected).into()))
+        }
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        self.poll_flush(cx)
+    }
+}
+
+/// The TX half of a client of a [`SimulatedPeripheral`].
+#[derive(Debug)]
+pub struct SimulatedTx {
+    state: Arc<Mutex<SimulatedState>>,
+}
+
+impl Stream for SimulatedTx {
+    type Item = Result<Vec<u8>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(value) = state.notifications.pop_front() {
+            Poll::Ready(Some(Ok(value)))
+        } else if !state.connected {
+            Poll::Ready(None)
+        } else {
+            state.reader = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+#[cfg(target_vendor = "apple")]
+mod apple {
+    use corebluetooth::CharacteristicWriteType;
+
+    use super::{NusStream, RX_CHARACTERISTIC, SERVICE, TX_CHARACTERISTIC};
+    use crate::error::Result;
+    use crate::shared::find_characteristic;
+    use crate::{
+        CharacteristicWriter, DeliveryPolicy, NotificationReceiver, PeripheralAsync, WriteStream,
+    };
+
+    /// The number of chunks queued by the stream returned by [`open()`] while it waits for the
+    /// peripheral to be ready.
+    pub const WRITE_QUEUE_CAPACITY: usize = 8;
+
+    /// A [`NusStream`] over the characteristics of a [`PeripheralAsync`].
+    pub type PeripheralNusStream =
+        NusStream<WriteStream<CharacteristicWriter>, NotificationReceiver<Result<Vec<u8>>>>;
+
+    /// Opens a Nordic UART Service stream to a connected peripheral.
+    ///
+    /// The service and its characteristics are discovered if necessary, and notifications of the
+    /// TX characteristic are enabled. Notifications stay enabled when the stream is dropped.
+    ///
+    /// The notification stream does not end when the peripheral disconnects; use
+    /// [`CentralManagerAsync::disconnections()`][crate::CentralManagerAsync::disconnections] to
+    /// detect disconnection. Writes fail once the peripheral has disconnected.
+    pub async fn open(peripheral: &PeripheralAsync) -> Result<PeripheralNusStream> {
+        let rx = find_characteristic(peripheral, SERVICE, RX_CHARACTERISTIC).await?;
+        let tx = find_characteristic(peripheral, SERVICE, TX_CHARACTERISTIC).await?;
+
+        let notifications =
+            peripheral.characteristic_value_updates_with_policy(&tx, DeliveryPolicy::Unbounded);
+        peripheral.set_notify(&tx, true).await?;
+
+        let max_write_len =
+            peripheral.max_write_value_len(CharacteristicWriteType::WithoutResponse);
+        let writes = peripheral.write_stream(&rx, WRITE_QUEUE_CAPACITY);
+        Ok(NusStream::new(writes, notifications, max_write_len))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::task::Wake;
+
+    use futures_lite::{AsyncReadExt, AsyncWriteExt, future};
+
+    use super::*;
+
+    /// A waker that counts how often it has been woken.
+    #[derive(Default)]
+    struct CountingWaker(AtomicUsize);
+
+    impl Wake for CountingWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    impl CountingWaker {
+        fn count(&self) -> usize {
+            self.0.load(Ordering::SeqCst)
+        }
+    }
+
+    fn poll_write(
+        stream: &mut NusStream<SimulatedRx, SimulatedTx>,
+        waker: &Arc<CountingWaker>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let waker = Waker::from(waker.clone());
+        Pin::new(stream).poll_write(&mut Context::from_waker(&waker), buf)
+    }
+
+    #[test]
+    fn writes_are_split_into_chunks() {
+        let peripheral = SimulatedPeripheral::new(4, 8);
+        let mut stream = peripheral.connect();
+        future::block_on(stream.write_all(b"0123456789")).unwrap();
+
+        let mut chunks = Vec::new();
+        while let Some(chunk) = future::block_on(future::poll_once(peripheral.receive())).flatten()
+        {
+            chunks.push(chunk);
+        }
+        assert_eq!(chunks, [&b"0123"[..], b"4567", b"89"]);
+    }
+
+    #[test]
+    fn writes_wait_while_the_peripheral_is_full() {
+        let peripheral = SimulatedPeripheral::new(4, 2);
+        let mut stream = peripheral.connect();
+        let waker = Arc::new(CountingWaker::default());
+
+        assert!(matches!(
+            poll_write(&mut stream, &waker, b"aaaa"),
+            Poll::Ready(Ok(4))
+        ));
+        assert!(matches!(
+            poll_write(&mut stream, &waker, b"bbbb"),
+            Poll::Ready(Ok(4))
+        ));
+        assert!(poll_write(&mut stream, &waker, b"cccc").is_pending());
+        assert_eq!(waker.count(), 0);
+
+        // Receiving a write makes room for one more.
+        assert_eq!(future::block_on(peripheral.receive()).unwrap(), b"aaaa");
+        assert_eq!(waker.count(), 1);
+        assert!(matches!(
+            poll_write(&mut stream, &waker, b"cccc"),
+            Poll::Ready(Ok(4))
+        ));
+        assert!(poll_write(&mut stream, &waker, b"dddd").is_pending());
+
+        assert_eq!(future::block_on(peripheral.receive()).unwrap(), b"bbbb");
+        assert_eq!(future::block_on(peripheral.receive()).unwrap(), b"cccc");
+    }
+
+    #[test]
+    fn disconnecting_fails_a_waiting_write() {
+        let peripheral = SimulatedPeripheral::new(4, 1);
+        let mut stream = peripheral.connect();
+        let waker = Arc::new(CountingWaker::default());
+
+        assert!(matches!(
+            poll_write(&mut stream, &waker, b"aaaa"),
+            Poll::Ready(Ok(4))
+        ));
+        assert!(poll_write(&mut stream, &waker, b"bbbb").is_pending());
+
+        peripheral.disconnect();
+        assert_eq!(waker.count(), 1);
+        let Poll::Ready(Err(err)) = poll_write(&mut stream, &waker, b"bbbb") else {
+            panic!("the write did not fail");
+        };
+        assert_eq!(err.kind(), io::ErrorKind::NotConnected);
+
+        // The write accepted before the disconnection is still delivered.
+        assert_eq!(future::block_on(peripheral.receive()).unwrap(), b"aaaa");
+        assert_eq!(future::block_on(peripheral.receive()), None);
+    }
+
+    #[test]
+    fn disconnecting_during_write_all_stops_it() {
+        let peripheral = SimulatedPeripheral::new(4, 1);
+        let mut stream = peripheral.connect();
+
+        let receiver = async {
+            let first = peripheral.receive().await;
+            peripheral.disconnect();
+            first
+        };
+        let (first, res) = future::block_on(future::zip(receiver, stream.write_all(&[7; 16])));
+        assert_eq!(first.unwrap(), [7; 4]);
+        assert_eq!(res.unwrap_err().kind(), io::ErrorKind::NotConnected);
+        assert_eq!(
+            future::block_on(stream.flush()).unwrap_err().kind(),
+            io::ErrorKind::NotConnected
+        );
+    }
+
+    #[test]
+    fn oversized_chunks_are_rejected() {
+        let peripheral = SimulatedPeripheral::new(4, 1);
+        let (mut rx, _tx) = peripheral.connect().into_inner();
+        let err = Pin::new(&mut rx).start_send(vec![0; 5]).unwrap_err();
+        assert_eq!(
+            err.kind(),
+            ErrorKind::ATT(CBATTError::InvalidAttributeValueLength)
+        );
+    }
+
+    #[test]
+    fn reads_drain_notifications_before_ending() {
+        let peripheral = SimulatedPeripheral::new(4, 1);
+        let mut stream = peripheral.connect();
+        peripheral.notify(b"hello world");
+        peripheral.disconnect();
+        // Notifications sent while disconnected are dropped.
+        peripheral.notify(b"lost");
+
+        let mut received = Vec::new();
+        future::block_on(stream.read_to_end(&mut received)).unwrap();
+        assert_eq!(received, b"hello world");
+    }
+
+    #[test]
+    fn reconnecting_discards_leftovers() {
+        let peripheral = SimulatedPeripheral::new(4, 2);
+        let mut stream = peripheral.connect();
+        future::block_on(stream.write_all(b"old")).unwrap();
+        peripheral.notify(b"old");
+        peripheral.disconnect();
+
+        let mut stream = peripheral.connect();
+        assert!(peripheral.is_connected());
+        peripheral.notify(b"new");
+        let mut buf = [0; 3];
+        future::block_on(stream.read_exact(&mut buf)).unwrap();
+        assert_eq!(&buf, b"new");
+        assert!(future::block_on(future::poll_once(peripheral.receive())).is_none());
+    }
+}
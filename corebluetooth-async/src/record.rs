@@ -0,0 +1,1476 @@
+//! Recording and replaying the callbacks delivered by CoreBluetooth.
+//!
+//! When a [`Recorder`] is installed with [`set_recorder()`], every delegate callback received by
+//! [`CentralManagerAsync`] and [`PeripheralAsync`] is written to it as an [`Event`], together with
+//! the time since the recording started. Recordings are versioned text files containing a header
+//! line followed by one JSON object per event.
+//!
+//! [`Recording::read()`] loads a recording on any platform, including those without
+//! CoreBluetooth, and [`Recording::replay()`] plays its events back as a [`Stream`], either as
+//! fast as possible or with the original timing. [`Replay::run()`] instead delivers each event to
+//! a [`ReplayCentralDelegate`] and the [`ReplayPeripheralDelegate`]s it creates, in the same
+//! shape as the delegate callbacks CoreBluetooth delivers. Because events refer to peripherals and
+//! attributes by identifier and UUID, application logic that handles them can be rerun
+//! deterministically away from the device on which the problem occurred.
+//!
+//! Replayed events are not delivered to the delegates of [`CentralManagerAsync`] and
+//! [`PeripheralAsync`] themselves. Their callbacks receive `CBPeripheral`, `CBCharacteristic` and
+//! other CoreBluetooth objects, which only CoreBluetooth can create and which do not exist on
+//! other platforms, and they read values such as a characteristic's from those objects rather than
+//! from the callback's arguments. The replay delegate traits mirror those callbacks with the
+//! identifiers and values that were recorded instead.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use std::fs::File;
+//! use std::io::BufReader;
+//! use std::sync::Arc;
+//!
+//! use corebluetooth_async::record::{self, Pacing, Recorder, Recording};
+//! use futures_lite::StreamExt;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! // While reproducing the problem:
+//! let recorder = Recorder::new(File::create("session.cbrec")?)?;
+//! record::set_recorder(Arc::new(recorder));
+//!
+//! // Later, anywhere:
+//! let recording = Recording::read(BufReader::new(File::open("session.cbrec")?))?;
+//! let mut replay = recording.replay(Pacing::Immediate);
+//! while let Some(record) = replay.next().await {
+//!     println!("{:?} {:?}", record.elapsed, record.event);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Replaying a recording through delegates:
+//!
+//! ```no_run
+//! use std::fs::File;
+//! use std::io::BufReader;
+//!
+//! use corebluetooth_async::record::{
+//!     Pacing, Recording, ReplayCentralDelegate, ReplayPeripheralDelegate,
+//! };
+//! use uuid::Uuid;
+//!
+//! struct Central;
+//!
+//! impl ReplayCentralDelegate for Central {
+//!     fn new_peripheral_delegate(&self, _peripheral: Uuid) -> Box<dyn ReplayPeripheralDelegate> {
+//!         Box::new(Peripheral)
+//!     }
+//!
+//!     fn did_connect(&self, peripheral: Uuid) {
+//!         println!("{peripheral} connected");
+//!     }
+//! }
+//!
+//! struct Peripheral;
+//!
+//! impl ReplayPeripheralDelegate for Peripheral {
+//!     fn did_read_rssi(&self, peripheral: Uuid, rssi: corebluetooth_async::error::Result<i16>) {
+//!         println!("{peripheral} RSSI {rssi:?}");
+//!     }
+//! }
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let recording = Recording::read(BufReader::new(File::open("session.cbrec")?))?;
+//! recording.replay(Pacing::RealTime).run(&Central).await;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`CentralManagerAsync`]: crate::CentralManagerAsync
+//! [`PeripheralAsync`]: crate::PeripheralAsync
+//! [`Stream`]: futures_core::Stream
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::io::{self, BufRead, Write};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, RwLock};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant, SystemTime};
+
+use btuuid::BluetoothUuid;
+use futures_core::Stream;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::error::{Error, ErrorKind, Result};
+use crate::{CBConnectionEvent, CBManagerState};
+
+/// The version of the recording format written by [`Recorder`].
+///
+/// Version 2 added the [`Event::CharacteristicWriteRequested`] and
+/// [`Event::DescriptorWriteRequested`] events and the MTU of [`Event::Connected`]. Recordings of
+/// any earlier version can be read.
+pub const FORMAT_VERSION: u32 = 2;
+
+const FORMAT_NAME: &str = "corebluetooth-recording";
+
+#[derive(Serialize, Deserialize)]
+struct Header {
+    format: String,
+    version: u32,
+    /// Milliseconds since the Unix epoch.
+    started_at: u64,
+}
+
+/// A callback delivered by CoreBluetooth.
+///
+/// Each variant corresponds to a method of `CentralManagerDelegate` or `PeripheralDelegate`,
+/// except for [`CharacteristicWriteRequested`][Self::CharacteristicWriteRequested] and
+/// [`DescriptorWriteRequested`][Self::DescriptorWriteRequested], which record the values written
+/// through [`PeripheralAsync`][crate::PeripheralAsync] because no callback reports them.
+/// Peripherals are identified by their identifier, and attributes by their UUID together
+/// with the UUIDs of the attributes that contain them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    /// The state of the central manager changed.
+    StateUpdated {
+        /// The raw value of the new `CBManagerState`.
+        state: isize,
+    },
+    /// A scan discovered a peripheral.
+    Discovered {
+        /// The peripheral.
+        peripheral: Uuid,
+        /// The name of the peripheral, if known.
+        name: Option<String>,
+        /// The advertisement data.
+        advertisement_data: RecordedAdvertisementData,
+        /// The RSSI.
+        rssi: i16,
+    },
+    /// A connection was established.
+    Connected {
+        /// The peripheral.
+        peripheral: Uuid,
+        /// The ATT MTU reported by CoreBluetooth when the connection was established.
+        #[serde(default)]
+        mtu: Option<u16>,
+    },
+    /// A connection attempt failed.
+    FailedToConnect {
+        /// The peripheral.
+        peripheral: Uuid,
+        /// The error.
+        error: RecordedError,
+    },
+    /// A peripheral disconnected.
+    Disconnected {
+        /// The peripheral.
+        peripheral: Uuid,
+        /// Whether the peripheral is being reconnected.
+        is_reconnecting: bool,
+        /// The error that caused the disconnection, if any.
+        error: Option<RecordedError>,
+    },
+    /// A connection event matching the registered options occurred.
+    ConnectionEvent {
+        /// The peripheral.
+        peripheral: Uuid,
+        /// The raw value of the `CBConnectionEvent`.
+        event: isize,
+    },
+    /// The ANCS authorization of a peripheral changed.
+    AncsAuthorizationUpdated {
+        /// The peripheral.
+        peripheral: Uuid,
+    },
+    /// The name of a peripheral changed.
+    NameUpdated {
+        /// The peripheral.
+        peripheral: Uuid,
+        /// The new name.
+        name: Option<String>,
+    },
+    /// Services of a peripheral were invalidated.
+    ServicesModified {
+        /// The peripheral.
+        peripheral: Uuid,
+        /// The invalidated services.
+        invalidated_services: Vec<BluetoothUuid>,
+    },
+    /// The RSSI of a connected peripheral was read.
+    RssiRead {
+        /// The peripheral.
+        peripheral: Uuid,
+        /// The RSSI, if the read succeeded.
+        rssi: Option<i16>,
+        /// The error, if the read failed.
+        error: Option<RecordedError>,
+    },
+    /// Service discovery completed.
+    ServicesDiscovered {
+        /// The peripheral.
+        peripheral: Uuid,
+        /// The services of the peripheral after discovery.
+        services: Vec<BluetoothUuid>,
+        /// The error, if discovery failed.
+        error: Option<RecordedError>,
+    },
+    /// Included service discovery completed.
+    IncludedServicesDiscovered {
+        /// The peripheral.
+        peripheral: Uuid,
+        /// The service.
+        service: BluetoothUuid,
+        /// The included services of the service after discovery.
+        included_services: Vec<BluetoothUuid>,
+        /// The error, if discovery failed.
+        error: Option<RecordedError>,
+    },
+    /// Characteristic discovery completed.
+    CharacteristicsDiscovered {
+        /// The peripheral.
+        peripheral: Uuid,
+        /// The service.
+        service: BluetoothUuid,
+        /// The characteristics of the service after discovery.
+        characteristics: Vec<BluetoothUuid>,
+        /// The error, if discovery failed.
+        error: Option<RecordedError>,
+    },
+    /// Descriptor discovery completed.
+    DescriptorsDiscovered {
+        /// The peripheral.
+        peripheral: Uuid,
+        /// The service containing the characteristic.
+        service: Option<BluetoothUuid>,
+        /// The characteristic.
+        characteristic: BluetoothUuid,
+        /// The descriptors of the characteristic after discovery.
+        descriptors: Vec<BluetoothUuid>,
+        /// The error, if discovery failed.
+        error: Option<RecordedError>,
+    },
+    /// A characteristic value was read or notified.
+    CharacteristicValueUpdated {
+        /// The peripheral.
+        peripheral: Uuid,
+        /// The service containing the characteristic.
+        service: Option<BluetoothUuid>,
+        /// The characteristic.
+        characteristic: BluetoothUuid,
+        /// Whether notifications were enabled for the characteristic.
+        is_notifying: bool,
+        /// The value. Empty if the update failed.
+        #[serde(with = "hex")]
+        value: Vec<u8>,
+        /// The error, if the update failed.
+        error: Option<RecordedError>,
+    },
+    /// A characteristic write was handed to CoreBluetooth.
+    CharacteristicWriteRequested {
+        /// The peripheral.
+        peripheral: Uuid,
+        /// The service containing the characteristic.
+        service: Option<BluetoothUuid>,
+        /// The characteristic.
+        characteristic: BluetoothUuid,
+        /// Whether the write is a write with response, which completes with a
+        /// [`CharacteristicValueWritten`][Self::CharacteristicValueWritten] event.
+        with_response: bool,
+        /// The value.
+        #[serde(with = "hex")]
+        value: Vec<u8>,
+    },
+    /// A characteristic write completed.
+    CharacteristicValueWritten {
+        /// The peripheral.
+        peripheral: Uuid,
+        /// The service containing the characteristic.
+        service: Option<BluetoothUuid>,
+        /// The characteristic.
+        characteristic: BluetoothUuid,
+        /// The error, if the write failed.
+        error: Option<RecordedError>,
+    },
+    /// Notifications were enabled or disabled for a characteristic.
+    NotificationStateUpdated {
+        /// The peripheral.
+        peripheral: Uuid,
+        /// The service containing the characteristic.
+        service: Option<BluetoothUuid>,
+        /// The characteristic.
+        characteristic: BluetoothUuid,
+        /// Whether notifications are enabled.
+        is_notifying: bool,
+        /// The error, if the update failed.
+        error: Option<RecordedError>,
+    },
+    /// A descriptor value was read.
+    DescriptorValueUpdated {
+        /// The peripheral.
+        peripheral: Uuid,
+        /// The characteristic containing the descriptor.
+        characteristic: Option<BluetoothUuid>,
+        /// The descriptor.
+        descriptor: BluetoothUuid,
+        /// The value. Empty if the read failed.
+        #[serde(with = "hex")]
+        value: Vec<u8>,
+        /// The error, if the read failed.
+        error: Option<RecordedError>,
+    },
+    /// A descriptor write was handed to CoreBluetooth.
+    DescriptorWriteRequested {
+        /// The peripheral.
+        peripheral: Uuid,
+        /// The characteristic containing the descriptor.
+        characteristic: Option<BluetoothUuid>,
+        /// The descriptor.
+        descriptor: BluetoothUuid,
+        /// The value.
+        #[serde(with = "hex")]
+        value: Vec<u8>,
+    },
+    /// A descriptor write completed.
+    DescriptorValueWritten {
+        /// The peripheral.
+        peripheral: Uuid,
+        /// The characteristic containing the descriptor.
+        characteristic: Option<BluetoothUuid>,
+        /// The descriptor.
+        descriptor: BluetoothUuid,
+        /// The error, if the write failed.
+        error: Option<RecordedError>,
+    },
+    /// A peripheral is ready to accept more writes without response.
+    ReadyToSendWriteWithoutResponse {
+        /// The peripheral.
+        peripheral: Uuid,
+    },
+    /// An L2CAP channel was opened.
+    L2capChannelOpened {
+        /// The peripheral.
+        peripheral: Uuid,
+        /// The PSM of the channel, if it was opened.
+        psm: Option<u16>,
+        /// The error, if the channel could not be opened.
+        error: Option<RecordedError>,
+    },
+}
+
+impl Event {
+    /// The peripheral that the event is for, if any.
+    pub fn peripheral(&self) -> Option<Uuid> {
+        match self {
+            Event::StateUpdated { .. } => None,
+            Event::Discovered { peripheral, .. }
+            | Event::Connected { peripheral, .. }
+            | Event::FailedToConnect { peripheral, .. }
+            | Event::Disconnected { peripheral, .. }
+            | Event::ConnectionEvent { peripheral, .. }
+            | Event::AncsAuthorizationUpdated { peripheral }
+            | Event::NameUpdated { peripheral, .. }
+            | Event::ServicesModified { peripheral, .. }
+            | Event::RssiRead { peripheral, .. }
+            | Event::ServicesDiscovered { peripheral, .. }
+            | Event::IncludedServicesDiscovered { peripheral, .. }
+            | Event::CharacteristicsDiscovered { peripheral, .. }
+            | Event::DescriptorsDiscovered { peripheral, .. }
+            | Event::CharacteristicValueUpdated { peripheral, .. }
+            | Event::CharacteristicWriteRequested { peripheral, .. }
+            | Event::CharacteristicValueWritten { peripheral, .. }
+            | Event::NotificationStateUpdated { peripheral, .. }
+            | Event::DescriptorValueUpdated { peripheral, .. }
+            | Event::DescriptorWriteRequested { peripheral, .. }
+            | Event::DescriptorValueWritten { peripheral, .. }
+            | Event::ReadyToSendWriteWithoutResponse { peripheral }
+            | Event::L2capChannelOpened { peripheral, .. } => Some(*peripheral),
+        }
+    }
+}
+
+/// Advertisement data included in an [`Event::Discovered`] event.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedAdvertisementData {
+    /// The local name.
+    pub local_name: Option<String>,
+    /// Manufacturer specific data.
+    pub manufacturer_data: Option<RecordedManufacturerData>,
+    /// Service data, ordered by service UUID.
+    pub service_data: Vec<RecordedServiceData>,
+    /// Advertised service UUIDs.
+    pub service_uuids: Vec<BluetoothUuid>,
+    /// Service UUIDs that did not fit in the main advertising packet.
+    pub overflow_service_uuids: Vec<BluetoothUuid>,
+    /// The transmitted power level.
+    pub tx_power_level: Option<i16>,
+    /// Whether the advertising packet was connectable.
+    pub is_connectable: bool,
+    /// Solicited service UUIDs.
+    pub solicited_service_uuids: Vec<BluetoothUuid>,
+}
+
+/// Manufacturer specific data included in [`RecordedAdvertisementData`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedManufacturerData {
+    /// The company identifier.
+    pub company_id: u16,
+    /// The data.
+    #[serde(with = "hex")]
+    pub data: Vec<u8>,
+}
+
+/// Service data included in [`RecordedAdvertisementData`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedServiceData {
+    /// The service UUID.
+    pub uuid: BluetoothUuid,
+    /// The data.
+    #[serde(with = "hex")]
+    pub data: Vec<u8>,
+}
+
+#[cfg(target_vendor = "apple")]
+impl From<&corebluetooth::advertisement_data::AdvertisementData> for RecordedAdvertisementData {
+    fn from(data: &corebluetooth::advertisement_data::AdvertisementData) -> Self {
+        let mut service_data: Vec<_> = data
+            .service_data
+            .iter()
+            .map(|(uuid, data)| RecordedServiceData {
+                uuid: *uuid,
+                data: data.clone(),
+            })
+            .collect();
+        service_data.sort_by_key(|data| data.uuid);
+
+        Self {
+            local_name: data.local_name.clone(),
+            manufacturer_data: data.manufacturer_data.as_ref().map(|data| {
+                RecordedManufacturerData {
+                    company_id: data.company_id,
+                    data: data.data.clone(),
+                }
+            }),
+            service_data,
+            service_uuids: data.service_uuids.clone(),
+            overflow_service_uuids: data.overflow_service_uuids.clone(),
+            tx_power_level: data.tx_power_level,
+            is_connectable: data.is_connectable,
+            solicited_service_uuids: data.solicited_service_uuids.clone(),
+        }
+    }
+}
+
+/// An error included in an [`Event`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedError {
+    /// The kind of error.
+    #[serde(with = "error_kind")]
+    pub kind: ErrorKind,
+    /// The description of the error.
+    pub message: String,
+}
+
+impl From<&Error> for RecordedError {
+    fn from(error: &Error) -> Self {
+        Self {
+            kind: error.kind(),
+            message: error.to_string(),
+        }
+    }
+}
+
+/// The error in `result`, for recording as an optional field.
+#[cfg(target_vendor = "apple")]
+pub(crate) fn error<T>(result: &crate::error::Result<T>) -> Option<RecordedError> {
+    result.as_ref().err().map(Into::into)
+}
+
+/// The UUIDs of `attributes`, or an empty list if they have not been discovered.
+#[cfg(target_vendor = "apple")]
+pub(crate) fn uuids<T>(
+    attributes: Option<Vec<T>>,
+    uuid: impl Fn(&T) -> BluetoothUuid,
+) -> Vec<BluetoothUuid> {
+    attributes.iter().flatten().map(uuid).collect()
+}
+
+impl From<RecordedError> for Error {
+    fn from(error: RecordedError) -> Self {
+        Error::described(error.kind, error.message)
+    }
+}
+
+/// An [`Event`] and the time at which it was recorded.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Record {
+    /// The time since the recording started.
+    #[serde(rename = "t", with = "micros")]
+    pub elapsed: Duration,
+    /// The event.
+    #[serde(flatten)]
+    pub event: Event,
+}
+
+/// Writes [`Event`]s to a recording.
+pub struct Recorder {
+    start: Instant,
+    writer: Mutex<Box<dyn Write + Send>>,
+}
+
+impl std::fmt::Debug for Recorder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Recorder")
+            .field("start", &self.start)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Recorder {
+    /// Starts a recording, writing its header to `writer`.
+    pub fn new(writer: impl Write + Send + 'static) -> io::Result<Self> {
+        let mut writer: Box<dyn Write + Send> = Box::new(writer);
+        let started_at = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default();
+        let header = Header {
+            format: FORMAT_NAME.to_owned(),
+            version: FORMAT_VERSION,
+            started_at: started_at.as_millis() as u64,
+        };
+        serde_json::to_writer(&mut writer, &header)?;
+        writer.write_all(b"\n")?;
+
+        Ok(Self {
+            start: Instant::now(),
+            writer: Mutex::new(writer),
+        })
+    }
+
+    /// Records `event` at the current time.
+    pub fn record(&self, event: Event) -> io::Result<()> {
+        self.record_at(self.start.elapsed(), event)
+    }
+
+    /// Records `event` at the given time since the recording started.
+    ///
+    /// This can be used to construct recordings for tests. Events should be recorded in order.
+    pub fn record_at(&self, elapsed: Duration, event: Event) -> io::Result<()> {
+        let mut writer = self.writer.lock().unwrap();
+        serde_json::to_writer(&mut *writer, &Record { elapsed, event })?;
+        writer.write_all(b"\n")
+    }
+
+    /// Flushes the underlying writer.
+    pub fn flush(&self) -> io::Result<()> {
+        self.writer.lock().unwrap().flush()
+    }
+}
+
+static RECORDER: RwLock<Option<Arc<Recorder>>> = RwLock::new(None);
+
+/// Sets the process-wide recorder, returning the previous recorder.
+pub fn set_recorder(recorder: Arc<Recorder>) -> Option<Arc<Recorder>> {
+    let mut current = RECORDER.write().unwrap_or_else(|err| err.into_inner());
+    current.replace(recorder)
+}
+
+/// Removes the process-wide recorder, returning it.
+pub fn clear_recorder() -> Option<Arc<Recorder>> {
+    let mut current = RECORDER.write().unwrap_or_else(|err| err.into_inner());
+    current.take()
+}
+
+/// Records the event produced by `func` with the process-wide recorder, if one is installed.
+///
+/// Write errors are ignored, as there is nobody to report them to.
+#[cfg(target_vendor = "apple")]
+pub(crate) fn with_recorder(func: impl FnOnce() -> Event) {
+    let recorder = RECORDER
+        .read()
+        .unwrap_or_else(|err| err.into_inner())
+        .clone();
+    if let Some(recorder) = recorder {
+        let _ = recorder.record(func());
+    }
+}
+
+/// An error that occurred while reading a recording.
+#[derive(Debug)]
+pub enum RecordingError {
+    /// The recording could not be read.
+    Io(io::Error),
+    /// The first line of the input is not a recording header.
+    NotARecording,
+    /// The recording was written by a newer, incompatible version of this crate.
+    UnsupportedVersion(u32),
+    /// A line of the recording could not be parsed.
+    Malformed {
+        /// The line number, starting from 1.
+        line: usize,
+        /// The parse error.
+        error: serde_json::Error,
+    },
+}
+
+impl Display for RecordingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RecordingError::Io(error) => error.fmt(f),
+            RecordingError::NotARecording => f.write_str("not a recording"),
+            RecordingError::UnsupportedVersion(version) => {
+                write!(f, "unsupported recording version {version}")
+            }
+            RecordingError::Malformed { line, error } => {
+                write!(f, "malformed recording at line {line}: {error}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for RecordingError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RecordingError::Io(error) => Some(error),
+            RecordingError::Malformed { error, .. } => Some(error),
+            RecordingError::NotARecording | RecordingError::UnsupportedVersion(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for RecordingError {
+    fn from(error: io::Error) -> Self {
+        RecordingError::Io(error)
+    }
+}
+
+/// A recording loaded with [`Recording::read()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recording {
+    started_at: SystemTime,
+    records: Vec<Record>,
+}
+
+impl Recording {
+    /// Reads a recording.
+    pub fn read(reader: impl BufRead) -> std::result::Result<Self, RecordingError> {
+        let mut lines = reader.lines();
+        let header = lines.next().ok_or(RecordingError::NotARecording)??;
+        let header: Header =
+            serde_json::from_str(&header).map_err(|_| RecordingError::NotARecording)?;
+        if header.format != FORMAT_NAME {
+            return Err(RecordingError::NotARecording);
+        }
+        if header.version > FORMAT_VERSION {
+            return Err(RecordingError::UnsupportedVersion(header.version));
+        }
+
+        let mut records = Vec::new();
+        for (i, line) in lines.enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record = serde_json::from_str(&line)
+                .map_err(|error| RecordingError::Malformed { line: i + 2, error })?;
+            records.push(record);
+        }
+
+        Ok(Self {
+            started_at: SystemTime::UNIX_EPOCH + Duration::from_millis(header.started_at),
+            records,
+        })
+    }
+
+    /// The wall-clock time at which the recording started.
+    pub fn started_at(&self) -> SystemTime {
+        self.started_at
+    }
+
+    /// The recorded events, in order.
+    pub fn records(&self) -> &[Record] {
+        &self.records
+    }
+
+    /// Returns a stream that plays back the recorded events.
+    pub fn replay(&self, pacing: Pacing) -> Replay {
+        Replay {
+            records: self.records.clone().into_iter(),
+            pacing,
+            start: None,
+            timer: None,
+        }
+    }
+}
+
+/// How [`Replay`] spaces out events.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pacing {
+    /// Yield every event as soon as it is polled.
+    Immediate,
+    /// Yield each event at the same offset from the start of the replay as it was recorded.
+    RealTime,
+    /// Like [`RealTime`][Self::RealTime], with time running the given number of times faster.
+    Scaled(f64),
+}
+
+/// A stream of the events in a [`Recording`].
+///
+/// Created by [`Recording::replay()`].
+#[derive(Debug)]
+pub struct Replay {
+    records: std::vec::IntoIter<Record>,
+    pacing: Pacing,
+    start: Option<Instant>,
+    timer: Option<async_io::Timer>,
+}
+
+impl Replay {
+    fn deadline(&mut self, elapsed: Duration) -> Option<Instant> {
+        let scale = match self.pacing {
+            Pacing::Immediate => return None,
+            Pacing::RealTime => 1.0,
+            Pacing::Scaled(speed) => speed,
+        };
+        let start = *self.start.get_or_insert_with(Instant::now);
+        Some(start + elapsed.div_f64(scale))
+    }
+}
+
+impl Stream for Replay {
+    type Item = Record;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Record>> {
+        let Some(elapsed) = self.records.as_slice().first().map(|record| record.elapsed) else {
+            return Poll::Ready(None);
+        };
+
+        if let Some(deadline) = self.deadline(elapsed)
+            && deadline > Instant::now()
+        {
+            let timer = self
+                .timer
+                .get_or_insert_with(|| async_io::Timer::at(deadline));
+            if Pin::new(timer).poll(cx).is_pending() {
+                return Poll::Pending;
+            }
+        }
+
+        self.timer = None;
+        Poll::Ready(self.records.next())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.records.size_hint()
+    }
+}
+
+impl Replay {
+    /// Plays back the remaining events by calling the corresponding methods of `delegate` and
+    /// of the peripheral delegates it creates, in order.
+    ///
+    /// A peripheral delegate is created with
+    /// [`new_peripheral_delegate()`][ReplayCentralDelegate::new_peripheral_delegate] the first time
+    /// an event refers to its peripheral, and receives every later event for that peripheral.
+    /// Events that record writes made by the application are skipped.
+    ///
+    /// See the [module documentation](self) for why events are delivered to these traits rather
+    /// than to the delegates of [`CentralManagerAsync`][crate::CentralManagerAsync].
+    pub async fn run(mut self, delegate: &(impl ReplayCentralDelegate + ?Sized)) {
+        let mut peripherals = HashMap::new();
+        while let Some(record) = std::future::poll_fn(|cx| Pin::new(&mut self).poll_next(cx)).await
+        {
+            dispatch(delegate, &mut peripherals, record.event);
+        }
+    }
+}
+
+/// Receives the central manager callbacks of a replayed [`Recording`].
+///
+/// The methods correspond to those of `corebluetooth::CentralManagerDelegate`, with peripherals
+/// identified by their identifier. Application logic that handles the callbacks delivered to
+/// [`CentralManagerAsync`][crate::CentralManagerAsync] can implement this trait to be rerun
+/// against a recording with [`Replay::run()`], on any platform.
+#[allow(unused_variables)]
+pub trait ReplayCentralDelegate {
+    /// Called the first time an event refers to `peripheral`.
+    fn new_peripheral_delegate(&self, peripheral: Uuid) -> Box<dyn ReplayPeripheralDelegate>;
+
+    /// Called when the central manager's state is updated.
+    fn did_update_state(&self, state: CBManagerState) {}
+
+    /// Called when a scan discovers a peripheral.
+    fn did_discover(
+        &self,
+        peripheral: Uuid,
+        name: Option<String>,
+        advertisement_data: RecordedAdvertisementData,
+        rssi: i16,
+    ) {
+    }
+
+    /// Called when a connection to a peripheral is established.
+    fn did_connect(&self, peripheral: Uuid) {}
+
+    /// Called when a connection to a peripheral fails.
+    fn did_fail_to_connect(&self, peripheral: Uuid, error: Error) {}
+
+    /// Called when a peripheral is disconnected.
+    fn did_disconnect(&self, peripheral: Uuid, is_reconnecting: bool, error: Option<Error>) {}
+
+    /// Called when a connection event occurs.
+    fn on_connection_event(&self, event: CBConnectionEvent, peripheral: Uuid) {}
+
+    /// Called when the ANCS authorization for a peripheral is updated.
+    fn did_update_ancs_authorization(&self, peripheral: Uuid) {}
+}
+
+/// Receives the peripheral callbacks of a replayed [`Recording`].
+///
+/// The methods correspond to those of `corebluetooth::PeripheralDelegate`, with attributes
+/// identified by their UUID and the UUID of the attribute that contains them. Discovery results
+/// are the UUIDs of the attributes found.
+#[allow(unused_variables)]
+pub trait ReplayPeripheralDelegate {
+    /// Called when the peripheral's name changes.
+    fn did_update_name(&self, peripheral: Uuid, name: Option<String>) {}
+
+    /// Called when the peripheral's services change.
+    fn did_modify_services(&self, peripheral: Uuid, invalidated_services: Vec<BluetoothUuid>) {}
+
+    /// Called when the peripheral's RSSI is read.
+    fn did_read_rssi(&self, peripheral: Uuid, rssi: Result<i16>) {}
+
+    /// Called when the peripheral's services are discovered.
+    fn did_discover_services(&self, peripheral: Uuid, result: Result<Vec<BluetoothUuid>>) {}
+
+    /// Called when a service's included services are discovered.
+    fn did_discover_included_services(
+        &self,
+        peripheral: Uuid,
+        service: BluetoothUuid,
+        result: Result<Vec<BluetoothUuid>>,
+    ) {
+    }
+
+    /// Called when a service's characteristics are discovered.
+    fn did_discover_characteristics(
+        &self,
+        peripheral: Uuid,
+        service: BluetoothUuid,
+        result: Result<Vec<BluetoothUuid>>,
+    ) {
+    }
+
+    /// Called when a characteristic's value is read or notified.
+    fn did_update_value_for_characteristic(
+        &self,
+        peripheral: Uuid,
+        service: Option<BluetoothUuid>,
+        characteristic: BluetoothUuid,
+        is_notifying: bool,
+        result: Result<Vec<u8>>,
+    ) {
+    }
+
+    /// Called when a characteristic's value is written.
+    fn did_write_value_for_characteristic(
+        &self,
+        peripheral: Uuid,
+        service: Option<BluetoothUuid>,
+        characteristic: BluetoothUuid,
+        result: Result<()>,
+    ) {
+    }
+
+    /// Called when a characteristic's notification state is updated, with whether notifications
+    /// are enabled.
+    fn did_update_notification_state_for_characteristic(
+        &self,
+        peripheral: Uuid,
+        service: Option<BluetoothUuid>,
+        characteristic: BluetoothUuid,
+        result: Result<bool>,
+    ) {
+    }
+
+    /// Called when a characteristic's descriptors are discovered.
+    fn did_discover_descriptors_for_characteristic(
+        &self,
+        peripheral: Uuid,
+        service: Option<BluetoothUuid>,
+        characteristic: BluetoothUuid,
+        result: Result<Vec<BluetoothUuid>>,
+    ) {
+    }
+
+    /// Called when a descriptor's value is read.
+    fn did_update_value_for_descriptor(
+        &self,
+        peripheral: Uuid,
+        characteristic: Option<BluetoothUuid>,
+        descriptor: BluetoothUuid,
+        result: Result<Vec<u8>>,
+    ) {
+    }
+
+    /// Called when a descriptor's value is written.
+    fn did_write_value_for_descriptor(
+        &self,
+        peripheral: Uuid,
+        characteristic: Option<BluetoothUuid>,
+        descriptor: BluetoothUuid,
+        result: Result<()>,
+    ) {
+    }
+
+    /// Called when the peripheral is ready to send a write without response.
+    fn is_ready_to_send_write_without_response(&self, peripheral: Uuid) {}
+
+    /// Called when an L2CAP channel is opened, with the PSM of the channel.
+    fn did_open_l2cap_channel(&self, peripheral: Uuid, result: Result<u16>) {}
+}
+
+/// `value` if `error` is `None`, or `error`.
+fn result<T>(value: T, error: Option<RecordedError>) -> Result<T> {
+    match error {
+        Some(error) => Err(error.into()),
+        None => Ok(value),
+    }
+}
+
+fn dispatch(
+    central: &(impl ReplayCentralDelegate + ?Sized),
+    peripherals: &mut HashMap<Uuid, Box<dyn ReplayPeripheralDelegate>>,
+    event: Event,
+) {
+    if let Some(peripheral) = event.peripheral() {
+        peripherals
+            .entry(peripheral)
+            .or_insert_with(|| central.new_peripheral_delegate(peripheral));
+    }
+    let peripherals = &*peripherals;
+
+    match event {
+        Event::StateUpdated { state } => central.did_update_state(CBManagerState(state)),
+        Event::Discovered {
+            peripheral,
+            name,
+            advertisement_data,
+            rssi,
+        } => central.did_discover(peripheral, name, advertisement_data, rssi),
+        Event::Connected { peripheral, .. } => central.did_connect(peripheral),
+        Event::FailedToConnect { peripheral, error } => {
+            central.did_fail_to_connect(peripheral, error.into())
+        }
+        Event::Disconnected {
+            peripheral,
+            is_reconnecting,
+            error,
+        } => central.did_disconnect(peripheral, is_reconnecting, error.map(Into::into)),
+        Event::ConnectionEvent { peripheral, event } => {
+            central.on_connection_event(CBConnectionEvent(event), peripheral)
+        }
+        Event::AncsAuthorizationUpdated { peripheral } => {
+            central.did_update_ancs_authorization(peripheral)
+        }
+        Event::NameUpdated { peripheral, name } => {
+            peripherals[&peripheral].did_update_name(peripheral, name)
+        }
+        Event::ServicesModified {
+            peripheral,
+            invalidated_services,
+        } => peripherals[&peripheral].did_modify_services(peripheral, invalidated_services),
+        Event::RssiRead {
+            peripheral,
+            rssi,
+            error,
+        } => {
+            let rssi = match (rssi, error) {
+                (_, Some(error)) => Err(error.into()),
+                (Some(rssi), None) => Ok(rssi),
+                (None, None) => Err(ErrorKind::Other.into()),
+            };
+            peripherals[&peripheral].did_read_rssi(peripheral, rssi)
+        }
+        Event::ServicesDiscovered {
+            peripheral,
+            services,
+            error,
+        } => peripherals[&peripheral].did_discover_services(peripheral, result(services, error)),
+        Event::IncludedServicesDiscovered {
+            peripheral,
+            service,
+            included_services,
+            error,
+        } => peripherals[&peripheral].did_discover_included_services(
+            peripheral,
+            service,
+            result(included_services, error),
+        ),
+        Event::CharacteristicsDiscovered {
+            peripheral,
+            service,
+            characteristics,
+            error,
+        } => peripherals[&peripheral].did_discover_characteristics(
+            peripheral,
+            service,
+            result(characteristics, error),
+        ),
+        Event::DescriptorsDiscovered {
+            peripheral,
+            service,
+            characteristic,
+            descriptors,
+            error,
+        } => peripherals[&peripheral].did_discover_descriptors_for_characteristic(
+            peripheral,
+            service,
+            characteristic,
+            result(descriptors, error),
+        ),
+        Event::CharacteristicValueUpdated {
+            peripheral,
+            service,
+            characteristic,
+            is_notifying,
+            value,
+            error,
+        } => peripherals[&peripheral].did_update_value_for_characteristic(
+            peripheral,
+            service,
+            characteristic,
+            is_notifying,
+            result(value, error),
+        ),
+        Event::CharacteristicValueWritten {
+            peripheral,
+            service,
+            characteristic,
+            error,
+        } => peripherals[&peripheral].did_write_value_for_characteristic(
+            peripheral,
+            service,
+            characteristic,
+            result((), error),
+        ),
+        Event::NotificationStateUpdated {
+            peripheral,
+            service,
+            characteristic,
+            is_notifying,
+            error,
+        } => peripherals[&peripheral].did_update_notification_state_for_characteristic(
+            peripheral,
+            service,
+            characteristic,
+            result(is_notifying, error),
+        ),
+        Event::DescriptorValueUpdated {
+            peripheral,
+            characteristic,
+            descriptor,
+            value,
+            error,
+        } => peripherals[&peripheral].did_update_value_for_descriptor(
+            peripheral,
+            characteristic,
+            descriptor,
+            result(value, error),
+        ),
+        Event::DescriptorValueWritten {
+            peripheral,
+            characteristic,
+            descriptor,
+            error,
+        } => peripherals[&peripheral].did_write_value_for_descriptor(
+            peripheral,
+            characteristic,
+            descriptor,
+            result((), error),
+        ),
+        Event::ReadyToSendWriteWithoutResponse { peripheral } => {
+            peripherals[&peripheral].is_ready_to_send_write_without_response(peripheral)
+        }
+        Event::L2capChannelOpened {
+            peripheral,
+            psm,
+            error,
+        } => {
+            let psm = match (psm, error) {
+                (_, Some(error)) => Err(error.into()),
+                (Some(psm), None) => Ok(psm),
+                (None, None) => Err(ErrorKind::Other.into()),
+            };
+            peripherals[&peripheral].did_open_l2cap_channel(peripheral, psm)
+        }
+        // Writes were made by the application rather than reported to a delegate.
+        Event::CharacteristicWriteRequested { .. } | Event::DescriptorWriteRequested { .. } => {}
+    }
+}
+
+/// Serializes a byte string as hex.
+mod hex {
+    use serde::{Deserialize, Deserializer, Serializer, de};
+
+    pub fn serialize<S: Serializer>(value: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        let hex: String = value.iter().map(|byte| format!("{byte:02x}")).collect();
+        serializer.serialize_str(&hex)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        if hex.len() % 2 != 0 {
+            return Err(de::Error::custom("odd number of hex digits"));
+        }
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).map_err(de::Error::custom))
+            .collect()
+    }
+}
+
+/// Serializes a duration as a whole number of microseconds.
+mod micros {
+    use std::time::Duration;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(value.as_micros() as u64)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        u64::deserialize(deserializer).map(Duration::from_micros)
+    }
+}
+
+/// Serializes an [`ErrorKind`] as a short string such as `"bluetooth:7"` or `"canceled"`.
+///
+/// I/O error kinds without a stable name are read back as [`std::io::ErrorKind::Other`].
+mod error_kind {
+    use serde::{Deserialize, Deserializer, Serializer, de};
+
+    use crate::error::{CBATTError, CBError, ErrorKind, io_error_kind};
+
+    pub fn serialize<S: Serializer>(kind: &ErrorKind, serializer: S) -> Result<S::Ok, S::Error> {
+        match kind {
+            ErrorKind::Bluetooth(error) => {
+                serializer.serialize_str(&format!("bluetooth:{}", error.0))
+            }
+            ErrorKind::ATT(error) => serializer.serialize_str(&format!("att:{}", error.0)),
+            ErrorKind::Canceled => serializer.serialize_str("canceled"),
+            ErrorKind::Lagged => serializer.serialize_str("lagged"),
+            ErrorKind::Io(kind) => serializer.serialize_str(&format!("io:{kind:?}")),
+            ErrorKind::NotFound => serializer.serialize_str("not_found"),
+            ErrorKind::Other => serializer.serialize_str("other"),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<ErrorKind, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        let code = |code: &str| code.parse::<isize>().map_err(de::Error::custom);
+        Ok(match value.split_once(':') {
+            Some(("bluetooth", error)) => ErrorKind::Bluetooth(CBError(code(error)?)),
+            Some(("att", error)) => ErrorKind::ATT(CBATTError(code(error)?)),
+            Some(("io", kind)) => ErrorKind::Io(
+                io_error_kind(kind)
+                    .ok_or_else(|| de::Error::custom(format!("unknown I/O error kind {kind:?}")))?,
+            ),
+            _ => match value.as_str() {
+                "canceled" => ErrorKind::Canceled,
+                "lagged" => ErrorKind::Lagged,
+                "not_found" => ErrorKind::NotFound,
+                "other" => ErrorKind::Other,
+                _ => return Err(de::Error::custom(format!("unknown error kind {value:?}"))),
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use futures_lite::{StreamExt, future};
+
+    use super::*;
+    use crate::error::CBError;
+
+    /// A writer whose contents can be read after it has been given to a [`Recorder`].
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    const PERIPHERAL: Uuid = Uuid::from_u128(1);
+    const SERVICE: BluetoothUuid = BluetoothUuid::from_u16(0x180f);
+    const CHARACTERISTIC: BluetoothUuid = BluetoothUuid::from_u16(0x2a19);
+
+    fn recorded_error(kind: ErrorKind) -> RecordedError {
+        RecordedError {
+            kind,
+            message: "failed".to_owned(),
+        }
+    }
+
+    fn events() -> Vec<Event> {
+        vec![
+            Event::StateUpdated { state: 5 },
+            Event::Discovered {
+                peripheral: PERIPHERAL,
+                name: Some("Sensor".to_owned()),
+                advertisement_data: RecordedAdvertisementData {
+                    local_name: Some("Sensor".to_owned()),
+                    manufacturer_data: Some(RecordedManufacturerData {
+                        company_id: 0x004c,
+                        data: vec![1, 2],
+                    }),
+                    is_connectable: true,
+                    ..Default::default()
+                },
+                rssi: -60,
+            },
+            Event::Connected {
+                peripheral: PERIPHERAL,
+                mtu: Some(185),
+            },
+            Event::ServicesDiscovered {
+                peripheral: PERIPHERAL,
+                services: vec![SERVICE],
+                error: None,
+            },
+            Event::CharacteristicValueUpdated {
+                peripheral: PERIPHERAL,
+                service: Some(SERVICE),
+                characteristic: CHARACTERISTIC,
+                is_notifying: true,
+                value: vec![0x64],
+                error: None,
+            },
+            Event::RssiRead {
+                peripheral: PERIPHERAL,
+                rssi: None,
+                error: Some(recorded_error(ErrorKind::Bluetooth(CBError::NotConnected))),
+            },
+            Event::Disconnected {
+                peripheral: PERIPHERAL,
+                is_reconnecting: false,
+                error: Some(recorded_error(ErrorKind::Io(io::ErrorKind::TimedOut))),
+            },
+        ]
+    }
+
+    fn recording(events: Vec<Event>) -> Recording {
+        let buffer = Buffer::default();
+        let recorder = Recorder::new(buffer.clone()).unwrap();
+        for (i, event) in events.into_iter().enumerate() {
+            recorder
+                .record_at(Duration::from_millis(i as u64), event)
+                .unwrap();
+        }
+        let data = buffer.0.lock().unwrap().clone();
+        Recording::read(&data[..]).unwrap()
+    }
+
+    #[test]
+    fn recording_round_trip() {
+        let recording = recording(events());
+        let records = recording.records();
+        assert_eq!(records.len(), events().len());
+        for (i, (record, event)) in records.iter().zip(events()).enumerate() {
+            assert_eq!(record.elapsed, Duration::from_millis(i as u64));
+            assert_eq!(record.event, event);
+        }
+        assert!(recording.started_at() <= SystemTime::now());
+    }
+
+    #[test]
+    fn read_rejects_other_input() {
+        let err = Recording::read(&b""[..]).unwrap_err();
+        assert!(matches!(err, RecordingError::NotARecording));
+
+        let err = Recording::read(&b"{\"format\":\"other\",\"version\":1,\"started_at\":0}\n"[..])
+            .unwrap_err();
+        assert!(matches!(err, RecordingError::NotARecording));
+
+        let header = format!(
+            "{{\"format\":\"{FORMAT_NAME}\",\"version\":{},\"started_at\":0}}\n",
+            FORMAT_VERSION + 1
+        );
+        let err = Recording::read(header.as_bytes()).unwrap_err();
+        assert!(matches!(err, RecordingError::UnsupportedVersion(v) if v == FORMAT_VERSION + 1));
+    }
+
+    #[test]
+    fn read_reports_malformed_line() {
+        let input = format!(
+            "{{\"format\":\"{FORMAT_NAME}\",\"version\":1,\"started_at\":0}}\n\
+             {{\"t\":0,\"type\":\"connected\",\"peripheral\":\"{PERIPHERAL}\"}}\n\
+             \n\
+             {{\"t\":1,\"type\":\"unknown\"}}\n"
+        );
+        let err = Recording::read(input.as_bytes()).unwrap_err();
+        assert!(matches!(err, RecordingError::Malformed { line: 4, .. }));
+    }
+
+    #[test]
+    fn replay_yields_records_in_order() {
+        let recording = recording(events());
+        let replayed: Vec<_> = future::block_on(recording.replay(Pacing::Immediate).collect());
+        assert_eq!(replayed, recording.records());
+    }
+
+    #[test]
+    fn replay_with_timing_waits_for_each_record() {
+        let recording = recording(events());
+        let start = Instant::now();
+        let replayed: Vec<_> = future::block_on(recording.replay(Pacing::Scaled(2.0)).collect());
+        assert_eq!(replayed.len(), events().len());
+        // The last event was recorded after 6 ms, so it is replayed after 3 ms.
+        assert!(start.elapsed() >= Duration::from_millis(3));
+    }
+
+    type Log = Rc<RefCell<Vec<String>>>;
+
+    struct Central(Log);
+
+    impl ReplayCentralDelegate for Central {
+        fn new_peripheral_delegate(&self, peripheral: Uuid) -> Box<dyn ReplayPeripheralDelegate> {
+            self.0.borrow_mut().push(format!("new {peripheral}"));
+            Box::new(Peripheral(self.0.clone()))
+        }
+
+        fn did_update_state(&self, state: CBManagerState) {
+            self.0.borrow_mut().push(format!("state {}", state.0));
+        }
+
+        fn did_discover(
+            &self,
+            _peripheral: Uuid,
+            name: Option<String>,
+            advertisement_data: RecordedAdvertisementData,
+            rssi: i16,
+        ) {
+            let company = advertisement_data
+                .manufacturer_data
+                .map(|data| data.company_id);
+            self.0
+                .borrow_mut()
+                .push(format!("discover {name:?} {company:?} {rssi}"));
+        }
+
+        fn did_connect(&self, _peripheral: Uuid) {
+            self.0.borrow_mut().push("connect".to_owned());
+        }
+
+        fn did_disconnect(&self, _peripheral: Uuid, is_reconnecting: bool, error: Option<Error>) {
+            let error = error.map(|error| error.kind());
+            self.0
+                .borrow_mut()
+                .push(format!("disconnect {is_reconnecting} {error:?}"));
+        }
+    }
+
+    struct Peripheral(Log);
+
+    impl ReplayPeripheralDelegate for Peripheral {
+        fn did_read_rssi(&self, _peripheral: Uuid, rssi: Result<i16>) {
+            let rssi = rssi.map_err(|error| error.kind());
+            self.0.borrow_mut().push(format!("rssi {rssi:?}"));
+        }
+
+        fn did_discover_services(&self, _peripheral: Uuid, result: Result<Vec<BluetoothUuid>>) {
+            let services = result.unwrap();
+            self.0.borrow_mut().push(format!("services {services:?}"));
+        }
+
+        fn did_update_value_for_characteristic(
+            &self,
+            _peripheral: Uuid,
+            service: Option<BluetoothUuid>,
+            characteristic: BluetoothUuid,
+            is_notifying: bool,
+            result: Result<Vec<u8>>,
+        ) {
+            let value = result.unwrap();
+            self.0.borrow_mut().push(format!(
+                "value {service:?} {characteristic} {is_notifying} {value:?}"
+            ));
+        }
+    }
+
+    #[test]
+    fn run_delivers_events_to_delegates() {
+        let log = Log::default();
+        let recording = recording(events());
+        future::block_on(
+            recording
+                .replay(Pacing::Immediate)
+                .run(&Central(log.clone())),
+        );
+
+        assert_eq!(
+            *log.borrow(),
+            [
+                "state 5".to_owned(),
+                format!("new {PERIPHERAL}"),
+                "discover Some(\"Sensor\") Some(76) -60".to_owned(),
+                "connect".to_owned(),
+                format!("services {:?}", [SERVICE]),
+                format!("value {:?} {CHARACTERISTIC} true [100]", Some(SERVICE)),
+                "rssi Err(Bluetooth(CBError(3)))".to_owned(),
+                "disconnect false Some(Io(TimedOut))".to_owned(),
+            ]
+        );
+    }
+
+    #[test]
+    fn run_creates_one_delegate_per_peripheral() {
+        let other = Uuid::from_u128(2);
+        let events = [PERIPHERAL, other, PERIPHERAL, other]
+            .into_iter()
+            .map(|peripheral| Event::ReadyToSendWriteWithoutResponse { peripheral })
+            .collect();
+        let log = Log::default();
+        future::block_on(
+            recording(events)
+                .replay(Pacing::Immediate)
+                .run(&Central(log.clone())),
+        );
+        assert_eq!(
+            *log.borrow(),
+            [format!("new {PERIPHERAL}"), format!("new {other}")]
+        );
+    }
+
+    #[test]
+    fn replayed_errors_keep_their_kind_and_message() {
+        let error = Error::from(recorded_error(ErrorKind::ATT(crate::error::CBATTError(
+            0x0d,
+        ))));
+        assert_eq!(error.kind(), ErrorKind::ATT(crate::error::CBATTError(0x0d)));
+        assert_eq!(error.to_string(), "failed");
+    }
+
+    #[test]
+    fn unknown_io_error_kinds_are_rejected() {
+        let json = r#"{"kind":"io:TimedOut","message":"failed"}"#;
+        let error: RecordedError = serde_json::from_str(json).unwrap();
+        assert_eq!(error.kind, ErrorKind::Io(io::ErrorKind::TimedOut));
+
+        let json = r#"{"kind":"io:NotARealKind","message":"failed"}"#;
+        let err = serde_json::from_str::<RecordedError>(json).unwrap_err();
+        assert!(err.to_string().contains("unknown I/O error kind"), "{err}");
+    }
+}
@@ -0,0 +1,295 @@
+//! Serialization of CoreBluetooth enumerations as strings.
+//!
+//! [`CBManagerState`] and [`CBConnectionEvent`] are defined by `objc2-core-bluetooth` and do not
+//! implement `serde` traits. The modules below serialize them as `snake_case` strings and can be
+//! used with `#[serde(with = "...")]` in your own types:
+//!
+//! ```
+//! use corebluetooth_async::CBManagerState;
+//!
+//! #[derive(serde::Serialize, serde::Deserialize)]
+//! struct Status {
+//!     #[serde(with = "corebluetooth_async::serialize::manager_state")]
+//!     state: CBManagerState,
+//! }
+//!
+//! let json = serde_json::to_string(&Status { state: CBManagerState::PoweredOn })?;
+//! assert_eq!(json, r#"{"state":"powered_on"}"#);
+//! let status: Status = serde_json::from_str(&json)?;
+//! assert_eq!(status.state, CBManagerState::PoweredOn);
+//! # Ok::<(), serde_json::Error>(())
+//! ```
+//!
+//! Peripherals cannot be deserialized, as they refer to live CoreBluetooth objects, so
+//! [`DidDiscover`][crate::DidDiscover], [`DidDisconnect`][crate::DidDisconnect] and
+//! [`ConnectionEvent`][crate::ConnectionEvent] are serialized as the types in this module, which
+//! replace each peripheral with its identifier and name:
+//!
+//! ```
+//! use corebluetooth_async::CBConnectionEvent;
+//! use corebluetooth_async::serialize::{ConnectionEvent, Peripheral};
+//!
+//! let json = r#"{"peripheral":{"identifier":"6f1b2c3d-4e5f-4a6b-8c7d-9e0f1a2b3c4d","name":null},"event":"peer_connected"}"#;
+//! let event: ConnectionEvent = serde_json::from_str(json)?;
+//! assert_eq!(event.peripheral.name, None);
+//! assert_eq!(event.event, CBConnectionEvent::PeerConnected);
+//! assert_eq!(serde_json::to_string(&event)?, json);
+//! # Ok::<(), serde_json::Error>(())
+//! ```
+//!
+//! [`CBManagerState`]: crate::CBManagerState
+//! [`CBConnectionEvent`]: crate::CBConnectionEvent
+
+use uuid::Uuid;
+
+#[cfg(target_vendor = "apple")]
+use corebluetooth::advertisement_data::AdvertisementData;
+
+use crate::CBConnectionEvent;
+use crate::error::Error;
+
+macro_rules! string_enum {
+    ($(#[$attr:meta])* $module:ident: $ty:ident { $($value:ident => $name:literal),* $(,)? }) => {
+        $(#[$attr])*
+        pub mod $module {
+            use serde::{Deserialize, Deserializer, Serializer, de, ser};
+
+            use crate::$ty as Value;
+
+            /// Serializes the value as a string.
+            pub fn serialize<S: Serializer>(value: &Value, serializer: S) -> Result<S::Ok, S::Error> {
+                let name = match *value {
+                    $(Value::$value => $name,)*
+                    _ => return Err(ser::Error::custom(format!("unknown {} {}", stringify!($ty), value.0))),
+                };
+                serializer.serialize_str(name)
+            }
+
+            /// Deserializes the value from a string.
+            pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Value, D::Error> {
+                let name = String::deserialize(deserializer)?;
+                match name.as_str() {
+                    $($name => Ok(Value::$value),)*
+                    _ => Err(de::Error::unknown_variant(&name, &[$($name),*])),
+                }
+            }
+        }
+    };
+}
+
+string_enum! {
+    /// Serializes a [`CBManagerState`][crate::CBManagerState] as a string such as `"powered_on"`.
+    manager_state: CBManagerState {
+        Unknown => "unknown",
+        Resetting => "resetting",
+        Unsupported => "unsupported",
+        Unauthorized => "unauthorized",
+        PoweredOff => "powered_off",
+        PoweredOn => "powered_on",
+    }
+}
+
+string_enum! {
+    /// Serializes a [`CBConnectionEvent`][crate::CBConnectionEvent] as `"peer_connected"` or
+    /// `"peer_disconnected"`.
+    connection_event: CBConnectionEvent {
+        PeerDisconnected => "peer_disconnected",
+        PeerConnected => "peer_connected",
+    }
+}
+
+/// The serialized form of a [`PeripheralAsync`][crate::PeripheralAsync].
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Peripheral {
+    /// The identifier of the peripheral.
+    pub identifier: Uuid,
+    /// The name of the peripheral, if known.
+    pub name: Option<String>,
+}
+
+/// The serialized form of a [`DidDiscover`][crate::DidDiscover] event.
+#[cfg(target_vendor = "apple")]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct DidDiscover {
+    /// The peripheral that was discovered.
+    pub peripheral: Peripheral,
+    /// The advertisement data of the peripheral.
+    pub advertisement_data: AdvertisementData,
+    /// The RSSI of the peripheral.
+    pub rssi: i16,
+}
+
+/// The serialized form of a [`DidDisconnect`][crate::DidDisconnect] event.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct DidDisconnect {
+    /// The peripheral that was disconnected.
+    pub peripheral: Peripheral,
+    /// The time at which the disconnection occurred.
+    pub timestamp: Option<std::time::SystemTime>,
+    /// Whether the peripheral is being reconnected.
+    pub is_reconnecting: bool,
+    /// The error that caused the disconnection, if any.
+    pub error: Option<Error>,
+}
+
+/// The serialized form of a [`ConnectionEvent`][crate::ConnectionEvent].
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ConnectionEvent {
+    /// The peripheral that the event is for.
+    pub peripheral: Peripheral,
+    /// The connection event.
+    #[serde(with = "connection_event")]
+    pub event: CBConnectionEvent,
+}
+
+#[cfg(target_vendor = "apple")]
+impl From<&crate::PeripheralAsync> for Peripheral {
+    fn from(peripheral: &crate::PeripheralAsync) -> Self {
+        Peripheral {
+            identifier: peripheral.identifier(),
+            name: peripheral.name(),
+        }
+    }
+}
+
+#[cfg(target_vendor = "apple")]
+impl From<crate::DidDiscover> for DidDiscover {
+    fn from(event: crate::DidDiscover) -> Self {
+        DidDiscover {
+            peripheral: Peripheral::from(&event.peripheral),
+            advertisement_data: event.advertisement_data,
+            rssi: event.rssi,
+        }
+    }
+}
+
+#[cfg(target_vendor = "apple")]
+impl From<crate::DidDisconnect> for DidDisconnect {
+    fn from(event: crate::DidDisconnect) -> Self {
+        DidDisconnect {
+            peripheral: Peripheral::from(&event.peripheral),
+            timestamp: event.timestamp,
+            is_reconnecting: event.is_reconnecting,
+            error: event.error,
+        }
+    }
+}
+
+#[cfg(target_vendor = "apple")]
+impl From<crate::ConnectionEvent> for ConnectionEvent {
+    fn from(event: crate::ConnectionEvent) -> Self {
+        ConnectionEvent {
+            peripheral: Peripheral::from(&event.peripheral),
+            event: event.event,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use crate::error::{CBError, ErrorKind};
+
+    use super::*;
+
+    const PERIPHERAL: Uuid = Uuid::from_u128(0x6f1b2c3d_4e5f_4a6b_8c7d_9e0f1a2b3c4d);
+
+    fn peripheral() -> Peripheral {
+        Peripheral {
+            identifier: PERIPHERAL,
+            name: Some("Thermometer".to_owned()),
+        }
+    }
+
+    fn round_trip<T: serde::Serialize + serde::de::DeserializeOwned>(value: &T) -> T {
+        serde_json::from_str(&serde_json::to_string(value).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn connection_events_round_trip() {
+        for event in [
+            CBConnectionEvent::PeerConnected,
+            CBConnectionEvent::PeerDisconnected,
+        ] {
+            let event = ConnectionEvent {
+                peripheral: peripheral(),
+                event,
+            };
+            assert_eq!(round_trip(&event), event);
+        }
+    }
+
+    #[test]
+    fn unknown_connection_events_are_rejected() {
+        let event = ConnectionEvent {
+            peripheral: peripheral(),
+            event: CBConnectionEvent(7),
+        };
+        assert!(serde_json::to_string(&event).is_err());
+
+        let json = format!(
+            r#"{{"peripheral":{{"identifier":"{PERIPHERAL}","name":null}},"event":"peer_lost"}}"#
+        );
+        assert!(serde_json::from_str::<ConnectionEvent>(&json).is_err());
+    }
+
+    #[test]
+    fn disconnections_round_trip() {
+        let event = DidDisconnect {
+            peripheral: peripheral(),
+            timestamp: Some(SystemTime::UNIX_EPOCH + Duration::from_millis(1_700_000_000_000)),
+            is_reconnecting: true,
+            error: Some(ErrorKind::Bluetooth(CBError::ConnectionTimeout).into()),
+        };
+        let deserialized = round_trip(&event);
+        assert_eq!(deserialized.peripheral, event.peripheral);
+        assert_eq!(deserialized.timestamp, event.timestamp);
+        assert!(deserialized.is_reconnecting);
+        let error = deserialized.error.unwrap();
+        assert_eq!(
+            error.kind(),
+            ErrorKind::Bluetooth(CBError::ConnectionTimeout)
+        );
+        assert_eq!(error.to_string(), event.error.unwrap().to_string());
+
+        let event = DidDisconnect {
+            peripheral: Peripheral {
+                identifier: PERIPHERAL,
+                name: None,
+            },
+            timestamp: None,
+            is_reconnecting: false,
+            error: None,
+        };
+        let deserialized = round_trip(&event);
+        assert_eq!(deserialized.peripheral, event.peripheral);
+        assert_eq!(deserialized.timestamp, None);
+        assert!(deserialized.error.is_none());
+    }
+
+    #[cfg(target_vendor = "apple")]
+    #[test]
+    fn discoveries_round_trip() {
+        use corebluetooth::advertisement_data::ManufacturerData;
+
+        let event = DidDiscover {
+            peripheral: peripheral(),
+            advertisement_data: AdvertisementData {
+                local_name: Some("Thermometer".to_owned()),
+                manufacturer_data: Some(ManufacturerData {
+                    company_id: 0x004c,
+                    data: vec![0x02, 0x15],
+                }),
+                service_data: [(btuuid::BluetoothUuid::from_u16(0x1809), vec![0x01])].into(),
+                service_uuids: vec![btuuid::BluetoothUuid::from_u16(0x1809)],
+                overflow_service_uuids: Vec::new(),
+                tx_power_level: Some(-8),
+                is_connectable: true,
+                solicited_service_uuids: Vec::new(),
+            },
+            rssi: -60,
+        };
+        assert_eq!(round_trip(&event), event);
+    }
+}
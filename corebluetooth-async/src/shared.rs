@@ -0,0 +1,1058 @@
+//! A `Send + Sync` facade over `CentralManagerAsync`.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use dispatch_executor::Executor;
+use futures_channel::{mpsc, oneshot};
+use futures_core::Stream;
+
+use crate::error::{ErrorKind, Result};
+use crate::notification::{
+    DeliveryPolicy, DeliveryStats, NotificationReceiver, NotificationSender, SendStatus,
+};
+
+#[cfg(target_vendor = "apple")]
+pub use apple::*;
+
+type Reply<T> = oneshot::Sender<Result<T>>;
+
+/// A stream of values forwarded from a `SharedCentralManager`'s actor task.
+///
+/// Values are buffered according to a [`DeliveryPolicy`]. Dropping the stream stops the
+/// underlying scan, or unsubscribes from the characteristic.
+#[derive(Debug)]
+pub struct SharedStream<T> {
+    receiver: NotificationReceiver<T>,
+    _cancel: oneshot::Sender<()>,
+}
+
+impl<T> SharedStream<T> {
+    /// Creates a stream of the values received by `receiver`, and a receiver that is canceled
+    /// when the stream is dropped.
+    fn new(receiver: NotificationReceiver<T>) -> (Self, oneshot::Receiver<()>) {
+        let (cancel_sender, cancel) = oneshot::channel();
+        let stream = Self {
+            receiver,
+            _cancel: cancel_sender,
+        };
+        (stream, cancel)
+    }
+
+    /// Returns the delivery policy of the stream.
+    pub fn policy(&self) -> DeliveryPolicy {
+        self.receiver.policy()
+    }
+
+    /// Returns the delivery statistics of the stream.
+    pub fn stats(&self) -> DeliveryStats {
+        self.receiver.stats()
+    }
+}
+
+impl<T> Stream for SharedStream<T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        Pin::new(&mut self.receiver).poll_next(cx)
+    }
+}
+
+/// The number of subscribers to each characteristic, so that notifications are only disabled
+/// when the last subscriber unsubscribes.
+struct Subscriptions<K> {
+    counts: RefCell<HashMap<K, usize>>,
+}
+
+impl<K> Default for Subscriptions<K> {
+    fn default() -> Self {
+        Self {
+            counts: RefCell::default(),
+        }
+    }
+}
+
+impl<K: Clone + Eq + Hash> Subscriptions<K> {
+    /// Adds a subscriber to `key` and enables notifications with `enable`.
+    ///
+    /// Notifications are enabled for every subscriber, so that each one sees any error. The
+    /// subscriber is not added if `enable` fails.
+    async fn subscribe<T>(&self, key: &K, enable: impl Future<Output = Result<T>>) -> Result<T> {
+        *self.counts.borrow_mut().entry(key.clone()).or_default() += 1;
+        let res = enable.await;
+        if res.is_err() {
+            self.remove(key);
+        }
+        res
+    }
+
+    /// Replies with a stream of `updates` to a subscriber added by
+    /// [`subscribe()`][Self::subscribe], then waits for the stream to be dropped and removes the
+    /// subscriber, disabling notifications with `disable` if it was the last one.
+    async fn serve<T, F>(
+        &self,
+        key: &K,
+        updates: NotificationReceiver<T>,
+        reply: Reply<SharedStream<T>>,
+        disable: impl FnOnce() -> F,
+    ) where
+        F: Future<Output = ()>,
+    {
+        let (stream, cancel) = SharedStream::new(updates);
+        let _ = reply.send(Ok(stream));
+        // Resolves with an error when the stream is dropped.
+        let _ = cancel.await;
+        if self.remove(key) {
+            disable().await;
+        }
+    }
+
+    /// Removes a subscriber to `key`, returning whether it was the last one.
+    fn remove(&self, key: &K) -> bool {
+        let mut counts = self.counts.borrow_mut();
+        let count = counts.get_mut(key).expect("unbalanced subscription");
+        *count -= 1;
+        if *count == 0 {
+            counts.remove(key);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// A command for an actor, sent by a [`Mailbox`].
+struct Envelope<C> {
+    command: C,
+    /// The caller's span, which the actor enters while handling the command.
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+}
+
+/// Sends commands to an actor task started with [`serve()`].
+struct Mailbox<C> {
+    commands: mpsc::UnboundedSender<Envelope<C>>,
+}
+
+impl<C> Clone for Mailbox<C> {
+    fn clone(&self) -> Self {
+        Self {
+            commands: self.commands.clone(),
+        }
+    }
+}
+
+impl<C> std::fmt::Debug for Mailbox<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Mailbox").finish_non_exhaustive()
+    }
+}
+
+impl<C> Mailbox<C> {
+    /// Creates a mailbox along with the receiver of its commands, to be passed to [`serve()`].
+    fn new() -> (Self, mpsc::UnboundedReceiver<Envelope<C>>) {
+        let (commands, receiver) = mpsc::unbounded();
+        (Self { commands }, receiver)
+    }
+
+    /// Sends the command built by `command` and waits for its reply.
+    ///
+    /// Fails with [`ErrorKind::Canceled`] if the actor has gone away, or drops the reply without
+    /// sending it.
+    async fn call<T>(&self, command: impl FnOnce(oneshot::Sender<T>) -> C) -> Result<T> {
+        let (reply, receiver) = oneshot::channel();
+        self.commands
+            .unbounded_send(Envelope {
+                command: command(reply),
+                #[cfg(feature = "tracing")]
+                span: tracing::Span::current(),
+            })
+            .map_err(|_| ErrorKind::Canceled)?;
+        Ok(receiver.await?)
+    }
+}
+
+/// State confined to an executor's queue that handles the commands sent to its [`Mailbox`].
+trait Actor: 'static {
+    type Command: 'static;
+
+    /// Handles a command and sends its reply.
+    fn handle(self: Rc<Self>, command: Self::Command) -> impl Future<Output = ()> + 'static;
+}
+
+/// Runs `actor` on `executor`, handling the commands received by `commands` until every clone
+/// of their mailbox has been dropped.
+///
+/// # Safety
+///
+/// The actor and the futures returned by its [`handle()`][Actor::handle] must meet the
+/// requirements of [`Executor::spawn_local()`].
+async unsafe fn serve<A: Actor>(
+    actor: Rc<A>,
+    executor: Executor,
+    mut commands: mpsc::UnboundedReceiver<Envelope<A::Command>>,
+) {
+    while let Some(envelope) =
+        std::future::poll_fn(|cx| Pin::new(&mut commands).poll_next(cx)).await
+    {
+        // Each command runs in its own task so that a slow operation, such as a connection
+        // attempt, does not hold up the others.
+        let task = actor.clone().handle(envelope.command);
+        #[cfg(feature = "tracing")]
+        let task = tracing::Instrument::instrument(task, envelope.span);
+        unsafe { executor.spawn_local(task) }.detach();
+    }
+}
+
+/// Forwards items from `source` to `sender` until `source` ends, the receiver is dropped, or
+/// `cancel` is dropped.
+async fn forward<S, T>(
+    mut source: S,
+    sender: &NotificationSender<T>,
+    mut cancel: oneshot::Receiver<()>,
+    mut map: impl FnMut(S::Item) -> Option<T>,
+) where
+    S: Stream + Unpin,
+{
+    std::future::poll_fn(|cx| {
+        if Pin::new(&mut cancel).poll(cx).is_ready() {
+            return Poll::Ready(());
+        }
+
+        loop {
+            match Pin::new(&mut source).poll_next(cx) {
+                Poll::Ready(Some(item)) => {
+                    if let Some(item) = map(item)
+                        && sender.send(item) == SendStatus::Closed
+                    {
+                        return Poll::Ready(());
+                    }
+                }
+                Poll::Ready(None) => return Poll::Ready(()),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use dispatch_executor::Task;
+    use futures_lite::{StreamExt, future};
+
+    use super::*;
+    use crate::notification;
+
+    /// A fake characteristic that records when notifications are enabled and disabled.
+    #[derive(Default)]
+    struct Log(RefCell<Vec<(&'static str, bool)>>);
+
+    impl Log {
+        async fn set_notify(&self, key: &'static str, enabled: bool) -> Result<()> {
+            self.0.borrow_mut().push((key, enabled));
+            Ok(())
+        }
+
+        fn take(&self) -> Vec<(&'static str, bool)> {
+            self.0.take()
+        }
+    }
+
+    struct Subscriber {
+        stream: SharedStream<u32>,
+        sender: NotificationSender<u32>,
+    }
+
+    /// Subscribes to `key` the way the actor does, serving the subscription in a local task.
+    async fn subscribe(
+        executor: &Executor,
+        subscriptions: &Rc<Subscriptions<&'static str>>,
+        log: &Rc<Log>,
+        key: &'static str,
+        policy: DeliveryPolicy,
+    ) -> Subscriber {
+        let (reply, receiver) = oneshot::channel();
+        let (subscriptions, log) = (subscriptions.clone(), log.clone());
+        let (sender, updates) = notification::channel(policy);
+        let task = async move {
+            subscriptions
+                .subscribe(&key, log.set_notify(key, true))
+                .await
+                .unwrap();
+            subscriptions
+                .serve(&key, updates, reply, || async {
+                    log.set_notify(key, false).await.unwrap()
+                })
+                .await;
+        };
+        unsafe { executor.spawn_local(task) }.detach();
+        let stream = receiver.await.unwrap().unwrap();
+        Subscriber { stream, sender }
+    }
+
+    /// Lets tasks on the executor run.
+    async fn settle(executor: &Executor) {
+        for _ in 0..10 {
+            executor.sleep(std::time::Duration::from_millis(1)).await;
+        }
+    }
+
+    fn run<Fut: Future<Output = ()>>(
+        test: impl FnOnce(Executor, Rc<Subscriptions<&'static str>>, Rc<Log>) -> Fut + Send,
+    ) {
+        Executor::run_background("shared-test", None, |executor| {
+            test(executor, Rc::default(), Rc::default())
+        });
+    }
+
+    #[test]
+    fn notifications_are_disabled_after_last_subscriber() {
+        run(|executor, subscriptions, log| async move {
+            let policy = DeliveryPolicy::default();
+            let a = subscribe(&executor, &subscriptions, &log, "a", policy).await;
+            let b = subscribe(&executor, &subscriptions, &log, "a", policy).await;
+            let other = subscribe(&executor, &subscriptions, &log, "b", policy).await;
+            assert_eq!(log.take(), [("a", true), ("a", true), ("b", true)]);
+
+            drop(a);
+            settle(&executor).await;
+            assert!(log.take().is_empty());
+
+            // The remaining subscriber still receives values.
+            let mut b = b;
+            b.sender.send(1);
+            assert_eq!(b.stream.next().await, Some(1));
+
+            drop(b);
+            settle(&executor).await;
+            assert_eq!(log.take(), [("a", false)]);
+
+            drop(other);
+            settle(&executor).await;
+            assert_eq!(log.take(), [("b", false)]);
+            assert!(subscriptions.counts.borrow().is_empty());
+        });
+    }
+
+    #[test]
+    fn resubscribing_after_last_subscriber_enables_again() {
+        run(|executor, subscriptions, log| async move {
+            let policy = DeliveryPolicy::default();
+            drop(subscribe(&executor, &subscriptions, &log, "a", policy).await);
+            settle(&executor).await;
+            let _a = subscribe(&executor, &subscriptions, &log, "a", policy).await;
+            assert_eq!(log.take(), [("a", true), ("a", false), ("a", true)]);
+        });
+    }
+
+    #[test]
+    fn failed_subscription_is_not_counted() {
+        run(|_executor, subscriptions, _log| async move {
+            let err = subscriptions
+                .subscribe(&"a", async { Err::<(), _>(ErrorKind::NotFound.into()) })
+                .await
+                .unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::NotFound);
+            assert!(subscriptions.counts.borrow().is_empty());
+        });
+    }
+
+    #[test]
+    fn stream_applies_delivery_policy() {
+        run(|executor, subscriptions, log| async move {
+            let policy = DeliveryPolicy::DropOldest(2);
+            let mut a = subscribe(&executor, &subscriptions, &log, "a", policy).await;
+            assert_eq!(a.stream.policy(), policy);
+            for value in 1..=4 {
+                a.sender.send(value);
+            }
+            assert_eq!(a.stream.next().await, Some(3));
+            assert_eq!(a.stream.next().await, Some(4));
+            assert_eq!(a.stream.stats().dropped, 2);
+
+            // The stream ends when the characteristic's sender is dropped.
+            drop(a.sender);
+            assert_eq!(a.stream.next().await, None);
+        });
+    }
+
+    /// An actor that keeps a running total.
+    #[derive(Default)]
+    struct Totalizer {
+        total: Cell<u32>,
+        /// The number of `Watch` commands that are still forwarding values.
+        watching: Cell<usize>,
+    }
+
+    enum TotalizerCommand {
+        Add(u32, oneshot::Sender<u32>),
+        /// Adds once `release` resolves.
+        AddLater(u32, oneshot::Receiver<()>, oneshot::Sender<u32>),
+        /// Drops the reply without sending it.
+        Ignore(oneshot::Sender<()>),
+        /// Replies with a stream of the values from `source`.
+        Watch(
+            mpsc::UnboundedReceiver<u32>,
+            oneshot::Sender<SharedStream<u32>>,
+        ),
+    }
+
+    impl Actor for Totalizer {
+        type Command = TotalizerCommand;
+
+        async fn handle(self: Rc<Self>, command: TotalizerCommand) {
+            match command {
+                TotalizerCommand::Add(value, reply) => {
+                    self.total.set(self.total.get() + value);
+                    let _ = reply.send(self.total.get());
+                }
+                TotalizerCommand::AddLater(value, release, reply) => {
+                    let _ = release.await;
+                    self.total.set(self.total.get() + value);
+                    let _ = reply.send(self.total.get());
+                }
+                TotalizerCommand::Ignore(reply) => drop(reply),
+                TotalizerCommand::Watch(source, reply) => {
+                    let (sender, receiver) = notification::channel(DeliveryPolicy::Unbounded);
+                    let (stream, cancel) = SharedStream::new(receiver);
+                    let _ = reply.send(stream);
+                    self.watching.set(self.watching.get() + 1);
+                    forward(source, &sender, cancel, Some).await;
+                    self.watching.set(self.watching.get() - 1);
+                }
+            }
+        }
+    }
+
+    /// Starts a `Totalizer` on `executor`, returning its mailbox and the actor task.
+    fn start(executor: &Executor) -> (Mailbox<TotalizerCommand>, Rc<Totalizer>, Task<()>) {
+        let (mailbox, commands) = Mailbox::new();
+        let actor = Rc::new(Totalizer::default());
+        let task =
+            unsafe { executor.spawn_local(serve(actor.clone(), executor.clone(), commands)) };
+        (mailbox, actor, task)
+    }
+
+    fn run_actor<Fut: Future<Output = ()>>(test: impl FnOnce(Executor) -> Fut + Send) {
+        Executor::run_background("shared-actor-test", None, test);
+    }
+
+    #[test]
+    fn commands_are_dispatched_to_the_actor() {
+        run_actor(|executor| async move {
+            let (mailbox, actor, _task) = start(&executor);
+            let add = |value| mailbox.call(move |reply| TotalizerCommand::Add(value, reply));
+            assert_eq!(add(1).await.unwrap(), 1);
+            assert_eq!(add(2).await.unwrap(), 3);
+            assert_eq!(actor.total.get(), 3);
+
+            // A command waiting on something does not hold up the ones sent after it.
+            let (release, released) = oneshot::channel();
+            let later = mailbox.call(|reply| TotalizerCommand::AddLater(10, released, reply));
+            let sooner = async {
+                assert_eq!(add(4).await.unwrap(), 7);
+                release.send(()).unwrap();
+            };
+            let (later, ()) = future::zip(later, sooner).await;
+            assert_eq!(later.unwrap(), 17);
+        });
+    }
+
+    #[test]
+    fn dropped_replies_are_canceled() {
+        run_actor(|executor| async move {
+            let (mailbox, _actor, _task) = start(&executor);
+            let err = mailbox.call(TotalizerCommand::Ignore).await.unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::Canceled);
+
+            // The actor carries on.
+            let add = mailbox.call(|reply| TotalizerCommand::Add(1, reply));
+            assert_eq!(add.await.unwrap(), 1);
+        });
+    }
+
+    #[test]
+    fn calls_are_canceled_when_the_actor_is_gone() {
+        run_actor(|executor| async move {
+            let (mailbox, actor, task) = start(&executor);
+            drop(task);
+            settle(&executor).await;
+            assert_eq!(Rc::strong_count(&actor), 1);
+
+            let err = mailbox
+                .call(|reply| TotalizerCommand::Add(1, reply))
+                .await
+                .unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::Canceled);
+            assert_eq!(actor.total.get(), 0);
+        });
+    }
+
+    #[test]
+    fn actor_exits_when_every_mailbox_is_dropped() {
+        run_actor(|executor| async move {
+            let (mailbox, actor, task) = start(&executor);
+            let clone = mailbox.clone();
+            drop(mailbox);
+            let add = clone.call(|reply| TotalizerCommand::Add(1, reply));
+            assert_eq!(add.await.unwrap(), 1);
+
+            drop(clone);
+            task.await;
+            assert_eq!(Rc::strong_count(&actor), 1);
+        });
+    }
+
+    #[test]
+    fn dropping_shared_stream_stops_forwarding() {
+        run_actor(|executor| async move {
+            let (mailbox, actor, _task) = start(&executor);
+            let (source, values) = mpsc::unbounded();
+            let mut stream = mailbox
+                .call(|reply| TotalizerCommand::Watch(values, reply))
+                .await
+                .unwrap();
+            source.unbounded_send(1).unwrap();
+            source.unbounded_send(2).unwrap();
+            assert_eq!(stream.next().await, Some(1));
+            assert_eq!(stream.next().await, Some(2));
+            assert_eq!(actor.watching.get(), 1);
+
+            drop(stream);
+            settle(&executor).await;
+            assert_eq!(actor.watching.get(), 0);
+            // The source was dropped along with the forwarding task.
+            assert!(source.unbounded_send(3).is_err());
+        });
+    }
+
+    #[test]
+    fn shared_stream_ends_with_its_source() {
+        run_actor(|executor| async move {
+            let (mailbox, actor, _task) = start(&executor);
+            let (source, values) = mpsc::unbounded();
+            let mut stream = mailbox
+                .call(|reply| TotalizerCommand::Watch(values, reply))
+                .await
+                .unwrap();
+            source.unbounded_send(1).unwrap();
+            drop(source);
+            assert_eq!(stream.next().await, Some(1));
+            assert_eq!(stream.next().await, None);
+            assert_eq!(actor.watching.get(), 0);
+        });
+    }
+
+    #[test]
+    fn dropping_stream_cancels() {
+        let (_sender, receiver) = notification::channel::<u32>(DeliveryPolicy::Unbounded);
+        let (stream, cancel) = SharedStream::new(receiver);
+        drop(stream);
+        assert!(future::block_on(cancel).is_err());
+    }
+}
+
+#[cfg(target_vendor = "apple")]
+mod apple {
+    use std::cell::{Cell, RefCell};
+    use std::collections::HashMap;
+    use std::rc::Rc;
+
+    use btuuid::BluetoothUuid;
+    use corebluetooth::advertisement_data::AdvertisementData;
+    use corebluetooth::dispatch::DispatchQoS;
+    use corebluetooth::{Characteristic, CharacteristicWriteType, Service};
+    use dispatch_executor::Executor;
+    use futures_channel::oneshot;
+    use objc2_core_bluetooth::CBManagerState;
+    use uuid::Uuid;
+
+    use super::{Actor, Mailbox, Reply, SharedStream, Subscriptions, forward, serve};
+    use crate::error::{ErrorKind, Result};
+    use crate::notification::{self, NotificationSender};
+    use crate::{CentralManagerAsync, DeliveryPolicy, PeripheralAsync};
+
+    /// A handle to a [`CentralManagerAsync`] that can be shared between threads.
+    ///
+    /// `CentralManagerAsync` and [`PeripheralAsync`] may only be used from the dispatch queue of
+    /// their [`Executor`]. `SharedCentralManager` runs the central manager in an actor task on that
+    /// queue and forwards commands to it over a channel, so its methods return `Send` futures that
+    /// can be awaited from any thread or async runtime, such as a multi-threaded tokio runtime.
+    ///
+    /// Peripherals are identified by their [`identifier()`][corebluetooth::Peripheral::identifier]
+    /// and attributes by their service and characteristic UUIDs. Services and characteristics are
+    /// discovered as needed the first time they are used.
+    ///
+    /// The actor task exits, and the central manager is dropped, when every clone of the handle has
+    /// been dropped.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use btuuid::{characteristic, service};
+    /// # use corebluetooth::dispatch::DispatchQoS;
+    /// # use corebluetooth_async::SharedCentralManager;
+    /// # use futures_lite::StreamExt;
+    /// # async fn example() -> corebluetooth_async::error::Result<()> {
+    /// let central = SharedCentralManager::background(DispatchQoS::default(), false);
+    /// central.wait_until_powered_on().await?;
+    ///
+    /// let mut scan = central.scan(None, false).await?;
+    /// let discovery = scan.next().await.unwrap();
+    /// drop(scan);
+    ///
+    /// central.connect(discovery.identifier).await?;
+    /// let level = central
+    ///     .read(
+    ///         discovery.identifier,
+    ///         service::BATTERY.into(),
+    ///         characteristic::BATTERY_LEVEL.into(),
+    ///     )
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[derive(Debug, Clone)]
+    pub struct SharedCentralManager {
+        commands: Mailbox<Command>,
+    }
+
+    /// A peripheral discovered by [`SharedCentralManager::scan()`].
+    #[derive(Debug, Clone)]
+    pub struct Discovery {
+        /// The identifier of the peripheral.
+        pub identifier: Uuid,
+        /// The name of the peripheral, if known.
+        pub name: Option<String>,
+        /// The advertisement data of the peripheral.
+        pub advertisement_data: AdvertisementData,
+        /// The RSSI of the peripheral.
+        pub rssi: i16,
+    }
+
+    enum Command {
+        State(oneshot::Sender<CBManagerState>),
+        WaitUntilPoweredOn(Reply<()>),
+        Scan {
+            services: Option<Vec<BluetoothUuid>>,
+            allow_duplicates: bool,
+            reply: Reply<SharedStream<Discovery>>,
+        },
+        Connect {
+            peripheral: Uuid,
+            reply: Reply<()>,
+        },
+        Disconnect {
+            peripheral: Uuid,
+            reply: Reply<()>,
+        },
+        Read {
+            peripheral: Uuid,
+            service: BluetoothUuid,
+            characteristic: BluetoothUuid,
+            reply: Reply<Vec<u8>>,
+        },
+        Write {
+            peripheral: Uuid,
+            service: BluetoothUuid,
+            characteristic: BluetoothUuid,
+            data: Vec<u8>,
+            write_type: CharacteristicWriteType,
+            reply: Reply<()>,
+        },
+        Subscribe {
+            peripheral: Uuid,
+            service: BluetoothUuid,
+            characteristic: BluetoothUuid,
+            policy: DeliveryPolicy,
+            reply: Reply<SharedStream<Result<Vec<u8>>>>,
+        },
+    }
+
+    impl SharedCentralManager {
+        /// Creates a new central manager on a background dispatch queue with the given quality of
+        /// service class and starts its actor task.
+        pub fn background(qos: DispatchQoS, show_power_alert: bool) -> Self {
+            CentralManagerAsync::background(qos, show_power_alert, |central, executor| {
+                Self::spawn(central, executor)
+            })
+        }
+
+        /// Starts an actor task for `central` on `executor`.
+        ///
+        /// `executor` must be the executor `central` was created on, as passed to the entry
+        /// function of [`CentralManagerAsync::background()`].
+        pub fn spawn(central: CentralManagerAsync, executor: &Executor) -> Self {
+            let (commands, receiver) = Mailbox::new();
+            let actor = CentralActor {
+                central,
+                peripherals: RefCell::default(),
+                subscriptions: Subscriptions::default(),
+                scan_generation: Cell::new(0),
+            };
+            // Safety: the actor and the tasks it spawns only touch CoreBluetooth objects from the
+            // executor's queue and do not depend on thread-local state.
+            unsafe { executor.spawn_local(serve(Rc::new(actor), executor.clone(), receiver)) }
+                .detach();
+            Self { commands }
+        }
+
+        async fn call<T>(&self, command: impl FnOnce(oneshot::Sender<T>) -> Command) -> Result<T> {
+            self.commands.call(command).await
+        }
+
+        /// Returns the current state of the central manager.
+        pub async fn state(&self) -> Result<CBManagerState> {
+            self.call(Command::State).await
+        }
+
+        /// Waits until the central manager is powered on.
+        pub async fn wait_until_powered_on(&self) -> Result<()> {
+            self.call(Command::WaitUntilPoweredOn).await?
+        }
+
+        /// Starts scanning for peripherals, stopping any scan already in progress.
+        ///
+        /// See [`CentralManagerAsync::scan()`]. The scan is stopped when the returned stream is
+        /// dropped, or when a new scan is started.
+        pub async fn scan(
+            &self,
+            services: Option<Vec<BluetoothUuid>>,
+            allow_duplicates: bool,
+        ) -> Result<SharedStream<Discovery>> {
+            self.call(|reply| Command::Scan {
+                services,
+                allow_duplicates,
+                reply,
+            })
+            .await?
+        }
+
+        /// Establishes a connection to a peripheral.
+        pub async fn connect(&self, peripheral: Uuid) -> Result<()> {
+            self.call(|reply| Command::Connect { peripheral, reply })
+                .await?
+        }
+
+        /// Cancels an active or pending connection to a peripheral.
+        pub async fn disconnect(&self, peripheral: Uuid) -> Result<()> {
+            self.call(|reply| Command::Disconnect { peripheral, reply })
+                .await?
+        }
+
+        /// Reads the value of a characteristic of a connected peripheral.
+        pub async fn read(
+            &self,
+            peripheral: Uuid,
+            service: BluetoothUuid,
+            characteristic: BluetoothUuid,
+        ) -> Result<Vec<u8>> {
+            self.call(|reply| Command::Read {
+                peripheral,
+                service,
+                characteristic,
+                reply,
+            })
+            .await?
+        }
+
+        /// Writes the value of a characteristic of a connected peripheral.
+        pub async fn write(
+            &self,
+            peripheral: Uuid,
+            service: BluetoothUuid,
+            characteristic: BluetoothUuid,
+            data: Vec<u8>,
+            write_type: CharacteristicWriteType,
+        ) -> Result<()> {
+            self.call(|reply| Command::Write {
+                peripheral,
+                service,
+                characteristic,
+                data,
+                write_type,
+                reply,
+            })
+            .await?
+        }
+
+        /// Enables notifications for a characteristic of a connected peripheral and returns a
+        /// stream of its values.
+        ///
+        /// Values are buffered according to the default [`DeliveryPolicy`]. Notifications are
+        /// disabled when the streams of every subscriber to the characteristic have been dropped.
+        pub async fn subscribe(
+            &self,
+            peripheral: Uuid,
+            service: BluetoothUuid,
+            characteristic: BluetoothUuid,
+        ) -> Result<SharedStream<Result<Vec<u8>>>> {
+            self.subscribe_with_policy(
+                peripheral,
+                service,
+                characteristic,
+                DeliveryPolicy::default(),
+            )
+            .await
+        }
+
+        /// Enables notifications for a characteristic of a connected peripheral and returns a
+        /// stream of its values, buffered according to `policy`.
+        ///
+        /// See [`subscribe()`][Self::subscribe].
+        pub async fn subscribe_with_policy(
+            &self,
+            peripheral: Uuid,
+            service: BluetoothUuid,
+            characteristic: BluetoothUuid,
+            policy: DeliveryPolicy,
+        ) -> Result<SharedStream<Result<Vec<u8>>>> {
+            self.call(|reply| Command::Subscribe {
+                peripheral,
+                service,
+                characteristic,
+                policy,
+                reply,
+            })
+            .await?
+        }
+    }
+
+    struct CentralActor {
+        central: CentralManagerAsync,
+        peripherals: RefCell<HashMap<Uuid, PeripheralAsync>>,
+        subscriptions: Subscriptions<(Uuid, BluetoothUuid, BluetoothUuid)>,
+        scan_generation: Cell<u64>,
+    }
+
+    impl Actor for CentralActor {
+        type Command = Command;
+
+        async fn handle(self: Rc<Self>, command: Command) {
+            match command {
+                Command::State(reply) => {
+                    let _ = reply.send(self.central.state());
+                }
+                Command::WaitUntilPoweredOn(reply) => {
+                    let _ = reply.send(self.wait_until_powered_on().await);
+                }
+                Command::Scan {
+                    services,
+                    allow_duplicates,
+                    reply,
+                } => {
+                    let (sender, receiver) = notification::channel(DeliveryPolicy::Unbounded);
+                    let (stream, cancel) = SharedStream::new(receiver);
+                    let _ = reply.send(Ok(stream));
+                    self.scan(services, allow_duplicates, sender, cancel).await;
+                }
+                Command::Connect { peripheral, reply } => {
+                    let res = match self.peripheral(peripheral) {
+                        Ok(peripheral) => self.central.connect(&peripheral).await,
+                        Err(err) => Err(err),
+                    };
+                    let _ = reply.send(res);
+                }
+                Command::Disconnect { peripheral, reply } => {
+                    let res = match self.peripheral(peripheral) {
+                        Ok(peripheral) => {
+                            self.central.cancel_peripheral_connection(&peripheral).await;
+                            Ok(())
+                        }
+                        Err(err) => Err(err),
+                    };
+                    let _ = reply.send(res);
+                }
+                Command::Read {
+                    peripheral,
+                    service,
+                    characteristic,
+                    reply,
+                } => {
+                    let res = async {
+                        let peripheral = self.peripheral(peripheral)?;
+                        let characteristic =
+                            find_characteristic(&peripheral, service, characteristic).await?;
+                        peripheral.read_characteristic_value(&characteristic).await
+                    };
+                    let _ = reply.send(res.await);
+                }
+                Command::Write {
+                    peripheral,
+                    service,
+                    characteristic,
+                    data,
+                    write_type,
+                    reply,
+                } => {
+                    let res = async {
+                        let peripheral = self.peripheral(peripheral)?;
+                        let characteristic =
+                            find_characteristic(&peripheral, service, characteristic).await?;
+                        peripheral
+                            .write_characteristic_value(&characteristic, data, write_type)
+                            .await
+                    };
+                    let _ = reply.send(res.await);
+                }
+                Command::Subscribe {
+                    peripheral,
+                    service,
+                    characteristic,
+                    policy,
+                    reply,
+                } => {
+                    let key = (peripheral, service, characteristic);
+                    let res = async {
+                        let peripheral = self.peripheral(peripheral)?;
+                        let characteristic =
+                            find_characteristic(&peripheral, service, characteristic).await?;
+                        let updates = peripheral
+                            .characteristic_value_updates_with_policy(&characteristic, policy);
+                        let enable = peripheral.set_notify(&characteristic, true);
+                        self.subscriptions.subscribe(&key, enable).await?;
+                        Ok((peripheral, characteristic, updates))
+                    };
+                    match res.await {
+                        Ok((peripheral, characteristic, updates)) => {
+                            self.subscriptions
+                                .serve(&key, updates, reply, || async {
+                                    let _ = peripheral.set_notify(&characteristic, false).await;
+                                })
+                                .await;
+                        }
+                        Err(err) => {
+                            let _ = reply.send(Err(err));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    impl CentralActor {
+        async fn wait_until_powered_on(&self) -> Result<()> {
+            let mut updates = self.central.state_updates();
+            while self.central.state() != CBManagerState::PoweredOn {
+                updates.recv().await?;
+            }
+            Ok(())
+        }
+
+        async fn scan(
+            &self,
+            services: Option<Vec<BluetoothUuid>>,
+            allow_duplicates: bool,
+            sender: NotificationSender<Discovery>,
+            cancel: oneshot::Receiver<()>,
+        ) {
+            if self.central.is_scanning() {
+                self.central.stop_scan();
+            }
+            let generation = self.scan_generation.get() + 1;
+            self.scan_generation.set(generation);
+
+            let discoveries = self
+                .central
+                .scan(services.as_deref(), allow_duplicates, None);
+            forward(discoveries, &sender, cancel, |did_discover| {
+                let identifier = did_discover.peripheral.identifier();
+                let discovery = Discovery {
+                    identifier,
+                    name: did_discover.peripheral.name(),
+                    advertisement_data: did_discover.advertisement_data,
+                    rssi: did_discover.rssi,
+                };
+                self.peripherals
+                    .borrow_mut()
+                    .insert(identifier, did_discover.peripheral);
+                Some(discovery)
+            })
+            .await;
+
+            // A newer scan replaces this one rather than being stopped by it.
+            if self.scan_generation.get() == generation {
+                self.central.stop_scan();
+            }
+        }
+
+        fn peripheral(&self, identifier: Uuid) -> Result<PeripheralAsync> {
+            if let Some(peripheral) = self.peripherals.borrow().get(&identifier) {
+                return Ok(peripheral.clone());
+            }
+
+            let peripheral = self
+                .central
+                .retrieve_peripherals(&[identifier])
+                .into_iter()
+                .next()
+                .ok_or(ErrorKind::NotFound)?;
+            let peripheral = PeripheralAsync::new(peripheral);
+            self.peripherals
+                .borrow_mut()
+                .insert(identifier, peripheral.clone());
+            Ok(peripheral)
+        }
+    }
+
+    /// Finds a characteristic of `peripheral`, discovering its service and the characteristic if
+    /// necessary.
+    pub(crate) async fn find_characteristic(
+        peripheral: &PeripheralAsync,
+        service: BluetoothUuid,
+        characteristic: BluetoothUuid,
+    ) -> Result<Characteristic> {
+        let service = find_service(peripheral, service).await?;
+        let find_characteristic = || {
+            service
+                .characteristics()
+                .unwrap_or_default()
+                .into_iter()
+                .find(|c| c.uuid() == characteristic)
+        };
+        match find_characteristic() {
+            Some(characteristic) => Ok(characteristic),
+            None => {
+                peripheral
+                    .discover_characteristics(&service, Some(&[characteristic]))
+                    .await?;
+                Ok(find_characteristic().ok_or(ErrorKind::NotFound)?)
+            }
+        }
+    }
+
+    /// Discovers all characteristics of a service of `peripheral`, discovering the service if it
+    /// has not been discovered yet.
+    pub(crate) async fn discover_characteristics(
+        peripheral: &PeripheralAsync,
+        service: BluetoothUuid,
+    ) -> Result<Vec<Characteristic>> {
+        let service = find_service(peripheral, service).await?;
+        peripheral.discover_characteristics(&service, None).await?;
+        Ok(service.characteristics().unwrap_or_default())
+    }
+
+    async fn find_service(peripheral: &PeripheralAsync, service: BluetoothUuid) -> Result<Service> {
+        let find_service = || {
+            peripheral
+                .services()
+                .unwrap_or_default()
+                .into_iter()
+                .find(|s| s.uuid() == service)
+        };
+        match find_service() {
+            Some(service) => Ok(service),
+            None => {
+                peripheral.discover_services(Some(&[service])).await?;
+                Ok(find_service().ok_or(ErrorKind::NotFound)?)
+            }
+        }
+    }
+}
@@ -0,0 +1,130 @@
+//! Optional [`tracing`](https://docs.rs/tracing) instrumentation.
+//!
+//! When the `tracing` feature is enabled, every operation on [`CentralManagerAsync`] and
+//! [`PeripheralAsync`] runs in a `DEBUG` span carrying the peripheral identifier and attribute
+//! UUID, and every delegate callback emits a `DEBUG` event with the byte length of any value and
+//! the kind of any error. Spans are entered each time the operation's future is polled, so events
+//! emitted while awaiting an operation are attributed to the caller's span.
+//!
+//! Characteristic and descriptor values are redacted from events by default. Use
+//! [`set_payload_redaction()`] to include them.
+//!
+//! [`CentralManagerAsync`]: crate::CentralManagerAsync
+//! [`PeripheralAsync`]: crate::PeripheralAsync
+
+#[cfg(feature = "tracing")]
+use std::fmt::Display;
+#[cfg(feature = "tracing")]
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+#[cfg(feature = "tracing")]
+use crate::error::{ErrorKind, Result};
+
+/// How characteristic and descriptor values are recorded in trace events.
+#[cfg(feature = "tracing")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum PayloadRedaction {
+    /// Record only the length of each value.
+    #[default]
+    Redacted,
+    /// Record at most the given number of leading bytes of each value, in hex.
+    Prefix(usize),
+    /// Record each value in full, in hex.
+    Full,
+}
+
+// `usize::MAX` encodes `Redacted`, `usize::MAX - 1` encodes `Full`, and any other value is the
+// length of a `Prefix`.
+#[cfg(feature = "tracing")]
+static PAYLOAD_REDACTION: AtomicUsize = AtomicUsize::new(usize::MAX);
+
+/// Sets how characteristic and descriptor values are recorded in trace events.
+#[cfg(feature = "tracing")]
+pub fn set_payload_redaction(redaction: PayloadRedaction) {
+    let value = match redaction {
+        PayloadRedaction::Redacted => usize::MAX,
+        PayloadRedaction::Full => usize::MAX - 1,
+        PayloadRedaction::Prefix(len) => len.min(usize::MAX - 2),
+    };
+    PAYLOAD_REDACTION.store(value, Ordering::Relaxed);
+}
+
+/// Returns how characteristic and descriptor values are recorded in trace events.
+#[cfg(feature = "tracing")]
+pub fn payload_redaction() -> PayloadRedaction {
+    match PAYLOAD_REDACTION.load(Ordering::Relaxed) {
+        usize::MAX => PayloadRedaction::Redacted,
+        value if value == usize::MAX - 1 => PayloadRedaction::Full,
+        len => PayloadRedaction::Prefix(len),
+    }
+}
+
+/// Formats a value according to the current [`PayloadRedaction`].
+#[cfg(feature = "tracing")]
+pub(crate) struct Payload<'a>(pub(crate) &'a [u8]);
+
+#[cfg(feature = "tracing")]
+impl Display for Payload<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let shown = match payload_redaction() {
+            PayloadRedaction::Redacted => return f.write_str("<redacted>"),
+            PayloadRedaction::Prefix(len) => &self.0[..len.min(self.0.len())],
+            PayloadRedaction::Full => self.0,
+        };
+        for byte in shown {
+            write!(f, "{byte:02x}")?;
+        }
+        if shown.len() < self.0.len() {
+            f.write_str("..")?;
+        }
+        Ok(())
+    }
+}
+
+/// The kind of the error in `result`, for recording as an optional field.
+#[cfg(feature = "tracing")]
+pub(crate) fn error_kind<T>(result: &Result<T>) -> Option<tracing::field::DebugValue<ErrorKind>> {
+    result
+        .as_ref()
+        .err()
+        .map(|err| tracing::field::debug(err.kind()))
+}
+
+/// Runs `$future` in a `DEBUG` span with the given name and fields, recording its outcome.
+#[cfg(feature = "tracing")]
+macro_rules! instrument {
+    ($future:expr, $name:literal $(, $($field:tt)*)?) => {{
+        // The span is created first so that its fields may borrow values the future consumes.
+        let span = ::tracing::debug_span!($name $(, $($field)*)?);
+        ::tracing::Instrument::instrument(
+            async {
+                let result = $future.await;
+                ::tracing::debug!(error = $crate::trace::error_kind(&result), "completed");
+                result
+            },
+            span,
+        )
+    }};
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! instrument {
+    ($future:expr, $name:literal $(, $($field:tt)*)?) => {
+        $future
+    };
+}
+
+/// Emits a `DEBUG` event.
+#[cfg(feature = "tracing")]
+macro_rules! event {
+    ($($arg:tt)*) => {
+        ::tracing::debug!($($arg)*)
+    };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! event {
+    ($($arg:tt)*) => {};
+}
+
+pub(crate) use {event, instrument};
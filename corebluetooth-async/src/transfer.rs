@@ -0,0 +1,416 @@
+//! Chunked transfers of values larger than a single characteristic write.
+//!
+//! [`PeripheralAsync::write_large()`][crate::PeripheralAsync::write_large] splits a payload into
+//! chunks no larger than [`max_write_value_len()`][corebluetooth::Peripheral::max_write_value_len].
+//! [`Framing`] optionally adds enough structure to the chunks for the receiver to detect lost,
+//! reordered or corrupted data, and [`Reassembler`] reverses it.
+
+use std::fmt::Display;
+
+/// Application-level framing applied to a chunked transfer.
+///
+/// With the default (empty) framing, chunks contain only payload bytes.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Framing {
+    /// Prefix the payload with its length as a little-endian `u32`.
+    pub length_prefix: bool,
+    /// Prefix each chunk with a wrapping 8-bit sequence number, starting at zero.
+    pub sequence_numbers: bool,
+    /// Append the CRC-32 (IEEE 802.3) of the payload as a little-endian `u32`.
+    pub crc: bool,
+}
+
+impl Framing {
+    /// Framing with a length prefix, sequence numbers and a CRC.
+    pub fn all() -> Self {
+        Self {
+            length_prefix: true,
+            sequence_numbers: true,
+            crc: true,
+        }
+    }
+
+    fn chunk_header_len(&self) -> usize {
+        if self.sequence_numbers { 1 } else { 0 }
+    }
+
+    /// Splits `data` into framed chunks of at most `max_chunk_len` bytes.
+    ///
+    /// An empty transfer without a length prefix or CRC is a single empty chunk, so that the
+    /// receiver still sees a write.
+    ///
+    /// Fails with [`TransferError::ChunkTooShort`] if `max_chunk_len` is too small to hold any
+    /// payload, or with [`TransferError::TooLarge`] if `data` does not fit in the length prefix.
+    pub fn chunks(&self, data: &[u8], max_chunk_len: usize) -> Result<Vec<Vec<u8>>, TransferError> {
+        let header_len = self.chunk_header_len();
+        if max_chunk_len <= header_len {
+            return Err(TransferError::ChunkTooShort { max_chunk_len });
+        }
+
+        let mut stream = Vec::with_capacity(data.len() + 8);
+        if self.length_prefix {
+            let len = u32::try_from(data.len()).map_err(|_| TransferError::TooLarge)?;
+            stream.extend_from_slice(&len.to_le_bytes());
+        }
+        stream.extend_from_slice(data);
+        if self.crc {
+            stream.extend_from_slice(&crc32(data).to_le_bytes());
+        }
+
+        let payload_len = max_chunk_len - header_len;
+        let mut chunks = Vec::with_capacity(stream.len().div_ceil(payload_len).max(1));
+        for (seq, payload) in stream.chunks(payload_len).enumerate() {
+            let mut chunk = Vec::with_capacity(header_len + payload.len());
+            if self.sequence_numbers {
+                chunk.push(seq as u8);
+            }
+            chunk.extend_from_slice(payload);
+            chunks.push(chunk);
+        }
+        if chunks.is_empty() {
+            chunks.push(if self.sequence_numbers {
+                vec![0]
+            } else {
+                Vec::new()
+            });
+        }
+        Ok(chunks)
+    }
+}
+
+/// An error detected while splitting or reassembling a chunked transfer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TransferError {
+    /// A chunk arrived out of order, or a chunk was lost.
+    Sequence {
+        /// The sequence number that was expected.
+        expected: u8,
+        /// The sequence number that was received.
+        actual: u8,
+    },
+    /// The payload did not match its CRC.
+    Crc {
+        /// The CRC included in the transfer.
+        expected: u32,
+        /// The CRC of the received payload.
+        actual: u32,
+    },
+    /// More data was received than the length prefix announced.
+    Overrun,
+    /// The transfer ended before all of its data was received.
+    Truncated,
+    /// The maximum chunk length is too small to hold any payload.
+    ChunkTooShort {
+        /// The maximum chunk length.
+        max_chunk_len: usize,
+    },
+    /// The payload is too large for the length prefix.
+    TooLarge,
+}
+
+impl Display for TransferError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TransferError::Sequence { expected, actual } => {
+                write!(f, "expected chunk {expected}, received chunk {actual}")
+            }
+            TransferError::Crc { expected, actual } => {
+                write!(
+                    f,
+                    "CRC mismatch (expected {expected:08x}, got {actual:08x})"
+                )
+            }
+            TransferError::Overrun => f.write_str("received more data than expected"),
+            TransferError::Truncated => f.write_str("transfer truncated"),
+            TransferError::ChunkTooShort { max_chunk_len } => {
+                write!(
+                    f,
+                    "chunk length {max_chunk_len} is too small for the framing"
+                )
+            }
+            TransferError::TooLarge => f.write_str("transfer too large for the length prefix"),
+        }
+    }
+}
+
+impl std::error::Error for TransferError {}
+
+/// Reassembles chunks produced by [`Framing::chunks()`].
+#[derive(Debug, Clone)]
+pub struct Reassembler {
+    framing: Framing,
+    buffer: Vec<u8>,
+    next_seq: u8,
+}
+
+impl Reassembler {
+    /// Creates a reassembler for transfers using `framing`.
+    pub fn new(framing: Framing) -> Self {
+        Self {
+            framing,
+            buffer: Vec::new(),
+            next_seq: 0,
+        }
+    }
+
+    /// Adds a chunk to the transfer.
+    ///
+    /// If the framing includes a length prefix, the complete payload is returned as soon as its
+    /// last chunk is received and the reassembler is reset for the next transfer. Otherwise, the
+    /// end of the transfer must be signaled by calling [`finish()`][Self::finish].
+    ///
+    /// After an error, the reassembler is reset and the rest of the failed transfer should be
+    /// discarded by the caller.
+    pub fn push(&mut self, chunk: &[u8]) -> Result<Option<Vec<u8>>, TransferError> {
+        let payload = if self.framing.sequence_numbers {
+            let Some((&seq, payload)) = chunk.split_first() else {
+                return Ok(None);
+            };
+            if seq != self.next_seq {
+                let expected = self.next_seq;
+                self.reset();
+                return Err(TransferError::Sequence {
+                    expected,
+                    actual: seq,
+                });
+            }
+            self.next_seq = self.next_seq.wrapping_add(1);
+            payload
+        } else {
+            chunk
+        };
+
+        self.buffer.extend_from_slice(payload);
+
+        if self.framing.length_prefix && self.buffer.len() >= 4 {
+            let len = u32::from_le_bytes(self.buffer[..4].try_into().unwrap()) as usize;
+            let total = 4 + len + if self.framing.crc { 4 } else { 0 };
+            if self.buffer.len() > total {
+                self.reset();
+                return Err(TransferError::Overrun);
+            } else if self.buffer.len() == total {
+                return self.complete().map(Some);
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Completes the current transfer, returning its payload.
+    pub fn finish(&mut self) -> Result<Vec<u8>, TransferError> {
+        if self.framing.length_prefix {
+            self.reset();
+            Err(TransferError::Truncated)
+        } else {
+            self.complete()
+        }
+    }
+
+    /// Discards any partially received transfer.
+    pub fn reset(&mut self) {
+        self.buffer.clear();
+        self.next_seq = 0;
+    }
+
+    fn complete(&mut self) -> Result<Vec<u8>, TransferError> {
+        let mut data = std::mem::take(&mut self.buffer);
+        self.next_seq = 0;
+
+        if self.framing.crc {
+            let min_len = if self.framing.length_prefix { 8 } else { 4 };
+            if data.len() < min_len {
+                return Err(TransferError::Truncated);
+            }
+            let split = data.len() - 4;
+            let expected = u32::from_le_bytes(data[split..].try_into().unwrap());
+            data.truncate(split);
+            let start = if self.framing.length_prefix { 4 } else { 0 };
+            let actual = crc32(&data[start..]);
+            if expected != actual {
+                return Err(TransferError::Crc { expected, actual });
+            }
+        }
+
+        if self.framing.length_prefix {
+            data.drain(..4);
+        }
+
+        Ok(data)
+    }
+}
+
+/// Computes the CRC-32 (IEEE 802.3) checksum of `data`.
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn framings() -> impl Iterator<Item = Framing> {
+        (0..8).map(|bits| Framing {
+            length_prefix: bits & 1 != 0,
+            sequence_numbers: bits & 2 != 0,
+            crc: bits & 4 != 0,
+        })
+    }
+
+    fn reassemble(framing: Framing, chunks: &[Vec<u8>]) -> Result<Vec<u8>, TransferError> {
+        let mut reassembler = Reassembler::new(framing);
+        for chunk in chunks {
+            if let Some(data) = reassembler.push(chunk)? {
+                return Ok(data);
+            }
+        }
+        reassembler.finish()
+    }
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+    }
+
+    #[test]
+    fn round_trip_every_framing() {
+        let data: Vec<u8> = (0..=255).cycle().take(1000).collect();
+        for framing in framings() {
+            for max_chunk_len in [2, 20, 182, 2000] {
+                let chunks = framing.chunks(&data, max_chunk_len).unwrap();
+                assert!(chunks.iter().all(|chunk| chunk.len() <= max_chunk_len));
+                assert_eq!(reassemble(framing, &chunks).unwrap(), data, "{framing:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn empty_transfer_is_one_chunk() {
+        for framing in framings() {
+            let chunks = framing.chunks(&[], 20).unwrap();
+            assert_eq!(chunks.len(), 1, "{framing:?}");
+            assert!(
+                reassemble(framing, &chunks).unwrap().is_empty(),
+                "{framing:?}"
+            );
+        }
+        assert_eq!(
+            Framing::default().chunks(&[], 20).unwrap(),
+            [Vec::<u8>::new()]
+        );
+    }
+
+    #[test]
+    fn chunk_too_short() {
+        let framing = Framing::all();
+        assert_eq!(
+            framing.chunks(b"data", 1),
+            Err(TransferError::ChunkTooShort { max_chunk_len: 1 })
+        );
+        assert_eq!(
+            Framing::default().chunks(b"data", 0),
+            Err(TransferError::ChunkTooShort { max_chunk_len: 0 })
+        );
+    }
+
+    #[test]
+    fn sequence_error_resets() {
+        let framing = Framing::all();
+        let chunks = framing.chunks(&[7; 50], 10).unwrap();
+        let mut reassembler = Reassembler::new(framing);
+        assert_eq!(reassembler.push(&chunks[0]), Ok(None));
+        assert_eq!(
+            reassembler.push(&chunks[2]),
+            Err(TransferError::Sequence {
+                expected: 1,
+                actual: 2
+            })
+        );
+        assert_eq!(reassemble(framing, &chunks).unwrap(), [7; 50]);
+    }
+
+    #[test]
+    fn sequence_numbers_wrap() {
+        let framing = Framing {
+            sequence_numbers: true,
+            ..Framing::default()
+        };
+        let data = vec![1; 300];
+        let chunks = framing.chunks(&data, 2).unwrap();
+        assert_eq!(chunks.len(), 300);
+        assert_eq!(chunks[256][0], 0);
+        assert_eq!(reassemble(framing, &chunks).unwrap(), data);
+    }
+
+    #[test]
+    fn crc_mismatch() {
+        let framing = Framing {
+            crc: true,
+            ..Framing::default()
+        };
+        let mut chunks = framing.chunks(b"hello", 20).unwrap();
+        chunks[0][0] ^= 1;
+        assert!(matches!(
+            reassemble(framing, &chunks),
+            Err(TransferError::Crc { .. })
+        ));
+    }
+
+    #[test]
+    fn crc_without_enough_data_is_truncated() {
+        let framing = Framing {
+            crc: true,
+            ..Framing::default()
+        };
+        assert_eq!(
+            reassemble(framing, &[vec![1, 2]]),
+            Err(TransferError::Truncated)
+        );
+    }
+
+    #[test]
+    fn overrun() {
+        let framing = Framing {
+            length_prefix: true,
+            ..Framing::default()
+        };
+        let mut reassembler = Reassembler::new(framing);
+        assert_eq!(
+            reassembler.push(&[2, 0, 0, 0, 1, 2, 3]),
+            Err(TransferError::Overrun)
+        );
+    }
+
+    #[test]
+    fn length_prefixed_finish_is_truncated() {
+        let framing = Framing {
+            length_prefix: true,
+            ..Framing::default()
+        };
+        let mut reassembler = Reassembler::new(framing);
+        assert_eq!(reassembler.push(&[4, 0, 0, 0, 1]), Ok(None));
+        assert_eq!(reassembler.finish(), Err(TransferError::Truncated));
+    }
+
+    #[test]
+    fn consecutive_transfers() {
+        let framing = Framing::all();
+        let mut reassembler = Reassembler::new(framing);
+        for data in [&b"first"[..], b"second transfer"] {
+            let chunks = framing.chunks(data, 4).unwrap();
+            let (last, rest) = chunks.split_last().unwrap();
+            for chunk in rest {
+                assert_eq!(reassembler.push(chunk), Ok(None));
+            }
+            assert_eq!(reassembler.push(last), Ok(Some(data.to_vec())));
+        }
+    }
+}
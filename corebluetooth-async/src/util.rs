@@ -59,3 +59,14 @@ pub fn broadcast<T>(cap: usize) -> BroadcastSender<T> {
 pub fn watch<T>() -> BroadcastSender<T> {
     broadcast(1)
 }
+
+/// Records the event produced by `$event` if the `record` feature is enabled and a recorder is
+/// installed.
+macro_rules! record {
+    ($event:expr) => {
+        #[cfg(feature = "record")]
+        $crate::record::with_recorder(|| $event);
+    };
+}
+
+pub(crate) use record;
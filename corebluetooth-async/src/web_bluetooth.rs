@@ -0,0 +1,1655 @@
+//! An API modeled on [Web Bluetooth](https://webbluetoothcg.github.io/web-bluetooth/).
+//!
+//! Code written against `navigator.bluetooth` can be ported to this module one call at a time:
+//!
+//! | Web Bluetooth                                   | This module                                                  |
+//! |-------------------------------------------------|--------------------------------------------------------------|
+//! | `navigator.bluetooth.requestDevice(options)`    | [`Bluetooth::request_device()`]                              |
+//! | `navigator.bluetooth.getDevices()`              | [`Bluetooth::get_devices()`]                                 |
+//! | `device.gatt.connect()`                         | [`BluetoothRemoteGattServer::connect()`]                     |
+//! | `server.getPrimaryService(uuid)`                | [`BluetoothRemoteGattServer::get_primary_service()`]         |
+//! | `service.getCharacteristic(uuid)`               | [`BluetoothRemoteGattService::get_characteristic()`]         |
+//! | `characteristic.readValue()`                    | [`BluetoothRemoteGattCharacteristic::read_value()`]          |
+//! | `characteristic.writeValueWithResponse(value)`  | [`BluetoothRemoteGattCharacteristic::write_value_with_response()`] |
+//! | `characteristic.startNotifications()`           | [`BluetoothRemoteGattCharacteristic::start_notifications()`] |
+//! | `characteristicvaluechanged` event              | [`BluetoothRemoteGattCharacteristic::characteristic_value_changed()`] |
+//! | `gattserverdisconnected` event                  | [`BluetoothDevice::gatt_server_disconnected()`]              |
+//!
+//! UUIDs are given as [`BluetoothUuid`]s, for example `service::HEART_RATE.into()` in place of
+//! `'heart_rate'`. Operations fail with an [`Error`] whose [`name()`][Error::name] is the name of
+//! the `DOMException` the corresponding Web Bluetooth call rejects with.
+//!
+//! As in browsers, a device only gives access to the services named in the filters and
+//! `optional_services` it was requested with, and attributes on the [`Blocklist`] are hidden or
+//! read- or write-protected.
+//!
+//! There is no device chooser: [`Bluetooth::request_device()`] returns the first device that
+//! matches the filters, and [`Bluetooth::request_device_with()`] lets the caller choose.
+//!
+//! [`RequestDeviceOptions`], the filters and the [`Blocklist`] are available on all platforms, so
+//! filters can be validated and matched against advertisements without CoreBluetooth:
+//!
+//! ```
+//! use btuuid::service;
+//! use corebluetooth_async::web_bluetooth::{
+//!     Blocklist, BluetoothAdvertisingEvent, BluetoothLeScanFilter, RequestDeviceOptions,
+//! };
+//!
+//! let options = RequestDeviceOptions {
+//!     filters: vec![BluetoothLeScanFilter {
+//!         services: vec![service::HEART_RATE.into()],
+//!         name_prefix: Some("Polar".to_owned()),
+//!         ..Default::default()
+//!     }],
+//!     optional_services: vec![service::BATTERY.into()],
+//!     ..Default::default()
+//! };
+//! options.validate(&Blocklist::default())?;
+//!
+//! let advertisement = BluetoothAdvertisingEvent {
+//!     name: Some("Polar H10".to_owned()),
+//!     uuids: vec![service::HEART_RATE.into()],
+//!     ..Default::default()
+//! };
+//! assert!(options.matches(&advertisement));
+//! # Ok::<(), corebluetooth_async::web_bluetooth::Error>(())
+//! ```
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+#[cfg(any(target_vendor = "apple", test))]
+use std::hash::Hash;
+#[cfg(any(target_vendor = "apple", test))]
+use std::rc::Rc;
+
+#[cfg(target_vendor = "apple")]
+pub use apple::*;
+use btuuid::BluetoothUuid;
+use uuid::Uuid;
+
+use crate::error::{CBATTError, CBError, ErrorKind};
+
+/// The longest value that may be read from or written to an attribute.
+pub const MAX_VALUE_LEN: usize = 512;
+
+/// The longest device name a filter may match, in bytes.
+const MAX_NAME_LEN: usize = 248;
+
+/// The GATT blocklist from the Web Bluetooth registries.
+const GATT_BLOCKLIST: &str = "\
+# org.bluetooth.service.human_interface_device
+00001812-0000-1000-8000-00805f9b34fb
+# Firmware update services that do not check signatures
+00001530-1212-efde-1523-785feabcd123
+f000ffc0-0451-4000-b000-000000000000
+# org.bluetooth.service.fido
+0000fffd-0000-1000-8000-00805f9b34fb
+# org.bluetooth.characteristic.gap.peripheral_privacy_flag
+00002a02-0000-1000-8000-00805f9b34fb exclude-writes
+# org.bluetooth.characteristic.gap.reconnection_address
+00002a03-0000-1000-8000-00805f9b34fb
+# org.bluetooth.characteristic.serial_number_string
+00002a25-0000-1000-8000-00805f9b34fb
+# org.bluetooth.descriptor.gatt.client_characteristic_configuration
+00002902-0000-1000-8000-00805f9b34fb exclude-writes
+# org.bluetooth.descriptor.gatt.server_characteristic_configuration
+00002903-0000-1000-8000-00805f9b34fb exclude-writes
+";
+
+/// An error from a Web Bluetooth operation.
+#[derive(Debug, Clone)]
+pub enum Error {
+    /// The arguments were invalid, for example a filter that matches every device.
+    Type(String),
+    /// The device, service, characteristic or descriptor was not found.
+    NotFound(String),
+    /// The attribute is blocklisted, or the service was not requested for the device.
+    Security(String),
+    /// The characteristic does not support the operation.
+    NotSupported(String),
+    /// The value is longer than [`MAX_VALUE_LEN`].
+    InvalidModification(String),
+    /// The operation cannot be performed now, for example because a scan is already running.
+    InvalidState(String),
+    /// The device is not connected.
+    Network(String),
+    /// The operation failed in CoreBluetooth.
+    Bluetooth(crate::error::Error),
+}
+
+impl Error {
+    /// Returns the name of the `DOMException` (or `TypeError`) that Web Bluetooth reports for
+    /// this error.
+    ///
+    /// Errors from CoreBluetooth are mapped as the Web Bluetooth specification maps ATT errors:
+    ///
+    /// ```
+    /// use corebluetooth_async::error::{CBATTError, ErrorKind};
+    /// use corebluetooth_async::web_bluetooth::Error;
+    ///
+    /// let error = Error::from(corebluetooth_async::error::Error::from(ErrorKind::ATT(
+    ///     CBATTError::InsufficientAuthentication,
+    /// )));
+    /// assert_eq!(error.name(), "SecurityError");
+    /// ```
+    pub fn name(&self) -> &'static str {
+        match self {
+            Error::Type(_) => "TypeError",
+            Error::NotFound(_) => "NotFoundError",
+            Error::Security(_) => "SecurityError",
+            Error::NotSupported(_) => "NotSupportedError",
+            Error::InvalidModification(_) => "InvalidModificationError",
+            Error::InvalidState(_) => "InvalidStateError",
+            Error::Network(_) => "NetworkError",
+            Error::Bluetooth(error) => match error.kind() {
+                ErrorKind::ATT(
+                    CBATTError::ReadNotPermitted
+                    | CBATTError::WriteNotPermitted
+                    | CBATTError::RequestNotSupported,
+                ) => "NotSupportedError",
+                ErrorKind::ATT(
+                    CBATTError::InsufficientAuthentication
+                    | CBATTError::InsufficientAuthorization
+                    | CBATTError::InsufficientEncryption
+                    | CBATTError::InsufficientEncryptionKeySize,
+                ) => "SecurityError",
+                ErrorKind::ATT(CBATTError::InvalidAttributeValueLength) => {
+                    "InvalidModificationError"
+                }
+                ErrorKind::Bluetooth(CBError::UUIDNotAllowed) => "SecurityError",
+                ErrorKind::Bluetooth(CBError::OperationNotSupported) => "NotSupportedError",
+                ErrorKind::NotFound => "NotFoundError",
+                ErrorKind::Canceled => "AbortError",
+                _ => "NetworkError",
+            },
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Type(message)
+            | Error::NotFound(message)
+            | Error::Security(message)
+            | Error::NotSupported(message)
+            | Error::InvalidModification(message)
+            | Error::InvalidState(message)
+            | Error::Network(message) => write!(f, "{}: {message}", self.name()),
+            Error::Bluetooth(error) => write!(f, "{}: {error}", self.name()),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Bluetooth(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<crate::error::Error> for Error {
+    fn from(error: crate::error::Error) -> Self {
+        Error::Bluetooth(error)
+    }
+}
+
+/// A convenience type alias for a `Result` with this module's [`Error`] type.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// How a [`Blocklist`] restricts access to a UUID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Exclusion {
+    /// The attribute may not be used at all.
+    All,
+    /// The attribute may not be read.
+    Reads,
+    /// The attribute may not be written.
+    Writes,
+}
+
+/// The GATT attributes that may not be used through this module.
+///
+/// [`Blocklist::default()`] is the blocklist from the Web Bluetooth registries. Other blocklists,
+/// such as a newer copy of the registry file, can be loaded with [`Blocklist::parse()`]:
+///
+/// ```
+/// use btuuid::{characteristic, descriptors, service};
+/// use corebluetooth_async::web_bluetooth::Blocklist;
+///
+/// let blocklist = Blocklist::default();
+/// assert!(blocklist.is_blocklisted(service::HUMAN_INTERFACE_DEVICE.into()));
+/// assert!(blocklist.is_blocklisted_for_writes(descriptors::CLIENT_CHARACTERISTIC_CONFIGURATION.into()));
+/// assert!(!blocklist.is_blocklisted_for_reads(descriptors::CLIENT_CHARACTERISTIC_CONFIGURATION.into()));
+/// assert!(!blocklist.is_blocklisted(characteristic::BATTERY_LEVEL.into()));
+///
+/// let blocklist = Blocklist::parse("# Battery\n0000180f-0000-1000-8000-00805f9b34fb exclude-reads\n")?;
+/// assert!(blocklist.is_blocklisted_for_reads(service::BATTERY.into()));
+/// # Ok::<(), corebluetooth_async::web_bluetooth::Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Blocklist {
+    entries: HashMap<Uuid, Exclusion>,
+}
+
+impl Blocklist {
+    /// Creates a blocklist that allows everything.
+    pub fn empty() -> Self {
+        Self {
+            entries: HashMap::new(),
+        }
+    }
+
+    /// Parses a blocklist in the format of the Web Bluetooth registry's `gatt_blocklist.txt`.
+    ///
+    /// Each line is a 128-bit UUID, optionally followed by `exclude-reads` or `exclude-writes`.
+    /// Blank lines and lines starting with `#` are ignored.
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut blocklist = Self::empty();
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = || Error::Type(format!("invalid blocklist entry {line:?}"));
+            let mut words = line.split_whitespace();
+            let uuid = words
+                .next()
+                .and_then(|uuid| Uuid::parse_str(uuid).ok())
+                .ok_or_else(invalid)?;
+            let exclusion = match words.next() {
+                None => Exclusion::All,
+                Some("exclude-reads") => Exclusion::Reads,
+                Some("exclude-writes") => Exclusion::Writes,
+                Some(_) => return Err(invalid()),
+            };
+            if words.next().is_some() {
+                return Err(invalid());
+            }
+            blocklist.insert(uuid.into(), exclusion);
+        }
+        Ok(blocklist)
+    }
+
+    /// Adds a UUID to the blocklist, replacing any existing entry for it.
+    pub fn insert(&mut self, uuid: BluetoothUuid, exclusion: Exclusion) {
+        self.entries.insert(uuid.into(), exclusion);
+    }
+
+    /// Returns how the blocklist restricts `uuid`, if at all.
+    pub fn get(&self, uuid: BluetoothUuid) -> Option<Exclusion> {
+        self.entries.get(&uuid.into()).copied()
+    }
+
+    /// Returns whether `uuid` may not be used at all.
+    pub fn is_blocklisted(&self, uuid: BluetoothUuid) -> bool {
+        self.get(uuid) == Some(Exclusion::All)
+    }
+
+    /// Returns whether `uuid` may not be read.
+    pub fn is_blocklisted_for_reads(&self, uuid: BluetoothUuid) -> bool {
+        matches!(self.get(uuid), Some(Exclusion::All | Exclusion::Reads))
+    }
+
+    /// Returns whether `uuid` may not be written.
+    pub fn is_blocklisted_for_writes(&self, uuid: BluetoothUuid) -> bool {
+        matches!(self.get(uuid), Some(Exclusion::All | Exclusion::Writes))
+    }
+}
+
+impl Default for Blocklist {
+    fn default() -> Self {
+        Self::parse(GATT_BLOCKLIST).expect("the built-in blocklist is valid")
+    }
+}
+
+/// An advertisement from a device, as seen by filters and device choosers.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BluetoothAdvertisingEvent {
+    /// The identifier of the device.
+    pub identifier: Uuid,
+    /// The advertised local name of the device, or its name if it did not advertise one.
+    pub name: Option<String>,
+    /// The advertised service UUIDs.
+    pub uuids: Vec<BluetoothUuid>,
+    /// The received signal strength, in dBm.
+    pub rssi: Option<i16>,
+    /// The advertised transmit power, in dBm.
+    pub tx_power: Option<i16>,
+    /// The advertised manufacturer data, by company identifier.
+    pub manufacturer_data: HashMap<u16, Vec<u8>>,
+    /// The advertised service data, by service UUID.
+    pub service_data: HashMap<BluetoothUuid, Vec<u8>>,
+}
+
+/// A pattern that matches data that starts with `data_prefix`, comparing only the bits set in
+/// `mask`.
+///
+/// An empty `mask` compares every bit of the prefix.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BluetoothDataFilter {
+    /// The bytes the data must start with.
+    pub data_prefix: Vec<u8>,
+    /// The bits of `data_prefix` to compare. Must be empty or as long as `data_prefix`.
+    pub mask: Vec<u8>,
+}
+
+impl BluetoothDataFilter {
+    /// Returns whether `data` matches the filter.
+    ///
+    /// ```
+    /// use corebluetooth_async::web_bluetooth::BluetoothDataFilter;
+    ///
+    /// let filter = BluetoothDataFilter { data_prefix: vec![0x02, 0x15], mask: vec![0xff, 0xf0] };
+    /// assert!(filter.matches(&[0x02, 0x1a, 0x00]));
+    /// assert!(!filter.matches(&[0x03, 0x15]));
+    /// assert!(!filter.matches(&[0x02]));
+    /// ```
+    pub fn matches(&self, data: &[u8]) -> bool {
+        data.len() >= self.data_prefix.len()
+            && self.data_prefix.iter().enumerate().all(|(i, prefix)| {
+                let mask = self.mask.get(i).copied().unwrap_or(0xff);
+                data[i] & mask == prefix & mask
+            })
+    }
+
+    fn validate(&self) -> Result<()> {
+        if !self.mask.is_empty() && self.mask.len() != self.data_prefix.len() {
+            return Err(Error::Type(
+                "a data filter's mask must be as long as its data prefix".to_owned(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// A filter on manufacturer data, matching `BluetoothManufacturerDataFilterInit`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BluetoothManufacturerDataFilter {
+    /// The company identifier the data must be advertised for.
+    pub company_identifier: u16,
+    /// The pattern the data must match.
+    pub filter: BluetoothDataFilter,
+}
+
+/// A filter on service data, matching `BluetoothServiceDataFilterInit`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BluetoothServiceDataFilter {
+    /// The service the data must be advertised for.
+    pub service: BluetoothUuid,
+    /// The pattern the data must match.
+    pub filter: BluetoothDataFilter,
+}
+
+/// A filter on advertisements, matching `BluetoothLEScanFilterInit`.
+///
+/// A device matches the filter if it matches every condition that is set.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BluetoothLeScanFilter {
+    /// Services that must all be advertised.
+    pub services: Vec<BluetoothUuid>,
+    /// The exact name of the device.
+    pub name: Option<String>,
+    /// A prefix of the name of the device.
+    pub name_prefix: Option<String>,
+    /// Manufacturer data that must all be advertised.
+    pub manufacturer_data: Vec<BluetoothManufacturerDataFilter>,
+    /// Service data that must all be advertised.
+    pub service_data: Vec<BluetoothServiceDataFilter>,
+}
+
+impl BluetoothLeScanFilter {
+    /// Returns whether an advertisement matches the filter.
+    pub fn matches(&self, advertisement: &BluetoothAdvertisingEvent) -> bool {
+        let name = advertisement.name.as_deref();
+        self.name
+            .as_deref()
+            .is_none_or(|expected| name == Some(expected))
+            && self
+                .name_prefix
+                .as_deref()
+                .is_none_or(|prefix| name.is_some_and(|name| name.starts_with(prefix)))
+            && self.services.iter().all(|service| {
+                advertisement
+                    .uuids
+                    .iter()
+                    .any(|uuid| same_uuid(*uuid, *service))
+            })
+            && self.manufacturer_data.iter().all(|filter| {
+                advertisement
+                    .manufacturer_data
+                    .get(&filter.company_identifier)
+                    .is_some_and(|data| filter.filter.matches(data))
+            })
+            && self.service_data.iter().all(|filter| {
+                advertisement.service_data.iter().any(|(uuid, data)| {
+                    same_uuid(*uuid, filter.service) && filter.filter.matches(data)
+                })
+            })
+    }
+
+    fn validate(&self, blocklist: &Blocklist) -> Result<()> {
+        if self.services.is_empty()
+            && self.name.is_none()
+            && self.name_prefix.is_none()
+            && self.manufacturer_data.is_empty()
+            && self.service_data.is_empty()
+        {
+            return Err(Error::Type(
+                "a filter must restrict the devices in some way".to_owned(),
+            ));
+        }
+        if self
+            .name
+            .as_ref()
+            .is_some_and(|name| name.len() > MAX_NAME_LEN)
+            || self
+                .name_prefix
+                .as_ref()
+                .is_some_and(|prefix| prefix.len() > MAX_NAME_LEN)
+        {
+            return Err(Error::Type(format!(
+                "a filter's name may be at most {MAX_NAME_LEN} bytes"
+            )));
+        }
+        if self.name_prefix.as_deref() == Some("") {
+            return Err(Error::Type(
+                "a filter's name prefix may not be empty".to_owned(),
+            ));
+        }
+        for service in self
+            .services
+            .iter()
+            .chain(self.service_data.iter().map(|filter| &filter.service))
+        {
+            if blocklist.is_blocklisted(*service) {
+                return Err(Error::Security(format!("service {service} is blocklisted")));
+            }
+        }
+        for filter in &self.manufacturer_data {
+            filter.filter.validate()?;
+        }
+        for filter in &self.service_data {
+            filter.filter.validate()?;
+        }
+        Ok(())
+    }
+}
+
+/// The options for [`Bluetooth::request_device()`], matching `RequestDeviceOptions`.
+///
+/// Exactly one of `filters` and `accept_all_devices` must be given.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RequestDeviceOptions {
+    /// Devices matching any of these filters may be chosen.
+    pub filters: Vec<BluetoothLeScanFilter>,
+    /// Devices matching any of these filters may not be chosen, even if they match `filters`.
+    pub exclusion_filters: Vec<BluetoothLeScanFilter>,
+    /// Services the device gives access to in addition to those named in `filters`.
+    pub optional_services: Vec<BluetoothUuid>,
+    /// Whether any device may be chosen.
+    pub accept_all_devices: bool,
+}
+
+impl RequestDeviceOptions {
+    /// Checks that the options are valid, returning the error `requestDevice()` would reject
+    /// with if not.
+    ///
+    /// ```
+    /// use btuuid::service;
+    /// use corebluetooth_async::web_bluetooth::{Blocklist, BluetoothLeScanFilter, RequestDeviceOptions};
+    ///
+    /// let options = RequestDeviceOptions::default();
+    /// assert_eq!(options.validate(&Blocklist::default()).unwrap_err().name(), "TypeError");
+    ///
+    /// let options = RequestDeviceOptions {
+    ///     filters: vec![BluetoothLeScanFilter {
+    ///         services: vec![service::HUMAN_INTERFACE_DEVICE.into()],
+    ///         ..Default::default()
+    ///     }],
+    ///     ..Default::default()
+    /// };
+    /// assert_eq!(options.validate(&Blocklist::default()).unwrap_err().name(), "SecurityError");
+    /// ```
+    pub fn validate(&self, blocklist: &Blocklist) -> Result<()> {
+        if self.accept_all_devices != self.filters.is_empty() {
+            return Err(Error::Type(
+                "either filters or accept_all_devices must be given, but not both".to_owned(),
+            ));
+        }
+        if self.accept_all_devices && !self.exclusion_filters.is_empty() {
+            return Err(Error::Type(
+                "exclusion_filters may not be given with accept_all_devices".to_owned(),
+            ));
+        }
+        for filter in self.filters.iter().chain(&self.exclusion_filters) {
+            filter.validate(blocklist)?;
+        }
+        Ok(())
+    }
+
+    /// Returns whether a device with this advertisement may be chosen.
+    pub fn matches(&self, advertisement: &BluetoothAdvertisingEvent) -> bool {
+        (self.accept_all_devices
+            || self
+                .filters
+                .iter()
+                .any(|filter| filter.matches(advertisement)))
+            && !self
+                .exclusion_filters
+                .iter()
+                .any(|filter| filter.matches(advertisement))
+    }
+
+    /// Returns the services to scan for, or `None` to scan for all devices.
+    ///
+    /// A scan can only be narrowed to services if every filter names at least one.
+    pub fn scan_services(&self) -> Option<Vec<BluetoothUuid>> {
+        if self.accept_all_devices || self.filters.iter().any(|filter| filter.services.is_empty()) {
+            return None;
+        }
+        let mut services = Vec::new();
+        for service in self.filters.iter().flat_map(|filter| &filter.services) {
+            if !services.iter().any(|known| same_uuid(*known, *service)) {
+                services.push(*service);
+            }
+        }
+        Some(services)
+    }
+
+    /// Returns the services a device requested with these options gives access to.
+    ///
+    /// Blocklisted optional services are left out.
+    pub fn allowed_services(&self, blocklist: &Blocklist) -> HashSet<Uuid> {
+        self.filters
+            .iter()
+            .flat_map(|filter| &filter.services)
+            .chain(&self.optional_services)
+            .filter(|service| !blocklist.is_blocklisted(**service))
+            .map(|service| Uuid::from(*service))
+            .collect()
+    }
+}
+
+/// Compares UUIDs regardless of whether they are in their 16-, 32- or 128-bit form.
+fn same_uuid(a: BluetoothUuid, b: BluetoothUuid) -> bool {
+    Uuid::from(a) == Uuid::from(b)
+}
+
+/// The services and attributes of a requested device that may be used.
+#[cfg(any(target_vendor = "apple", test))]
+#[derive(Debug)]
+struct Access {
+    allowed_services: HashSet<Uuid>,
+    blocklist: Rc<Blocklist>,
+}
+
+#[cfg(any(target_vendor = "apple", test))]
+impl Access {
+    fn new(options: &RequestDeviceOptions, blocklist: Rc<Blocklist>) -> Self {
+        Self {
+            allowed_services: options.allowed_services(&blocklist),
+            blocklist,
+        }
+    }
+
+    /// Checks that the service was requested for the device and is not blocklisted.
+    fn check_service(&self, uuid: BluetoothUuid) -> Result<()> {
+        if self.blocklist.is_blocklisted(uuid) {
+            return Err(Error::Security(format!("service {uuid} is blocklisted")));
+        }
+        if !self.allowed_services.contains(&uuid.into()) {
+            return Err(Error::Security(format!(
+                "service {uuid} was not requested for this device"
+            )));
+        }
+        Ok(())
+    }
+
+    /// Checks that a characteristic or descriptor is not blocklisted.
+    fn check_attribute(&self, attribute: &str, uuid: BluetoothUuid) -> Result<()> {
+        if self.blocklist.is_blocklisted(uuid) {
+            return Err(Error::Security(format!(
+                "{attribute} {uuid} is blocklisted"
+            )));
+        }
+        Ok(())
+    }
+
+    fn check_read(&self, attribute: &str, uuid: BluetoothUuid) -> Result<()> {
+        if self.blocklist.is_blocklisted_for_reads(uuid) {
+            return Err(Error::Security(format!(
+                "{attribute} {uuid} is blocklisted for reads"
+            )));
+        }
+        Ok(())
+    }
+
+    fn check_write(&self, attribute: &str, uuid: BluetoothUuid, value: &[u8]) -> Result<()> {
+        if self.blocklist.is_blocklisted_for_writes(uuid) {
+            return Err(Error::Security(format!(
+                "{attribute} {uuid} is blocklisted for writes"
+            )));
+        }
+        if value.len() > MAX_VALUE_LEN {
+            return Err(Error::InvalidModification(format!(
+                "values may be at most {MAX_VALUE_LEN} bytes"
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// The contexts that have started notifications on each characteristic, matching the active
+/// notification context sets of the specification.
+///
+/// Notifications are only disabled once every context that started them has stopped them.
+#[cfg(any(target_vendor = "apple", test))]
+#[derive(Debug)]
+pub(crate) struct NotificationContexts<K> {
+    active: HashMap<K, HashSet<u64>>,
+}
+
+#[cfg(any(target_vendor = "apple", test))]
+impl<K: Hash + Eq> NotificationContexts<K> {
+    pub(crate) fn new() -> Self {
+        Self {
+            active: HashMap::new(),
+        }
+    }
+
+    /// Adds `context` to the characteristic's set.
+    fn start(&mut self, characteristic: K, context: u64) {
+        self.active
+            .entry(characteristic)
+            .or_default()
+            .insert(context);
+    }
+
+    /// Removes `context` from the characteristic's set, returning whether notifications should
+    /// be disabled because it was the last context in the set.
+    fn stop(&mut self, characteristic: &K, context: u64) -> bool {
+        let Some(contexts) = self.active.get_mut(characteristic) else {
+            return false;
+        };
+        if !contexts.remove(&context) || !contexts.is_empty() {
+            return false;
+        }
+        self.active.remove(characteristic);
+        true
+    }
+}
+
+#[cfg(target_vendor = "apple")]
+mod apple {
+    use std::cell::RefCell;
+    use std::pin::Pin;
+    use std::rc::Rc;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::task::{Context, Poll};
+
+    use btuuid::BluetoothUuid;
+    use corebluetooth::{
+        Characteristic, CharacteristicWriteType, Descriptor, Service,
+        advertisement_data::AdvertisementData,
+    };
+    use futures_core::Stream;
+    use objc2_core_bluetooth::{CBCharacteristicProperties, CBManagerState, CBPeripheralState};
+    use uuid::Uuid;
+
+    use super::{
+        Access, Blocklist, BluetoothAdvertisingEvent, Error, NotificationContexts,
+        RequestDeviceOptions, Result,
+    };
+    use crate::util::{BroadcastReceiver, defer};
+    use crate::{
+        CentralManagerAsync, DeliveryPolicy, DidDisconnect, NotificationReceiver, PeripheralAsync,
+    };
+
+    /// The entry point of the API, matching `navigator.bluetooth`.
+    ///
+    /// Each `Bluetooth` and its clones act as one context for notifications: a characteristic's
+    /// notifications stay enabled until every context that started them has stopped them.
+    #[derive(Debug, Clone)]
+    pub struct Bluetooth {
+        central: CentralManagerAsync,
+        blocklist: Rc<Blocklist>,
+        devices: Rc<RefCell<Vec<BluetoothDevice>>>,
+        context: u64,
+        notification_contexts: Rc<RefCell<NotificationContexts<Characteristic>>>,
+    }
+
+    impl Bluetooth {
+        /// Creates a `Bluetooth` that uses `central` and the default [`Blocklist`].
+        pub fn new(central: CentralManagerAsync) -> Self {
+            Self::with_blocklist(central, Blocklist::default())
+        }
+
+        /// Creates a `Bluetooth` that uses `central` and `blocklist`.
+        pub fn with_blocklist(central: CentralManagerAsync, blocklist: Blocklist) -> Self {
+            static NEXT_CONTEXT: AtomicU64 = AtomicU64::new(0);
+
+            Self {
+                blocklist: Rc::new(blocklist),
+                devices: Default::default(),
+                context: NEXT_CONTEXT.fetch_add(1, Ordering::Relaxed),
+                notification_contexts: central.notification_contexts(),
+                central,
+            }
+        }
+
+        /// Returns whether Bluetooth is powered on.
+        pub fn get_availability(&self) -> bool {
+            self.central.state() == CBManagerState::PoweredOn
+        }
+
+        /// Returns the devices previously returned by
+        /// [`request_device()`][Self::request_device].
+        pub fn get_devices(&self) -> Vec<BluetoothDevice> {
+            self.devices.borrow().clone()
+        }
+
+        /// Scans for and returns the first device matching `options`.
+        ///
+        /// The scan runs until a device is found; use a timeout to give up sooner.
+        pub async fn request_device(
+            &self,
+            options: &RequestDeviceOptions,
+        ) -> Result<BluetoothDevice> {
+            self.request_device_with(options, |_| true).await
+        }
+
+        /// Scans for devices matching `options` and returns the first one `choose` accepts.
+        ///
+        /// This takes the place of the browser's device chooser.
+        pub async fn request_device_with(
+            &self,
+            options: &RequestDeviceOptions,
+            mut choose: impl FnMut(&BluetoothAdvertisingEvent) -> bool,
+        ) -> Result<BluetoothDevice> {
+            options.validate(&self.blocklist)?;
+            if self.central.is_scanning() {
+                return Err(Error::InvalidState(
+                    "a scan is already in progress".to_owned(),
+                ));
+            }
+
+            let services = options.scan_services();
+            let mut discoveries = self.central.scan(services.as_deref(), false, None);
+            let _stop = defer(|| self.central.stop_scan());
+            while let Some(discovery) =
+                std::future::poll_fn(|cx| Pin::new(&mut discoveries).poll_next(cx)).await
+            {
+                let event = advertising_event(
+                    &discovery.peripheral,
+                    &discovery.advertisement_data,
+                    discovery.rssi,
+                );
+                if options.matches(&event) && choose(&event) {
+                    let device = BluetoothDevice {
+                        inner: Rc::new(DeviceInner {
+                            central: self.central.clone(),
+                            peripheral: discovery.peripheral,
+                            access: Access::new(options, self.blocklist.clone()),
+                            context: self.context,
+                            notification_contexts: self.notification_contexts.clone(),
+                        }),
+                    };
+                    let mut devices = self.devices.borrow_mut();
+                    devices.retain(|known| known.id() != device.id());
+                    devices.push(device.clone());
+                    return Ok(device);
+                }
+            }
+            Err(Error::NotFound(
+                "the scan ended before a device was chosen".to_owned(),
+            ))
+        }
+    }
+
+    fn advertising_event(
+        peripheral: &PeripheralAsync,
+        advertisement_data: &AdvertisementData,
+        rssi: i16,
+    ) -> BluetoothAdvertisingEvent {
+        BluetoothAdvertisingEvent {
+            identifier: peripheral.identifier(),
+            name: advertisement_data
+                .local_name
+                .clone()
+                .or_else(|| peripheral.name()),
+            uuids: advertisement_data
+                .service_uuids
+                .iter()
+                .chain(&advertisement_data.overflow_service_uuids)
+                .copied()
+                .collect(),
+            rssi: Some(rssi),
+            tx_power: advertisement_data.tx_power_level,
+            manufacturer_data: advertisement_data
+                .manufacturer_data
+                .iter()
+                .map(|data| (data.company_id, data.data.clone()))
+                .collect(),
+            service_data: advertisement_data.service_data.clone(),
+        }
+    }
+
+    #[derive(Debug)]
+    struct DeviceInner {
+        central: CentralManagerAsync,
+        peripheral: PeripheralAsync,
+        access: Access,
+        context: u64,
+        notification_contexts: Rc<RefCell<NotificationContexts<Characteristic>>>,
+    }
+
+    /// A device returned by [`Bluetooth::request_device()`], matching `BluetoothDevice`.
+    #[derive(Debug, Clone)]
+    pub struct BluetoothDevice {
+        inner: Rc<DeviceInner>,
+    }
+
+    impl BluetoothDevice {
+        /// Returns the identifier of the device.
+        pub fn id(&self) -> Uuid {
+            self.inner.peripheral.identifier()
+        }
+
+        /// Returns the name of the device, if known.
+        pub fn name(&self) -> Option<String> {
+            self.inner.peripheral.name()
+        }
+
+        /// Returns the device's GATT server.
+        pub fn gatt(&self) -> BluetoothRemoteGattServer {
+            BluetoothRemoteGattServer {
+                device: self.clone(),
+            }
+        }
+
+        /// Returns the underlying peripheral.
+        pub fn peripheral(&self) -> &PeripheralAsync {
+            &self.inner.peripheral
+        }
+
+        /// Returns a stream that yields each time the device disconnects, matching the
+        /// `gattserverdisconnected` event.
+        pub fn gatt_server_disconnected(&self) -> GattServerDisconnected {
+            GattServerDisconnected {
+                peripheral: self.inner.peripheral.clone(),
+                disconnections: self.inner.central.disconnections(),
+            }
+        }
+
+        fn ensure_connected(&self) -> Result<()> {
+            if self.inner.peripheral.state() != CBPeripheralState::Connected {
+                return Err(Error::Network("the device is not connected".to_owned()));
+            }
+            Ok(())
+        }
+
+        fn check_service(&self, uuid: BluetoothUuid) -> Result<()> {
+            self.inner.access.check_service(uuid)
+        }
+
+        fn is_service_visible(&self, service: &Service) -> bool {
+            self.check_service(service.uuid()).is_ok()
+        }
+
+        fn access(&self) -> &Access {
+            &self.inner.access
+        }
+    }
+
+    impl PartialEq for BluetoothDevice {
+        fn eq(&self, other: &Self) -> bool {
+            self.id() == other.id()
+        }
+    }
+
+    impl Eq for BluetoothDevice {}
+
+    /// The stream returned by [`BluetoothDevice::gatt_server_disconnected()`].
+    #[derive(Debug)]
+    pub struct GattServerDisconnected {
+        peripheral: PeripheralAsync,
+        disconnections: BroadcastReceiver<DidDisconnect>,
+    }
+
+    impl Stream for GattServerDisconnected {
+        type Item = DidDisconnect;
+
+        fn poll_next(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+        ) -> Poll<Option<DidDisconnect>> {
+            loop {
+                match Pin::new(&mut self.disconnections).poll_next(cx) {
+                    Poll::Ready(Some(disconnect)) if disconnect.peripheral != self.peripheral => {}
+                    poll => return poll,
+                }
+            }
+        }
+    }
+
+    /// A device's GATT server, matching `BluetoothRemoteGATTServer`.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct BluetoothRemoteGattServer {
+        device: BluetoothDevice,
+    }
+
+    impl BluetoothRemoteGattServer {
+        /// Returns the device this server belongs to.
+        pub fn device(&self) -> &BluetoothDevice {
+            &self.device
+        }
+
+        /// Returns whether the device is connected.
+        pub fn connected(&self) -> bool {
+            self.device.inner.peripheral.state() == CBPeripheralState::Connected
+        }
+
+        /// Connects to the device, if it is not already connected.
+        pub async fn connect(&self) -> Result<Self> {
+            if !self.connected() {
+                let inner = &self.device.inner;
+                inner.central.connect(&inner.peripheral).await?;
+            }
+            Ok(self.clone())
+        }
+
+        /// Disconnects from the device.
+        pub async fn disconnect(&self) {
+            let inner = &self.device.inner;
+            inner
+                .central
+                .cancel_peripheral_connection(&inner.peripheral)
+                .await;
+        }
+
+        /// Returns the primary service with the given UUID.
+        pub async fn get_primary_service(
+            &self,
+            service: BluetoothUuid,
+        ) -> Result<BluetoothRemoteGattService> {
+            self.device.check_service(service)?;
+            self.get_primary_services(Some(service))
+                .await?
+                .into_iter()
+                .next()
+                .ok_or_else(|| Error::NotFound(format!("service {service} not found")))
+        }
+
+        /// Returns the primary services with the given UUID, or all accessible primary services.
+        pub async fn get_primary_services(
+            &self,
+            service: Option<BluetoothUuid>,
+        ) -> Result<Vec<BluetoothRemoteGattService>> {
+            if let Some(service) = service {
+                self.device.check_service(service)?;
+            }
+            self.device.ensure_connected()?;
+            let peripheral = &self.device.inner.peripheral;
+            let filter = service.map(|service| [service]);
+            peripheral
+                .discover_services(filter.as_ref().map(|filter| &filter[..]))
+                .await?;
+            let services: Vec<_> = peripheral
+                .services()
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|found| {
+                    found.is_primary()
+                        && service.is_none_or(|service| super::same_uuid(found.uuid(), service))
+                        && self.device.is_service_visible(found)
+                })
+                .map(|service| BluetoothRemoteGattService {
+                    device: self.device.clone(),
+                    service,
+                })
+                .collect();
+            if services.is_empty() {
+                return Err(Error::NotFound("no services found".to_owned()));
+            }
+            Ok(services)
+        }
+    }
+
+    /// A service, matching `BluetoothRemoteGATTService`.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct BluetoothRemoteGattService {
+        device: BluetoothDevice,
+        service: Service,
+    }
+
+    impl BluetoothRemoteGattService {
+        /// Returns the device this service belongs to.
+        pub fn device(&self) -> &BluetoothDevice {
+            &self.device
+        }
+
+        /// Returns the UUID of the service.
+        pub fn uuid(&self) -> BluetoothUuid {
+            self.service.uuid()
+        }
+
+        /// Returns whether this is a primary service.
+        pub fn is_primary(&self) -> bool {
+            self.service.is_primary()
+        }
+
+        /// Returns the underlying service.
+        pub fn service(&self) -> &Service {
+            &self.service
+        }
+
+        /// Returns the characteristic with the given UUID.
+        pub async fn get_characteristic(
+            &self,
+            characteristic: BluetoothUuid,
+        ) -> Result<BluetoothRemoteGattCharacteristic> {
+            self.get_characteristics(Some(characteristic))
+                .await?
+                .into_iter()
+                .next()
+                .ok_or_else(|| {
+                    Error::NotFound(format!("characteristic {characteristic} not found"))
+                })
+        }
+
+        /// Returns the characteristics with the given UUID, or all accessible characteristics.
+        pub async fn get_characteristics(
+            &self,
+            characteristic: Option<BluetoothUuid>,
+        ) -> Result<Vec<BluetoothRemoteGattCharacteristic>> {
+            let access = self.device.access();
+            if let Some(characteristic) = characteristic {
+                access.check_attribute("characteristic", characteristic)?;
+            }
+            self.device.ensure_connected()?;
+            let filter = characteristic.map(|characteristic| [characteristic]);
+            self.device
+                .inner
+                .peripheral
+                .discover_characteristics(&self.service, filter.as_ref().map(|filter| &filter[..]))
+                .await?;
+            let characteristics: Vec<_> = self
+                .service
+                .characteristics()
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|found| {
+                    characteristic.is_none_or(|uuid| super::same_uuid(found.uuid(), uuid))
+                        && access
+                            .check_attribute("characteristic", found.uuid())
+                            .is_ok()
+                })
+                .map(|characteristic| BluetoothRemoteGattCharacteristic {
+                    service: self.clone(),
+                    characteristic,
+                })
+                .collect();
+            if characteristics.is_empty() {
+                return Err(Error::NotFound("no characteristics found".to_owned()));
+            }
+            Ok(characteristics)
+        }
+
+        /// Returns the included service with the given UUID.
+        pub async fn get_included_service(
+            &self,
+            service: BluetoothUuid,
+        ) -> Result<BluetoothRemoteGattService> {
+            self.get_included_services(Some(service))
+                .await?
+                .into_iter()
+                .next()
+                .ok_or_else(|| Error::NotFound(format!("service {service} not found")))
+        }
+
+        /// Returns the included services with the given UUID, or all accessible included
+        /// services.
+        pub async fn get_included_services(
+            &self,
+            service: Option<BluetoothUuid>,
+        ) -> Result<Vec<BluetoothRemoteGattService>> {
+            if let Some(service) = service {
+                self.device.check_service(service)?;
+            }
+            self.device.ensure_connected()?;
+            let filter = service.map(|service| [service]);
+            self.device
+                .inner
+                .peripheral
+                .discover_included_services(
+                    &self.service,
+                    filter.as_ref().map(|filter| &filter[..]),
+                )
+                .await?;
+            let services: Vec<_> = self
+                .service
+                .included_services()
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|found| {
+                    service.is_none_or(|service| super::same_uuid(found.uuid(), service))
+                        && self.device.is_service_visible(found)
+                })
+                .map(|service| BluetoothRemoteGattService {
+                    device: self.device.clone(),
+                    service,
+                })
+                .collect();
+            if services.is_empty() {
+                return Err(Error::NotFound("no included services found".to_owned()));
+            }
+            Ok(services)
+        }
+    }
+
+    /// The properties of a characteristic, matching `BluetoothCharacteristicProperties`.
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+    pub struct BluetoothCharacteristicProperties {
+        /// The value may be broadcast.
+        pub broadcast: bool,
+        /// The value may be read.
+        pub read: bool,
+        /// The value may be written without response.
+        pub write_without_response: bool,
+        /// The value may be written with response.
+        pub write: bool,
+        /// The value may be notified.
+        pub notify: bool,
+        /// The value may be indicated.
+        pub indicate: bool,
+        /// The value may be written with a signature.
+        pub authenticated_signed_writes: bool,
+        /// The characteristic has an extended properties descriptor.
+        ///
+        /// CoreBluetooth does not expose the reliable write and writable auxiliaries bits of that
+        /// descriptor.
+        pub extended_properties: bool,
+    }
+
+    impl From<CBCharacteristicProperties> for BluetoothCharacteristicProperties {
+        fn from(properties: CBCharacteristicProperties) -> Self {
+            Self {
+                broadcast: properties.contains(CBCharacteristicProperties::Broadcast),
+                read: properties.contains(CBCharacteristicProperties::Read),
+                write_without_response: properties
+                    .contains(CBCharacteristicProperties::WriteWithoutResponse),
+                write: properties.contains(CBCharacteristicProperties::Write),
+                notify: properties.contains(CBCharacteristicProperties::Notify),
+                indicate: properties.contains(CBCharacteristicProperties::Indicate),
+                authenticated_signed_writes: properties
+                    .contains(CBCharacteristicProperties::AuthenticatedSignedWrites),
+                extended_properties: properties
+                    .contains(CBCharacteristicProperties::ExtendedProperties),
+            }
+        }
+    }
+
+    /// A characteristic, matching `BluetoothRemoteGATTCharacteristic`.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct BluetoothRemoteGattCharacteristic {
+        service: BluetoothRemoteGattService,
+        characteristic: Characteristic,
+    }
+
+    impl BluetoothRemoteGattCharacteristic {
+        /// Returns the service this characteristic belongs to.
+        pub fn service(&self) -> &BluetoothRemoteGattService {
+            &self.service
+        }
+
+        /// Returns the UUID of the characteristic.
+        pub fn uuid(&self) -> BluetoothUuid {
+            self.characteristic.uuid()
+        }
+
+        /// Returns the properties of the characteristic.
+        pub fn properties(&self) -> BluetoothCharacteristicProperties {
+            self.characteristic.properties().into()
+        }
+
+        /// Returns the most recently read or notified value, if any.
+        pub fn value(&self) -> Option<Vec<u8>> {
+            self.characteristic.value()
+        }
+
+        /// Returns the underlying characteristic.
+        pub fn characteristic(&self) -> &Characteristic {
+            &self.characteristic
+        }
+
+        fn device(&self) -> &BluetoothDevice {
+            &self.service.device
+        }
+
+        /// Returns the descriptor with the given UUID.
+        pub async fn get_descriptor(
+            &self,
+            descriptor: BluetoothUuid,
+        ) -> Result<BluetoothRemoteGattDescriptor> {
+            self.get_descriptors(Some(descriptor))
+                .await?
+                .into_iter()
+                .next()
+                .ok_or_else(|| Error::NotFound(format!("descriptor {descriptor} not found")))
+        }
+
+        /// Returns the descriptors with the given UUID, or all accessible descriptors.
+        pub async fn get_descriptors(
+            &self,
+            descriptor: Option<BluetoothUuid>,
+        ) -> Result<Vec<BluetoothRemoteGattDescriptor>> {
+            let access = self.device().access();
+            if let Some(descriptor) = descriptor {
+                access.check_attribute("descriptor", descriptor)?;
+            }
+            self.device().ensure_connected()?;
+            self.device()
+                .inner
+                .peripheral
+                .discover_descriptors(&self.characteristic)
+                .await?;
+            let descriptors: Vec<_> = self
+                .characteristic
+                .descriptors()
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|found| {
+                    descriptor.is_none_or(|uuid| super::same_uuid(found.uuid(), uuid))
+                        && access.check_attribute("descriptor", found.uuid()).is_ok()
+                })
+                .map(|descriptor| BluetoothRemoteGattDescriptor {
+                    characteristic: self.clone(),
+                    descriptor,
+                })
+                .collect();
+            if descriptors.is_empty() {
+                return Err(Error::NotFound("no descriptors found".to_owned()));
+            }
+            Ok(descriptors)
+        }
+
+        /// Reads the value of the characteristic.
+        pub async fn read_value(&self) -> Result<Vec<u8>> {
+            self.device()
+                .access()
+                .check_read("characteristic", self.uuid())?;
+            self.device().ensure_connected()?;
+            if !self.properties().read {
+                return Err(Error::NotSupported(
+                    "the characteristic does not support reads".to_owned(),
+                ));
+            }
+            Ok(self
+                .device()
+                .inner
+                .peripheral
+                .read_characteristic_value(&self.characteristic)
+                .await?)
+        }
+
+        /// Writes the value of the characteristic with response if the characteristic supports
+        /// it, or without response otherwise, matching the deprecated `writeValue()`.
+        pub async fn write_value(&self, value: &[u8]) -> Result<()> {
+            let properties = self.properties();
+            let write_type = if properties.write || !properties.write_without_response {
+                CharacteristicWriteType::WithResponse
+            } else {
+                CharacteristicWriteType::WithoutResponse
+            };
+            self.write(value, write_type).await
+        }
+
+        /// Writes the value of the characteristic with response.
+        pub async fn write_value_with_response(&self, value: &[u8]) -> Result<()> {
+            self.write(value, CharacteristicWriteType::WithResponse)
+                .await
+        }
+
+        /// Writes the value of the characteristic without response.
+        pub async fn write_value_without_response(&self, value: &[u8]) -> Result<()> {
+            self.write(value, CharacteristicWriteType::WithoutResponse)
+                .await
+        }
+
+        async fn write(&self, value: &[u8], write_type: CharacteristicWriteType) -> Result<()> {
+            self.device()
+                .access()
+                .check_write("characteristic", self.uuid(), value)?;
+            self.device().ensure_connected()?;
+            let properties = self.properties();
+            let supported = match write_type {
+                CharacteristicWriteType::WithoutResponse => properties.write_without_response,
+                _ => properties.write,
+            };
+            if !supported {
+                return Err(Error::NotSupported(format!(
+                    "the characteristic does not support {write_type:?} writes"
+                )));
+            }
+            let peripheral = &self.device().inner.peripheral;
+            if write_type == CharacteristicWriteType::WithoutResponse {
+                peripheral.ready_to_send_write_without_response().await?;
+            }
+            Ok(peripheral
+                .write_characteristic_value(&self.characteristic, value.to_vec(), write_type)
+                .await?)
+        }
+
+        /// Enables notifications or indications for the characteristic.
+        ///
+        /// Values are delivered to the streams returned by
+        /// [`characteristic_value_changed()`][Self::characteristic_value_changed].
+        pub async fn start_notifications(&self) -> Result<Self> {
+            self.check_notify()?;
+            if !self.characteristic.is_notifying() {
+                self.set_notify(true).await?;
+            }
+            let inner = &self.device().inner;
+            inner
+                .notification_contexts
+                .borrow_mut()
+                .start(self.characteristic.clone(), inner.context);
+            Ok(self.clone())
+        }
+
+        /// Stops notifications or indications for the characteristic in this context.
+        ///
+        /// Notifications are only disabled once every [`Bluetooth`] that started them has
+        /// stopped them.
+        pub async fn stop_notifications(&self) -> Result<Self> {
+            self.check_notify()?;
+            let inner = &self.device().inner;
+            let last = inner
+                .notification_contexts
+                .borrow_mut()
+                .stop(&self.characteristic, inner.context);
+            if last && self.characteristic.is_notifying() {
+                self.set_notify(false).await?;
+            }
+            Ok(self.clone())
+        }
+
+        fn check_notify(&self) -> Result<()> {
+            self.device().ensure_connected()?;
+            let properties = self.properties();
+            if !properties.notify && !properties.indicate {
+                return Err(Error::NotSupported(
+                    "the characteristic does not support notifications".to_owned(),
+                ));
+            }
+            Ok(())
+        }
+
+        async fn set_notify(&self, notify: bool) -> Result<()> {
+            self.device()
+                .inner
+                .peripheral
+                .set_notify(&self.characteristic, notify)
+                .await?;
+            Ok(())
+        }
+
+        /// Returns a stream of the characteristic's values as they are read or notified,
+        /// matching the `characteristicvaluechanged` event.
+        pub fn characteristic_value_changed(&self) -> CharacteristicValueChanged {
+            CharacteristicValueChanged {
+                values: self
+                    .device()
+                    .inner
+                    .peripheral
+                    .characteristic_value_updates_with_policy(
+                        &self.characteristic,
+                        DeliveryPolicy::default(),
+                    ),
+            }
+        }
+    }
+
+    /// The stream returned by
+    /// [`BluetoothRemoteGattCharacteristic::characteristic_value_changed()`].
+    ///
+    /// Failed reads are skipped, as they do not fire the event in Web Bluetooth.
+    #[derive(Debug)]
+    pub struct CharacteristicValueChanged {
+        values: NotificationReceiver<crate::error::Result<Vec<u8>>>,
+    }
+
+    impl CharacteristicValueChanged {
+        /// Returns the underlying receiver, for example to inspect its
+        /// [`stats()`][NotificationReceiver::stats].
+        pub fn receiver(&self) -> &NotificationReceiver<crate::error::Result<Vec<u8>>> {
+            &self.values
+        }
+    }
+
+    impl Stream for CharacteristicValueChanged {
+        type Item = Vec<u8>;
+
+        fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Vec<u8>>> {
+            loop {
+                match Pin::new(&mut self.values).poll_next(cx) {
+                    Poll::Ready(Some(Ok(value))) => return Poll::Ready(Some(value)),
+                    Poll::Ready(Some(Err(_))) => {}
+                    Poll::Ready(None) => return Poll::Ready(None),
+                    Poll::Pending => return Poll::Pending,
+                }
+            }
+        }
+    }
+
+    /// A descriptor, matching `BluetoothRemoteGATTDescriptor`.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct BluetoothRemoteGattDescriptor {
+        characteristic: BluetoothRemoteGattCharacteristic,
+        descriptor: Descriptor,
+    }
+
+    impl BluetoothRemoteGattDescriptor {
+        /// Returns the characteristic this descriptor belongs to.
+        pub fn characteristic(&self) -> &BluetoothRemoteGattCharacteristic {
+            &self.characteristic
+        }
+
+        /// Returns the UUID of the descriptor.
+        pub fn uuid(&self) -> BluetoothUuid {
+            self.descriptor.uuid()
+        }
+
+        /// Returns the most recently read value, if any.
+        pub fn value(&self) -> Option<Vec<u8>> {
+            self.descriptor.value()
+        }
+
+        /// Returns the underlying descriptor.
+        pub fn descriptor(&self) -> &Descriptor {
+            &self.descriptor
+        }
+
+        fn device(&self) -> &BluetoothDevice {
+            self.characteristic.device()
+        }
+
+        /// Reads the value of the descriptor.
+        pub async fn read_value(&self) -> Result<Vec<u8>> {
+            self.device()
+                .access()
+                .check_read("descriptor", self.uuid())?;
+            self.device().ensure_connected()?;
+            Ok(self
+                .device()
+                .inner
+                .peripheral
+                .read_descriptor_value(&self.descriptor)
+                .await?)
+        }
+
+        /// Writes the value of the descriptor.
+        pub async fn write_value(&self, value: &[u8]) -> Result<()> {
+            self.device()
+                .access()
+                .check_write("descriptor", self.uuid(), value)?;
+            self.device().ensure_connected()?;
+            Ok(self
+                .device()
+                .inner
+                .peripheral
+                .write_descriptor_value(&self.descriptor, value.to_vec())
+                .await?)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use btuuid::{characteristic, descriptors, service};
+
+    use super::*;
+
+    fn access(options: RequestDeviceOptions) -> Access {
+        Access::new(&options, Rc::new(Blocklist::default()))
+    }
+
+    fn heart_rate_monitor() -> Access {
+        access(RequestDeviceOptions {
+            filters: vec![BluetoothLeScanFilter {
+                services: vec![service::HEART_RATE.into()],
+                ..Default::default()
+            }],
+            optional_services: vec![
+                service::BATTERY.into(),
+                service::HUMAN_INTERFACE_DEVICE.into(),
+            ],
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn requested_services_are_accessible() {
+        let access = heart_rate_monitor();
+        access.check_service(service::HEART_RATE.into()).unwrap();
+        access.check_service(service::BATTERY.into()).unwrap();
+        // The same service in its 128-bit form.
+        let battery = BluetoothUuid::from(Uuid::from(BluetoothUuid::from(service::BATTERY)));
+        access.check_service(battery).unwrap();
+    }
+
+    #[test]
+    fn other_services_are_not_accessible() {
+        let access = heart_rate_monitor();
+        let err = access
+            .check_service(service::DEVICE_INFORMATION.into())
+            .unwrap_err();
+        assert_eq!(err.name(), "SecurityError");
+        assert!(err.to_string().contains("was not requested"), "{err}");
+    }
+
+    #[test]
+    fn blocklisted_services_are_not_accessible_even_if_requested() {
+        let access = heart_rate_monitor();
+        let err = access
+            .check_service(service::HUMAN_INTERFACE_DEVICE.into())
+            .unwrap_err();
+        assert_eq!(err.name(), "SecurityError");
+        assert!(err.to_string().contains("blocklisted"), "{err}");
+    }
+
+    #[test]
+    fn accept_all_devices_only_gives_access_to_optional_services() {
+        let access = access(RequestDeviceOptions {
+            accept_all_devices: true,
+            optional_services: vec![service::BATTERY.into()],
+            ..Default::default()
+        });
+        access.check_service(service::BATTERY.into()).unwrap();
+        assert!(access.check_service(service::HEART_RATE.into()).is_err());
+    }
+
+    #[test]
+    fn blocklisted_attributes_are_hidden() {
+        let access = heart_rate_monitor();
+        access
+            .check_attribute(
+                "characteristic",
+                characteristic::HEART_RATE_MEASUREMENT.into(),
+            )
+            .unwrap();
+        let err = access
+            .check_attribute(
+                "characteristic",
+                characteristic::SERIAL_NUMBER_STRING.into(),
+            )
+            .unwrap_err();
+        assert_eq!(err.name(), "SecurityError");
+
+        // Write-excluded attributes are still visible.
+        access
+            .check_attribute(
+                "descriptor",
+                descriptors::CLIENT_CHARACTERISTIC_CONFIGURATION.into(),
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn read_and_write_exclusions_are_enforced() {
+        let access = heart_rate_monitor();
+        let cccd = descriptors::CLIENT_CHARACTERISTIC_CONFIGURATION.into();
+        access.check_read("descriptor", cccd).unwrap();
+        let err = access.check_write("descriptor", cccd, &[1, 0]).unwrap_err();
+        assert_eq!(err.name(), "SecurityError");
+
+        let serial_number = characteristic::SERIAL_NUMBER_STRING.into();
+        assert!(access.check_read("characteristic", serial_number).is_err());
+        assert!(
+            access
+                .check_write("characteristic", serial_number, &[])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn long_writes_are_rejected() {
+        let access = heart_rate_monitor();
+        let control_point = characteristic::HEART_RATE_CONTROL_POINT.into();
+        access
+            .check_write("characteristic", control_point, &[0; MAX_VALUE_LEN])
+            .unwrap();
+        let err = access
+            .check_write("characteristic", control_point, &[0; MAX_VALUE_LEN + 1])
+            .unwrap_err();
+        assert_eq!(err.name(), "InvalidModificationError");
+    }
+
+    #[test]
+    fn notifications_stop_when_the_last_context_stops() {
+        let mut contexts = NotificationContexts::new();
+        contexts.start("a", 1);
+        contexts.start("a", 2);
+        // Starting twice in one context is the same as starting once.
+        contexts.start("a", 2);
+        contexts.start("b", 1);
+
+        assert!(!contexts.stop(&"a", 1));
+        assert!(contexts.stop(&"a", 2));
+        assert!(contexts.stop(&"b", 1));
+    }
+
+    #[test]
+    fn stopping_without_starting_keeps_notifications() {
+        let mut contexts = NotificationContexts::new();
+        assert!(!contexts.stop(&"a", 1));
+
+        contexts.start("a", 1);
+        assert!(!contexts.stop(&"a", 2));
+        assert!(contexts.stop(&"a", 1));
+        // The set is empty again, so a second stop does not disable them a second time.
+        assert!(!contexts.stop(&"a", 1));
+    }
+}
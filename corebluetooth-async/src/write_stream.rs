@@ -0,0 +1,384 @@
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use futures_sink::Sink;
+
+use crate::error::{CBATTError, Error, ErrorKind, Result};
+
+#[cfg(target_vendor = "apple")]
+pub use apple::*;
+
+/// Throughput statistics for a [`WriteStream`].
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct WriteStats {
+    /// The number of writes sent to the peripheral.
+    pub writes: u64,
+    /// The number of bytes sent to the peripheral.
+    pub bytes: u64,
+    /// The number of times sending stalled waiting for the peripheral to become ready.
+    pub stalls: u64,
+    /// The time between the first and the most recent write.
+    pub elapsed: Duration,
+}
+
+impl WriteStats {
+    /// The average throughput in bytes per second.
+    pub fn bytes_per_second(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs > 0.0 {
+            self.bytes as f64 / secs
+        } else {
+            0.0
+        }
+    }
+}
+
+/// The destination of a [`WriteStream`]: a characteristic that accepts writes without response
+/// and signals when it is ready for more.
+///
+/// On macOS and iOS, `CharacteristicWriter` writes to a characteristic of a `PeripheralAsync`.
+/// Other implementations can be used to test or benchmark code written against [`WriteStream`]
+/// without a peripheral.
+pub trait WriteTarget {
+    /// The maximum length of a single write.
+    fn max_write_len(&self) -> usize;
+
+    /// Checks whether a write can be sent now.
+    ///
+    /// If it cannot, the waker of `cx` is woken when the target may have become ready. Errors,
+    /// such as the peripheral disconnecting, fail the stream and discard its queued writes.
+    fn poll_ready_to_send(&mut self, cx: &mut Context<'_>) -> Poll<Result<()>>;
+
+    /// Sends a write. Only called after [`poll_ready_to_send()`][Self::poll_ready_to_send] has
+    /// returned `Poll::Ready(Ok(()))`.
+    fn send(&mut self, data: Vec<u8>);
+}
+
+/// A [`Sink`] that queues writes without response to a characteristic.
+///
+/// Writes are sent to the [target][WriteTarget] as fast as it is ready for them; for a
+/// peripheral, that is as fast as its transmit buffer allows, using
+/// `is_ready_to_send_write_without_response` callbacks for flow control. At most `capacity`
+/// writes are queued in the stream; once the queue is full, [`poll_ready()`][Sink::poll_ready]
+/// waits until there is room.
+///
+/// On macOS and iOS, created by `PeripheralAsync::write_stream()`.
+#[derive(Debug)]
+pub struct WriteStream<T> {
+    target: T,
+    queue: VecDeque<Vec<u8>>,
+    capacity: usize,
+    stalled: bool,
+    stats: WriteStats,
+    first_write: Option<Instant>,
+}
+
+impl<T: WriteTarget> WriteStream<T> {
+    /// Creates a stream that queues at most `capacity` writes to `target`.
+    pub fn new(target: T, capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            target,
+            queue: VecDeque::with_capacity(capacity),
+            capacity,
+            stalled: false,
+            stats: WriteStats::default(),
+            first_write: None,
+        }
+    }
+
+    /// The target this stream writes to.
+    pub fn target(&self) -> &T {
+        &self.target
+    }
+
+    /// The number of writes queued in the stream that have not been sent to the peripheral.
+    pub fn queued(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Returns the throughput statistics of this stream.
+    pub fn stats(&self) -> WriteStats {
+        self.stats
+    }
+
+    fn poll_drain(&mut self, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        while !self.queue.is_empty() {
+            match self.target.poll_ready_to_send(cx) {
+                Poll::Ready(Ok(())) => {}
+                Poll::Ready(Err(err)) => {
+                    self.queue.clear();
+                    return Poll::Ready(Err(err));
+                }
+                Poll::Pending => {
+                    if !self.stalled {
+                        self.stalled = true;
+                        self.stats.stalls += 1;
+                    }
+                    return Poll::Pending;
+                }
+            }
+            self.stalled = false;
+
+            let data = self.queue.pop_front().unwrap();
+            let now = Instant::now();
+            let first_write = *self.first_write.get_or_insert(now);
+            self.stats.writes += 1;
+            self.stats.bytes += data.len() as u64;
+            self.stats.elapsed = now - first_write;
+            self.target.send(data);
+        }
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<T: WriteTarget + Unpin> Sink<Vec<u8>> for WriteStream<T> {
+    type Error = Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        let this = self.get_mut();
+        match this.poll_drain(cx) {
+            Poll::Ready(Err(err)) => Poll::Ready(Err(err)),
+            _ if this.queue.len() < this.capacity => Poll::Ready(Ok(())),
+            _ => Poll::Pending,
+        }
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: Vec<u8>) -> Result<()> {
+        let this = self.get_mut();
+        if item.len() > this.target.max_write_len() {
+            return Err(ErrorKind::ATT(CBATTError::InvalidAttributeValueLength).into());
+        }
+        this.queue.push_back(item);
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        self.get_mut().poll_drain(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        self.get_mut().poll_drain(cx)
+    }
+}
+
+#[cfg(target_vendor = "apple")]
+mod apple {
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    use corebluetooth::{CBPeripheralState, Characteristic, CharacteristicWriteType};
+    use futures_core::Stream;
+
+    use super::{WriteStream, WriteTarget};
+    use crate::error::{CBError, ErrorKind, Result};
+    use crate::peripheral::PeripheralAsync;
+    use crate::util::BroadcastReceiver;
+
+    /// A [`WriteTarget`] that writes without response to a characteristic of a
+    /// [`PeripheralAsync`].
+    ///
+    /// Created by [`PeripheralAsync::write_stream()`].
+    #[derive(Debug)]
+    pub struct CharacteristicWriter {
+        peripheral: PeripheralAsync,
+        characteristic: Characteristic,
+        ready: BroadcastReceiver<()>,
+    }
+
+    impl CharacteristicWriter {
+        pub(crate) fn new(
+            peripheral: PeripheralAsync,
+            characteristic: Characteristic,
+            ready: BroadcastReceiver<()>,
+        ) -> Self {
+            Self {
+                peripheral,
+                characteristic,
+                ready,
+            }
+        }
+
+        /// The characteristic this writer writes to.
+        pub fn characteristic(&self) -> &Characteristic {
+            &self.characteristic
+        }
+    }
+
+    impl WriteTarget for CharacteristicWriter {
+        fn max_write_len(&self) -> usize {
+            self.peripheral
+                .max_write_value_len(CharacteristicWriteType::WithoutResponse)
+        }
+
+        fn poll_ready_to_send(&mut self, cx: &mut Context<'_>) -> Poll<Result<()>> {
+            loop {
+                if self.peripheral.state() != CBPeripheralState::Connected {
+                    return Poll::Ready(Err(ErrorKind::Bluetooth(CBError::NotConnected).into()));
+                }
+                if self.peripheral.can_send_write_without_repsonse() {
+                    return Poll::Ready(Ok(()));
+                }
+                match Pin::new(&mut self.ready).poll_next(cx) {
+                    Poll::Ready(Some(())) => continue,
+                    Poll::Ready(None) => return Poll::Ready(Err(ErrorKind::Canceled.into())),
+                    Poll::Pending => return Poll::Pending,
+                }
+            }
+        }
+
+        fn send(&mut self, data: Vec<u8>) {
+            self.peripheral.send_characteristic_value(
+                &self.characteristic,
+                data,
+                CharacteristicWriteType::WithoutResponse,
+            );
+        }
+    }
+
+    impl WriteStream<CharacteristicWriter> {
+        /// The characteristic this stream writes to.
+        pub fn characteristic(&self) -> &Characteristic {
+            self.target.characteristic()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::task::Waker;
+
+    use futures_lite::future;
+
+    use super::*;
+
+    #[derive(Debug, Default)]
+    struct FakeTarget {
+        ready: bool,
+        error: Option<ErrorKind>,
+        sent: Vec<Vec<u8>>,
+        waker: Option<Waker>,
+    }
+
+    impl WriteTarget for FakeTarget {
+        fn max_write_len(&self) -> usize {
+            4
+        }
+
+        fn poll_ready_to_send(&mut self, cx: &mut Context<'_>) -> Poll<Result<()>> {
+            if let Some(kind) = self.error {
+                Poll::Ready(Err(kind.into()))
+            } else if self.ready {
+                Poll::Ready(Ok(()))
+            } else {
+                self.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+
+        fn send(&mut self, data: Vec<u8>) {
+            self.sent.push(data);
+        }
+    }
+
+    fn poll<R>(
+        stream: &mut WriteStream<FakeTarget>,
+        f: impl FnOnce(Pin<&mut WriteStream<FakeTarget>>, &mut Context<'_>) -> Poll<R>,
+    ) -> Poll<R> {
+        let mut f = Some(f);
+        future::block_on(future::poll_once(std::future::poll_fn(|cx| {
+            (f.take().unwrap())(Pin::new(&mut *stream), cx)
+        })))
+        .map_or(Poll::Pending, Poll::Ready)
+    }
+
+    #[test]
+    fn sends_immediately_when_ready() {
+        let target = FakeTarget {
+            ready: true,
+            ..Default::default()
+        };
+        let mut stream = WriteStream::new(target, 2);
+        for data in [vec![1], vec![2, 3]] {
+            assert!(matches!(
+                poll(&mut stream, |s, cx| s.poll_ready(cx)),
+                Poll::Ready(Ok(()))
+            ));
+            Pin::new(&mut stream).start_send(data).unwrap();
+        }
+        assert!(matches!(
+            poll(&mut stream, |s, cx| s.poll_flush(cx)),
+            Poll::Ready(Ok(()))
+        ));
+
+        assert_eq!(stream.target().sent, [vec![1], vec![2, 3]]);
+        assert_eq!(stream.queued(), 0);
+        let stats = stream.stats();
+        assert_eq!((stats.writes, stats.bytes, stats.stalls), (2, 3, 0));
+    }
+
+    #[test]
+    fn applies_backpressure_when_full() {
+        let mut stream = WriteStream::new(FakeTarget::default(), 2);
+        for data in [vec![1], vec![2]] {
+            assert!(matches!(
+                poll(&mut stream, |s, cx| s.poll_ready(cx)),
+                Poll::Ready(Ok(()))
+            ));
+            Pin::new(&mut stream).start_send(data).unwrap();
+        }
+        assert!(poll(&mut stream, |s, cx| s.poll_ready(cx)).is_pending());
+        assert!(poll(&mut stream, |s, cx| s.poll_flush(cx)).is_pending());
+        assert_eq!(stream.queued(), 2);
+        assert_eq!(stream.stats().stalls, 1);
+        assert!(stream.target.waker.is_some());
+
+        stream.target.ready = true;
+        assert!(matches!(
+            poll(&mut stream, |s, cx| s.poll_ready(cx)),
+            Poll::Ready(Ok(()))
+        ));
+        assert_eq!(stream.target().sent, [vec![1], vec![2]]);
+        assert_eq!(stream.stats().stalls, 1);
+    }
+
+    #[test]
+    fn zero_capacity_queues_one_write() {
+        let mut stream = WriteStream::new(FakeTarget::default(), 0);
+        assert!(matches!(
+            poll(&mut stream, |s, cx| s.poll_ready(cx)),
+            Poll::Ready(Ok(()))
+        ));
+        Pin::new(&mut stream).start_send(vec![1]).unwrap();
+        assert!(poll(&mut stream, |s, cx| s.poll_ready(cx)).is_pending());
+    }
+
+    #[test]
+    fn rejects_writes_longer_than_max() {
+        let mut stream = WriteStream::new(FakeTarget::default(), 2);
+        let err = Pin::new(&mut stream).start_send(vec![0; 5]).unwrap_err();
+        assert_eq!(
+            err.kind(),
+            ErrorKind::ATT(CBATTError::InvalidAttributeValueLength)
+        );
+        assert_eq!(stream.queued(), 0);
+    }
+
+    #[test]
+    fn target_error_discards_queue() {
+        let mut stream = WriteStream::new(FakeTarget::default(), 4);
+        Pin::new(&mut stream).start_send(vec![1]).unwrap();
+        Pin::new(&mut stream).start_send(vec![2]).unwrap();
+        stream.target.error = Some(ErrorKind::Canceled);
+
+        let Poll::Ready(Err(err)) = poll(&mut stream, |s, cx| s.poll_flush(cx)) else {
+            panic!("flush did not fail");
+        };
+        assert_eq!(err.kind(), ErrorKind::Canceled);
+        assert_eq!(stream.queued(), 0);
+        assert!(stream.target().sent.is_empty());
+    }
+}
@@ -0,0 +1,197 @@
+//! Checks the btsnoop encoding against captures in `fixtures/`, which were encoded independently
+//! of this crate from the btsnoop format and the HCI and ATT packet formats of the Bluetooth Core
+//! Specification.
+
+use std::io;
+use std::time::{Duration, SystemTime};
+
+use corebluetooth_async::btsnoop::{
+    AttPdu, BtsnoopWriter, Direction, acl_packet, connection_complete_event,
+    disconnection_complete_event,
+};
+
+/// The capture of `fixtures/session.cbrec`.
+const CAPTURE: &[u8] = include_bytes!("fixtures/session.btsnoop");
+
+/// The connection handle assigned to the peripheral in the fixtures.
+const CONNECTION: u16 = 0x0001;
+
+/// The address derived from the peripheral identifier in the fixtures.
+const ADDRESS: [u8; 6] = [0xef, 0x1b, 0x2c, 0x3d, 0x4e, 0x5f];
+
+struct Record<'a> {
+    flags: u32,
+    packet: &'a [u8],
+}
+
+/// Splits a capture into its header and records.
+fn parse(capture: &[u8]) -> (&[u8], Vec<Record<'_>>) {
+    let u32_at = |buf: &[u8], at: usize| u32::from_be_bytes(buf[at..at + 4].try_into().unwrap());
+    let (header, mut rest) = capture.split_at(16);
+    let mut records = Vec::new();
+    while !rest.is_empty() {
+        let len = u32_at(rest, 0) as usize;
+        assert_eq!(u32_at(rest, 4) as usize, len, "truncated packet");
+        records.push(Record {
+            flags: u32_at(rest, 8),
+            packet: &rest[24..24 + len],
+        });
+        rest = &rest[24 + len..];
+    }
+    (header, records)
+}
+
+fn at(seconds: f64) -> SystemTime {
+    SystemTime::UNIX_EPOCH
+        + Duration::from_millis(1_700_000_000_000)
+        + Duration::from_secs_f64(seconds)
+}
+
+#[test]
+fn header() {
+    let mut expected = b"btsnoop\0".to_vec();
+    expected.extend_from_slice(&1u32.to_be_bytes());
+    expected.extend_from_slice(&1002u32.to_be_bytes());
+    assert_eq!(parse(CAPTURE).0, expected);
+    assert_eq!(
+        BtsnoopWriter::new(Vec::new()).unwrap().into_inner(),
+        expected
+    );
+}
+
+#[test]
+fn hci_events() {
+    let (_, records) = parse(CAPTURE);
+    let connected = records.first().unwrap();
+    assert_eq!(
+        connected.packet,
+        connection_complete_event(0x00, CONNECTION, ADDRESS)
+    );
+    // Received event.
+    assert_eq!(connected.flags, 3);
+
+    let disconnected = records.last().unwrap();
+    // Connection Timeout.
+    assert_eq!(
+        disconnected.packet,
+        disconnection_complete_event(CONNECTION, 0x08)
+    );
+    assert_eq!(disconnected.flags, 3);
+}
+
+#[test]
+fn att_packets() {
+    let pdus = [
+        AttPdu::ExchangeMtuRequest { mtu: 185 },
+        AttPdu::ExchangeMtuResponse { mtu: 185 },
+        AttPdu::HandleValueNotification {
+            handle: 0x0001,
+            value: &[0x64],
+        },
+        AttPdu::ReadRequest { handle: 0x0002 },
+        AttPdu::ReadResponse { value: b"Acme" },
+        AttPdu::ReadRequest { handle: 0x0003 },
+        AttPdu::ErrorResponse {
+            request: 0x0a,
+            handle: 0x0003,
+            error: 0x02,
+        },
+        AttPdu::WriteRequest {
+            handle: 0x0004,
+            value: b"hello",
+        },
+        AttPdu::WriteResponse,
+        AttPdu::WriteCommand {
+            handle: 0x0004,
+            value: &[0x01, 0x02],
+        },
+        AttPdu::WriteRequest {
+            handle: 0x0005,
+            value: &[0x01, 0x00],
+        },
+        AttPdu::WriteResponse,
+    ];
+    let (_, records) = parse(CAPTURE);
+    let acl = &records[1..records.len() - 1];
+    assert_eq!(acl.len(), pdus.len());
+    for (record, pdu) in acl.iter().zip(&pdus) {
+        assert_eq!(
+            record.packet,
+            acl_packet(CONNECTION, pdu).unwrap(),
+            "{pdu:?}"
+        );
+    }
+}
+
+#[test]
+fn writer_matches_capture() {
+    // The first three packets of the capture: the connection and the MTU exchange.
+    let timestamp = at(2.0);
+    let mut writer = BtsnoopWriter::new(Vec::new()).unwrap();
+    let event = connection_complete_event(0x00, CONNECTION, ADDRESS);
+    writer
+        .write_packet(timestamp, Direction::Received, &event)
+        .unwrap();
+    let request = AttPdu::ExchangeMtuRequest { mtu: 185 };
+    writer
+        .write_att(timestamp, Direction::Sent, CONNECTION, &request)
+        .unwrap();
+    let response = AttPdu::ExchangeMtuResponse { mtu: 185 };
+    writer
+        .write_att(timestamp, Direction::Received, CONNECTION, &response)
+        .unwrap();
+
+    let capture = writer.into_inner();
+    assert_eq!(capture, CAPTURE[..capture.len()]);
+}
+
+#[test]
+fn acl_packet_rejects_oversized_pdu() {
+    // A 1 byte opcode and 2 byte handle leave room for 65,532 bytes of value.
+    let value = vec![0; 65_532];
+    let pdu = AttPdu::WriteCommand {
+        handle: 1,
+        value: &value[..65_528],
+    };
+    let packet = acl_packet(CONNECTION, &pdu).unwrap();
+    assert_eq!(packet[3..5], u16::MAX.to_le_bytes());
+
+    let pdu = AttPdu::WriteCommand {
+        handle: 1,
+        value: &value[..65_529],
+    };
+    let err = acl_packet(CONNECTION, &pdu).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+    let mut writer = BtsnoopWriter::new(Vec::new()).unwrap();
+    let err = writer
+        .write_att(at(0.0), Direction::Sent, CONNECTION, &pdu)
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+}
+
+#[cfg(feature = "record")]
+mod export {
+    use std::io::BufReader;
+
+    use corebluetooth_async::btsnoop;
+    use corebluetooth_async::record::Recording;
+
+    fn export(recording: &[u8]) -> Vec<u8> {
+        let recording = Recording::read(BufReader::new(recording)).unwrap();
+        btsnoop::export(&recording, Vec::new()).unwrap()
+    }
+
+    #[test]
+    fn matches_capture() {
+        let capture = export(include_bytes!("fixtures/session.cbrec"));
+        assert_eq!(capture, super::CAPTURE);
+    }
+
+    #[test]
+    fn version_1_recording_matches_capture() {
+        // Without write values, write requests carry no value and write commands are missing.
+        let capture = export(include_bytes!("fixtures/session-v1.cbrec"));
+        assert_eq!(capture, include_bytes!("fixtures/session-v1.btsnoop"));
+    }
+}
@@ -0,0 +1,343 @@
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, Weak};
+
+use async_trait::async_trait;
+use btleplug::api::{CentralEvent, CentralState, ScanFilter};
+use btleplug::platform::PeripheralId;
+use btleplug::{Error, Result};
+use btuuid::BluetoothUuid;
+use corebluetooth_async::dispatch::DispatchQoS;
+use corebluetooth_async::{CentralManagerAsync, DidDiscover, PeripheralAsync};
+use dispatch_executor::{Executor, Handle, Task};
+use futures_core::Stream;
+use uuid::Uuid;
+
+use crate::Peripheral;
+use crate::util::{Broadcast, central_state, next};
+
+/// A central manager implementing [`btleplug::api::Central`].
+///
+/// Discovered peripherals are remembered for the lifetime of the adapter. Events are delivered
+/// to every stream returned by [`events()`][btleplug::api::Central::events] that is alive when
+/// they occur.
+///
+/// # Example
+///
+/// ```no_run
+/// # use btleplug::api::{Central, CentralEvent, Peripheral as _, ScanFilter};
+/// # use corebluetooth_async::dispatch::DispatchQoS;
+/// # use corebluetooth_btleplug::Adapter;
+/// # use futures_lite::StreamExt;
+/// # async fn example() -> btleplug::Result<()> {
+/// let adapter = Adapter::background(DispatchQoS::default(), false);
+/// let mut events = adapter.events().await?;
+/// adapter.start_scan(ScanFilter::default()).await?;
+///
+/// while let Some(event) = events.next().await {
+///     if let CentralEvent::DeviceDiscovered(id) = event {
+///         let peripheral = adapter.peripheral(&id).await?;
+///         println!("{:?}", peripheral.properties().await?);
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct Adapter {
+    central: Handle<CentralManagerAsync>,
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    peripherals: Mutex<HashMap<PeripheralId, Peripheral>>,
+    events: Arc<Broadcast<CentralEvent>>,
+    scan: Mutex<Option<Task<()>>>,
+    _watch: [Task<()>; 2],
+}
+
+impl std::fmt::Debug for Adapter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Adapter").finish_non_exhaustive()
+    }
+}
+
+impl Adapter {
+    /// Creates a new central manager on a background dispatch queue with the given quality of
+    /// service class and returns an adapter for it.
+    pub fn background(qos: DispatchQoS, show_power_alert: bool) -> Self {
+        CentralManagerAsync::background(qos, show_power_alert, |central, executor| {
+            Self::new(central, executor)
+        })
+    }
+
+    /// Creates an adapter for `central`.
+    ///
+    /// `executor` must be the executor `central` was created on, and this must be called on its
+    /// queue, for example from the entry function of [`CentralManagerAsync::background()`].
+    pub fn new(central: CentralManagerAsync, executor: &Executor) -> Self {
+        let shared = Arc::new_cyclic(|shared: &Weak<Shared>| {
+            // Safety: the tasks only use CoreBluetooth objects from the executor's queue and do
+            // not depend on thread-local state.
+            let watch = unsafe {
+                [
+                    executor.spawn_local(watch_state(central.clone(), shared.clone())),
+                    executor.spawn_local(watch_disconnections(central.clone(), shared.clone())),
+                ]
+            };
+            Shared {
+                peripherals: Mutex::default(),
+                events: Arc::new(Broadcast::new()),
+                scan: Mutex::new(None),
+                _watch: watch,
+            }
+        });
+        Self {
+            central: executor.handle(central),
+            shared,
+        }
+    }
+}
+
+impl Shared {
+    /// Returns the peripheral for `peripheral`, creating it if this is the first time it has been
+    /// seen, and whether it was created.
+    fn peripheral(
+        &self,
+        central: &CentralManagerAsync,
+        executor: &Executor,
+        peripheral: PeripheralAsync,
+    ) -> (Peripheral, bool) {
+        let id = PeripheralId::from(peripheral.identifier());
+        let mut peripherals = self.peripherals.lock().unwrap();
+        if let Some(peripheral) = peripherals.get(&id) {
+            return (peripheral.clone(), false);
+        }
+        let peripheral = Peripheral::new(central, peripheral, executor, self.events.clone());
+        peripherals.insert(id, peripheral.clone());
+        (peripheral, true)
+    }
+
+    fn discovered(
+        &self,
+        central: &CentralManagerAsync,
+        executor: &Executor,
+        discovery: DidDiscover,
+    ) {
+        let DidDiscover {
+            peripheral,
+            advertisement_data,
+            rssi,
+        } = discovery;
+        let name = peripheral.name();
+        let (peripheral, is_new) = self.peripheral(central, executor, peripheral);
+        peripheral.update_properties(name, &advertisement_data, rssi);
+
+        let id = btleplug::api::Peripheral::id(&peripheral);
+        self.events.send(if is_new {
+            CentralEvent::DeviceDiscovered(id.clone())
+        } else {
+            CentralEvent::DeviceUpdated(id.clone())
+        });
+        if let Some(manufacturer_data) = advertisement_data.manufacturer_data {
+            self.events
+                .send(CentralEvent::ManufacturerDataAdvertisement {
+                    id: id.clone(),
+                    manufacturer_data: HashMap::from([(
+                        manufacturer_data.company_id,
+                        manufacturer_data.data,
+                    )]),
+                });
+        }
+        if !advertisement_data.service_data.is_empty() {
+            let service_data = advertisement_data
+                .service_data
+                .into_iter()
+                .map(|(uuid, data)| (Uuid::from(uuid), data))
+                .collect();
+            self.events.send(CentralEvent::ServiceDataAdvertisement {
+                id: id.clone(),
+                service_data,
+            });
+        }
+        if !advertisement_data.service_uuids.is_empty() {
+            let services = advertisement_data
+                .service_uuids
+                .into_iter()
+                .map(Uuid::from)
+                .collect();
+            self.events
+                .send(CentralEvent::ServicesAdvertisement { id, services });
+        }
+    }
+}
+
+#[async_trait]
+impl btleplug::api::Central for Adapter {
+    type Peripheral = Peripheral;
+
+    async fn events(&self) -> Result<Pin<Box<dyn Stream<Item = CentralEvent> + Send>>> {
+        Ok(Box::pin(self.shared.events.subscribe()))
+    }
+
+    async fn start_scan(&self, filter: ScanFilter) -> Result<()> {
+        // The previous scan task must be canceled first, so that it stops its scan before the
+        // new one starts.
+        drop(self.shared.scan.lock().unwrap().take());
+
+        let services = filter
+            .services
+            .into_iter()
+            .map(BluetoothUuid::from)
+            .collect();
+        let shared = Arc::downgrade(&self.shared);
+        // Safety: the task only uses CoreBluetooth objects from the executor's queue and does not
+        // depend on thread-local state.
+        let task = unsafe {
+            self.central
+                .spawn(move |central, executor| scan(central, executor, services, shared))
+        };
+        *self.shared.scan.lock().unwrap() = Some(task);
+        Ok(())
+    }
+
+    async fn stop_scan(&self) -> Result<()> {
+        drop(self.shared.scan.lock().unwrap().take());
+        let task = self.central.lock_async(|central, _| {
+            if central.is_scanning() {
+                central.stop_scan();
+            }
+        });
+        task.await;
+        Ok(())
+    }
+
+    async fn peripherals(&self) -> Result<Vec<Peripheral>> {
+        let peripherals = self.shared.peripherals.lock().unwrap();
+        Ok(peripherals.values().cloned().collect())
+    }
+
+    async fn peripheral(&self, id: &PeripheralId) -> Result<Peripheral> {
+        if let Some(peripheral) = self.shared.peripherals.lock().unwrap().get(id) {
+            return Ok(peripheral.clone());
+        }
+
+        // `PeripheralId` does not expose its identifier other than through its `Display` impl.
+        let identifier: Uuid = id.to_string().parse()?;
+        let shared = self.shared.clone();
+        let task = self.central.lock_async(move |central, executor| {
+            let peripheral = central
+                .retrieve_peripherals(&[identifier])
+                .into_iter()
+                .next()?;
+            let peripheral = PeripheralAsync::new(peripheral);
+            Some(shared.peripheral(central, executor, peripheral).0)
+        });
+        task.await.ok_or(Error::DeviceNotFound)
+    }
+
+    async fn add_peripheral(&self, _id: &PeripheralId) -> Result<Peripheral> {
+        Err(Error::NotSupported(
+            "Can't add a Peripheral from a PeripheralId".to_owned(),
+        ))
+    }
+
+    async fn adapter_info(&self) -> Result<String> {
+        Ok("CoreBluetooth".to_owned())
+    }
+
+    async fn adapter_state(&self) -> Result<CentralState> {
+        let task = self.central.lock_async(|central, _| central.state());
+        Ok(central_state(task.await))
+    }
+}
+
+/// A [`btleplug::api::Manager`] with a single [`Adapter`].
+#[derive(Debug, Clone)]
+pub struct Manager {
+    adapter: Adapter,
+}
+
+impl Manager {
+    /// Creates a manager whose adapter runs on a new background dispatch queue.
+    pub fn new() -> Self {
+        Self {
+            adapter: Adapter::background(DispatchQoS::default(), false),
+        }
+    }
+
+    /// Creates a manager for an existing adapter.
+    pub fn with_adapter(adapter: Adapter) -> Self {
+        Self { adapter }
+    }
+}
+
+impl Default for Manager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl btleplug::api::Manager for Manager {
+    type Adapter = Adapter;
+
+    async fn adapters(&self) -> Result<Vec<Adapter>> {
+        Ok(vec![self.adapter.clone()])
+    }
+}
+
+/// Stops the scan when the scan task is canceled.
+struct StopScan(CentralManagerAsync);
+
+impl Drop for StopScan {
+    fn drop(&mut self) {
+        if self.0.is_scanning() {
+            self.0.stop_scan();
+        }
+    }
+}
+
+async fn scan(
+    central: CentralManagerAsync,
+    executor: Executor,
+    services: Vec<BluetoothUuid>,
+    shared: Weak<Shared>,
+) {
+    if central.is_scanning() {
+        central.stop_scan();
+    }
+    let services = (!services.is_empty()).then_some(&services[..]);
+    let mut discoveries = central.scan(services, true, None);
+    let _stop = StopScan(central.clone());
+
+    while let Some(discovery) = next(&mut discoveries).await {
+        let Some(shared) = shared.upgrade() else {
+            break;
+        };
+        shared.discovered(&central, &executor, discovery);
+    }
+}
+
+async fn watch_state(central: CentralManagerAsync, shared: Weak<Shared>) {
+    let mut updates = central.state_updates();
+    while let Some(state) = next(&mut updates).await {
+        let Some(shared) = shared.upgrade() else {
+            break;
+        };
+        shared
+            .events
+            .send(CentralEvent::StateUpdate(central_state(state)));
+    }
+}
+
+async fn watch_disconnections(central: CentralManagerAsync, shared: Weak<Shared>) {
+    let mut disconnections = central.disconnections();
+    while let Some(disconnection) = next(&mut disconnections).await {
+        let Some(shared) = shared.upgrade() else {
+            break;
+        };
+        let id = PeripheralId::from(disconnection.peripheral.identifier());
+        shared.events.send(CentralEvent::DeviceDisconnected(id));
+    }
+}
@@ -0,0 +1,32 @@
+//! An implementation of the [`btleplug`] API traits on top of `corebluetooth-async`.
+//!
+//! Code written against [`btleplug::api::Central`] and [`btleplug::api::Peripheral`] can use
+//! [`Adapter`] and [`Peripheral`] in place of `btleplug::platform::Adapter` and
+//! `btleplug::platform::Peripheral`, and [`Manager`] in place of `btleplug::platform::Manager`.
+//! The CoreBluetooth objects are owned by the central manager's dispatch queue, and every
+//! operation runs there, so the adapter types are `Send + Sync` and can be used from any thread
+//! or async runtime.
+//!
+//! The following differences from btleplug's own CoreBluetooth backend apply:
+//!
+//! - [`Peripheral::address()`][btleplug::api::Peripheral::address] always returns
+//!   [`BDAddr::default()`][btleplug::api::BDAddr], because CoreBluetooth does not expose
+//!   peripheral addresses.
+//! - [`Central::peripheral()`][btleplug::api::Central::peripheral] also finds peripherals that
+//!   were not discovered by the current scan but are known to the system, for example from a
+//!   previous run, using `CBCentralManager`'s `retrievePeripheralsWithIdentifiers:`.
+//! - [`Central::add_peripheral()`][btleplug::api::Central::add_peripheral] is not supported.
+//!
+//! This crate is empty on platforms other than macOS and iOS.
+
+#[cfg(target_vendor = "apple")]
+mod adapter;
+#[cfg(target_vendor = "apple")]
+mod peripheral;
+#[cfg(target_vendor = "apple")]
+mod util;
+
+#[cfg(target_vendor = "apple")]
+pub use adapter::*;
+#[cfg(target_vendor = "apple")]
+pub use peripheral::*;
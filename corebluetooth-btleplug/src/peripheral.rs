@@ -0,0 +1,498 @@
+use std::collections::{BTreeSet, HashMap};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use btleplug::api::{
+    BDAddr, CentralEvent, CharPropFlags, Characteristic, Descriptor, PeripheralProperties, Service,
+    ValueNotification, WriteType,
+};
+use btleplug::platform::PeripheralId;
+use btleplug::{Error, Result};
+use corebluetooth::CharacteristicWriteType;
+use corebluetooth::advertisement_data::AdvertisementData;
+use corebluetooth_async::{CentralManagerAsync, DeliveryPolicy, PeripheralAsync};
+use dispatch_executor::{Executor, Handle, Task};
+use futures_core::Stream;
+use objc2_core_bluetooth::CBPeripheralState;
+use uuid::Uuid;
+
+use crate::util::{Broadcast, char_prop_flags, error, next};
+
+/// A peripheral implementing [`btleplug::api::Peripheral`].
+///
+/// Peripherals are obtained from an [`Adapter`][crate::Adapter]. Clones refer to the same
+/// peripheral and share its properties, discovered services and subscriptions.
+#[derive(Clone)]
+pub struct Peripheral {
+    handle: Handle<(CentralManagerAsync, PeripheralAsync)>,
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    id: PeripheralId,
+    events: Arc<Broadcast<CentralEvent>>,
+    properties: Mutex<PeripheralProperties>,
+    services: Mutex<BTreeSet<Service>>,
+    notifications: Arc<Broadcast<ValueNotification>>,
+    /// The tasks forwarding notifications, by service and characteristic UUID.
+    subscriptions: Mutex<HashMap<(Uuid, Uuid), Task<()>>>,
+}
+
+impl std::fmt::Debug for Peripheral {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Peripheral")
+            .field("id", &self.shared.id)
+            .field("properties", &self.shared.properties.lock().unwrap())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Peripheral {
+    /// Creates a peripheral for `peripheral`.
+    ///
+    /// Must be called on the queue of `executor`, which must be the executor of `central`.
+    pub(crate) fn new(
+        central: &CentralManagerAsync,
+        peripheral: PeripheralAsync,
+        executor: &Executor,
+        events: Arc<Broadcast<CentralEvent>>,
+    ) -> Self {
+        let id = PeripheralId::from(peripheral.identifier());
+        let properties = PeripheralProperties {
+            local_name: peripheral.name(),
+            ..Default::default()
+        };
+        let shared = Shared {
+            id,
+            events,
+            properties: Mutex::new(properties),
+            services: Mutex::default(),
+            notifications: Arc::new(Broadcast::new()),
+            subscriptions: Mutex::default(),
+        };
+        Self {
+            handle: executor.handle((central.clone(), peripheral)),
+            shared: Arc::new(shared),
+        }
+    }
+
+    /// Updates the properties of the peripheral from an advertisement.
+    pub(crate) fn update_properties(
+        &self,
+        name: Option<String>,
+        advertisement_data: &AdvertisementData,
+        rssi: i16,
+    ) {
+        let mut properties = self.shared.properties.lock().unwrap();
+        merge_advertisement(&mut properties, name, advertisement_data, rssi);
+    }
+
+    /// Runs `func` on the central manager's queue with the central manager, the peripheral and
+    /// the executor, and returns its result.
+    async fn run<F, Fut, R>(&self, func: F) -> Result<R>
+    where
+        F: FnOnce(CentralManagerAsync, PeripheralAsync, Executor) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<R>> + 'static,
+        R: Send + 'static,
+    {
+        // Safety: the future only uses CoreBluetooth objects from the executor's queue and does
+        // not depend on thread-local state.
+        let task = unsafe {
+            self.handle
+                .spawn(|(central, peripheral), executor| func(central, peripheral, executor))
+        };
+        task.await
+    }
+}
+
+#[async_trait]
+impl btleplug::api::Peripheral for Peripheral {
+    fn id(&self) -> PeripheralId {
+        self.shared.id.clone()
+    }
+
+    fn address(&self) -> BDAddr {
+        BDAddr::default()
+    }
+
+    async fn properties(&self) -> Result<Option<PeripheralProperties>> {
+        Ok(Some(self.shared.properties.lock().unwrap().clone()))
+    }
+
+    fn services(&self) -> BTreeSet<Service> {
+        self.shared.services.lock().unwrap().clone()
+    }
+
+    async fn is_connected(&self) -> Result<bool> {
+        let task = self
+            .handle
+            .lock_async(|(_, peripheral), _| peripheral.state() == CBPeripheralState::Connected);
+        Ok(task.await)
+    }
+
+    async fn connect(&self) -> Result<()> {
+        self.run(|central, peripheral, _| async move {
+            central.connect(&peripheral).await.map_err(error)
+        })
+        .await?;
+        self.shared
+            .events
+            .send(CentralEvent::DeviceConnected(self.id()));
+        Ok(())
+    }
+
+    async fn disconnect(&self) -> Result<()> {
+        self.run(|central, peripheral, _| async move {
+            central.cancel_peripheral_connection(&peripheral).await;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn discover_services(&self) -> Result<()> {
+        let services = self
+            .run(|_, peripheral, _| async move {
+                peripheral.discover_services(None).await.map_err(error)?;
+                let mut services = BTreeSet::new();
+                for service in peripheral.services().unwrap_or_default() {
+                    peripheral
+                        .discover_characteristics(&service, None)
+                        .await
+                        .map_err(error)?;
+                    let service_uuid = Uuid::from(service.uuid());
+                    let mut characteristics = BTreeSet::new();
+                    for characteristic in service.characteristics().unwrap_or_default() {
+                        peripheral
+                            .discover_descriptors(&characteristic)
+                            .await
+                            .map_err(error)?;
+                        let uuid = Uuid::from(characteristic.uuid());
+                        let descriptors = characteristic
+                            .descriptors()
+                            .unwrap_or_default()
+                            .into_iter()
+                            .map(|descriptor| Descriptor {
+                                uuid: descriptor.uuid().into(),
+                                service_uuid,
+                                characteristic_uuid: uuid,
+                            })
+                            .collect();
+                        characteristics.insert(Characteristic {
+                            uuid,
+                            service_uuid,
+                            properties: char_prop_flags(characteristic.properties()),
+                            descriptors,
+                        });
+                    }
+                    services.insert(Service {
+                        uuid: service_uuid,
+                        primary: service.is_primary(),
+                        characteristics,
+                    });
+                }
+                Ok(services)
+            })
+            .await?;
+        *self.shared.services.lock().unwrap() = services;
+        Ok(())
+    }
+
+    async fn write(
+        &self,
+        characteristic: &Characteristic,
+        data: &[u8],
+        write_type: WriteType,
+    ) -> Result<()> {
+        let characteristic = characteristic.clone();
+        let data = data.to_vec();
+        self.run(move |_, peripheral, _| async move {
+            let characteristic = find_characteristic(&peripheral, &characteristic)?;
+            let write_type = match write_type {
+                WriteType::WithResponse => CharacteristicWriteType::WithResponse,
+                WriteType::WithoutResponse => {
+                    peripheral
+                        .ready_to_send_write_without_response()
+                        .await
+                        .map_err(error)?;
+                    CharacteristicWriteType::WithoutResponse
+                }
+            };
+            peripheral
+                .write_characteristic_value(&characteristic, data, write_type)
+                .await
+                .map_err(error)
+        })
+        .await
+    }
+
+    async fn read(&self, characteristic: &Characteristic) -> Result<Vec<u8>> {
+        let characteristic = characteristic.clone();
+        self.run(move |_, peripheral, _| async move {
+            let characteristic = find_characteristic(&peripheral, &characteristic)?;
+            peripheral
+                .read_characteristic_value(&characteristic)
+                .await
+                .map_err(error)
+        })
+        .await
+    }
+
+    async fn subscribe(&self, characteristic: &Characteristic) -> Result<()> {
+        let key = (characteristic.service_uuid, characteristic.uuid);
+        let uuid = characteristic.uuid;
+        let characteristic = characteristic.clone();
+        let notifications = self.shared.notifications.clone();
+        let task = self
+            .run(move |_, peripheral, executor| async move {
+                let characteristic = find_characteristic(&peripheral, &characteristic)?;
+                let mut updates = peripheral.characteristic_value_updates_with_policy(
+                    &characteristic,
+                    DeliveryPolicy::default(),
+                );
+                peripheral
+                    .set_notify(&characteristic, true)
+                    .await
+                    .map_err(error)?;
+                let forward = async move {
+                    while let Some(value) = next(&mut updates).await {
+                        if let Ok(value) = value {
+                            notifications.send(ValueNotification { uuid, value });
+                        }
+                    }
+                };
+                // Safety: the task only uses CoreBluetooth objects from the executor's queue and
+                // does not depend on thread-local state.
+                Ok(unsafe { executor.spawn_local(forward) })
+            })
+            .await?;
+        self.shared.subscriptions.lock().unwrap().insert(key, task);
+        Ok(())
+    }
+
+    async fn unsubscribe(&self, characteristic: &Characteristic) -> Result<()> {
+        let key = (characteristic.service_uuid, characteristic.uuid);
+        drop(self.shared.subscriptions.lock().unwrap().remove(&key));
+        let characteristic = characteristic.clone();
+        self.run(move |_, peripheral, _| async move {
+            let characteristic = find_characteristic(&peripheral, &characteristic)?;
+            peripheral
+                .set_notify(&characteristic, false)
+                .await
+                .map_err(error)?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn notifications(&self) -> Result<Pin<Box<dyn Stream<Item = ValueNotification> + Send>>> {
+        Ok(Box::pin(self.shared.notifications.subscribe()))
+    }
+
+    async fn write_descriptor(&self, descriptor: &Descriptor, data: &[u8]) -> Result<()> {
+        let descriptor = descriptor.clone();
+        let data = data.to_vec();
+        self.run(move |_, peripheral, _| async move {
+            let descriptor = find_descriptor(&peripheral, &descriptor)?;
+            peripheral
+                .write_descriptor_value(&descriptor, data)
+                .await
+                .map_err(error)
+        })
+        .await
+    }
+
+    async fn read_descriptor(&self, descriptor: &Descriptor) -> Result<Vec<u8>> {
+        let descriptor = descriptor.clone();
+        self.run(move |_, peripheral, _| async move {
+            let descriptor = find_descriptor(&peripheral, &descriptor)?;
+            peripheral
+                .read_descriptor_value(&descriptor)
+                .await
+                .map_err(error)
+        })
+        .await
+    }
+}
+
+/// Updates `properties` from an advertisement, keeping the values it does not include.
+///
+/// The local name in the advertisement takes precedence over `name`, the peripheral's GAP name.
+/// Advertised service UUIDs are added to those already known.
+fn merge_advertisement(
+    properties: &mut PeripheralProperties,
+    name: Option<String>,
+    advertisement_data: &AdvertisementData,
+    rssi: i16,
+) {
+    if let Some(local_name) = advertisement_data.local_name.clone().or(name) {
+        properties.local_name = Some(local_name);
+    }
+    properties.rssi = Some(rssi);
+    if advertisement_data.tx_power_level.is_some() {
+        properties.tx_power_level = advertisement_data.tx_power_level;
+    }
+    if let Some(manufacturer_data) = &advertisement_data.manufacturer_data {
+        properties
+            .manufacturer_data
+            .insert(manufacturer_data.company_id, manufacturer_data.data.clone());
+    }
+    for (uuid, data) in &advertisement_data.service_data {
+        properties
+            .service_data
+            .insert(Uuid::from(*uuid), data.clone());
+    }
+    let uuids = advertisement_data
+        .service_uuids
+        .iter()
+        .chain(&advertisement_data.overflow_service_uuids);
+    for uuid in uuids {
+        let uuid = Uuid::from(*uuid);
+        if !properties.services.contains(&uuid) {
+            properties.services.push(uuid);
+        }
+    }
+}
+
+/// Finds the discovered CoreBluetooth characteristic matching `characteristic`.
+fn find_characteristic(
+    peripheral: &PeripheralAsync,
+    characteristic: &Characteristic,
+) -> Result<corebluetooth_async::Characteristic> {
+    peripheral
+        .services()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|service| Uuid::from(service.uuid()) == characteristic.service_uuid)
+        .flat_map(|service| service.characteristics().unwrap_or_default())
+        .find(|c| Uuid::from(c.uuid()) == characteristic.uuid)
+        .ok_or(Error::NoSuchCharacteristic)
+}
+
+/// Finds the discovered CoreBluetooth descriptor matching `descriptor`.
+fn find_descriptor(
+    peripheral: &PeripheralAsync,
+    descriptor: &Descriptor,
+) -> Result<corebluetooth_async::Descriptor> {
+    let characteristic = Characteristic {
+        uuid: descriptor.characteristic_uuid,
+        service_uuid: descriptor.service_uuid,
+        properties: CharPropFlags::empty(),
+        descriptors: BTreeSet::new(),
+    };
+    find_characteristic(peripheral, &characteristic)?
+        .descriptors()
+        .unwrap_or_default()
+        .into_iter()
+        .find(|d| Uuid::from(d.uuid()) == descriptor.uuid)
+        .ok_or(Error::NoSuchCharacteristic)
+}
+
+#[cfg(test)]
+mod tests {
+    use btuuid::BluetoothUuid;
+    use corebluetooth::advertisement_data::ManufacturerData;
+
+    use super::*;
+
+    fn advertisement() -> AdvertisementData {
+        AdvertisementData {
+            local_name: None,
+            manufacturer_data: None,
+            service_data: HashMap::new(),
+            service_uuids: Vec::new(),
+            overflow_service_uuids: Vec::new(),
+            tx_power_level: None,
+            is_connectable: true,
+            solicited_service_uuids: Vec::new(),
+        }
+    }
+
+    fn uuid16(uuid: u16) -> BluetoothUuid {
+        BluetoothUuid::from_u16(uuid)
+    }
+
+    #[test]
+    fn advertised_name_takes_precedence() {
+        let mut properties = PeripheralProperties::default();
+        let named = AdvertisementData {
+            local_name: Some("Sensor-1".to_owned()),
+            ..advertisement()
+        };
+        merge_advertisement(&mut properties, Some("Sensor".to_owned()), &named, -40);
+        assert_eq!(properties.local_name.as_deref(), Some("Sensor-1"));
+
+        // Without an advertised name, the peripheral's name is used.
+        merge_advertisement(
+            &mut properties,
+            Some("Sensor".to_owned()),
+            &advertisement(),
+            -41,
+        );
+        assert_eq!(properties.local_name.as_deref(), Some("Sensor"));
+
+        // Without either, the last known name is kept.
+        merge_advertisement(&mut properties, None, &advertisement(), -42);
+        assert_eq!(properties.local_name.as_deref(), Some("Sensor"));
+        assert_eq!(properties.rssi, Some(-42));
+    }
+
+    #[test]
+    fn service_uuids_are_added_once() {
+        let mut properties = PeripheralProperties::default();
+        let first = AdvertisementData {
+            service_uuids: vec![uuid16(0x180d), uuid16(0x180f), uuid16(0x180d)],
+            overflow_service_uuids: vec![uuid16(0x181a)],
+            ..advertisement()
+        };
+        merge_advertisement(&mut properties, None, &first, -40);
+        let second = AdvertisementData {
+            service_uuids: vec![uuid16(0x180f)],
+            overflow_service_uuids: vec![uuid16(0x181a), uuid16(0xfe59)],
+            ..advertisement()
+        };
+        merge_advertisement(&mut properties, None, &second, -40);
+        let expected: Vec<Uuid> = [0x180d, 0x180f, 0x181a, 0xfe59]
+            .into_iter()
+            .map(|uuid| uuid16(uuid).into())
+            .collect();
+        assert_eq!(properties.services, expected);
+    }
+
+    #[test]
+    fn missing_values_are_kept() {
+        let mut properties = PeripheralProperties::default();
+        let full = AdvertisementData {
+            manufacturer_data: Some(ManufacturerData {
+                company_id: 0x004c,
+                data: vec![0x02, 0x15],
+            }),
+            service_data: HashMap::from([(uuid16(0x180f), vec![87])]),
+            tx_power_level: Some(-8),
+            ..advertisement()
+        };
+        merge_advertisement(&mut properties, None, &full, -40);
+        merge_advertisement(&mut properties, None, &advertisement(), -50);
+        assert_eq!(properties.tx_power_level, Some(-8));
+        assert_eq!(properties.rssi, Some(-50));
+        assert_eq!(
+            properties.manufacturer_data,
+            HashMap::from([(0x004c, vec![0x02, 0x15])])
+        );
+
+        let update = AdvertisementData {
+            manufacturer_data: Some(ManufacturerData {
+                company_id: 0x0059,
+                data: vec![0x01],
+            }),
+            service_data: HashMap::from([(uuid16(0x180f), vec![86])]),
+            ..advertisement()
+        };
+        merge_advertisement(&mut properties, None, &update, -50);
+        assert_eq!(properties.manufacturer_data.len(), 2);
+        assert_eq!(
+            properties.service_data,
+            HashMap::from([(uuid16(0x180f).into(), vec![86])])
+        );
+    }
+}
@@ -0,0 +1,211 @@
+use std::pin::Pin;
+use std::sync::Mutex;
+
+use btleplug::Error;
+use btleplug::api::{CentralState, CharPropFlags};
+use corebluetooth_async::CBManagerState;
+use corebluetooth_async::error::{CBATTError, CBError, ErrorKind};
+use futures_channel::mpsc;
+use futures_core::Stream;
+use objc2_core_bluetooth::CBCharacteristicProperties;
+
+/// Sends values to any number of unbounded receivers, dropping receivers that have been closed.
+#[derive(Debug)]
+pub(crate) struct Broadcast<T> {
+    senders: Mutex<Vec<mpsc::UnboundedSender<T>>>,
+}
+
+impl<T: Clone> Broadcast<T> {
+    pub fn new() -> Self {
+        Self {
+            senders: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn subscribe(&self) -> mpsc::UnboundedReceiver<T> {
+        let (sender, receiver) = mpsc::unbounded();
+        self.senders.lock().unwrap().push(sender);
+        receiver
+    }
+
+    pub fn send(&self, value: T) {
+        self.senders
+            .lock()
+            .unwrap()
+            .retain(|sender| sender.unbounded_send(value.clone()).is_ok());
+    }
+}
+
+/// Waits for the next item of a stream.
+pub(crate) async fn next<S: Stream + Unpin>(stream: &mut S) -> Option<S::Item> {
+    std::future::poll_fn(|cx| Pin::new(&mut *stream).poll_next(cx)).await
+}
+
+/// Converts an error from `corebluetooth-async` to the closest `btleplug` error.
+pub(crate) fn error(error: corebluetooth_async::error::Error) -> Error {
+    match error.kind() {
+        ErrorKind::NotFound | ErrorKind::Bluetooth(CBError::UnknownDevice) => Error::DeviceNotFound,
+        ErrorKind::Bluetooth(CBError::NotConnected | CBError::PeripheralDisconnected) => {
+            Error::NotConnected
+        }
+        ErrorKind::Bluetooth(CBError::OperationNotSupported)
+        | ErrorKind::ATT(CBATTError::RequestNotSupported) => Error::NotSupported(error.to_string()),
+        ErrorKind::ATT(
+            CBATTError::ReadNotPermitted
+            | CBATTError::WriteNotPermitted
+            | CBATTError::InsufficientAuthentication
+            | CBATTError::InsufficientAuthorization
+            | CBATTError::InsufficientEncryption
+            | CBATTError::InsufficientEncryptionKeySize,
+        ) => Error::PermissionDenied,
+        _ => Error::Other(Box::new(error)),
+    }
+}
+
+pub(crate) fn central_state(state: CBManagerState) -> CentralState {
+    match state {
+        CBManagerState::PoweredOn => CentralState::PoweredOn,
+        CBManagerState::PoweredOff => CentralState::PoweredOff,
+        _ => CentralState::Unknown,
+    }
+}
+
+/// Converts the properties of a CoreBluetooth characteristic to btleplug's.
+///
+/// Only the properties defined by the Bluetooth specification fit in `CharPropFlags`; the
+/// encryption requirements CoreBluetooth defines above them are dropped.
+pub(crate) fn char_prop_flags(properties: CBCharacteristicProperties) -> CharPropFlags {
+    CharPropFlags::from_bits_truncate(properties.0 as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_lite::{StreamExt, future};
+
+    use super::*;
+
+    fn convert(kind: ErrorKind) -> Error {
+        error(kind.into())
+    }
+
+    #[test]
+    fn errors_map_to_the_closest_btleplug_error() {
+        assert!(matches!(
+            convert(ErrorKind::NotFound),
+            Error::DeviceNotFound
+        ));
+        assert!(matches!(
+            convert(ErrorKind::Bluetooth(CBError::UnknownDevice)),
+            Error::DeviceNotFound
+        ));
+        assert!(matches!(
+            convert(ErrorKind::Bluetooth(CBError::NotConnected)),
+            Error::NotConnected
+        ));
+        assert!(matches!(
+            convert(ErrorKind::Bluetooth(CBError::PeripheralDisconnected)),
+            Error::NotConnected
+        ));
+        assert!(matches!(
+            convert(ErrorKind::Bluetooth(CBError::OperationNotSupported)),
+            Error::NotSupported(_)
+        ));
+        assert!(matches!(
+            convert(ErrorKind::ATT(CBATTError::RequestNotSupported)),
+            Error::NotSupported(_)
+        ));
+        for error in [
+            CBATTError::ReadNotPermitted,
+            CBATTError::WriteNotPermitted,
+            CBATTError::InsufficientAuthentication,
+            CBATTError::InsufficientAuthorization,
+            CBATTError::InsufficientEncryption,
+            CBATTError::InsufficientEncryptionKeySize,
+        ] {
+            assert!(matches!(
+                convert(ErrorKind::ATT(error)),
+                Error::PermissionDenied
+            ));
+        }
+    }
+
+    #[test]
+    fn other_errors_keep_the_original() {
+        for kind in [
+            ErrorKind::Canceled,
+            ErrorKind::Bluetooth(CBError::ConnectionTimeout),
+            ErrorKind::ATT(CBATTError::InvalidHandle),
+        ] {
+            let Error::Other(other) = convert(kind) else {
+                panic!("{kind:?} should map to Error::Other");
+            };
+            let original = other
+                .downcast_ref::<corebluetooth_async::error::Error>()
+                .unwrap();
+            assert_eq!(original.kind(), kind);
+        }
+    }
+
+    #[test]
+    fn central_states() {
+        assert_eq!(
+            central_state(CBManagerState::PoweredOn),
+            CentralState::PoweredOn
+        );
+        assert_eq!(
+            central_state(CBManagerState::PoweredOff),
+            CentralState::PoweredOff
+        );
+        for state in [
+            CBManagerState::Unknown,
+            CBManagerState::Resetting,
+            CBManagerState::Unsupported,
+            CBManagerState::Unauthorized,
+        ] {
+            assert_eq!(central_state(state), CentralState::Unknown);
+        }
+    }
+
+    #[test]
+    fn broadcast_drops_closed_receivers() {
+        let broadcast = Broadcast::new();
+        let mut kept = broadcast.subscribe();
+        let dropped = broadcast.subscribe();
+        let mut closed = broadcast.subscribe();
+        broadcast.send(1);
+        assert_eq!(broadcast.senders.lock().unwrap().len(), 3);
+
+        drop(dropped);
+        closed.close();
+        broadcast.send(2);
+        assert_eq!(broadcast.senders.lock().unwrap().len(), 1);
+
+        future::block_on(async {
+            assert_eq!(kept.next().await, Some(1));
+            assert_eq!(kept.next().await, Some(2));
+            // Values sent before the receiver was closed can still be received.
+            assert_eq!(closed.next().await, Some(1));
+            assert_eq!(closed.next().await, None);
+        });
+    }
+
+    #[test]
+    fn characteristic_properties_keep_the_specification_bits() {
+        let properties = CBCharacteristicProperties::Read
+            | CBCharacteristicProperties::Notify
+            | CBCharacteristicProperties::ExtendedProperties;
+        assert_eq!(
+            char_prop_flags(properties),
+            CharPropFlags::READ | CharPropFlags::NOTIFY | CharPropFlags::EXTENDED_PROPERTIES
+        );
+        assert_eq!(
+            char_prop_flags(CBCharacteristicProperties(0xff)),
+            CharPropFlags::all()
+        );
+        // The encryption requirements do not fit in a byte, and do not spill into the other bits.
+        let properties = CBCharacteristicProperties::Indicate
+            | CBCharacteristicProperties::NotifyEncryptionRequired
+            | CBCharacteristicProperties::IndicateEncryptionRequired;
+        assert_eq!(char_prop_flags(properties), CharPropFlags::INDICATE);
+    }
+}
@@ -0,0 +1,205 @@
+//! The interface between commands and the Bluetooth stack.
+//!
+//! Commands are written against [`Backend`] rather than [`CentralManagerAsync`] directly, so
+//! that they can run against the [`SimulatedBackend`][crate::simulated::SimulatedBackend] on
+//! platforms without CoreBluetooth.
+//!
+//! [`CentralManagerAsync`]: https://docs.rs/corebluetooth-async/latest/corebluetooth_async/struct.CentralManagerAsync.html
+
+use std::fmt::Display;
+use std::pin::Pin;
+
+use btuuid::BluetoothUuid;
+use corebluetooth_async::error::Result;
+use futures_core::Stream;
+use futures_io::{AsyncRead, AsyncWrite};
+use uuid::Uuid;
+
+/// A boxed stream that need not be `Send`.
+pub type LocalStream<T> = Pin<Box<dyn Stream<Item = T>>>;
+
+/// A peripheral found by [`Backend::scan()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Discovery {
+    /// The identifier of the peripheral.
+    pub identifier: Uuid,
+    /// The name of the peripheral, if known.
+    pub name: Option<String>,
+    /// The RSSI of the advertisement.
+    pub rssi: i16,
+    /// The local name included in the advertisement.
+    pub local_name: Option<String>,
+    /// The service UUIDs included in the advertisement.
+    pub service_uuids: Vec<BluetoothUuid>,
+    /// The company identifier and data of any manufacturer specific data in the advertisement.
+    pub manufacturer_data: Option<(u16, Vec<u8>)>,
+    /// The transmitted power level included in the advertisement.
+    pub tx_power_level: Option<i16>,
+    /// Whether the advertisement was connectable.
+    pub is_connectable: bool,
+}
+
+impl Discovery {
+    /// The name of the peripheral, falling back to the advertised local name.
+    pub fn display_name(&self) -> Option<&str> {
+        self.name.as_deref().or(self.local_name.as_deref())
+    }
+}
+
+/// The properties of a characteristic, as defined by the Bluetooth Core Specification.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Properties(pub u8);
+
+impl Properties {
+    /// The value may be broadcast.
+    pub const BROADCAST: Self = Self(0x01);
+    /// The value may be read.
+    pub const READ: Self = Self(0x02);
+    /// The value may be written without response.
+    pub const WRITE_WITHOUT_RESPONSE: Self = Self(0x04);
+    /// The value may be written.
+    pub const WRITE: Self = Self(0x08);
+    /// The value may be notified.
+    pub const NOTIFY: Self = Self(0x10);
+    /// The value may be indicated.
+    pub const INDICATE: Self = Self(0x20);
+    /// The value may be written with a signature.
+    pub const AUTHENTICATED_SIGNED_WRITES: Self = Self(0x40);
+    /// Additional properties are defined in the extended properties descriptor.
+    pub const EXTENDED_PROPERTIES: Self = Self(0x80);
+
+    const NAMES: [(Self, &'static str); 8] = [
+        (Self::BROADCAST, "broadcast"),
+        (Self::READ, "read"),
+        (Self::WRITE_WITHOUT_RESPONSE, "write-without-response"),
+        (Self::WRITE, "write"),
+        (Self::NOTIFY, "notify"),
+        (Self::INDICATE, "indicate"),
+        (Self::AUTHENTICATED_SIGNED_WRITES, "signed-write"),
+        (Self::EXTENDED_PROPERTIES, "extended"),
+    ];
+
+    /// Returns whether all of the properties in `other` are set.
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Returns the names of the properties that are set, such as `"read"` or `"notify"`.
+    pub fn names(self) -> impl Iterator<Item = &'static str> {
+        Self::NAMES
+            .into_iter()
+            .filter(move |(property, _)| self.contains(*property))
+            .map(|(_, name)| name)
+    }
+}
+
+impl std::ops::BitOr for Properties {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl Display for Properties {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut names = self.names();
+        if let Some(first) = names.next() {
+            f.write_str(first)?;
+        }
+        for name in names {
+            write!(f, "|{name}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A discovered characteristic.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GattCharacteristic {
+    /// The characteristic UUID.
+    pub uuid: BluetoothUuid,
+    /// The characteristic properties.
+    pub properties: Properties,
+    /// The UUIDs of the characteristic's descriptors.
+    pub descriptors: Vec<BluetoothUuid>,
+}
+
+/// A discovered service.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GattService {
+    /// The service UUID.
+    pub uuid: BluetoothUuid,
+    /// Whether this is a primary service.
+    pub is_primary: bool,
+    /// The characteristics of the service.
+    pub characteristics: Vec<GattCharacteristic>,
+}
+
+/// Identifies a characteristic of a peripheral.
+///
+/// If `service` is `None`, the first characteristic with the given UUID in any service is used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CharacteristicRef {
+    /// The peripheral.
+    pub peripheral: Uuid,
+    /// The service containing the characteristic.
+    pub service: Option<BluetoothUuid>,
+    /// The characteristic.
+    pub characteristic: BluetoothUuid,
+}
+
+/// The Bluetooth operations used by commands.
+///
+/// Peripherals are identified by their identifier. Operations other than [`scan()`][Self::scan]
+/// and [`connect()`][Self::connect] require the peripheral to be connected, and discover
+/// services and characteristics as needed.
+#[allow(async_fn_in_trait)]
+pub trait Backend {
+    /// A byte stream over an L2CAP channel.
+    type L2capStream: AsyncRead + AsyncWrite + Unpin + 'static;
+
+    /// Waits until the backend is ready to use, for example until Bluetooth is powered on.
+    async fn wait_until_ready(&self) -> Result<()>;
+
+    /// Scans for peripherals advertising any of `services`, or all peripherals if it is empty.
+    ///
+    /// The scan stops when the stream is dropped.
+    async fn scan(
+        &self,
+        services: &[BluetoothUuid],
+        allow_duplicates: bool,
+    ) -> Result<LocalStream<Discovery>>;
+
+    /// Connects to a peripheral.
+    async fn connect(&self, peripheral: Uuid) -> Result<()>;
+
+    /// Disconnects from a peripheral.
+    async fn disconnect(&self, peripheral: Uuid) -> Result<()>;
+
+    /// Returns whether a peripheral is currently connected.
+    fn is_connected(&self, peripheral: Uuid) -> bool;
+
+    /// Discovers all services, characteristics and descriptors of a peripheral.
+    async fn discover(&self, peripheral: Uuid) -> Result<Vec<GattService>>;
+
+    /// Reads the value of a characteristic.
+    async fn read(&self, characteristic: CharacteristicRef) -> Result<Vec<u8>>;
+
+    /// Writes the value of a characteristic, with or without response.
+    async fn write(
+        &self,
+        characteristic: CharacteristicRef,
+        value: &[u8],
+        with_response: bool,
+    ) -> Result<()>;
+
+    /// Enables notifications for a characteristic and returns a stream of its values.
+    async fn subscribe(
+        &self,
+        characteristic: CharacteristicRef,
+    ) -> Result<LocalStream<Result<Vec<u8>>>>;
+
+    /// Opens an L2CAP channel to a peripheral.
+    async fn open_l2cap(&self, peripheral: Uuid, psm: u16) -> Result<Self::L2capStream>;
+}
@@ -0,0 +1,117 @@
+use std::io;
+use std::net::SocketAddr;
+use std::os::unix::fs::FileTypeExt;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use async_net::TcpListener;
+use async_net::unix::UnixListener;
+use clap::Parser;
+use corebluetooth_cli::backend::Backend;
+use corebluetooth_cli::bridge::Bridge;
+use corebluetooth_cli::simulated::SimulatedBackend;
+use futures_lite::future;
+
+/// Expose Bluetooth LE peripherals to local processes over JSON-RPC.
+///
+/// Listens for WebSocket clients on 127.0.0.1:8765 unless another listener is given.
+#[derive(Debug, Clone, Parser)]
+#[command(name = "corebluetooth-bridge", version)]
+struct Args {
+    /// Use simulated peripherals instead of CoreBluetooth.
+    #[arg(long)]
+    simulate: bool,
+
+    /// Accept WebSocket clients on this address.
+    #[arg(long, value_name = "ADDRESS")]
+    websocket: Option<SocketAddr>,
+
+    /// Accept newline-delimited JSON clients on a Unix socket at this path.
+    #[arg(long, value_name = "PATH")]
+    unix: Option<PathBuf>,
+
+    /// Accept WebSocket clients from web pages at this origin, such as `https://example.com`.
+    ///
+    /// Browsers send the origin of the page that opened a WebSocket. Clients with an origin are
+    /// rejected unless it is allowed; clients without one, such as scripts, are always accepted.
+    #[arg(long, value_name = "ORIGIN")]
+    allow_origin: Vec<String>,
+}
+
+fn main() -> ExitCode {
+    let mut args = Args::parse();
+    if args.websocket.is_none() && args.unix.is_none() {
+        args.websocket = Some(SocketAddr::from(([127, 0, 0, 1], 8765)));
+    }
+
+    let res = if args.simulate {
+        future::block_on(serve(&SimulatedBackend::demo(), &args)).map_err(|err| err.to_string())
+    } else {
+        run_native(args)
+    };
+
+    match res {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn serve<B: Backend>(backend: &B, args: &Args) -> io::Result<()> {
+    backend.wait_until_ready().await.map_err(io::Error::other)?;
+    let bridge = args
+        .allow_origin
+        .iter()
+        .fold(Bridge::new(backend), |bridge, origin| {
+            bridge.with_allowed_origin(origin)
+        });
+
+    let websocket = async {
+        let Some(address) = args.websocket else {
+            return future::pending().await;
+        };
+        let listener = TcpListener::bind(address).await?;
+        eprintln!("listening on ws://{}", listener.local_addr()?);
+        bridge.listen_websocket(listener).await
+    };
+    let unix = async {
+        let Some(path) = &args.unix else {
+            return future::pending().await;
+        };
+        // Remove the socket left behind by a previous run, if any. Anything else at the path is
+        // left alone, and binding fails.
+        if std::fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_socket()) {
+            std::fs::remove_file(path)?;
+        }
+        let listener = UnixListener::bind(path)?;
+        eprintln!("listening on {}", path.display());
+        bridge.listen_unix(listener).await
+    };
+    future::try_zip(websocket, unix).await.map(|_| ())
+}
+
+#[cfg(target_vendor = "apple")]
+fn run_native(args: Args) -> Result<(), String> {
+    use corebluetooth_async::CentralManagerAsync;
+    use corebluetooth_async::dispatch::DispatchQoS;
+    use corebluetooth_cli::core_bluetooth::CoreBluetoothBackend;
+
+    let task =
+        CentralManagerAsync::background(DispatchQoS::default(), false, |central, executor| {
+            let task = async move {
+                let backend = CoreBluetoothBackend::new(central);
+                serve(&backend, &args).await.map_err(|err| err.to_string())
+            };
+            // Safety: the task only uses CoreBluetooth objects from the executor's queue and does
+            // not depend on thread-local state.
+            unsafe { executor.spawn_local(task) }
+        });
+    future::block_on(task)
+}
+
+#[cfg(not(target_vendor = "apple"))]
+fn run_native(_args: Args) -> Result<(), String> {
+    Err("CoreBluetooth is only available on macOS and iOS; use --simulate".to_owned())
+}
@@ -0,0 +1,831 @@
+//! A JSON-RPC server that exposes a [`Backend`] to other local processes.
+//!
+//! The `corebluetooth-bridge` binary lets programs that cannot use CoreBluetooth directly, such
+//! as scripts or web pages, scan for, connect to and talk to peripherals through a local
+//! WebSocket or Unix socket. The protocol is described in [`rpc`].
+//!
+//! WebSocket clients that send an `Origin` header, which browsers always do, are rejected unless
+//! their origin has been allowed with [`Bridge::with_allowed_origin()`]. Otherwise any web page
+//! open in a browser on this machine could connect to the bridge and talk to nearby peripherals.
+//!
+//! Each client has its own subscriptions, which are ended when the client disconnects.
+//! Connections are shared between clients: a peripheral is disconnected once every client that
+//! connected to it has disconnected from it or gone away. If a peripheral disconnects
+//! unexpectedly, the next `connect` call from any of its clients connects to it again.
+//!
+//! [`Bridge::serve_lines()`] speaks newline-delimited JSON over any byte stream, so the protocol
+//! can be exercised with a [`SimulatedBackend`][crate::simulated::SimulatedBackend] and a local
+//! client:
+//!
+//! ```
+//! use async_net::unix::UnixStream;
+//! use corebluetooth_cli::bridge::Bridge;
+//! use corebluetooth_cli::simulated::SimulatedBackend;
+//! use futures_lite::io::BufReader;
+//! use futures_lite::{AsyncBufReadExt, AsyncWriteExt, StreamExt, future};
+//! use serde_json::{Value, json};
+//!
+//! let backend = SimulatedBackend::demo();
+//! let bridge = Bridge::new(&backend);
+//! let (server, client) = UnixStream::pair()?;
+//! let peripheral = "6b0c5c39-2f1d-4e6c-9a51-2d4f3c2b1a01";
+//!
+//! let client = async move {
+//!     let mut lines = BufReader::new(client.clone()).lines();
+//!     let mut writer = client;
+//!     let mut call = async |method: &str, params: Value| {
+//!         let request = json!({"jsonrpc": "2.0", "id": 1, "method": method, "params": params});
+//!         writer.write_all(format!("{request}\n").as_bytes()).await?;
+//!         let response = lines.next().await.unwrap()?;
+//!         Ok::<Value, std::io::Error>(serde_json::from_str(&response)?)
+//!     };
+//!
+//!     call("connect", json!({"peripheral": peripheral})).await?;
+//!     let params = json!({"peripheral": peripheral, "characteristic": "battery_level"});
+//!     let response = call("read", params).await?;
+//!     assert_eq!(response["result"]["value"], "57");
+//!     Ok(())
+//! };
+//!
+//! future::block_on(future::try_zip(bridge.serve_lines(server), client))?;
+//! assert!(!backend.is_connected(peripheral.parse()?));
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::pin::pin;
+
+use async_executor::{LocalExecutor, Task};
+use async_net::TcpListener;
+use async_net::unix::UnixListener;
+use async_tungstenite::tungstenite::Message;
+use async_tungstenite::tungstenite::handshake::server::{self as handshake, ErrorResponse};
+use async_tungstenite::tungstenite::http::{StatusCode, header};
+use corebluetooth_async::error::Result;
+use futures_channel::mpsc::{self, UnboundedSender};
+use futures_core::Stream;
+use futures_io::{AsyncRead, AsyncWrite};
+use futures_lite::io::BufReader;
+use futures_lite::{AsyncBufReadExt, AsyncWriteExt, StreamExt, future};
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+use crate::backend::{Backend, Discovery, LocalStream};
+use crate::rpc::{self, Method, Request, RpcError, UNKNOWN_SUBSCRIPTION};
+
+/// Serves JSON-RPC clients using a [`Backend`].
+#[derive(Debug)]
+pub struct Bridge<'a, B> {
+    backend: &'a B,
+    /// The number of clients connected to each peripheral.
+    connections: RefCell<HashMap<Uuid, usize>>,
+    /// The browser origins that may connect over WebSocket.
+    allowed_origins: Vec<String>,
+}
+
+/// The result of a successful call.
+enum Outcome {
+    Done(Value),
+    Subscription(LocalStream<Event>),
+}
+
+/// An item from a subscription.
+enum Event {
+    Discovered(Discovery),
+    Value(Result<Vec<u8>>),
+}
+
+impl<'a, B: Backend> Bridge<'a, B> {
+    /// Creates a bridge for `backend`.
+    pub fn new(backend: &'a B) -> Self {
+        Self {
+            backend,
+            connections: RefCell::new(HashMap::new()),
+            allowed_origins: Vec::new(),
+        }
+    }
+
+    /// Allows WebSocket clients from `origin`, such as `https://example.com`, to connect.
+    ///
+    /// Clients that do not send an `Origin` header are always allowed.
+    pub fn with_allowed_origin(mut self, origin: impl Into<String>) -> Self {
+        self.allowed_origins.push(origin.into());
+        self
+    }
+
+    /// Whether a WebSocket client whose handshake carries `origin` may connect.
+    fn is_allowed_origin(&self, origin: Option<&[u8]>) -> bool {
+        origin.is_none_or(|origin| {
+            self.allowed_origins
+                .iter()
+                .any(|allowed| allowed.as_bytes().eq_ignore_ascii_case(origin))
+        })
+    }
+
+    /// Accepts WebSocket clients from `listener` and serves them until accepting fails.
+    pub async fn listen_websocket(&self, listener: TcpListener) -> io::Result<()> {
+        let clients = LocalExecutor::new();
+        clients
+            .run(async {
+                loop {
+                    let (stream, _) = listener.accept().await?;
+                    clients
+                        .spawn(async move {
+                            let _ = self.serve_websocket(stream).await;
+                        })
+                        .detach();
+                }
+            })
+            .await
+    }
+
+    /// Accepts newline-delimited JSON clients from `listener` and serves them until accepting
+    /// fails.
+    pub async fn listen_unix(&self, listener: UnixListener) -> io::Result<()> {
+        let clients = LocalExecutor::new();
+        clients
+            .run(async {
+                loop {
+                    let (stream, _) = listener.accept().await?;
+                    clients
+                        .spawn(async move {
+                            let _ = self.serve_lines(stream).await;
+                        })
+                        .detach();
+                }
+            })
+            .await
+    }
+
+    /// Serves a client that sends and receives one message per WebSocket text frame.
+    ///
+    /// The handshake is refused with `403 Forbidden` if the client's origin is not allowed.
+    pub async fn serve_websocket(
+        &self,
+        stream: impl AsyncRead + AsyncWrite + Unpin,
+    ) -> io::Result<()> {
+        // The response types are dictated by tungstenite's handshake callback.
+        #[allow(clippy::result_large_err)]
+        let check_origin = |request: &handshake::Request, response: handshake::Response| {
+            let origin = request.headers().get(header::ORIGIN);
+            if self.is_allowed_origin(origin.map(|origin| origin.as_bytes())) {
+                Ok(response)
+            } else {
+                let mut response = ErrorResponse::new(Some("origin not allowed".to_owned()));
+                *response.status_mut() = StatusCode::FORBIDDEN;
+                Err(response)
+            }
+        };
+        let websocket = async_tungstenite::accept_hdr_async(stream, check_origin)
+            .await
+            .map_err(io::Error::other)?;
+        let (mut sender, receiver) = websocket.split();
+        let incoming = receiver
+            .take_while(|message| message.is_ok())
+            .filter_map(|message| match message {
+                Ok(Message::Text(text)) => Some(text.as_str().to_owned()),
+                _ => None,
+            });
+
+        let (outgoing, mut messages) = mpsc::unbounded::<String>();
+        let send = async {
+            while let Some(message) = messages.next().await {
+                sender
+                    .send(Message::text(message))
+                    .await
+                    .map_err(io::Error::other)?;
+            }
+            Ok(())
+        };
+        future::zip(self.serve(incoming, outgoing), send).await.1
+    }
+
+    /// Serves a client that sends and receives one message per line.
+    pub async fn serve_lines(&self, stream: impl AsyncRead + AsyncWrite + Unpin) -> io::Result<()> {
+        let (reader, mut writer) = futures_lite::io::split(stream);
+        let incoming = BufReader::new(reader)
+            .lines()
+            .take_while(|line| line.is_ok())
+            .filter_map(|line| line.ok());
+
+        let (outgoing, mut messages) = mpsc::unbounded::<String>();
+        let send = async {
+            while let Some(mut message) = messages.next().await {
+                message.push('\n');
+                writer.write_all(message.as_bytes()).await?;
+                writer.flush().await?;
+            }
+            Ok(())
+        };
+        future::zip(self.serve(incoming, outgoing), send).await.1
+    }
+
+    /// Serves a client that sends the messages in `incoming` and receives the messages sent to
+    /// `outgoing`.
+    ///
+    /// Returns once `incoming` ends, after ending the client's subscriptions and disconnecting
+    /// from the peripherals it was the last client connected to.
+    pub async fn serve(
+        &self,
+        incoming: impl Stream<Item = String>,
+        outgoing: UnboundedSender<String>,
+    ) {
+        let connected = RefCell::new(HashSet::new());
+        let mut subscriptions = HashMap::<u64, Task<()>>::new();
+        let next_subscription = Cell::new(1);
+        // Requests run on their own tasks so that slow operations do not hold up other requests.
+        // Dropping the executor cancels any that are still running along with the subscriptions.
+        let requests = LocalExecutor::new();
+
+        requests
+            .run(async {
+                let (started, mut started_subscriptions) = mpsc::unbounded();
+                let mut incoming = pin!(incoming);
+                loop {
+                    let message = future::or(async { Ok(incoming.next().await) }, async {
+                        Err(started_subscriptions.next().await)
+                    })
+                    .await;
+
+                    match message {
+                        Ok(None) => break,
+                        Ok(Some(message)) => match Request::parse(&message) {
+                            Ok(Request {
+                                id,
+                                method: Method::Unsubscribe(subscription),
+                            }) => {
+                                let result = match subscriptions.remove(&subscription) {
+                                    Some(_) => Ok(Value::Null),
+                                    None => Err(RpcError::new(
+                                        UNKNOWN_SUBSCRIPTION,
+                                        format!("unknown subscription {subscription}"),
+                                    )),
+                                };
+                                if let Some(id) = id {
+                                    let _ = outgoing.unbounded_send(rpc::response(id, result));
+                                }
+                            }
+                            Ok(Request { id, method }) => {
+                                let connected = &connected;
+                                let outgoing = outgoing.clone();
+                                let started = started.clone();
+                                let request = async move {
+                                    match self.call(connected, method).await {
+                                        Ok(Outcome::Done(result)) => {
+                                            if let Some(id) = id {
+                                                let response = rpc::response(id, Ok(result));
+                                                let _ = outgoing.unbounded_send(response);
+                                            }
+                                        }
+                                        Ok(Outcome::Subscription(events)) => {
+                                            let _ = started.unbounded_send((id, events));
+                                        }
+                                        Err(error) => {
+                                            if let Some(id) = id {
+                                                let response = rpc::response(id, Err(error));
+                                                let _ = outgoing.unbounded_send(response);
+                                            }
+                                        }
+                                    }
+                                };
+                                requests.spawn(request).detach();
+                            }
+                            Err((id, error)) => {
+                                let response = rpc::response(id.unwrap_or(Value::Null), Err(error));
+                                let _ = outgoing.unbounded_send(response);
+                            }
+                        },
+                        Err(Some((id, events))) => {
+                            let subscription = next_subscription.get();
+                            next_subscription.set(subscription + 1);
+                            let task =
+                                requests.spawn(forward(subscription, events, outgoing.clone()));
+                            subscriptions.insert(subscription, task);
+                            if let Some(id) = id {
+                                let result = Ok(json!({ "subscription": subscription }));
+                                let _ = outgoing.unbounded_send(rpc::response(id, result));
+                            }
+                        }
+                        // `started` is held by this loop, so the stream never ends.
+                        Err(None) => unreachable!(),
+                    }
+                }
+            })
+            .await;
+
+        drop(requests);
+        drop(subscriptions);
+        for peripheral in connected.take() {
+            let _ = self.release(peripheral).await;
+        }
+    }
+
+    async fn call(
+        &self,
+        connected: &RefCell<HashSet<Uuid>>,
+        method: Method,
+    ) -> std::result::Result<Outcome, RpcError> {
+        let backend = self.backend;
+        Ok(match method {
+            Method::Scan {
+                services,
+                allow_duplicates,
+            } => {
+                let discoveries = backend.scan(&services, allow_duplicates).await?;
+                Outcome::Subscription(Box::pin(discoveries.map(Event::Discovered)))
+            }
+            Method::Connect(peripheral) => {
+                // The backend is asked rather than the clients' connections, which stay recorded
+                // when the peripheral disconnects unexpectedly and must then be re-established.
+                if !backend.is_connected(peripheral) {
+                    backend.connect(peripheral).await?;
+                }
+                // Another request from this client may have connected while this one waited.
+                if connected.borrow_mut().insert(peripheral) {
+                    *self.connections.borrow_mut().entry(peripheral).or_default() += 1;
+                }
+                Outcome::Done(Value::Null)
+            }
+            Method::Disconnect(peripheral) => {
+                if connected.borrow_mut().remove(&peripheral) {
+                    self.release(peripheral).await?;
+                }
+                Outcome::Done(Value::Null)
+            }
+            Method::Discover(peripheral) => {
+                Outcome::Done(rpc::services(&backend.discover(peripheral).await?))
+            }
+            Method::Read(characteristic) => {
+                let value = backend.read(characteristic).await?;
+                Outcome::Done(json!({ "value": rpc::hex(&value) }))
+            }
+            Method::Write {
+                characteristic,
+                value,
+                with_response,
+            } => {
+                backend.write(characteristic, &value, with_response).await?;
+                Outcome::Done(Value::Null)
+            }
+            Method::Subscribe(characteristic) => {
+                let values = backend.subscribe(characteristic).await?;
+                Outcome::Subscription(Box::pin(values.map(Event::Value)))
+            }
+            Method::Unsubscribe(_) => unreachable!("handled by the client loop"),
+        })
+    }
+
+    /// Releases one client's connection to `peripheral`, disconnecting if it was the last.
+    async fn release(&self, peripheral: Uuid) -> Result<()> {
+        let last = {
+            let mut connections = self.connections.borrow_mut();
+            let Some(count) = connections.get_mut(&peripheral) else {
+                return Ok(());
+            };
+            *count -= 1;
+            *count == 0
+        };
+        if !last {
+            return Ok(());
+        }
+        self.connections.borrow_mut().remove(&peripheral);
+        self.backend.disconnect(peripheral).await
+    }
+}
+
+/// Sends the events of a subscription to a client as notifications.
+async fn forward(
+    subscription: u64,
+    mut events: LocalStream<Event>,
+    outgoing: UnboundedSender<String>,
+) {
+    while let Some(event) = events.next().await {
+        let notification = match event {
+            Event::Discovered(discovery) => rpc::notification(
+                "discovered",
+                json!({ "subscription": subscription, "peripheral": rpc::discovery(&discovery) }),
+            ),
+            Event::Value(Ok(value)) => rpc::notification(
+                "value",
+                json!({ "subscription": subscription, "value": rpc::hex(&value) }),
+            ),
+            Event::Value(Err(error)) => rpc::notification(
+                "value",
+                json!({ "subscription": subscription, "error": RpcError::from(error) }),
+            ),
+        };
+        if outgoing.unbounded_send(notification).is_err() {
+            return;
+        }
+    }
+    let _ = outgoing.unbounded_send(rpc::notification(
+        "subscription_ended",
+        json!({ "subscription": subscription }),
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+    use std::task::Poll;
+
+    use async_net::unix::UnixStream;
+    use async_tungstenite::tungstenite::client::IntoClientRequest;
+    use async_tungstenite::tungstenite::http::HeaderValue;
+    use btuuid::BluetoothUuid;
+    use futures_lite::io::Lines;
+
+    use super::*;
+    use crate::backend::{CharacteristicRef, GattService};
+    use crate::rpc::{BLUETOOTH_ERROR, INVALID_REQUEST, METHOD_NOT_FOUND, PARSE_ERROR};
+    use crate::simulated::{EchoStream, SimulatedBackend};
+
+    const PERIPHERAL: &str = "6b0c5c39-2f1d-4e6c-9a51-2d4f3c2b1a01";
+
+    fn peripheral() -> Uuid {
+        PERIPHERAL.parse().unwrap()
+    }
+
+    /// A newline-delimited JSON client of a bridge.
+    struct Client {
+        lines: Lines<BufReader<UnixStream>>,
+        writer: UnixStream,
+        next_id: u64,
+    }
+
+    impl Client {
+        /// Returns a client along with the bridge serving it.
+        fn new<'a, B: Backend>(
+            bridge: &'a Bridge<B>,
+        ) -> (impl Future<Output = io::Result<()>> + 'a, Client) {
+            let (server, client) = UnixStream::pair().unwrap();
+            let client = Client {
+                lines: BufReader::new(client.clone()).lines(),
+                writer: client,
+                next_id: 1,
+            };
+            (bridge.serve_lines(server), client)
+        }
+
+        async fn send(&mut self, message: &str) {
+            self.writer
+                .write_all(format!("{message}\n").as_bytes())
+                .await
+                .unwrap();
+        }
+
+        async fn receive(&mut self) -> Value {
+            serde_json::from_str(&self.lines.next().await.unwrap().unwrap()).unwrap()
+        }
+
+        /// Calls `method` and returns the response, which must be the next message.
+        async fn call(&mut self, method: &str, params: Value) -> Value {
+            let id = self.next_id;
+            self.next_id += 1;
+            let request = json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params});
+            self.send(&request.to_string()).await;
+            let response = self.receive().await;
+            assert_eq!(response["id"], id, "{response}");
+            response
+        }
+
+        /// Calls `method` and returns its result, which must be successful.
+        async fn result(&mut self, method: &str, params: Value) -> Value {
+            let response = self.call(method, params).await;
+            assert!(response.get("error").is_none(), "{response}");
+            response["result"].clone()
+        }
+    }
+
+    fn error_code(response: &Value) -> i64 {
+        response["error"]["code"].as_i64().unwrap()
+    }
+
+    /// A backend whose scans and subscriptions stay open after their simulated items, so that
+    /// the bridge ending them can be observed.
+    #[derive(Default)]
+    struct HeldBackend {
+        backend: SimulatedBackend,
+        open: Rc<Cell<usize>>,
+    }
+
+    /// Counts a scan or subscription as open until dropped.
+    struct Open(Rc<Cell<usize>>);
+
+    impl Drop for Open {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() - 1);
+        }
+    }
+
+    impl HeldBackend {
+        fn hold<T: 'static>(&self, stream: LocalStream<T>) -> LocalStream<T> {
+            self.open.set(self.open.get() + 1);
+            let open = Open(self.open.clone());
+            Box::pin(stream.chain(futures_lite::stream::poll_fn(move |_| {
+                let _ = &open;
+                Poll::Pending
+            })))
+        }
+    }
+
+    impl Backend for HeldBackend {
+        type L2capStream = EchoStream;
+
+        async fn wait_until_ready(&self) -> Result<()> {
+            self.backend.wait_until_ready().await
+        }
+
+        async fn scan(
+            &self,
+            services: &[BluetoothUuid],
+            allow_duplicates: bool,
+        ) -> Result<LocalStream<Discovery>> {
+            Ok(self.hold(self.backend.scan(services, allow_duplicates).await?))
+        }
+
+        async fn connect(&self, peripheral: Uuid) -> Result<()> {
+            self.backend.connect(peripheral).await
+        }
+
+        async fn disconnect(&self, peripheral: Uuid) -> Result<()> {
+            self.backend.disconnect(peripheral).await
+        }
+
+        fn is_connected(&self, peripheral: Uuid) -> bool {
+            self.backend.is_connected(peripheral)
+        }
+
+        async fn discover(&self, peripheral: Uuid) -> Result<Vec<GattService>> {
+            self.backend.discover(peripheral).await
+        }
+
+        async fn read(&self, characteristic: CharacteristicRef) -> Result<Vec<u8>> {
+            self.backend.read(characteristic).await
+        }
+
+        async fn write(
+            &self,
+            characteristic: CharacteristicRef,
+            value: &[u8],
+            with_response: bool,
+        ) -> Result<()> {
+            self.backend
+                .write(characteristic, value, with_response)
+                .await
+        }
+
+        async fn subscribe(
+            &self,
+            characteristic: CharacteristicRef,
+        ) -> Result<LocalStream<Result<Vec<u8>>>> {
+            Ok(self.hold(self.backend.subscribe(characteristic).await?))
+        }
+
+        async fn open_l2cap(&self, peripheral: Uuid, psm: u16) -> Result<EchoStream> {
+            self.backend.open_l2cap(peripheral, psm).await
+        }
+    }
+
+    #[test]
+    fn subscriptions_send_notifications() {
+        let backend = SimulatedBackend::demo();
+        let bridge = Bridge::new(&backend);
+        let (server, mut client) = Client::new(&bridge);
+        let client = async move {
+            let result = client.result("scan", json!({})).await;
+            assert_eq!(result, json!({"subscription": 1}));
+            let discovered = client.receive().await;
+            assert_eq!(discovered["method"], "discovered");
+            assert_eq!(discovered["params"]["subscription"], 1);
+            assert_eq!(discovered["params"]["peripheral"]["identifier"], PERIPHERAL);
+            assert_eq!(discovered["params"]["peripheral"]["name"], "Heart Sensor");
+            let ended = client.receive().await;
+            assert_eq!(ended["method"], "subscription_ended");
+            assert_eq!(ended["params"], json!({"subscription": 1}));
+
+            client
+                .result("connect", json!({"peripheral": PERIPHERAL}))
+                .await;
+            let params =
+                json!({"peripheral": PERIPHERAL, "characteristic": "heart_rate_measurement"});
+            let result = client.result("subscribe", params).await;
+            assert_eq!(result, json!({"subscription": 2}));
+            for value in ["0048", "004a", "0049"] {
+                let notification = client.receive().await;
+                assert_eq!(notification["method"], "value");
+                assert_eq!(
+                    notification["params"],
+                    json!({"subscription": 2, "value": value})
+                );
+            }
+            assert_eq!(client.receive().await["method"], "subscription_ended");
+
+            // Ended subscriptions can still be unsubscribed from, but only once.
+            let result = client
+                .result("unsubscribe", json!({"subscription": 2}))
+                .await;
+            assert_eq!(result, Value::Null);
+            let response = client.call("unsubscribe", json!({"subscription": 2})).await;
+            assert_eq!(error_code(&response), UNKNOWN_SUBSCRIPTION);
+            let response = client.call("unsubscribe", json!({"subscription": 7})).await;
+            assert_eq!(error_code(&response), UNKNOWN_SUBSCRIPTION);
+        };
+        future::block_on(future::zip(server, client)).0.unwrap();
+        assert!(!backend.is_connected(peripheral()));
+    }
+
+    #[test]
+    fn unsubscribing_ends_subscriptions() {
+        let backend = HeldBackend {
+            backend: SimulatedBackend::demo(),
+            ..Default::default()
+        };
+        let bridge = Bridge::new(&backend);
+        let (server, mut client) = Client::new(&bridge);
+        let backend = &backend;
+        let client = async move {
+            client.result("scan", json!({})).await;
+            assert_eq!(client.receive().await["method"], "discovered");
+            assert_eq!(backend.open.get(), 1);
+
+            let result = client
+                .result("unsubscribe", json!({"subscription": 1}))
+                .await;
+            assert_eq!(result, Value::Null);
+            assert_eq!(backend.open.get(), 0);
+        };
+        future::block_on(future::zip(server, client)).0.unwrap();
+    }
+
+    #[test]
+    fn clients_going_away_end_their_subscriptions_and_connections() {
+        let backend = HeldBackend {
+            backend: SimulatedBackend::demo(),
+            ..Default::default()
+        };
+        let bridge = Bridge::new(&backend);
+        let (server_a, mut a) = Client::new(&bridge);
+        let (server_b, mut b) = Client::new(&bridge);
+        let clients = async {
+            b.result("scan", json!({})).await;
+            let client_a = async {
+                a.result("connect", json!({"peripheral": PERIPHERAL})).await;
+                let params = json!({"peripheral": PERIPHERAL, "characteristic": "battery_level"});
+                a.result("subscribe", params).await;
+                assert_eq!(a.receive().await["params"]["value"], "56");
+                assert_eq!(backend.open.get(), 2);
+                drop(a);
+            };
+            future::zip(server_a, client_a).await.0.unwrap();
+            assert_eq!(backend.open.get(), 1);
+            assert!(!backend.is_connected(peripheral()));
+            assert_eq!(b.receive().await["method"], "discovered");
+            drop(b);
+        };
+        future::block_on(future::zip(server_b, clients)).0.unwrap();
+        assert_eq!(backend.open.get(), 0);
+    }
+
+    #[test]
+    fn connections_are_shared_between_clients() {
+        let backend = SimulatedBackend::demo();
+        let bridge = Bridge::new(&backend);
+        let (server_a, mut a) = Client::new(&bridge);
+        let (server_b, mut b) = Client::new(&bridge);
+        let params = json!({"peripheral": PERIPHERAL});
+        let clients = async {
+            let client_a = async {
+                a.result("connect", params.clone()).await;
+                b.result("connect", params.clone()).await;
+                // Connecting twice from one client still needs only one disconnection.
+                b.result("connect", params.clone()).await;
+                a.result("disconnect", params.clone()).await;
+                assert!(backend.is_connected(peripheral()));
+                a.result("connect", params.clone()).await;
+                drop(a);
+            };
+            future::zip(server_a, client_a).await.0.unwrap();
+            assert!(backend.is_connected(peripheral()));
+            let params = json!({"peripheral": PERIPHERAL, "characteristic": "battery_level"});
+            assert_eq!(b.result("read", params).await, json!({"value": "57"}));
+            b.result("disconnect", json!({"peripheral": PERIPHERAL}))
+                .await;
+            assert!(!backend.is_connected(peripheral()));
+            // Disconnecting again is harmless.
+            b.result("disconnect", json!({"peripheral": PERIPHERAL}))
+                .await;
+            drop(b);
+        };
+        future::block_on(future::zip(server_b, clients)).0.unwrap();
+    }
+
+    #[test]
+    fn unexpected_disconnections_are_reconnected() {
+        let backend = SimulatedBackend::demo();
+        let bridge = Bridge::new(&backend);
+        let (server, mut client) = Client::new(&bridge);
+        let backend = &backend;
+        let client = async move {
+            let params = json!({"peripheral": PERIPHERAL});
+            client.result("connect", params.clone()).await;
+            // The peripheral goes out of range.
+            Backend::disconnect(backend, peripheral()).await.unwrap();
+
+            let read = json!({"peripheral": PERIPHERAL, "characteristic": "battery_level"});
+            let response = client.call("read", read.clone()).await;
+            assert_eq!(error_code(&response), BLUETOOTH_ERROR);
+            client.result("connect", params.clone()).await;
+            assert_eq!(client.result("read", read).await, json!({"value": "57"}));
+            client.result("disconnect", params).await;
+            assert!(!backend.is_connected(peripheral()));
+        };
+        future::block_on(future::zip(server, client)).0.unwrap();
+    }
+
+    #[test]
+    fn malformed_requests_are_rejected() {
+        let backend = SimulatedBackend::demo();
+        let bridge = Bridge::new(&backend);
+        let (server, mut client) = Client::new(&bridge);
+        let client = async move {
+            for (request, id, code) in [
+                ("{\"jsonrpc\": \"2.0\",", Value::Null, PARSE_ERROR),
+                ("[]", Value::Null, INVALID_REQUEST),
+                (r#"{"id": 1, "method": "scan"}"#, json!(1), INVALID_REQUEST),
+                (
+                    r#"{"jsonrpc": "1.0", "id": 2, "method": "scan"}"#,
+                    json!(2),
+                    INVALID_REQUEST,
+                ),
+                (
+                    r#"{"jsonrpc": "2.0", "id": "three", "method": "pair"}"#,
+                    json!("three"),
+                    METHOD_NOT_FOUND,
+                ),
+            ] {
+                client.send(request).await;
+                let response = client.receive().await;
+                assert_eq!(response["id"], id, "{request}");
+                assert_eq!(error_code(&response), code, "{request}");
+            }
+            // The client can carry on after errors.
+            client
+                .result("connect", json!({"peripheral": PERIPHERAL}))
+                .await;
+        };
+        future::block_on(future::zip(server, client)).0.unwrap();
+    }
+
+    async fn websocket_handshake(
+        bridge: &Bridge<'_, SimulatedBackend>,
+        origin: Option<&'static str>,
+    ) -> (
+        io::Result<()>,
+        std::result::Result<Value, async_tungstenite::tungstenite::Error>,
+    ) {
+        let (server, client) = UnixStream::pair().unwrap();
+        let mut request = "ws://localhost/".into_client_request().unwrap();
+        if let Some(origin) = origin {
+            request
+                .headers_mut()
+                .insert(header::ORIGIN, HeaderValue::from_static(origin));
+        }
+        let client = async {
+            let (mut websocket, _) = async_tungstenite::client_async(request, client).await?;
+            let request = json!({"jsonrpc": "2.0", "id": 1, "method": "discover", "params": {"peripheral": PERIPHERAL}});
+            websocket.send(Message::text(request.to_string())).await?;
+            let response = websocket.next().await.unwrap()?;
+            websocket.close(None).await?;
+            Ok(serde_json::from_str(response.to_text()?).unwrap())
+        };
+        future::zip(bridge.serve_websocket(server), client).await
+    }
+
+    #[test]
+    fn websocket_origins_must_be_allowed() {
+        let backend = SimulatedBackend::demo();
+        let bridge = Bridge::new(&backend).with_allowed_origin("https://example.com");
+        let (server, client) =
+            future::block_on(websocket_handshake(&bridge, Some("https://example.org")));
+        assert!(server.is_err());
+        let Err(async_tungstenite::tungstenite::Error::Http(response)) = client else {
+            panic!("unexpected handshake result {client:?}");
+        };
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[test]
+    fn websocket_clients_from_allowed_origins_are_served() {
+        let backend = SimulatedBackend::demo();
+        let bridge = Bridge::new(&backend).with_allowed_origin("https://example.com");
+        for origin in [Some("https://EXAMPLE.com"), None] {
+            let (server, client) = future::block_on(websocket_handshake(&bridge, origin));
+            server.unwrap();
+            // The peripheral is not connected, but the request got through.
+            assert_eq!(error_code(&client.unwrap()), BLUETOOTH_ERROR);
+        }
+    }
+}
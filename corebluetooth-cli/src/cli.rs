@@ -0,0 +1,173 @@
+//! Command-line arguments.
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use btuuid::BluetoothUuid;
+use clap::{Args, Parser, Subcommand};
+
+use crate::format::Format;
+use crate::names::{UuidKind, parse_uuid};
+
+/// Explore Bluetooth LE peripherals.
+///
+/// Peripherals are given by identifier or by name; a name is looked up by scanning. UUIDs are
+/// given as 4 or 8 hex digits, a 128-bit UUID, or the name of an assigned number such as
+/// `battery_level`.
+#[derive(Debug, Clone, Parser)]
+#[command(name = "corebluetooth-cli", version)]
+pub struct Cli {
+    /// Use simulated peripherals instead of CoreBluetooth.
+    #[arg(long, global = true)]
+    pub simulate: bool,
+
+    /// How long to scan for, or to look for a peripheral by name, in seconds.
+    #[arg(long, global = true, default_value = "10", value_parser = seconds)]
+    pub timeout: Duration,
+
+    /// The command to run.
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+/// A command.
+#[derive(Debug, Clone, Subcommand)]
+pub enum Command {
+    /// Scan for peripherals.
+    Scan(ScanArgs),
+    /// Connect to a peripheral and then disconnect.
+    Connect {
+        /// The identifier or name of the peripheral.
+        peripheral: String,
+    },
+    /// Show the services, characteristics and descriptors of a peripheral.
+    Gatt {
+        /// The identifier or name of the peripheral.
+        peripheral: String,
+    },
+    /// Read the value of a characteristic.
+    Read(ReadArgs),
+    /// Write the value of a characteristic.
+    Write(WriteArgs),
+    /// Show the values of a characteristic as they are notified.
+    Subscribe(SubscribeArgs),
+    /// Start an interactive shell, or run a script of shell commands.
+    Repl {
+        /// A file of commands to run, one per line, instead of reading them from the terminal.
+        script: Option<PathBuf>,
+    },
+    /// Open an L2CAP channel, copying standard input to the channel and the channel to standard
+    /// output.
+    L2cap {
+        /// The identifier or name of the peripheral.
+        peripheral: String,
+        /// The protocol/service multiplexer, in decimal or hex with a `0x` prefix.
+        #[arg(value_parser = psm)]
+        psm: u16,
+    },
+}
+
+/// Arguments for [`Command::Scan`].
+#[derive(Debug, Clone, Args)]
+pub struct ScanArgs {
+    /// Only show peripherals advertising this service. May be given more than once.
+    #[arg(long = "service", value_parser = service_uuid)]
+    pub services: Vec<BluetoothUuid>,
+
+    /// Only show peripherals whose name contains this text, ignoring case.
+    #[arg(long)]
+    pub name: Option<String>,
+
+    /// Only show peripherals with at least this RSSI.
+    #[arg(long, allow_hyphen_values = true)]
+    pub min_rssi: Option<i16>,
+
+    /// Show every advertisement rather than only the first from each peripheral.
+    #[arg(long)]
+    pub duplicates: bool,
+}
+
+/// Identifies a characteristic.
+#[derive(Debug, Clone, Args)]
+pub struct CharacteristicArgs {
+    /// The identifier or name of the peripheral.
+    pub peripheral: String,
+
+    /// The characteristic UUID.
+    #[arg(value_parser = characteristic_uuid)]
+    pub characteristic: BluetoothUuid,
+
+    /// The UUID of the service containing the characteristic, if more than one service has a
+    /// characteristic with the same UUID.
+    #[arg(long, value_parser = service_uuid)]
+    pub service: Option<BluetoothUuid>,
+}
+
+/// Arguments for [`Command::Read`].
+#[derive(Debug, Clone, Args)]
+pub struct ReadArgs {
+    #[command(flatten)]
+    pub characteristic: CharacteristicArgs,
+
+    /// How to show the value.
+    #[arg(long, value_enum, default_value_t)]
+    pub format: Format,
+}
+
+/// Arguments for [`Command::Write`].
+#[derive(Debug, Clone, Args)]
+pub struct WriteArgs {
+    #[command(flatten)]
+    pub characteristic: CharacteristicArgs,
+
+    /// The value to write.
+    pub value: String,
+
+    /// How to parse the value.
+    #[arg(long, value_enum, default_value_t)]
+    pub format: Format,
+
+    /// Write without response.
+    #[arg(long)]
+    pub without_response: bool,
+}
+
+/// Arguments for [`Command::Subscribe`].
+#[derive(Debug, Clone, Args)]
+pub struct SubscribeArgs {
+    #[command(flatten)]
+    pub characteristic: CharacteristicArgs,
+
+    /// How to show values.
+    #[arg(long, value_enum, default_value_t)]
+    pub format: Format,
+
+    /// Stop after this many values.
+    #[arg(long)]
+    pub count: Option<usize>,
+}
+
+fn seconds(value: &str) -> Result<Duration, String> {
+    value
+        .parse::<f64>()
+        .ok()
+        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+        .ok_or_else(|| format!("invalid duration {value:?}"))
+}
+
+fn psm(value: &str) -> Result<u16, String> {
+    match value.strip_prefix("0x") {
+        Some(hex) => u16::from_str_radix(hex, 16),
+        None => value.parse(),
+    }
+    .map_err(|error| error.to_string())
+}
+
+fn service_uuid(value: &str) -> Result<BluetoothUuid, String> {
+    parse_uuid(value, UuidKind::Service).ok_or_else(|| format!("unknown service {value:?}"))
+}
+
+fn characteristic_uuid(value: &str) -> Result<BluetoothUuid, String> {
+    parse_uuid(value, UuidKind::Characteristic)
+        .ok_or_else(|| format!("unknown characteristic {value:?}"))
+}
@@ -0,0 +1,253 @@
+//! A [`Backend`] built on [`CentralManagerAsync`].
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use btuuid::BluetoothUuid;
+use corebluetooth::{CBPeripheralState, CharacteristicWriteType};
+use corebluetooth_async::error::{ErrorKind, Result};
+use corebluetooth_async::{
+    CBManagerState, CentralManagerAsync, Characteristic, DeliveryPolicy, DidDiscover, L2capStream,
+    PeripheralAsync,
+};
+use futures_core::Stream;
+use uuid::Uuid;
+
+use crate::backend::{
+    Backend, CharacteristicRef, Discovery, GattCharacteristic, GattService, LocalStream, Properties,
+};
+
+/// A [`Backend`] that uses CoreBluetooth.
+///
+/// The backend must be used from the dispatch queue of the central manager's executor, for
+/// example from a task spawned with `spawn_local` in the entry function of
+/// [`CentralManagerAsync::background()`].
+#[derive(Debug)]
+pub struct CoreBluetoothBackend {
+    central: CentralManagerAsync,
+    peripherals: Rc<RefCell<HashMap<Uuid, PeripheralAsync>>>,
+}
+
+impl CoreBluetoothBackend {
+    /// Creates a backend for `central`.
+    pub fn new(central: CentralManagerAsync) -> Self {
+        Self {
+            central,
+            peripherals: Rc::default(),
+        }
+    }
+
+    fn peripheral(&self, identifier: Uuid) -> Result<PeripheralAsync> {
+        if let Some(peripheral) = self.peripherals.borrow().get(&identifier) {
+            return Ok(peripheral.clone());
+        }
+
+        let peripheral = self
+            .central
+            .retrieve_peripherals(&[identifier])
+            .into_iter()
+            .next()
+            .ok_or(ErrorKind::NotFound)?;
+        let peripheral = PeripheralAsync::new(peripheral);
+        self.peripherals
+            .borrow_mut()
+            .insert(identifier, peripheral.clone());
+        Ok(peripheral)
+    }
+
+    /// Finds a characteristic, discovering services and characteristics as necessary.
+    async fn characteristic(
+        &self,
+        characteristic: CharacteristicRef,
+    ) -> Result<(PeripheralAsync, Characteristic)> {
+        let peripheral = self.peripheral(characteristic.peripheral)?;
+        if peripheral.services().is_none() {
+            let services = characteristic.service.map(|service| [service]);
+            peripheral
+                .discover_services(services.as_ref().map(|s| &s[..]))
+                .await?;
+        }
+
+        let services = peripheral
+            .services()
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|service| {
+                characteristic
+                    .service
+                    .is_none_or(|uuid| uuid == service.uuid())
+            });
+        for service in services {
+            if service.characteristics().is_none() {
+                peripheral.discover_characteristics(&service, None).await?;
+            }
+            if let Some(found) = service
+                .characteristics()
+                .unwrap_or_default()
+                .into_iter()
+                .find(|candidate| candidate.uuid() == characteristic.characteristic)
+            {
+                return Ok((peripheral, found));
+            }
+        }
+        Err(ErrorKind::NotFound.into())
+    }
+}
+
+impl Backend for CoreBluetoothBackend {
+    type L2capStream = L2capStream<PeripheralAsync>;
+
+    async fn wait_until_ready(&self) -> Result<()> {
+        let mut updates = self.central.state_updates();
+        while self.central.state() != CBManagerState::PoweredOn {
+            updates.recv().await?;
+        }
+        Ok(())
+    }
+
+    async fn scan(
+        &self,
+        services: &[BluetoothUuid],
+        allow_duplicates: bool,
+    ) -> Result<LocalStream<Discovery>> {
+        if self.central.is_scanning() {
+            self.central.stop_scan();
+        }
+        let services = (!services.is_empty()).then_some(services);
+        Ok(Box::pin(Scan {
+            discoveries: Box::pin(self.central.scan(services, allow_duplicates, None)),
+            central: self.central.clone(),
+            peripherals: self.peripherals.clone(),
+        }))
+    }
+
+    async fn connect(&self, peripheral: Uuid) -> Result<()> {
+        self.central.connect(&self.peripheral(peripheral)?).await
+    }
+
+    async fn disconnect(&self, peripheral: Uuid) -> Result<()> {
+        self.central
+            .cancel_peripheral_connection(&self.peripheral(peripheral)?)
+            .await;
+        Ok(())
+    }
+
+    fn is_connected(&self, peripheral: Uuid) -> bool {
+        self.peripheral(peripheral)
+            .is_ok_and(|peripheral| peripheral.state() == CBPeripheralState::Connected)
+    }
+
+    async fn discover(&self, peripheral: Uuid) -> Result<Vec<GattService>> {
+        let peripheral = self.peripheral(peripheral)?;
+        peripheral.discover_services(None).await?;
+
+        let mut services = Vec::new();
+        for service in peripheral.services().unwrap_or_default() {
+            peripheral.discover_characteristics(&service, None).await?;
+            let mut characteristics = Vec::new();
+            for characteristic in service.characteristics().unwrap_or_default() {
+                peripheral.discover_descriptors(&characteristic).await?;
+                characteristics.push(GattCharacteristic {
+                    uuid: characteristic.uuid(),
+                    properties: Properties(characteristic.properties().0 as u8),
+                    descriptors: characteristic
+                        .descriptors()
+                        .unwrap_or_default()
+                        .iter()
+                        .map(|descriptor| descriptor.uuid())
+                        .collect(),
+                });
+            }
+            services.push(GattService {
+                uuid: service.uuid(),
+                is_primary: service.is_primary(),
+                characteristics,
+            });
+        }
+        Ok(services)
+    }
+
+    async fn read(&self, characteristic: CharacteristicRef) -> Result<Vec<u8>> {
+        let (peripheral, characteristic) = self.characteristic(characteristic).await?;
+        peripheral.read_characteristic_value(&characteristic).await
+    }
+
+    async fn write(
+        &self,
+        characteristic: CharacteristicRef,
+        value: &[u8],
+        with_response: bool,
+    ) -> Result<()> {
+        let (peripheral, characteristic) = self.characteristic(characteristic).await?;
+        let write_type = if with_response {
+            CharacteristicWriteType::WithResponse
+        } else {
+            CharacteristicWriteType::WithoutResponse
+        };
+        peripheral
+            .write_characteristic_value(&characteristic, value.to_vec(), write_type)
+            .await
+    }
+
+    async fn subscribe(
+        &self,
+        characteristic: CharacteristicRef,
+    ) -> Result<LocalStream<Result<Vec<u8>>>> {
+        let (peripheral, characteristic) = self.characteristic(characteristic).await?;
+        let updates = peripheral
+            .characteristic_value_updates_with_policy(&characteristic, DeliveryPolicy::default());
+        peripheral.set_notify(&characteristic, true).await?;
+        Ok(Box::pin(updates))
+    }
+
+    async fn open_l2cap(&self, peripheral: Uuid, psm: u16) -> Result<Self::L2capStream> {
+        self.peripheral(peripheral)?.open_l2cap_stream(psm).await
+    }
+}
+
+/// A scan that remembers discovered peripherals and stops scanning when dropped.
+struct Scan {
+    discoveries: Pin<Box<dyn Stream<Item = DidDiscover>>>,
+    central: CentralManagerAsync,
+    peripherals: Rc<RefCell<HashMap<Uuid, PeripheralAsync>>>,
+}
+
+impl Stream for Scan {
+    type Item = Discovery;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Discovery>> {
+        let Some(did_discover) = std::task::ready!(self.discoveries.as_mut().poll_next(cx)) else {
+            return Poll::Ready(None);
+        };
+
+        let identifier = did_discover.peripheral.identifier();
+        let advertisement = did_discover.advertisement_data;
+        let discovery = Discovery {
+            identifier,
+            name: did_discover.peripheral.name(),
+            rssi: did_discover.rssi,
+            local_name: advertisement.local_name,
+            service_uuids: advertisement.service_uuids,
+            manufacturer_data: advertisement
+                .manufacturer_data
+                .map(|data| (data.company_id, data.data)),
+            tx_power_level: advertisement.tx_power_level,
+            is_connectable: advertisement.is_connectable,
+        };
+        self.peripherals
+            .borrow_mut()
+            .insert(identifier, did_discover.peripheral);
+        Poll::Ready(Some(discovery))
+    }
+}
+
+impl Drop for Scan {
+    fn drop(&mut self) {
+        if self.central.is_scanning() {
+            self.central.stop_scan();
+        }
+    }
+}
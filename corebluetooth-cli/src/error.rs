@@ -0,0 +1,82 @@
+//! Error types for this crate.
+
+use std::fmt::Display;
+
+use crate::format::ParseValueError;
+
+/// A convenience type alias for a `Result` with an `Error` type.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// An error that caused a command to fail.
+#[derive(Debug)]
+pub enum Error {
+    /// A Bluetooth operation failed.
+    Bluetooth(corebluetooth_async::error::Error),
+    /// Reading input or writing output failed.
+    Io(std::io::Error),
+    /// A value could not be parsed.
+    InvalidValue(ParseValueError),
+    /// No peripheral matching the given identifier or name was found.
+    PeripheralNotFound(String),
+    /// No characteristic matching the given UUID was found.
+    CharacteristicNotFound(String),
+    /// A shell command was not valid.
+    Usage(String),
+    /// A command in a script failed.
+    Script {
+        /// The line number of the command, starting at 1.
+        line: usize,
+        /// The error.
+        error: Box<Error>,
+    },
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Bluetooth(error) => error.fmt(f),
+            Error::Io(error) => error.fmt(f),
+            Error::InvalidValue(error) => error.fmt(f),
+            Error::PeripheralNotFound(peripheral) => {
+                write!(f, "peripheral {peripheral:?} not found")
+            }
+            Error::CharacteristicNotFound(characteristic) => {
+                write!(f, "characteristic {characteristic:?} not found")
+            }
+            Error::Usage(message) => f.write_str(message),
+            Error::Script { line, error } => write!(f, "line {line}: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Bluetooth(error) => Some(error),
+            Error::Io(error) => Some(error),
+            Error::InvalidValue(error) => Some(error),
+            Error::Script { error, .. } => Some(error),
+            Error::PeripheralNotFound(_) | Error::CharacteristicNotFound(_) | Error::Usage(_) => {
+                None
+            }
+        }
+    }
+}
+
+impl From<corebluetooth_async::error::Error> for Error {
+    fn from(error: corebluetooth_async::error::Error) -> Self {
+        Error::Bluetooth(error)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(error: std::io::Error) -> Self {
+        Error::Io(error)
+    }
+}
+
+impl From<ParseValueError> for Error {
+    fn from(error: ParseValueError) -> Self {
+        Error::InvalidValue(error)
+    }
+}
@@ -0,0 +1,243 @@
+//! Formatting and parsing of attribute values.
+
+use std::fmt::Display;
+
+/// How attribute values are displayed and parsed.
+///
+/// Typed formats treat a value as a sequence of little-endian numbers, as used by most
+/// characteristics defined by the Bluetooth SIG.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, clap::ValueEnum)]
+pub enum Format {
+    /// Hexadecimal bytes.
+    #[default]
+    Hex,
+    /// UTF-8 text.
+    Utf8,
+    /// Unsigned 8-bit integers.
+    U8,
+    /// Unsigned 16-bit integers.
+    U16,
+    /// Unsigned 32-bit integers.
+    U32,
+    /// Signed 8-bit integers.
+    I8,
+    /// Signed 16-bit integers.
+    I16,
+    /// Signed 32-bit integers.
+    I32,
+    /// 32-bit floating point numbers.
+    F32,
+}
+
+/// An error parsing a value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseValueError {
+    format: Format,
+    input: String,
+}
+
+impl Display for ParseValueError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid {:?} value {:?}", self.format, self.input)
+    }
+}
+
+impl std::error::Error for ParseValueError {}
+
+macro_rules! numbers {
+    ($value:expr, $ty:ty) => {
+        $value
+            .chunks_exact(size_of::<$ty>())
+            .map(|chunk| <$ty>::from_le_bytes(chunk.try_into().unwrap()).to_string())
+            .collect::<Vec<_>>()
+    };
+}
+
+impl Format {
+    fn width(self) -> usize {
+        match self {
+            Format::Hex | Format::Utf8 | Format::U8 | Format::I8 => 1,
+            Format::U16 | Format::I16 => 2,
+            Format::U32 | Format::I32 | Format::F32 => 4,
+        }
+    }
+
+    /// Formats `value`.
+    ///
+    /// Values that cannot be shown in this format, such as invalid UTF-8 or a length that is not
+    /// a multiple of the width of a typed format, are shown in hex.
+    ///
+    /// ```
+    /// use corebluetooth_cli::format::Format;
+    ///
+    /// assert_eq!(Format::Hex.format(&[0x01, 0xff]), "01 ff");
+    /// assert_eq!(Format::U16.format(&[0x01, 0x02, 0xff, 0xff]), "513 65535");
+    /// assert_eq!(Format::I8.format(&[0xff]), "-1");
+    /// assert_eq!(Format::Utf8.format(b"hello"), "\"hello\"");
+    /// assert_eq!(Format::U16.format(&[0x01]), "01");
+    /// ```
+    pub fn format(self, value: &[u8]) -> String {
+        if !value.len().is_multiple_of(self.width()) {
+            return Format::Hex.format(value);
+        }
+        let values = match self {
+            Format::Hex => value.iter().map(|byte| format!("{byte:02x}")).collect(),
+            Format::Utf8 => match std::str::from_utf8(value) {
+                Ok(text) => return format!("{text:?}"),
+                Err(_) => return Format::Hex.format(value),
+            },
+            Format::U8 => numbers!(value, u8),
+            Format::U16 => numbers!(value, u16),
+            Format::U32 => numbers!(value, u32),
+            Format::I8 => numbers!(value, i8),
+            Format::I16 => numbers!(value, i16),
+            Format::I32 => numbers!(value, i32),
+            Format::F32 => numbers!(value, f32),
+        };
+        values.join(" ")
+    }
+
+    /// Parses `input` as a value in this format.
+    ///
+    /// Hex values may contain whitespace and an `0x` prefix. Typed values are separated by
+    /// whitespace or commas.
+    ///
+    /// ```
+    /// use corebluetooth_cli::format::Format;
+    ///
+    /// assert_eq!(Format::Hex.parse("0x01ff").unwrap(), [0x01, 0xff]);
+    /// assert_eq!(Format::Hex.parse("01 ff").unwrap(), [0x01, 0xff]);
+    /// assert_eq!(Format::U16.parse("513, 65535").unwrap(), [0x01, 0x02, 0xff, 0xff]);
+    /// assert_eq!(Format::Utf8.parse("hi").unwrap(), b"hi");
+    /// assert!(Format::U8.parse("256").is_err());
+    /// ```
+    pub fn parse(self, input: &str) -> Result<Vec<u8>, ParseValueError> {
+        let error = || ParseValueError {
+            format: self,
+            input: input.to_owned(),
+        };
+        let parse = |bytes: fn(&str) -> Option<Vec<u8>>| -> Result<Vec<u8>, ParseValueError> {
+            let mut value = Vec::new();
+            for item in input.split([' ', ',']).filter(|item| !item.is_empty()) {
+                value.extend(bytes(item).ok_or_else(error)?);
+            }
+            Ok(value)
+        };
+
+        match self {
+            Format::Hex => {
+                let digits: String = input
+                    .strip_prefix("0x")
+                    .unwrap_or(input)
+                    .split_whitespace()
+                    .collect();
+                if !digits.is_ascii() || !digits.len().is_multiple_of(2) {
+                    return Err(error());
+                }
+                (0..digits.len())
+                    .step_by(2)
+                    .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).map_err(|_| error()))
+                    .collect()
+            }
+            Format::Utf8 => Ok(input.as_bytes().to_vec()),
+            Format::U8 => parse(|item| item.parse::<u8>().ok().map(|n| n.to_le_bytes().to_vec())),
+            Format::U16 => parse(|item| item.parse::<u16>().ok().map(|n| n.to_le_bytes().to_vec())),
+            Format::U32 => parse(|item| item.parse::<u32>().ok().map(|n| n.to_le_bytes().to_vec())),
+            Format::I8 => parse(|item| item.parse::<i8>().ok().map(|n| n.to_le_bytes().to_vec())),
+            Format::I16 => parse(|item| item.parse::<i16>().ok().map(|n| n.to_le_bytes().to_vec())),
+            Format::I32 => parse(|item| item.parse::<i32>().ok().map(|n| n.to_le_bytes().to_vec())),
+            Format::F32 => parse(|item| item.parse::<f32>().ok().map(|n| n.to_le_bytes().to_vec())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FORMATS: [Format; 9] = [
+        Format::Hex,
+        Format::Utf8,
+        Format::U8,
+        Format::U16,
+        Format::U32,
+        Format::I8,
+        Format::I16,
+        Format::I32,
+        Format::F32,
+    ];
+
+    #[test]
+    fn formatted_values_parse_back() {
+        let value = [0x00, 0x01, 0x7f, 0x80, 0xfe, 0xff, 0x12, 0x34];
+        for format in FORMATS.into_iter().filter(|&format| format != Format::Utf8) {
+            let text = format.format(&value);
+            assert_eq!(format.parse(&text), Ok(value.to_vec()), "{format:?} {text}");
+        }
+        assert_eq!(Format::Utf8.parse("héllo"), Ok("héllo".as_bytes().to_vec()));
+        assert_eq!(Format::Utf8.format("héllo".as_bytes()), "\"héllo\"");
+    }
+
+    #[test]
+    fn parsed_values_format_back() {
+        for (format, input) in [
+            (Format::Hex, "00 7f 80 ff"),
+            (Format::U8, "0 127 128 255"),
+            (Format::U16, "0 513 65535"),
+            (Format::U32, "0 4294967295"),
+            (Format::I8, "-128 -1 0 127"),
+            (Format::I16, "-32768 -1 32767"),
+            (Format::I32, "-2147483648 2147483647"),
+            (Format::F32, "-1.5 0 36.6"),
+        ] {
+            assert_eq!(format.format(&format.parse(input).unwrap()), input);
+        }
+    }
+
+    #[test]
+    fn separators_are_flexible() {
+        assert_eq!(Format::U8.parse("1,2, 3  4,,"), Ok(vec![1, 2, 3, 4]));
+        assert_eq!(Format::Hex.parse("0x 01 02 0304"), Ok(vec![1, 2, 3, 4]));
+        assert_eq!(Format::Hex.parse(""), Ok(vec![]));
+        assert_eq!(Format::U16.parse(""), Ok(vec![]));
+    }
+
+    #[test]
+    fn invalid_input_is_rejected() {
+        for (format, input) in [
+            (Format::Hex, "0x1"),
+            (Format::Hex, "0g"),
+            (Format::Hex, "é0"),
+            (Format::U8, "-1"),
+            (Format::U16, "65536"),
+            (Format::I8, "128"),
+            (Format::I32, "1.5"),
+            (Format::F32, "x"),
+        ] {
+            assert_eq!(
+                format.parse(input),
+                Err(ParseValueError {
+                    format,
+                    input: input.to_owned()
+                })
+            );
+        }
+        assert_eq!(
+            Format::U8.parse("256").unwrap_err().to_string(),
+            "invalid U8 value \"256\""
+        );
+    }
+
+    #[test]
+    fn unformattable_values_are_shown_in_hex() {
+        assert_eq!(Format::Utf8.format(&[0xff, 0xfe]), "ff fe");
+        assert_eq!(Format::U32.format(&[1, 2, 3]), "01 02 03");
+        assert_eq!(Format::I16.format(&[0xff]), "ff");
+        for format in FORMATS {
+            assert_eq!(
+                format.format(&[]),
+                if format == Format::Utf8 { "\"\"" } else { "" }
+            );
+        }
+    }
+}
@@ -0,0 +1,60 @@
+//! A command-line explorer for Bluetooth LE peripherals built on `corebluetooth-async`.
+//!
+//! The `corebluetooth-cli` binary scans for peripherals, shows their GATT database, reads,
+//! writes and subscribes to characteristics, and pipes standard input and output through L2CAP
+//! channels:
+//!
+//! ```text
+//! corebluetooth-cli scan --service heart_rate
+//! corebluetooth-cli gatt "Heart Sensor"
+//! corebluetooth-cli read "Heart Sensor" battery_level --format u8
+//! corebluetooth-cli subscribe "Heart Sensor" heart_rate_measurement --count 10
+//! echo hello | corebluetooth-cli l2cap "Heart Sensor" 0x80
+//! ```
+//!
+//! `corebluetooth-cli repl` starts an interactive [shell][repl] with history and tab completion,
+//! and `corebluetooth-cli repl SCRIPT` runs a file of shell commands.
+//!
+//! The `corebluetooth-bridge` binary exposes the same operations to other local processes as
+//! JSON-RPC over a WebSocket or Unix socket; see [`bridge`] and [`rpc`].
+//!
+//! Commands run against a [`Backend`][backend::Backend]. On macOS and iOS this is
+//! [`CoreBluetoothBackend`][core_bluetooth::CoreBluetoothBackend]. With `--simulate` it is
+//! [`SimulatedBackend`][simulated::SimulatedBackend], which is available on every platform, so
+//! argument parsing, output formatting and command execution can be exercised anywhere:
+//!
+//! ```
+//! use clap::Parser;
+//! use corebluetooth_cli::cli::Cli;
+//! use corebluetooth_cli::run::run;
+//! use corebluetooth_cli::simulated::SimulatedBackend;
+//!
+//! let cli = Cli::parse_from([
+//!     "corebluetooth-cli",
+//!     "--simulate",
+//!     "read",
+//!     "Heart Sensor",
+//!     "battery_level",
+//!     "--format",
+//!     "u8",
+//! ]);
+//! let backend = SimulatedBackend::demo();
+//! let mut output = Vec::new();
+//! futures_lite::future::block_on(run(&backend, &cli, futures_lite::io::empty(), &mut output))?;
+//! assert_eq!(output, b"87\n");
+//! # Ok::<(), corebluetooth_cli::error::Error>(())
+//! ```
+
+pub mod backend;
+pub mod bridge;
+pub mod cli;
+#[cfg(target_vendor = "apple")]
+pub mod core_bluetooth;
+pub mod error;
+pub mod format;
+pub mod names;
+pub mod repl;
+pub mod rpc;
+pub mod run;
+pub mod simulated;
+pub mod values;
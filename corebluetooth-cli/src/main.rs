@@ -0,0 +1,61 @@
+use std::process::ExitCode;
+
+use clap::Parser;
+use corebluetooth_cli::cli::Cli;
+use corebluetooth_cli::run::run;
+use corebluetooth_cli::simulated::SimulatedBackend;
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    let res = if cli.simulate {
+        futures_lite::future::block_on(async {
+            let input = blocking::Unblock::new(std::io::stdin());
+            run(
+                &SimulatedBackend::demo(),
+                &cli,
+                input,
+                &mut std::io::stdout(),
+            )
+            .await
+            .map_err(|err| err.to_string())
+        })
+    } else {
+        run_native(cli)
+    };
+
+    match res {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(target_vendor = "apple")]
+fn run_native(cli: Cli) -> Result<(), String> {
+    use corebluetooth_async::CentralManagerAsync;
+    use corebluetooth_async::dispatch::DispatchQoS;
+    use corebluetooth_cli::core_bluetooth::CoreBluetoothBackend;
+
+    let task =
+        CentralManagerAsync::background(DispatchQoS::default(), false, |central, executor| {
+            let task = async move {
+                let backend = CoreBluetoothBackend::new(central);
+                let input = blocking::Unblock::new(std::io::stdin());
+                run(&backend, &cli, input, &mut std::io::stdout())
+                    .await
+                    .map_err(|err| err.to_string())
+            };
+            // Safety: the task only uses CoreBluetooth objects from the executor's queue and does
+            // not depend on thread-local state.
+            unsafe { executor.spawn_local(task) }
+        });
+    futures_lite::future::block_on(task)
+}
+
+#[cfg(not(target_vendor = "apple"))]
+fn run_native(_cli: Cli) -> Result<(), String> {
+    Err("CoreBluetooth is only available on macOS and iOS; use --simulate".to_owned())
+}
@@ -0,0 +1,311 @@
+//! Names of the UUIDs assigned by the Bluetooth SIG.
+//!
+//! Names are the names of the constants in [`btuuid`], compared case-insensitively, so the battery
+//! level characteristic can be referred to as `battery_level` or `BATTERY_LEVEL`.
+
+use btuuid::{BluetoothUuid, BluetoothUuid16};
+
+/// The kind of attribute a UUID identifies, used to choose between names that are assigned to
+/// more than one kind of attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UuidKind {
+    /// A service UUID.
+    Service,
+    /// A characteristic UUID.
+    Characteristic,
+    /// A descriptor UUID.
+    Descriptor,
+}
+
+impl UuidKind {
+    fn table(self) -> &'static [(&'static str, BluetoothUuid16)] {
+        match self {
+            UuidKind::Service => SERVICES,
+            UuidKind::Characteristic => CHARACTERISTICS,
+            UuidKind::Descriptor => DESCRIPTORS,
+        }
+    }
+}
+
+/// Parses a UUID given as 4 or 8 hex digits (with an optional `0x` prefix), a 128-bit UUID, or
+/// the name of an assigned number.
+///
+/// Names are looked up among UUIDs of the given kind first, then among all assigned UUIDs. A name
+/// may start with a common abbreviation of a service, such as `hr` for `heart_rate` or `dis` for
+/// `device_information`.
+///
+/// ```
+/// use btuuid::{BluetoothUuid, characteristic};
+/// use corebluetooth_cli::names::{UuidKind, parse_uuid};
+///
+/// let battery_level = BluetoothUuid::from(characteristic::BATTERY_LEVEL);
+/// assert_eq!(parse_uuid("battery_level", UuidKind::Characteristic), Some(battery_level));
+/// assert_eq!(parse_uuid("0x2A19", UuidKind::Characteristic), Some(battery_level));
+/// assert_eq!(
+///     parse_uuid("hr_measurement", UuidKind::Characteristic),
+///     Some(characteristic::HEART_RATE_MEASUREMENT.into()),
+/// );
+/// assert_eq!(
+///     parse_uuid("00002a19-0000-1000-8000-00805f9b34fb", UuidKind::Characteristic),
+///     Some(battery_level),
+/// );
+/// ```
+pub fn parse_uuid(value: &str, kind: UuidKind) -> Option<BluetoothUuid> {
+    let digits = value.strip_prefix("0x").unwrap_or(value);
+    if digits.len() == 4
+        && let Ok(uuid) = u16::from_str_radix(digits, 16)
+    {
+        return Some(BluetoothUuid::from_u16(uuid));
+    }
+    if digits.len() == 8
+        && let Ok(uuid) = u32::from_str_radix(digits, 16)
+    {
+        return Some(BluetoothUuid::from_u32(uuid));
+    }
+    if let Ok(uuid) = uuid::Uuid::parse_str(value) {
+        return Some(BluetoothUuid::from(uuid));
+    }
+
+    let mut name = value.replace(['-', ' '], "_");
+    let (prefix, rest) = name.split_once('_').unwrap_or((&name, ""));
+    if let Some((_, expansion)) = ABBREVIATIONS
+        .iter()
+        .find(|(abbreviation, _)| abbreviation.eq_ignore_ascii_case(prefix))
+    {
+        name = if rest.is_empty() {
+            expansion.to_string()
+        } else {
+            format!("{expansion}_{rest}")
+        };
+    }
+    [
+        kind.table(),
+        SERVICES,
+        CHARACTERISTICS,
+        DESCRIPTORS,
+        DECLARATIONS,
+    ]
+    .into_iter()
+    .flatten()
+    .find(|(candidate, _)| candidate.eq_ignore_ascii_case(&name))
+    .map(|(_, uuid)| (*uuid).into())
+}
+
+/// Returns the lowercase name of an assigned UUID of the given kind, if it has one.
+///
+/// ```
+/// use btuuid::service;
+/// use corebluetooth_cli::names::{UuidKind, name};
+///
+/// assert_eq!(name(service::BATTERY.into(), UuidKind::Service).as_deref(), Some("battery"));
+/// ```
+pub fn name(uuid: BluetoothUuid, kind: UuidKind) -> Option<String> {
+    kind.table()
+        .iter()
+        .find(|(_, candidate)| BluetoothUuid::from(*candidate) == uuid)
+        .map(|(name, _)| name.to_ascii_lowercase())
+}
+
+/// Returns the lowercase names of the assigned UUIDs of the given kind.
+pub fn names(kind: UuidKind) -> impl Iterator<Item = String> {
+    kind.table()
+        .iter()
+        .map(|(name, _)| name.to_ascii_lowercase())
+}
+
+/// Formats a UUID followed by its name, if it has one.
+pub fn describe(uuid: BluetoothUuid, kind: UuidKind) -> String {
+    match name(uuid, kind) {
+        Some(name) => format!("{uuid} ({name})"),
+        None => uuid.to_string(),
+    }
+}
+
+/// Abbreviations that may replace the first word of a name.
+const ABBREVIATIONS: &[(&str, &str)] = &[
+    ("bas", "battery"),
+    ("bp", "blood_pressure"),
+    ("csc", "cycling_speed_and_cadence"),
+    ("dis", "device_information"),
+    ("hid", "human_interface_device"),
+    ("hr", "heart_rate"),
+    ("hrs", "heart_rate"),
+    ("hts", "health_thermometer"),
+    ("rsc", "running_speed_and_cadence"),
+];
+
+macro_rules! names {
+    ($module:ident: $($name:ident),* $(,)?) => {
+        &[$((stringify!($name), btuuid::$module::$name)),*]
+    };
+}
+
+#[rustfmt::skip]
+const SERVICES: &[(&str, BluetoothUuid16)] = names! { service:
+    GAP, GATT, IMMEDIATE_ALERT, LINK_LOSS, TX_POWER, CURRENT_TIME, REFERENCE_TIME_UPDATE,
+    NEXT_DST_CHANGE, GLUCOSE, HEALTH_THERMOMETER, DEVICE_INFORMATION, HEART_RATE,
+    PHONE_ALERT_STATUS, BATTERY, BLOOD_PRESSURE, ALERT_NOTIFICATION, HUMAN_INTERFACE_DEVICE,
+    SCAN_PARAMETERS, RUNNING_SPEED_AND_CADENCE, AUTOMATION_IO, CYCLING_SPEED_AND_CADENCE,
+    CYCLING_POWER, LOCATION_AND_NAVIGATION, ENVIRONMENTAL_SENSING, BODY_COMPOSITION, USER_DATA,
+    WEIGHT_SCALE, BOND_MANAGEMENT, CONTINUOUS_GLUCOSE_MONITORING, INTERNET_PROTOCOL_SUPPORT,
+    INDOOR_POSITIONING, PULSE_OXIMETER, HTTP_PROXY, TRANSPORT_DISCOVERY, OBJECT_TRANSFER,
+    FITNESS_MACHINE, MESH_PROVISIONING, MESH_PROXY, RECONNECTION_CONFIGURATION,
+    INSULIN_DELIVERY, BINARY_SENSOR, EMERGENCY_CONFIGURATION, AUTHORIZATION_CONTROL,
+    PHYSICAL_ACTIVITY_MONITOR, ELAPSED_TIME, GENERIC_HEALTH_SENSOR, AUDIO_INPUT_CONTROL,
+    VOLUME_CONTROL, VOLUME_OFFSET_CONTROL, COORDINATED_SET_IDENTIFICATION, DEVICE_TIME,
+    MEDIA_CONTROL, GENERIC_MEDIA_CONTROL, CONSTANT_TONE_EXTENSION, TELEPHONE_BEARER,
+    GENERIC_TELEPHONE_BEARER, MICROPHONE_CONTROL, AUDIO_STREAM_CONTROL, BROADCAST_AUDIO_SCAN,
+    PUBLISHED_AUDIO_CAPABILITIES, BASIC_AUDIO_ANNOUNCEMENT, BROADCAST_AUDIO_ANNOUNCEMENT,
+    COMMON_AUDIO, HEARING_ACCESS, TELEPHONY_AND_MEDIA_AUDIO, PUBLIC_BROADCAST_ANNOUNCEMENT,
+    ELECTRONIC_SHELF_LABEL, GAMING_AUDIO, MESH_PROXY_SOLICITATION,
+    INDUSTRIAL_MEASUREMENT_DEVICE, RANGING,
+};
+
+#[rustfmt::skip]
+const CHARACTERISTICS: &[(&str, BluetoothUuid16)] = names! { characteristic:
+    DEVICE_NAME, APPEARANCE, PERIPHERAL_PRIVACY_FLAG, RECONNECTION_ADDRESS,
+    PERIPHERAL_PREFERRED_CONNECTION_PARAMETERS, SERVICE_CHANGED, ALERT_LEVEL, TX_POWER_LEVEL,
+    DATE_TIME, DAY_OF_WEEK, DAY_DATE_TIME, EXACT_TIME_256, DST_OFFSET, TIME_ZONE,
+    LOCAL_TIME_INFORMATION, TIME_WITH_DST, TIME_ACCURACY, TIME_SOURCE,
+    REFERENCE_TIME_INFORMATION, TIME_UPDATE_CONTROL_POINT, TIME_UPDATE_STATE,
+    GLUCOSE_MEASUREMENT, BATTERY_LEVEL, TEMPERATURE_MEASUREMENT, TEMPERATURE_TYPE,
+    INTERMEDIATE_TEMPERATURE, MEASUREMENT_INTERVAL, BOOT_KEYBOARD_INPUT_REPORT, SYSTEM_ID,
+    MODEL_NUMBER_STRING, SERIAL_NUMBER_STRING, FIRMWARE_REVISION_STRING,
+    HARDWARE_REVISION_STRING, SOFTWARE_REVISION_STRING, MANUFACTURER_NAME_STRING,
+    IEEE_11073_20601_REGULATORY_CERTIFICATION_DATA_LIST, CURRENT_TIME, MAGNETIC_DECLINATION,
+    SCAN_REFRESH, BOOT_KEYBOARD_OUTPUT_REPORT, BOOT_MOUSE_INPUT_REPORT,
+    GLUCOSE_MEASUREMENT_CONTEXT, BLOOD_PRESSURE_MEASUREMENT, INTERMEDIATE_CUFF_PRESSURE,
+    HEART_RATE_MEASUREMENT, BODY_SENSOR_LOCATION, HEART_RATE_CONTROL_POINT, ALERT_STATUS,
+    RINGER_CONTROL_POINT, RINGER_SETTING, ALERT_CATEGORY_ID_BIT_MASK, ALERT_CATEGORY_ID,
+    ALERT_NOTIFICATION_CONTROL_POINT, UNREAD_ALERT_STATUS, NEW_ALERT,
+    SUPPORTED_NEW_ALERT_CATEGORY, SUPPORTED_UNREAD_ALERT_CATEGORY, BLOOD_PRESSURE_FEATURE,
+    HID_INFORMATION, REPORT_MAP, HID_CONTROL_POINT, REPORT, PROTOCOL_MODE, SCAN_INTERVAL_WINDOW,
+    PNP_ID, GLUCOSE_FEATURE, RECORD_ACCESS_CONTROL_POINT, RSC_MEASUREMENT, RSC_FEATURE,
+    SC_CONTROL_POINT, AGGREGATE, CSC_MEASUREMENT, CSC_FEATURE, SENSOR_LOCATION,
+    PLX_SPOT_CHECK_MEASUREMENT, PLX_CONTINUOUS_MEASUREMENT, PLX_FEATURES,
+    CYCLING_POWER_MEASUREMENT, CYCLING_POWER_VECTOR, CYCLING_POWER_FEATURE,
+    CYCLING_POWER_CONTROL_POINT, LOCATION_AND_SPEED, NAVIGATION, POSITION_QUALITY, LN_FEATURE,
+    LN_CONTROL_POINT, ELEVATION, PRESSURE, TEMPERATURE, HUMIDITY, TRUE_WIND_SPEED,
+    TRUE_WIND_DIRECTION, APPARENT_WIND_SPEED, APPARENT_WIND_DIRECTION, GUST_FACTOR,
+    POLLEN_CONCENTRATION, UV_INDEX, IRRADIANCE, RAINFALL, WIND_CHILL, HEAT_INDEX, DEW_POINT,
+    DESCRIPTOR_VALUE_CHANGED, AEROBIC_HEART_RATE_LOWER_LIMIT, AEROBIC_THRESHOLD, AGE,
+    ANAEROBIC_HEART_RATE_LOWER_LIMIT, ANAEROBIC_HEART_RATE_UPPER_LIMIT, ANAEROBIC_THRESHOLD,
+    AEROBIC_HEART_RATE_UPPER_LIMIT, DATE_OF_BIRTH, DATE_OF_THRESHOLD_ASSESSMENT, EMAIL_ADDRESS,
+    FAT_BURN_HEART_RATE_LOWER_LIMIT, FAT_BURN_HEART_RATE_UPPER_LIMIT, FIRST_NAME,
+    FIVE_ZONE_HEART_RATE_LIMITS, GENDER, HEART_RATE_MAX, HEIGHT, HIP_CIRCUMFERENCE, LAST_NAME,
+    MAXIMUM_RECOMMENDED_HEART_RATE, RESTING_HEART_RATE,
+    SPORT_TYPE_FOR_AEROBIC_AND_ANAEROBIC_THRESHOLDS, THREE_ZONE_HEART_RATE_LIMITS,
+    TWO_ZONE_HEART_RATE_LIMITS, VO2_MAX, WAIST_CIRCUMFERENCE, WEIGHT, DATABASE_CHANGE_INCREMENT,
+    USER_INDEX, BODY_COMPOSITION_FEATURE, BODY_COMPOSITION_MEASUREMENT, WEIGHT_MEASUREMENT,
+    WEIGHT_SCALE_FEATURE, USER_CONTROL_POINT, MAGNETIC_FLUX_DENSITY_2D,
+    MAGNETIC_FLUX_DENSITY_3D, LANGUAGE, BAROMETRIC_PRESSURE_TREND,
+    BOND_MANAGEMENT_CONTROL_POINT, BOND_MANAGEMENT_FEATURE, CENTRAL_ADDRESS_RESOLUTION,
+    CGM_MEASUREMENT, CGM_FEATURE, CGM_STATUS, CGM_SESSION_START_TIME, CGM_SESSION_RUN_TIME,
+    CGM_SPECIFIC_OPS_CONTROL_POINT, INDOOR_POSITIONING_CONFIGURATION, LATITUDE, LONGITUDE,
+    LOCAL_NORTH_COORDINATE, LOCAL_EAST_COORDINATE, FLOOR_NUMBER, ALTITUDE, UNCERTAINTY,
+    LOCATION_NAME, URI, HTTP_HEADERS, HTTP_STATUS_CODE, HTTP_ENTITY_BODY, HTTP_CONTROL_POINT,
+    HTTPS_SECURITY, TDS_CONTROL_POINT, OTS_FEATURE, OBJECT_NAME, OBJECT_TYPE, OBJECT_SIZE,
+    OBJECT_FIRST_CREATED, OBJECT_LAST_MODIFIED, OBJECT_ID, OBJECT_PROPERTIES,
+    OBJECT_ACTION_CONTROL_POINT, OBJECT_LIST_CONTROL_POINT, OBJECT_LIST_FILTER, OBJECT_CHANGED,
+    RESOLVABLE_PRIVATE_ADDRESS_ONLY, FITNESS_MACHINE_FEATURE, TREADMILL_DATA,
+    CROSS_TRAINER_DATA, STEP_CLIMBER_DATA, STAIR_CLIMBER_DATA, ROWER_DATA, INDOOR_BIKE_DATA,
+    TRAINING_STATUS, SUPPORTED_SPEED_RANGE, SUPPORTED_INCLINATION_RANGE,
+    SUPPORTED_RESISTANCE_LEVEL_RANGE, SUPPORTED_HEART_RATE_RANGE, SUPPORTED_POWER_RANGE,
+    FITNESS_MACHINE_CONTROL_POINT, FITNESS_MACHINE_STATUS, MESH_PROVISIONING_DATA_IN,
+    MESH_PROVISIONING_DATA_OUT, MESH_PROXY_DATA_IN, MESH_PROXY_DATA_OUT, AVERAGE_CURRENT,
+    AVERAGE_VOLTAGE, BOOLEAN, CHROMATIC_DISTANCE_FROM_PLANCKIAN, CHROMATICITY_COORDINATES,
+    CHROMATICITY_IN_CCT_AND_DUV_VALUES, CHROMATICITY_TOLERANCE,
+    CIE_133_1995_COLOR_RENDERING_INDEX, COEFFICIENT, CORRELATED_COLOR_TEMPERATURE, COUNT_16,
+    COUNT_24, COUNTRY_CODE, DATE_UTC, ELECTRIC_CURRENT, ELECTRIC_CURRENT_RANGE,
+    ELECTRIC_CURRENT_SPECIFICATION, ELECTRIC_CURRENT_STATISTICS, ENERGY,
+    ENERGY_IN_A_PERIOD_OF_DAY, EVENT_STATISTICS, FIXED_STRING_16, FIXED_STRING_24,
+    FIXED_STRING_36, FIXED_STRING_8, GENERIC_LEVEL, GLOBAL_TRADE_ITEM_NUMBER, ILLUMINANCE,
+    LUMINOUS_EFFICACY, LUMINOUS_ENERGY, LUMINOUS_EXPOSURE, LUMINOUS_FLUX, LUMINOUS_FLUX_RANGE,
+    LUMINOUS_INTENSITY, MASS_FLOW, PERCEIVED_LIGHTNESS, PERCENTAGE_8, POWER,
+    POWER_SPECIFICATION, RELATIVE_RUNTIME_IN_A_CURRENT_RANGE,
+    RELATIVE_RUNTIME_IN_A_GENERIC_LEVEL_RANGE, RELATIVE_VALUE_IN_A_VOLTAGE_RANGE,
+    RELATIVE_VALUE_IN_AN_ILLUMINANCE_RANGE, RELATIVE_VALUE_IN_A_PERIOD_OF_DAY,
+    RELATIVE_VALUE_IN_A_TEMPERATURE_RANGE, TEMPERATURE_8, TEMPERATURE_8_IN_A_PERIOD_OF_DAY,
+    TEMPERATURE_8_STATISTICS, TEMPERATURE_RANGE, TEMPERATURE_STATISTICS, TIME_DECIHOUR_8,
+    TIME_EXPONENTIAL_8, TIME_HOUR_24, TIME_MILLISECOND_24, TIME_SECOND_16, TIME_SECOND_8,
+    VOLTAGE, VOLTAGE_SPECIFICATION, VOLTAGE_STATISTICS, VOLUME_FLOW, CHROMATICITY_COORDINATE,
+    RC_FEATURE, RC_SETTINGS, RECONNECTION_CONFIGURATION_CONTROL_POINT, IDD_STATUS_CHANGED,
+    IDD_STATUS, IDD_ANNUNCIATION_STATUS, IDD_FEATURES, IDD_STATUS_READER_CONTROL_POINT,
+    IDD_COMMAND_CONTROL_POINT, IDD_COMMAND_DATA, IDD_RECORD_ACCESS_CONTROL_POINT,
+    IDD_HISTORY_DATA, CLIENT_SUPPORTED_FEATURES, DATABASE_HASH, BSS_CONTROL_POINT, BSS_RESPONSE,
+    EMERGENCY_ID, EMERGENCY_TEXT, ACS_STATUS, ACS_DATA_IN, ACS_DATA_OUT_NOTIFY,
+    ACS_DATA_OUT_INDICATE, ACS_CONTROL_POINT, ENHANCED_BLOOD_PRESSURE_MEASUREMENT,
+    ENHANCED_INTERMEDIATE_CUFF_PRESSURE, BLOOD_PRESSURE_RECORD, REGISTERED_USER,
+    BR_EDR_HANDOVER_DATA, BLUETOOTH_SIG_DATA, SERVER_SUPPORTED_FEATURES,
+    PHYSICAL_ACTIVITY_MONITOR_FEATURES, GENERAL_ACTIVITY_INSTANTANEOUS_DATA,
+    GENERAL_ACTIVITY_SUMMARY_DATA, CARDIORESPIRATORY_ACTIVITY_INSTANTANEOUS_DATA,
+    CARDIORESPIRATORY_ACTIVITY_SUMMARY_DATA, STEP_COUNTER_ACTIVITY_SUMMARY_DATA,
+    SLEEP_ACTIVITY_INSTANTANEOUS_DATA, SLEEP_ACTIVITY_SUMMARY_DATA,
+    PHYSICAL_ACTIVITY_MONITOR_CONTROL_POINT, PHYSICAL_ACTIVITY_CURRENT_SESSION,
+    PHYSICAL_ACTIVITY_SESSION_DESCRIPTOR, PREFERRED_UNITS, HIGH_RESOLUTION_HEIGHT, MIDDLE_NAME,
+    STRIDE_LENGTH, HANDEDNESS, DEVICE_WEARING_POSITION, FOUR_ZONE_HEART_RATE_LIMITS,
+    HIGH_INTENSITY_EXERCISE_THRESHOLD, ACTIVITY_GOAL, SEDENTARY_INTERVAL_NOTIFICATION,
+    CALORIC_INTAKE, TMAP_ROLE, AUDIO_INPUT_STATE, GAIN_SETTINGS_ATTRIBUTE, AUDIO_INPUT_TYPE,
+    AUDIO_INPUT_STATUS, AUDIO_INPUT_CONTROL_POINT, AUDIO_INPUT_DESCRIPTION, VOLUME_STATE,
+    VOLUME_CONTROL_POINT, VOLUME_FLAGS, VOLUME_OFFSET_STATE, AUDIO_LOCATION,
+    VOLUME_OFFSET_CONTROL_POINT, AUDIO_OUTPUT_DESCRIPTION, SET_IDENTITY_RESOLVING_KEY,
+    COORDINATED_SET_SIZE, SET_MEMBER_LOCK, SET_MEMBER_RANK, ENCRYPTED_DATA_KEY_MATERIAL,
+    APPARENT_ENERGY_32, APPARENT_POWER, LIVE_HEALTH_OBSERVATIONS, CO2_CONCENTRATION,
+    COSINE_OF_THE_ANGLE, DEVICE_TIME_FEATURE, DEVICE_TIME_PARAMETERS, DEVICE_TIME,
+    DEVICE_TIME_CONTROL_POINT, TIME_CHANGE_LOG_DATA, MEDIA_PLAYER_NAME,
+    MEDIA_PLAYER_ICON_OBJECT_ID, MEDIA_PLAYER_ICON_URL, TRACK_CHANGED, TRACK_TITLE,
+    TRACK_DURATION, TRACK_POSITION, PLAYBACK_SPEED, SEEKING_SPEED,
+    CURRENT_TRACK_SEGMENTS_OBJECT_ID, CURRENT_TRACK_OBJECT_ID, NEXT_TRACK_OBJECT_ID,
+    PARENT_GROUP_OBJECT_ID, CURRENT_GROUP_OBJECT_ID, PLAYING_ORDER, PLAYING_ORDERS_SUPPORTED,
+    MEDIA_STATE, MEDIA_CONTROL_POINT, MEDIA_CONTROL_POINT_OPCODES_SUPPORTED,
+    SEARCH_RESULTS_OBJECT_ID, SEARCH_CONTROL_POINT, ENERGY_32, CONSTANT_TONE_EXTENSION_ENABLE,
+    ADVERTISING_CONSTANT_TONE_EXTENSION_MINIMUM_LENGTH,
+    ADVERTISING_CONSTANT_TONE_EXTENSION_MINIMUM_TRANSMIT_COUNT,
+    ADVERTISING_CONSTANT_TONE_EXTENSION_TRANSMIT_DURATION,
+    ADVERTISING_CONSTANT_TONE_EXTENSION_INTERVAL, ADVERTISING_CONSTANT_TONE_EXTENSION_PHY,
+    BEARER_PROVIDER_NAME, BEARER_UCI, BEARER_TECHNOLOGY, BEARER_URI_SCHEMES_SUPPORTED_LIST,
+    BEARER_SIGNAL_STRENGTH, BEARER_SIGNAL_STRENGTH_REPORTING_INTERVAL,
+    BEARER_LIST_CURRENT_CALLS, CONTENT_CONTROL_ID, STATUS_FLAGS,
+    INCOMING_CALL_TARGET_BEARER_URI, CALL_STATE, CALL_CONTROL_POINT,
+    CALL_CONTROL_POINT_OPTIONAL_OPCODES, TERMINATION_REASON, INCOMING_CALL, CALL_FRIENDLY_NAME,
+    MUTE, SINK_ASE, SOURCE_ASE, ASE_CONTROL_POINT, BROADCAST_AUDIO_SCAN_CONTROL_POINT,
+    BROADCAST_RECEIVE_STATE, SINK_PAC, SINK_AUDIO_LOCATIONS, SOURCE_PAC, SOURCE_AUDIO_LOCATIONS,
+    AVAILABLE_AUDIO_CONTEXTS, SUPPORTED_AUDIO_CONTEXTS, AMMONIA_CONCENTRATION,
+    CARBON_MONOXIDE_CONCENTRATION, METHANE_CONCENTRATION, NITROGEN_DIOXIDE_CONCENTRATION,
+    NON_METHANE_VOLATILE_ORGANIC_COMPOUNDS_CONCENTRATION, OZONE_CONCENTRATION,
+    PARTICULATE_MATTER_PM1_CONCENTRATION, PARTICULATE_MATTER_PM25_CONCENTRATION,
+    PARTICULATE_MATTER_PM10_CONCENTRATION, SULFUR_DIOXIDE_CONCENTRATION,
+    SULFUR_HEXAFLUORIDE_CONCENTRATION, HEARING_AID_FEATURES, HEARING_AID_PRESET_CONTROL_POINT,
+    ACTIVE_PRESET_INDEX, STORED_HEALTH_OBSERVATIONS, FIXED_STRING_64, HIGH_TEMPERATURE,
+    HIGH_VOLTAGE, LIGHT_DISTRIBUTION, LIGHT_OUTPUT, LIGHT_SOURCE_TYPE, NOISE,
+    RELATIVE_RUNTIME_IN_A_CORRELATED_COLOR_TEMPERATURE_RANGE, TIME_SECOND_32, VOC_CONCENTRATION,
+    VOLTAGE_FREQUENCY, BATTERY_CRITICAL_STATUS, BATTERY_HEALTH_STATUS,
+    BATTERY_HEALTH_INFORMATION, BATTERY_INFORMATION, BATTERY_LEVEL_STATUS, BATTERY_TIME_STATUS,
+    ESTIMATED_SERVICE_DATE, BATTERY_ENERGY_STATUS, OBSERVATION_SCHEDULE_CHANGED,
+    CURRENT_ELAPSED_TIME, HEALTH_SENSOR_FEATURES, GHS_CONTROL_POINT, LE_GATT_SECURITY_LEVELS,
+    ESL_ADDRESS, AP_SYNC_KEY_MATERIAL, ESL_RESPONSE_KEY_MATERIAL, ESL_CURRENT_ABSOLUTE_TIME,
+    ESL_DISPLAY_INFORMATION, ESL_IMAGE_INFORMATION, ESL_SENSOR_INFORMATION, ESL_LED_INFORMATION,
+    ESL_CONTROL_POINT, UDI_FOR_MEDICAL_DEVICES, GMAP_ROLE, UGG_FEATURES, UGT_FEATURES,
+    BGS_FEATURES, BGR_FEATURES, PERCENTAGE_8_STEPS, ACCELERATION, FORCE, LINEAR_POSITION,
+    ROTATIONAL_SPEED, LENGTH, TORQUE, IMD_STATUS, IMDS_DESCRIPTOR_VALUE_CHANGED, FIRST_USE_DATE,
+    LIFE_CYCLE_DATA, WORK_CYCLE_DATA, SERVICE_CYCLE_DATA, IMD_CONTROL, IMD_HISTORICAL_DATA,
+    RAS_FEATURES, REAL_TIME_RANGING_DATA, ON_DEMAND_RANGING_DATA, RAS_CONTROL_POINT,
+    RANGING_DATA_READY, RANGING_DATA_OVERWRITTEN,
+};
+
+#[rustfmt::skip]
+const DESCRIPTORS: &[(&str, BluetoothUuid16)] = names! { descriptors:
+    CHARACTERISTIC_EXTENDED_PROPERTIES, CHARACTERISTIC_USER_DESCRIPTION,
+    CLIENT_CHARACTERISTIC_CONFIGURATION, SERVER_CHARACTERISTIC_CONFIGURATION,
+    CHARACTERISTIC_PRESENTATION_FORMAT, CHARACTERISTIC_AGGREGATE_FORMAT, VALID_RANGE,
+    EXTERNAL_REPORT_REFERENCE, REPORT_REFERENCE, NUMBER_OF_DIGITALS, VALUE_TRIGGER_SETTING,
+    ENVIRONMENTAL_SENSING_CONFIGURATION, ENVIRONMENTAL_SENSING_MEASUREMENT,
+    ENVIRONMENTAL_SENSING_TRIGGER_SETTING, TIME_TRIGGER_SETTING,
+    COMPLETE_BR_EDR_TRANSPORT_BLOCK_DATA, OBSERVATION_SCHEDULE, VALID_RANGE_AND_ACCURACY,
+    MEASUREMENT_DESCRIPTION, MANUFACTURER_LIMITS, PROCESS_TOLERANCES, IMD_TRIGGER_SETTING,
+};
+
+#[rustfmt::skip]
+const DECLARATIONS: &[(&str, BluetoothUuid16)] = names! { declarations:
+    PRIMARY_SERVICE, SECONDARY_SERVICE, INCLUDE, CHARACTERISTIC,
+};
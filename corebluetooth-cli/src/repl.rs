@@ -0,0 +1,875 @@
+//! An interactive shell for exploring a peripheral.
+//!
+//! A [`Session`] keeps track of the most recent scan and the connected peripheral, so commands
+//! can refer to peripherals by name or by their number in the scan results, and to
+//! characteristics by name:
+//!
+//! ```text
+//! > scan
+//!   1  6b0c5c39-2f1d-4e6c-9a51-2d4f3c2b1a01   -52 dBm  Heart Sensor  services=heart_rate,battery
+//! > connect 1
+//! connected to Heart Sensor
+//! > read body_sensor_location
+//! chest
+//! > sub hr_measurement 2
+//! 72 bpm
+//! 74 bpm
+//! ```
+//!
+//! Values of well-known characteristics are shown with [`values::describe()`]. The same
+//! commands can be run non-interactively from a script, one per line, for reproducible test
+//! sessions:
+//!
+//! ```
+//! use std::time::Duration;
+//!
+//! use corebluetooth_cli::repl::Session;
+//! use corebluetooth_cli::simulated::SimulatedBackend;
+//!
+//! let backend = SimulatedBackend::demo();
+//! let mut session = Session::new(&backend, Duration::from_secs(1));
+//! let script = "
+//!     connect \"Heart Sensor\"
+//!     read battery_level
+//!     read battery_level hex
+//! ";
+//! let mut output = Vec::new();
+//! futures_lite::future::block_on(session.run_script(script, &mut output))?;
+//! assert_eq!(
+//!     String::from_utf8(output).unwrap(),
+//!     "> connect \"Heart Sensor\"\n\
+//!      connected to Heart Sensor\n\
+//!      > read battery_level\n\
+//!      87%\n\
+//!      > read battery_level hex\n\
+//!      57\n",
+//! );
+//! # Ok::<(), corebluetooth_cli::error::Error>(())
+//! ```
+
+use std::io::Write;
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use btuuid::BluetoothUuid;
+use clap::ValueEnum;
+use futures_channel::oneshot;
+use futures_lite::{StreamExt, future};
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Editor, Helper};
+use uuid::Uuid;
+
+use crate::backend::{Backend, CharacteristicRef, Discovery};
+use crate::error::{Error, Result};
+use crate::format::Format;
+use crate::names::{self, UuidKind, parse_uuid};
+use crate::run::{not_found, resolve, write_discovery, write_services};
+use crate::values;
+
+const HELP: &str = "\
+commands:
+  scan [SERVICE]...                        scan for peripherals
+  connect PERIPHERAL                       connect by number, name or identifier
+  disconnect                               disconnect from the peripheral
+  services                                 show the peripheral's GATT database
+  read CHARACTERISTIC [FORMAT]             read a characteristic
+  write CHARACTERISTIC VALUE [FORMAT]      write a characteristic
+  write-nr CHARACTERISTIC VALUE [FORMAT]   write a characteristic without response
+  sub CHARACTERISTIC [COUNT]               show notified values
+  help                                     show this message
+  quit                                     disconnect and exit
+";
+
+const COMMANDS: &[&str] = &[
+    "scan",
+    "connect",
+    "disconnect",
+    "services",
+    "read",
+    "write",
+    "write-nr",
+    "sub",
+    "subscribe",
+    "help",
+    "quit",
+    "exit",
+];
+
+/// The state of an interactive session.
+#[derive(Debug)]
+pub struct Session<'a, B> {
+    backend: &'a B,
+    timeout: Duration,
+    discoveries: Vec<Discovery>,
+    peripheral: Option<Uuid>,
+    completions: Arc<Mutex<Completions>>,
+}
+
+impl<'a, B: Backend> Session<'a, B> {
+    /// Creates a session. `timeout` limits how long scans and subscriptions run for.
+    pub fn new(backend: &'a B, timeout: Duration) -> Self {
+        Self {
+            backend,
+            timeout,
+            discoveries: Vec::new(),
+            peripheral: None,
+            completions: Arc::default(),
+        }
+    }
+
+    /// Returns the completions for this session, which are updated as peripherals are scanned
+    /// for and connected to.
+    pub fn completions(&self) -> Arc<Mutex<Completions>> {
+        self.completions.clone()
+    }
+
+    /// Runs a single command, writing its results to `output`.
+    ///
+    /// Returns `false` if the command ends the session.
+    pub async fn execute(&mut self, line: &str, output: &mut impl Write) -> Result<bool> {
+        let words = split_words(line);
+        let Some((command, args)) = words.split_first() else {
+            return Ok(true);
+        };
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+        match (command.as_str(), &args[..]) {
+            ("scan", services) => {
+                let services = services
+                    .iter()
+                    .map(|service| uuid(service, UuidKind::Service))
+                    .collect::<Result<Vec<_>>>()?;
+                self.scan(&services, output).await?;
+            }
+            ("connect", [peripheral]) => self.connect(peripheral, output).await?,
+            ("disconnect", []) => {
+                self.disconnect().await?;
+            }
+            ("services", []) => {
+                let services = self.backend.discover(self.connected()?).await?;
+                write_services(output, &services)?;
+            }
+            ("read", [characteristic, format @ ..]) if format.len() <= 1 => {
+                let characteristic = self.characteristic(characteristic)?;
+                let value = self
+                    .backend
+                    .read(characteristic)
+                    .await
+                    .map_err(|error| not_found(error, &characteristic))?;
+                writeln!(output, "{}", show(&characteristic, &value, format.first())?)?;
+            }
+            ("write" | "write-nr", [characteristic, value, format @ ..]) if format.len() <= 1 => {
+                let characteristic = self.characteristic(characteristic)?;
+                let format = match format.first() {
+                    Some(format) => parse_format(format)?,
+                    None => Format::Hex,
+                };
+                let value = format.parse(value)?;
+                self.backend
+                    .write(characteristic, &value, command == "write")
+                    .await
+                    .map_err(|error| not_found(error, &characteristic))?;
+            }
+            ("sub" | "subscribe", [characteristic, count @ ..]) if count.len() <= 1 => {
+                let characteristic = self.characteristic(characteristic)?;
+                let count = match count.first() {
+                    Some(count) => count
+                        .parse()
+                        .map_err(|_| Error::Usage(format!("invalid count {count:?}")))?,
+                    None => usize::MAX,
+                };
+                self.subscribe(characteristic, count, output).await?;
+            }
+            ("help", []) => write!(output, "{HELP}")?,
+            ("quit" | "exit", []) => return Ok(false),
+            (command, _) if COMMANDS.contains(&command) => {
+                return Err(Error::Usage(format!(
+                    "invalid arguments for {command:?}; type \"help\" for usage"
+                )));
+            }
+            (command, _) => {
+                return Err(Error::Usage(format!(
+                    "unknown command {command:?}; type \"help\" for a list of commands"
+                )));
+            }
+        }
+        output.flush()?;
+        Ok(true)
+    }
+
+    /// Runs each line of `script` as a command, stopping at the first error.
+    ///
+    /// Each command is echoed to `output` before its results. Blank lines and lines starting
+    /// with `#` are ignored.
+    pub async fn run_script(&mut self, script: &str, output: &mut impl Write) -> Result<()> {
+        for (number, line) in script.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            writeln!(output, "> {line}")?;
+            let keep_going = self
+                .execute(line, output)
+                .await
+                .map_err(|error| Error::Script {
+                    line: number + 1,
+                    error: Box::new(error),
+                })?;
+            if !keep_going {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads commands from the terminal until the user quits, with line editing, history and
+    /// tab completion.
+    ///
+    /// History is kept in `.corebluetooth-cli-history` in the user's home directory. Errors from
+    /// individual commands are written to `output` rather than ending the session.
+    pub async fn interactive(&mut self, output: &mut impl Write) -> Result<()> {
+        let mut reader = LineReader::spawn(self.completions())?;
+        loop {
+            match reader.read_line().await {
+                Ok(line) => match self.execute(&line, output).await {
+                    Ok(true) => {}
+                    Ok(false) => break,
+                    Err(error) => writeln!(output, "error: {error}")?,
+                },
+                Err(ReadlineError::Interrupted) => {}
+                Err(ReadlineError::Eof) => break,
+                Err(error) => return Err(std::io::Error::other(error).into()),
+            }
+        }
+        Ok(())
+    }
+
+    /// Disconnects from the connected peripheral, if any.
+    pub async fn disconnect(&mut self) -> Result<()> {
+        if let Some(peripheral) = self.peripheral.take() {
+            self.backend.disconnect(peripheral).await?;
+        }
+        self.completions.lock().unwrap().characteristics.clear();
+        Ok(())
+    }
+
+    async fn scan(&mut self, services: &[BluetoothUuid], output: &mut impl Write) -> Result<()> {
+        self.discoveries.clear();
+        let mut discoveries = self.backend.scan(services, false).await?;
+        let scan = async {
+            while let Some(discovery) = discoveries.next().await {
+                if self
+                    .discoveries
+                    .iter()
+                    .any(|known| known.identifier == discovery.identifier)
+                {
+                    continue;
+                }
+                write!(output, "{:>3}  ", self.discoveries.len() + 1)?;
+                write_discovery(output, &discovery)?;
+                output.flush()?;
+                self.discoveries.push(discovery);
+            }
+            Ok::<_, Error>(())
+        };
+        future::or(scan, async {
+            async_io::Timer::after(self.timeout).await;
+            Ok(())
+        })
+        .await?;
+
+        self.completions.lock().unwrap().peripherals = self
+            .discoveries
+            .iter()
+            .filter_map(|discovery| discovery.display_name().map(quote))
+            .collect();
+        Ok(())
+    }
+
+    async fn connect(&mut self, peripheral: &str, output: &mut impl Write) -> Result<()> {
+        let discovery = match peripheral.parse::<usize>() {
+            Ok(index) => Some(
+                index
+                    .checked_sub(1)
+                    .and_then(|index| self.discoveries.get(index))
+                    .ok_or_else(|| Error::PeripheralNotFound(peripheral.to_owned()))?,
+            ),
+            Err(_) => self.discoveries.iter().find(|discovery| {
+                discovery
+                    .display_name()
+                    .is_some_and(|name| name.eq_ignore_ascii_case(peripheral))
+            }),
+        };
+        let (identifier, name) = match discovery {
+            Some(discovery) => (
+                discovery.identifier,
+                discovery.display_name().map(str::to_owned),
+            ),
+            None => {
+                let identifier = resolve(self.backend, peripheral, self.timeout).await?;
+                let name = Uuid::parse_str(peripheral)
+                    .is_err()
+                    .then(|| peripheral.to_owned());
+                (identifier, name)
+            }
+        };
+
+        self.disconnect().await?;
+        self.backend.connect(identifier).await?;
+        self.peripheral = Some(identifier);
+
+        let services = self.backend.discover(identifier).await?;
+        let mut characteristics: Vec<String> = services
+            .iter()
+            .flat_map(|service| &service.characteristics)
+            .map(|characteristic| {
+                names::name(characteristic.uuid, UuidKind::Characteristic)
+                    .unwrap_or_else(|| characteristic.uuid.to_string())
+            })
+            .collect();
+        characteristics.dedup();
+        self.completions.lock().unwrap().characteristics = characteristics;
+
+        writeln!(
+            output,
+            "connected to {}",
+            name.unwrap_or_else(|| identifier.to_string())
+        )?;
+        Ok(())
+    }
+
+    async fn subscribe(
+        &self,
+        characteristic: CharacteristicRef,
+        count: usize,
+        output: &mut impl Write,
+    ) -> Result<()> {
+        let updates = self
+            .backend
+            .subscribe(characteristic)
+            .await
+            .map_err(|error| not_found(error, &characteristic))?;
+        let mut updates = updates.take(count);
+        let show_updates = async {
+            while let Some(value) = updates.next().await {
+                writeln!(output, "{}", show(&characteristic, &value?, None)?)?;
+                output.flush()?;
+            }
+            Ok::<_, Error>(())
+        };
+        future::or(show_updates, async {
+            async_io::Timer::after(self.timeout).await;
+            Ok(())
+        })
+        .await
+    }
+
+    fn connected(&self) -> Result<Uuid> {
+        self.peripheral
+            .ok_or_else(|| Error::Usage("not connected; use \"connect\" first".to_owned()))
+    }
+
+    fn characteristic(&self, characteristic: &str) -> Result<CharacteristicRef> {
+        Ok(CharacteristicRef {
+            peripheral: self.connected()?,
+            service: None,
+            characteristic: uuid(characteristic, UuidKind::Characteristic)?,
+        })
+    }
+}
+
+/// The candidates offered by tab completion.
+#[derive(Debug, Default)]
+pub struct Completions {
+    peripherals: Vec<String>,
+    characteristics: Vec<String>,
+}
+
+impl Completions {
+    /// Returns the start of the word being completed at the end of `line`, and the candidates to
+    /// replace it with.
+    ///
+    /// ```
+    /// use corebluetooth_cli::repl::Completions;
+    ///
+    /// let completions = Completions::default();
+    /// assert_eq!(completions.complete("su"), (0, vec!["sub".to_owned(), "subscribe".to_owned()]));
+    /// assert_eq!(
+    ///     completions.complete("read body_sensor"),
+    ///     (5, vec!["body_sensor_location".to_owned()]),
+    /// );
+    /// ```
+    pub fn complete(&self, line: &str) -> (usize, Vec<String>) {
+        let start = line.rfind(' ').map_or(0, |i| i + 1);
+        let prefix = line[start..].to_ascii_lowercase();
+        let words: Vec<&str> = line[..start].split_whitespace().collect();
+
+        let formats = || {
+            Format::value_variants()
+                .iter()
+                .filter_map(|format| format.to_possible_value())
+                .map(|value| value.get_name().to_owned())
+                .collect::<Vec<_>>()
+        };
+        let candidates: Vec<String> = match words[..] {
+            [] => COMMANDS.iter().map(|command| command.to_string()).collect(),
+            ["help"] => COMMANDS.iter().map(|command| command.to_string()).collect(),
+            ["scan", ..] => names::names(UuidKind::Service).collect(),
+            ["connect"] => self.peripherals.clone(),
+            ["read" | "write" | "write-nr" | "sub" | "subscribe"] => {
+                let mut candidates = self.characteristics.clone();
+                candidates.extend(
+                    names::names(UuidKind::Characteristic)
+                        .filter(|name| !self.characteristics.contains(name)),
+                );
+                candidates
+            }
+            ["read", _] | ["write" | "write-nr", _, _] => formats(),
+            _ => Vec::new(),
+        };
+        let candidates = candidates
+            .into_iter()
+            .filter(|candidate| {
+                candidate
+                    .trim_start_matches('"')
+                    .to_ascii_lowercase()
+                    .starts_with(prefix.trim_start_matches('"'))
+            })
+            .collect();
+        (start, candidates)
+    }
+}
+
+/// Reads lines from the terminal on a separate thread, so that waiting for input does not block
+/// the executor.
+struct LineReader {
+    requests: Option<mpsc::Sender<oneshot::Sender<rustyline::Result<String>>>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl LineReader {
+    fn spawn(completions: Arc<Mutex<Completions>>) -> Result<Self> {
+        let mut editor =
+            Editor::<ReplHelper, DefaultHistory>::new().map_err(std::io::Error::other)?;
+        editor.set_helper(Some(ReplHelper { completions }));
+        let history = std::env::home_dir().map(|home| home.join(".corebluetooth-cli-history"));
+        if let Some(history) = &history {
+            let _ = editor.load_history(history);
+        }
+
+        let (requests, receiver) = mpsc::channel::<oneshot::Sender<_>>();
+        let thread = std::thread::spawn(move || {
+            for reply in receiver {
+                let line = editor.readline("> ");
+                if let Ok(line) = &line {
+                    let _ = editor.add_history_entry(line.as_str());
+                }
+                let _ = reply.send(line);
+            }
+            if let Some(history) = &history {
+                let _ = editor.save_history(history);
+            }
+        });
+        Ok(Self {
+            requests: Some(requests),
+            thread: Some(thread),
+        })
+    }
+
+    async fn read_line(&mut self) -> rustyline::Result<String> {
+        let (reply, line) = oneshot::channel();
+        if let Some(requests) = &self.requests {
+            let _ = requests.send(reply);
+        }
+        line.await.unwrap_or(Err(ReadlineError::Eof))
+    }
+}
+
+impl Drop for LineReader {
+    fn drop(&mut self) {
+        // Closing the channel ends the thread, which then saves the history.
+        self.requests = None;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+struct ReplHelper {
+    completions: Arc<Mutex<Completions>>,
+}
+
+impl Completer for ReplHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &rustyline::Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        Ok(self.completions.lock().unwrap().complete(&line[..pos]))
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ReplHelper {}
+
+impl Validator for ReplHelper {}
+
+impl Helper for ReplHelper {}
+
+/// Shows a value in `format`, or with [`values::describe_or_hex()`] if no format is given.
+fn show(characteristic: &CharacteristicRef, value: &[u8], format: Option<&&str>) -> Result<String> {
+    Ok(match format {
+        Some(format) => parse_format(format)?.format(value),
+        None => values::describe_or_hex(characteristic.characteristic, value),
+    })
+}
+
+fn parse_format(format: &str) -> Result<Format> {
+    Format::from_str(format, true).map_err(|_| Error::Usage(format!("unknown format {format:?}")))
+}
+
+fn uuid(value: &str, kind: UuidKind) -> Result<BluetoothUuid> {
+    parse_uuid(value, kind).ok_or_else(|| Error::Usage(format!("unknown UUID {value:?}")))
+}
+
+fn quote(name: &str) -> String {
+    if name.contains(' ') {
+        format!("\"{name}\"")
+    } else {
+        name.to_owned()
+    }
+}
+
+/// Splits a command line into words, treating text in double quotes as a single word.
+fn split_words(line: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = None::<String>;
+    let mut quoted = false;
+    for c in line.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                word.get_or_insert_default();
+            }
+            c if c.is_whitespace() && !quoted => words.extend(word.take()),
+            c => word.get_or_insert_default().push(c),
+        }
+    }
+    words.extend(word);
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::Properties;
+    use crate::simulated::{
+        SimulatedBackend, SimulatedCharacteristic, SimulatedPeripheral, SimulatedService,
+    };
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    fn execute(session: &mut Session<'_, SimulatedBackend>, line: &str) -> Result<String> {
+        let mut output = Vec::new();
+        future::block_on(session.execute(line, &mut output))?;
+        Ok(String::from_utf8(output).unwrap())
+    }
+
+    fn run_script(
+        session: &mut Session<'_, SimulatedBackend>,
+        script: &str,
+    ) -> (Result<()>, String) {
+        let mut output = Vec::new();
+        let res = future::block_on(session.run_script(script, &mut output));
+        (res, String::from_utf8(output).unwrap())
+    }
+
+    /// The demo backend with a second peripheral, which has a vendor-specific characteristic
+    /// that can be written with and without response.
+    fn backend() -> SimulatedBackend {
+        SimulatedBackend::demo().with_peripheral(
+            SimulatedPeripheral::new(Uuid::from_u128(2), "Lamp")
+                .with_rssi(-60)
+                .with_service(
+                    SimulatedService::new(BluetoothUuid::from_u16(0xfff0)).with_characteristic(
+                        SimulatedCharacteristic::new(
+                            BluetoothUuid::from_u16(0xfff1),
+                            Properties::READ
+                                | Properties::WRITE
+                                | Properties::WRITE_WITHOUT_RESPONSE,
+                        )
+                        .with_value([0]),
+                    ),
+                ),
+        )
+    }
+
+    #[test]
+    fn words_are_split_on_unquoted_whitespace() {
+        assert_eq!(
+            split_words("  read   battery_level\thex "),
+            ["read", "battery_level", "hex"]
+        );
+        assert_eq!(
+            split_words("connect \"Heart Sensor\""),
+            ["connect", "Heart Sensor"]
+        );
+        assert_eq!(
+            split_words("write fff1 \"a  b\"c utf8"),
+            ["write", "fff1", "a  bc", "utf8"]
+        );
+        assert_eq!(
+            split_words("write fff1 \"\" utf8"),
+            ["write", "fff1", "", "utf8"]
+        );
+        // An unterminated quote runs to the end of the line.
+        assert_eq!(
+            split_words("connect \"Heart Sensor"),
+            ["connect", "Heart Sensor"]
+        );
+        assert!(split_words("   ").is_empty());
+    }
+
+    #[test]
+    fn peripherals_are_connected_by_scan_number() {
+        let backend = backend();
+        let mut session = Session::new(&backend, TIMEOUT);
+        let output = execute(&mut session, "scan").unwrap();
+        let lines: Vec<_> = output.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("  1  6b0c5c39-"), "{output}");
+        assert!(lines[1].starts_with("  2  00000000-"), "{output}");
+
+        assert_eq!(
+            execute(&mut session, "connect 2").unwrap(),
+            "connected to Lamp\n"
+        );
+        assert!(backend.is_connected(Uuid::from_u128(2)));
+        // Connecting to another peripheral disconnects from the first.
+        assert_eq!(
+            execute(&mut session, "connect 1").unwrap(),
+            "connected to Heart Sensor\n"
+        );
+        assert!(!backend.is_connected(Uuid::from_u128(2)));
+
+        for number in ["0", "3"] {
+            let res = execute(&mut session, &format!("connect {number}"));
+            assert!(
+                matches!(&res, Err(Error::PeripheralNotFound(peripheral)) if peripheral == number),
+                "{res:?}"
+            );
+        }
+        execute(&mut session, "disconnect").unwrap();
+        assert!(!backend.is_connected(Uuid::from_u128(0x6b0c_5c39_2f1d_4e6c_9a51_2d4f_3c2b_1a01)));
+    }
+
+    #[test]
+    fn peripherals_are_connected_by_name_without_scanning() {
+        let backend = backend();
+        let mut session = Session::new(&backend, TIMEOUT);
+        assert_eq!(
+            execute(&mut session, "connect \"heart sensor\"").unwrap(),
+            "connected to heart sensor\n"
+        );
+        assert_eq!(
+            execute(&mut session, "connect 00000000-0000-0000-0000-000000000002").unwrap(),
+            "connected to 00000000-0000-0000-0000-000000000002\n"
+        );
+    }
+
+    #[test]
+    fn subscriptions_stop_after_count() {
+        let backend = backend();
+        let mut session = Session::new(&backend, TIMEOUT);
+        execute(&mut session, "connect \"Heart Sensor\"").unwrap();
+        assert_eq!(
+            execute(&mut session, "sub heart_rate_measurement 2").unwrap(),
+            "72 bpm\n74 bpm\n"
+        );
+        assert_eq!(
+            execute(&mut session, "subscribe heart_rate_measurement").unwrap(),
+            "72 bpm\n74 bpm\n73 bpm\n"
+        );
+        assert_eq!(execute(&mut session, "sub battery_level 0").unwrap(), "");
+        let res = execute(&mut session, "sub heart_rate_measurement two");
+        assert!(matches!(res, Err(Error::Usage(_))), "{res:?}");
+        let res = execute(&mut session, "sub body_sensor_location 1");
+        assert!(matches!(res, Err(Error::Bluetooth(_))), "{res:?}");
+    }
+
+    #[test]
+    fn values_are_written_in_the_given_format() {
+        let backend = backend();
+        let mut session = Session::new(&backend, TIMEOUT);
+        execute(&mut session, "connect Lamp").unwrap();
+        assert_eq!(execute(&mut session, "write fff1 0x0102").unwrap(), "");
+        assert_eq!(execute(&mut session, "read fff1").unwrap(), "01 02\n");
+        execute(&mut session, "write fff1 \"513, 65535\" u16").unwrap();
+        assert_eq!(
+            execute(&mut session, "read fff1 U16").unwrap(),
+            "513 65535\n"
+        );
+        execute(&mut session, "write-nr fff1 \"on off\" utf8").unwrap();
+        assert_eq!(
+            execute(&mut session, "read fff1 utf8").unwrap(),
+            "\"on off\"\n"
+        );
+        execute(&mut session, "write-nr fff1 -1 i8").unwrap();
+        assert_eq!(execute(&mut session, "read fff1").unwrap(), "ff\n");
+
+        let res = execute(&mut session, "write fff1 256 u8");
+        assert!(matches!(res, Err(Error::InvalidValue(_))), "{res:?}");
+        let res = execute(&mut session, "write fff1 01 binary");
+        assert!(matches!(res, Err(Error::Usage(_))), "{res:?}");
+        let res = execute(&mut session, "write fff2 01");
+        assert!(
+            matches!(res, Err(Error::CharacteristicNotFound(_))),
+            "{res:?}"
+        );
+        assert_eq!(execute(&mut session, "read fff1").unwrap(), "ff\n");
+    }
+
+    #[test]
+    fn writes_without_response_need_the_property() {
+        let backend = backend();
+        let mut session = Session::new(&backend, TIMEOUT);
+        execute(&mut session, "connect \"Heart Sensor\"").unwrap();
+        execute(&mut session, "write heart_rate_control_point 01").unwrap();
+        let res = execute(&mut session, "write-nr heart_rate_control_point 01");
+        assert!(matches!(res, Err(Error::Bluetooth(_))), "{res:?}");
+    }
+
+    #[test]
+    fn commands_need_a_connection() {
+        let backend = backend();
+        let mut session = Session::new(&backend, TIMEOUT);
+        for line in [
+            "services",
+            "read battery_level",
+            "write fff1 01",
+            "sub battery_level",
+        ] {
+            let res = execute(&mut session, line);
+            assert!(matches!(res, Err(Error::Usage(_))), "{line}: {res:?}");
+        }
+        let res = execute(&mut session, "read");
+        assert!(matches!(res, Err(Error::Usage(_))), "{res:?}");
+        let res = execute(&mut session, "pair");
+        assert!(matches!(res, Err(Error::Usage(_))), "{res:?}");
+    }
+
+    fn complete(session: &Session<'_, SimulatedBackend>, line: &str) -> Vec<String> {
+        session.completions().lock().unwrap().complete(line).1
+    }
+
+    #[test]
+    fn completions_offer_discovered_names_first() {
+        let backend = backend();
+        let mut session = Session::new(&backend, TIMEOUT);
+        assert_eq!(complete(&session, "connect "), Vec::<String>::new());
+        assert_eq!(complete(&session, "scan heart"), ["heart_rate"]);
+        assert_eq!(complete(&session, "write-nr "), complete(&session, "sub "));
+        assert!(complete(&session, "read ").contains(&"battery_level".to_owned()));
+
+        execute(&mut session, "scan").unwrap();
+        assert_eq!(complete(&session, "connect "), ["\"Heart Sensor\"", "Lamp"]);
+        assert_eq!(complete(&session, "connect \"he"), ["\"Heart Sensor\""]);
+        assert_eq!(complete(&session, "connect L"), ["Lamp"]);
+
+        execute(&mut session, "connect 1").unwrap();
+        let candidates = complete(&session, "read ");
+        assert_eq!(
+            candidates[..6],
+            [
+                "heart_rate_measurement",
+                "body_sensor_location",
+                "heart_rate_control_point",
+                "battery_level",
+                "manufacturer_name_string",
+                "model_number_string",
+            ]
+        );
+        // Discovered characteristics are not offered twice.
+        let battery_level = candidates.iter().filter(|name| *name == "battery_level");
+        assert_eq!(battery_level.count(), 1);
+        assert_eq!(
+            complete(&session, "sub battery_l"),
+            ["battery_level", "battery_level_status"]
+        );
+
+        execute(&mut session, "connect 2").unwrap();
+        assert_eq!(
+            complete(&session, "write ")[0],
+            BluetoothUuid::from_u16(0xfff1).to_string()
+        );
+        assert_eq!(
+            complete(&session, "read fff1 u"),
+            ["utf8", "u8", "u16", "u32"]
+        );
+        assert_eq!(complete(&session, "write fff1 01 I"), ["i8", "i16", "i32"]);
+        assert_eq!(
+            complete(&session, "write fff1 01 hex "),
+            Vec::<String>::new()
+        );
+
+        execute(&mut session, "disconnect").unwrap();
+        assert!(
+            !complete(&session, "write ").contains(&BluetoothUuid::from_u16(0xfff1).to_string())
+        );
+    }
+
+    #[test]
+    fn scripts_stop_at_the_first_error() {
+        let backend = backend();
+        let mut session = Session::new(&backend, TIMEOUT);
+        let script = "
+            # Comments and blank lines are skipped.
+            connect Lamp
+
+            read battery_level
+            read fff1
+        ";
+        let (res, output) = run_script(&mut session, script);
+        let Err(Error::Script { line, error }) = res else {
+            panic!("unexpected result {res:?}");
+        };
+        assert_eq!(line, 5);
+        assert!(
+            matches!(*error, Error::CharacteristicNotFound(_)),
+            "{error:?}"
+        );
+        assert_eq!(
+            output,
+            "> connect Lamp\nconnected to Lamp\n> read battery_level\n"
+        );
+
+        // The session carries on from where the script stopped.
+        assert_eq!(execute(&mut session, "read fff1").unwrap(), "00\n");
+    }
+
+    #[test]
+    fn scripts_stop_at_quit() {
+        let backend = backend();
+        let mut session = Session::new(&backend, TIMEOUT);
+        let script = "connect Lamp\nwrite fff1 01\nquit\nwrite fff1 02\n";
+        let (res, output) = run_script(&mut session, script);
+        res.unwrap();
+        assert_eq!(
+            output,
+            "> connect Lamp\nconnected to Lamp\n> write fff1 01\n> quit\n"
+        );
+        assert_eq!(execute(&mut session, "read fff1").unwrap(), "01\n");
+    }
+}
@@ -0,0 +1,388 @@
+//! The JSON-RPC 2.0 protocol spoken by the [bridge][crate::bridge].
+//!
+//! # Methods
+//!
+//! | Method        | Params                                                   | Result                   |
+//! |---------------|----------------------------------------------------------|--------------------------|
+//! | `scan`        | `services`?, `allow_duplicates`?                         | `{"subscription": id}`   |
+//! | `connect`     | `peripheral`                                             | `null`                   |
+//! | `disconnect`  | `peripheral`                                             | `null`                   |
+//! | `discover`    | `peripheral`                                             | array of services        |
+//! | `read`        | `peripheral`, `service`?, `characteristic`               | `{"value": hex}`         |
+//! | `write`       | `peripheral`, `service`?, `characteristic`, `value`, `with_response`? | `null`      |
+//! | `subscribe`   | `peripheral`, `service`?, `characteristic`               | `{"subscription": id}`   |
+//! | `unsubscribe` | `subscription`                                           | `null`                   |
+//!
+//! Peripherals are identified by their identifier. UUIDs may be given in any form accepted by
+//! [`parse_uuid()`][crate::names::parse_uuid], including names such as `"battery_level"`, and
+//! are returned as lowercase 128-bit UUIDs. Values are hex strings.
+//!
+//! `scan` and `subscribe` start subscriptions, which send notifications to the client until they
+//! are ended with `unsubscribe` or the client disconnects:
+//!
+//! - `discovered` with `subscription` and `peripheral` for each peripheral found by a scan.
+//! - `value` with `subscription` and either `value` or `error` for each characteristic value.
+//! - `subscription_ended` with `subscription` when a subscription ends on its own, for example
+//!   because another scan was started.
+//!
+//! Failed Bluetooth operations return an error with code [`BLUETOOTH_ERROR`] whose `data` is the
+//! serialized [`Error`][corebluetooth_async::error::Error].
+
+use btuuid::BluetoothUuid;
+use serde::{Deserialize, Deserializer};
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+use crate::backend::{CharacteristicRef, Discovery, GattService};
+use crate::format::Format;
+use crate::names::{UuidKind, parse_uuid};
+
+/// The message was not valid JSON.
+pub const PARSE_ERROR: i64 = -32700;
+/// The message was not a valid request.
+pub const INVALID_REQUEST: i64 = -32600;
+/// The method does not exist.
+pub const METHOD_NOT_FOUND: i64 = -32601;
+/// The params were not valid for the method.
+pub const INVALID_PARAMS: i64 = -32602;
+/// A Bluetooth operation failed.
+pub const BLUETOOTH_ERROR: i64 = -32000;
+/// The subscription given to `unsubscribe` does not exist.
+pub const UNKNOWN_SUBSCRIPTION: i64 = -32001;
+
+/// A JSON-RPC error object.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct RpcError {
+    /// The error code.
+    pub code: i64,
+    /// A description of the error.
+    pub message: String,
+    /// Additional information about the error.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+impl RpcError {
+    /// Creates an error without data.
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+}
+
+impl From<corebluetooth_async::error::Error> for RpcError {
+    fn from(error: corebluetooth_async::error::Error) -> Self {
+        Self {
+            code: BLUETOOTH_ERROR,
+            message: error.to_string(),
+            data: serde_json::to_value(&error).ok(),
+        }
+    }
+}
+
+/// A method call and its parameters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Method {
+    /// Starts a scan.
+    Scan {
+        /// The services to scan for, or all peripherals if empty.
+        services: Vec<BluetoothUuid>,
+        /// Whether to report every advertisement.
+        allow_duplicates: bool,
+    },
+    /// Connects to a peripheral.
+    Connect(Uuid),
+    /// Disconnects from a peripheral.
+    Disconnect(Uuid),
+    /// Discovers the GATT database of a peripheral.
+    Discover(Uuid),
+    /// Reads a characteristic.
+    Read(CharacteristicRef),
+    /// Writes a characteristic.
+    Write {
+        /// The characteristic.
+        characteristic: CharacteristicRef,
+        /// The value to write.
+        value: Vec<u8>,
+        /// Whether to write with response.
+        with_response: bool,
+    },
+    /// Subscribes to a characteristic's values.
+    Subscribe(CharacteristicRef),
+    /// Ends a subscription.
+    Unsubscribe(u64),
+}
+
+/// A request from a client.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Request {
+    /// The request id, or `None` for a notification, which has no response.
+    pub id: Option<Value>,
+    /// The method to call.
+    pub method: Method,
+}
+
+impl Request {
+    /// Parses a request.
+    ///
+    /// On failure, returns the id of the request, if it could be determined, along with the
+    /// error to respond with.
+    ///
+    /// ```
+    /// use corebluetooth_cli::rpc::{Method, Request};
+    ///
+    /// let request = Request::parse(r#"{"jsonrpc": "2.0", "id": 1, "method": "scan"}"#).unwrap();
+    /// assert_eq!(request.id, Some(1.into()));
+    /// assert_eq!(request.method, Method::Scan { services: vec![], allow_duplicates: false });
+    /// ```
+    pub fn parse(message: &str) -> Result<Request, (Option<Value>, RpcError)> {
+        #[derive(Deserialize)]
+        struct Envelope {
+            jsonrpc: String,
+            id: Option<Value>,
+            method: String,
+            #[serde(default)]
+            params: Value,
+        }
+
+        let value: Value = serde_json::from_str(message)
+            .map_err(|error| (None, RpcError::new(PARSE_ERROR, error.to_string())))?;
+        let id = value.get("id").cloned();
+        let envelope: Envelope = serde_json::from_value(value).map_err(|error| {
+            (
+                id.clone(),
+                RpcError::new(INVALID_REQUEST, error.to_string()),
+            )
+        })?;
+        if envelope.jsonrpc != "2.0" {
+            return Err((
+                envelope.id,
+                RpcError::new(INVALID_REQUEST, "unsupported JSON-RPC version"),
+            ));
+        }
+
+        let params = match envelope.params {
+            Value::Null => Value::Object(Default::default()),
+            params => params,
+        };
+        Method::parse(&envelope.method, params)
+            .map(|method| Request {
+                id: envelope.id.clone(),
+                method,
+            })
+            .map_err(|error| (envelope.id, error))
+    }
+}
+
+impl Method {
+    fn parse(method: &str, params: Value) -> Result<Method, RpcError> {
+        fn decode<T: serde::de::DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+            T::deserialize(params).map_err(|error| RpcError::new(INVALID_PARAMS, error.to_string()))
+        }
+
+        Ok(match method {
+            "scan" => {
+                let ScanParams {
+                    services,
+                    allow_duplicates,
+                } = decode(params)?;
+                Method::Scan {
+                    services,
+                    allow_duplicates,
+                }
+            }
+            "connect" => Method::Connect(decode::<PeripheralParams>(params)?.peripheral),
+            "disconnect" => Method::Disconnect(decode::<PeripheralParams>(params)?.peripheral),
+            "discover" => Method::Discover(decode::<PeripheralParams>(params)?.peripheral),
+            "read" => Method::Read(decode::<CharacteristicParams>(params)?.into()),
+            "write" => {
+                let WriteParams {
+                    characteristic,
+                    value,
+                    with_response,
+                } = decode(params)?;
+                Method::Write {
+                    characteristic: characteristic.into(),
+                    value,
+                    with_response,
+                }
+            }
+            "subscribe" => Method::Subscribe(decode::<CharacteristicParams>(params)?.into()),
+            "unsubscribe" => Method::Unsubscribe(decode::<UnsubscribeParams>(params)?.subscription),
+            method => {
+                return Err(RpcError::new(
+                    METHOD_NOT_FOUND,
+                    format!("unknown method {method:?}"),
+                ));
+            }
+        })
+    }
+}
+
+/// Serializes a response.
+///
+/// ```
+/// use corebluetooth_cli::rpc::response;
+///
+/// assert_eq!(
+///     response(1.into(), Ok(serde_json::Value::Null)),
+///     r#"{"id":1,"jsonrpc":"2.0","result":null}"#,
+/// );
+/// ```
+pub fn response(id: Value, result: Result<Value, RpcError>) -> String {
+    match result {
+        Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+        Err(error) => json!({"jsonrpc": "2.0", "id": id, "error": error}),
+    }
+    .to_string()
+}
+
+/// Serializes a notification.
+pub fn notification(method: &str, params: Value) -> String {
+    json!({"jsonrpc": "2.0", "method": method, "params": params}).to_string()
+}
+
+/// Converts a discovered peripheral to JSON.
+pub fn discovery(discovery: &Discovery) -> Value {
+    json!({
+        "identifier": discovery.identifier,
+        "name": discovery.name,
+        "rssi": discovery.rssi,
+        "local_name": discovery.local_name,
+        "service_uuids": discovery.service_uuids.iter().copied().map(uuid).collect::<Vec<_>>(),
+        "manufacturer_data": discovery.manufacturer_data.as_ref().map(|(company_id, data)| {
+            json!({"company_id": company_id, "data": hex(data)})
+        }),
+        "tx_power_level": discovery.tx_power_level,
+        "is_connectable": discovery.is_connectable,
+    })
+}
+
+/// Converts a GATT database to JSON.
+pub fn services(services: &[GattService]) -> Value {
+    services
+        .iter()
+        .map(|service| {
+            let characteristics: Vec<_> = service
+                .characteristics
+                .iter()
+                .map(|characteristic| {
+                    json!({
+                        "uuid": uuid(characteristic.uuid),
+                        "properties": characteristic.properties.names().collect::<Vec<_>>(),
+                        "descriptors": characteristic
+                            .descriptors
+                            .iter()
+                            .copied()
+                            .map(uuid)
+                            .collect::<Vec<_>>(),
+                    })
+                })
+                .collect();
+            json!({
+                "uuid": uuid(service.uuid),
+                "is_primary": service.is_primary,
+                "characteristics": characteristics,
+            })
+        })
+        .collect()
+}
+
+/// Formats a value as a hex string.
+pub fn hex(value: &[u8]) -> String {
+    value.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn uuid(uuid: BluetoothUuid) -> String {
+    Uuid::from(uuid).to_string()
+}
+
+#[derive(Deserialize)]
+struct ScanParams {
+    #[serde(default, deserialize_with = "service_uuids")]
+    services: Vec<BluetoothUuid>,
+    #[serde(default)]
+    allow_duplicates: bool,
+}
+
+#[derive(Deserialize)]
+struct PeripheralParams {
+    peripheral: Uuid,
+}
+
+#[derive(Deserialize)]
+struct CharacteristicParams {
+    peripheral: Uuid,
+    #[serde(default, deserialize_with = "service_uuid")]
+    service: Option<BluetoothUuid>,
+    #[serde(deserialize_with = "characteristic_uuid")]
+    characteristic: BluetoothUuid,
+}
+
+impl From<CharacteristicParams> for CharacteristicRef {
+    fn from(params: CharacteristicParams) -> Self {
+        CharacteristicRef {
+            peripheral: params.peripheral,
+            service: params.service,
+            characteristic: params.characteristic,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct WriteParams {
+    #[serde(flatten)]
+    characteristic: CharacteristicParams,
+    #[serde(deserialize_with = "hex_value")]
+    value: Vec<u8>,
+    #[serde(default = "with_response")]
+    with_response: bool,
+}
+
+#[derive(Deserialize)]
+struct UnsubscribeParams {
+    subscription: u64,
+}
+
+fn with_response() -> bool {
+    true
+}
+
+fn parse<E: serde::de::Error>(value: &str, kind: UuidKind) -> Result<BluetoothUuid, E> {
+    parse_uuid(value, kind).ok_or_else(|| E::custom(format!("invalid UUID {value:?}")))
+}
+
+fn service_uuids<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<BluetoothUuid>, D::Error> {
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|value| parse(value, UuidKind::Service))
+        .collect()
+}
+
+fn service_uuid<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<BluetoothUuid>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+        .map(|value| parse(&value, UuidKind::Service))
+        .transpose()
+}
+
+fn characteristic_uuid<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<BluetoothUuid, D::Error> {
+    parse(
+        &String::deserialize(deserializer)?,
+        UuidKind::Characteristic,
+    )
+}
+
+fn hex_value<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+    Format::Hex
+        .parse(&String::deserialize(deserializer)?)
+        .map_err(serde::de::Error::custom)
+}
@@ -0,0 +1,562 @@
+//! Running commands against a [`Backend`].
+
+use std::collections::HashSet;
+use std::io::Write;
+use std::time::Duration;
+
+use btuuid::BluetoothUuid;
+use corebluetooth_async::error::ErrorKind;
+use futures_io::AsyncRead;
+use futures_lite::{AsyncReadExt, AsyncWriteExt, StreamExt, future};
+use uuid::Uuid;
+
+use crate::backend::{Backend, CharacteristicRef, Discovery, GattService};
+use crate::cli::{CharacteristicArgs, Cli, Command, ScanArgs};
+use crate::error::{Error, Result};
+use crate::names::{UuidKind, describe, name};
+use crate::repl::Session;
+
+/// Runs the command given by `cli`.
+///
+/// `input` is only used by the `l2cap` command; the interactive shell started by `repl` reads
+/// from the terminal. Results are written to `output` as they become
+/// available.
+pub async fn run<B: Backend>(
+    backend: &B,
+    cli: &Cli,
+    input: impl AsyncRead + Unpin,
+    output: &mut impl Write,
+) -> Result<()> {
+    backend.wait_until_ready().await?;
+
+    match &cli.command {
+        Command::Scan(args) => scan(backend, args, cli.timeout, output).await,
+        Command::Connect { peripheral } => {
+            let identifier = resolve(backend, peripheral, cli.timeout).await?;
+            connected(backend, identifier, async || {
+                writeln!(output, "connected to {identifier}")?;
+                Ok(())
+            })
+            .await
+        }
+        Command::Gatt { peripheral } => {
+            let identifier = resolve(backend, peripheral, cli.timeout).await?;
+            let services = connected(backend, identifier, async || {
+                Ok(backend.discover(identifier).await?)
+            })
+            .await?;
+            write_services(output, &services)?;
+            Ok(())
+        }
+        Command::Read(args) => {
+            let characteristic = characteristic(backend, &args.characteristic, cli.timeout).await?;
+            let value = connected(backend, characteristic.peripheral, async || {
+                backend
+                    .read(characteristic)
+                    .await
+                    .map_err(|error| not_found(error, &characteristic))
+            })
+            .await?;
+            writeln!(output, "{}", args.format.format(&value))?;
+            Ok(())
+        }
+        Command::Write(args) => {
+            let value = args.format.parse(&args.value)?;
+            let characteristic = characteristic(backend, &args.characteristic, cli.timeout).await?;
+            connected(backend, characteristic.peripheral, async || {
+                backend
+                    .write(characteristic, &value, !args.without_response)
+                    .await
+                    .map_err(|error| not_found(error, &characteristic))
+            })
+            .await
+        }
+        Command::Subscribe(args) => {
+            let characteristic = characteristic(backend, &args.characteristic, cli.timeout).await?;
+            connected(backend, characteristic.peripheral, async || {
+                let updates = backend
+                    .subscribe(characteristic)
+                    .await
+                    .map_err(|error| not_found(error, &characteristic))?;
+                let mut updates = updates.take(args.count.unwrap_or(usize::MAX));
+                while let Some(value) = updates.next().await {
+                    writeln!(output, "{}", args.format.format(&value?))?;
+                    output.flush()?;
+                }
+                Ok(())
+            })
+            .await
+        }
+        Command::Repl { script } => {
+            let mut session = Session::new(backend, cli.timeout);
+            let res = match script {
+                Some(script) => {
+                    let script = std::fs::read_to_string(script)?;
+                    session.run_script(&script, output).await
+                }
+                None => session.interactive(output).await,
+            };
+            session.disconnect().await?;
+            res
+        }
+        Command::L2cap { peripheral, psm } => {
+            let identifier = resolve(backend, peripheral, cli.timeout).await?;
+            connected(backend, identifier, async || {
+                let stream = backend.open_l2cap(identifier, *psm).await?;
+                let (mut reader, mut writer) = futures_lite::io::split(stream);
+                let send = async {
+                    futures_lite::io::copy(input, &mut writer).await?;
+                    writer.close().await
+                };
+                let receive = async {
+                    let mut buf = [0; 1024];
+                    loop {
+                        let len = reader.read(&mut buf).await?;
+                        if len == 0 {
+                            return Ok(());
+                        }
+                        output.write_all(&buf[..len])?;
+                        output.flush()?;
+                    }
+                };
+                future::try_zip(send, receive).await?;
+                Ok(())
+            })
+            .await
+        }
+    }
+}
+
+async fn scan<B: Backend>(
+    backend: &B,
+    args: &ScanArgs,
+    timeout: Duration,
+    output: &mut impl Write,
+) -> Result<()> {
+    let mut discoveries = backend.scan(&args.services, args.duplicates).await?;
+    let mut seen = HashSet::new();
+    let scan = async {
+        while let Some(discovery) = discoveries.next().await {
+            if args
+                .min_rssi
+                .is_some_and(|min_rssi| discovery.rssi < min_rssi)
+            {
+                continue;
+            }
+            if let Some(filter) = &args.name
+                && !discovery
+                    .display_name()
+                    .is_some_and(|name| name.to_lowercase().contains(&filter.to_lowercase()))
+            {
+                continue;
+            }
+            if !args.duplicates && !seen.insert(discovery.identifier) {
+                continue;
+            }
+            write_discovery(output, &discovery)?;
+            output.flush()?;
+        }
+        Ok(())
+    };
+    future::or(scan, async {
+        async_io::Timer::after(timeout).await;
+        Ok(())
+    })
+    .await
+}
+
+/// Resolves a peripheral given by identifier or by name.
+///
+/// Names are matched exactly, ignoring case, against peripherals found by scanning for up to
+/// `timeout`.
+pub(crate) async fn resolve<B: Backend>(
+    backend: &B,
+    peripheral: &str,
+    timeout: Duration,
+) -> Result<Uuid> {
+    if let Ok(identifier) = Uuid::parse_str(peripheral) {
+        return Ok(identifier);
+    }
+
+    let mut discoveries = backend.scan(&[], false).await?;
+    let find = async {
+        while let Some(discovery) = discoveries.next().await {
+            if discovery
+                .display_name()
+                .is_some_and(|name| name.eq_ignore_ascii_case(peripheral))
+            {
+                return Some(discovery.identifier);
+            }
+        }
+        None
+    };
+    let identifier = future::or(find, async {
+        async_io::Timer::after(timeout).await;
+        None
+    })
+    .await;
+    identifier.ok_or_else(|| Error::PeripheralNotFound(peripheral.to_owned()))
+}
+
+async fn characteristic<B: Backend>(
+    backend: &B,
+    args: &CharacteristicArgs,
+    timeout: Duration,
+) -> Result<CharacteristicRef> {
+    Ok(CharacteristicRef {
+        peripheral: resolve(backend, &args.peripheral, timeout).await?,
+        service: args.service,
+        characteristic: args.characteristic,
+    })
+}
+
+/// Connects to `peripheral`, runs `f`, and then disconnects whether or not `f` succeeded.
+async fn connected<B: Backend, T>(
+    backend: &B,
+    peripheral: Uuid,
+    f: impl AsyncFnOnce() -> Result<T>,
+) -> Result<T> {
+    backend.connect(peripheral).await?;
+    let res = f().await;
+    backend.disconnect(peripheral).await?;
+    res
+}
+
+pub(crate) fn not_found(
+    error: corebluetooth_async::error::Error,
+    characteristic: &CharacteristicRef,
+) -> Error {
+    if error.kind() == ErrorKind::NotFound {
+        Error::CharacteristicNotFound(describe(
+            characteristic.characteristic,
+            UuidKind::Characteristic,
+        ))
+    } else {
+        error.into()
+    }
+}
+
+fn short_name(uuid: BluetoothUuid, kind: UuidKind) -> String {
+    name(uuid, kind).unwrap_or_else(|| uuid.to_string())
+}
+
+pub(crate) fn write_discovery(
+    output: &mut impl Write,
+    discovery: &Discovery,
+) -> std::io::Result<()> {
+    write!(
+        output,
+        "{}  {:>4} dBm  {}",
+        discovery.identifier,
+        discovery.rssi,
+        discovery.display_name().unwrap_or("(unnamed)")
+    )?;
+    if !discovery.service_uuids.is_empty() {
+        let services: Vec<_> = discovery
+            .service_uuids
+            .iter()
+            .map(|uuid| short_name(*uuid, UuidKind::Service))
+            .collect();
+        write!(output, "  services={}", services.join(","))?;
+    }
+    if let Some((company_id, data)) = &discovery.manufacturer_data {
+        let data: String = data.iter().map(|byte| format!("{byte:02x}")).collect();
+        write!(output, "  manufacturer={company_id:04x}:{data}")?;
+    }
+    if let Some(tx_power_level) = discovery.tx_power_level {
+        write!(output, "  tx_power={tx_power_level}")?;
+    }
+    writeln!(output)
+}
+
+pub(crate) fn write_services(
+    output: &mut impl Write,
+    services: &[GattService],
+) -> std::io::Result<()> {
+    for service in services {
+        let kind = if service.is_primary {
+            "service"
+        } else {
+            "secondary service"
+        };
+        writeln!(
+            output,
+            "{kind} {}",
+            describe(service.uuid, UuidKind::Service)
+        )?;
+        for characteristic in &service.characteristics {
+            writeln!(
+                output,
+                "  characteristic {} [{}]",
+                describe(characteristic.uuid, UuidKind::Characteristic),
+                characteristic.properties
+            )?;
+            for descriptor in &characteristic.descriptors {
+                writeln!(
+                    output,
+                    "    descriptor {}",
+                    describe(*descriptor, UuidKind::Descriptor)
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use btuuid::service;
+    use clap::Parser;
+
+    use super::*;
+    use crate::backend::Properties;
+    use crate::simulated::{
+        SimulatedBackend, SimulatedCharacteristic, SimulatedPeripheral, SimulatedService,
+    };
+
+    const HEART_SENSOR: Uuid = Uuid::from_u128(0x6b0c_5c39_2f1d_4e6c_9a51_2d4f_3c2b_1a01);
+
+    fn run_args(backend: &SimulatedBackend, args: &[&str], input: &[u8]) -> (Result<()>, String) {
+        let cli = Cli::parse_from(["corebluetooth-cli", "--simulate"].iter().chain(args));
+        let mut output = Vec::new();
+        let res = future::block_on(run(backend, &cli, input, &mut output));
+        (res, String::from_utf8(output).unwrap())
+    }
+
+    fn run_ok(backend: &SimulatedBackend, args: &[&str]) -> String {
+        let (res, output) = run_args(backend, args, b"");
+        res.unwrap();
+        output
+    }
+
+    fn peripheral(id: u128, name: &str, rssi: i16) -> SimulatedPeripheral {
+        SimulatedPeripheral::new(Uuid::from_u128(id), name).with_rssi(rssi)
+    }
+
+    fn names(output: &str) -> Vec<&str> {
+        output
+            .lines()
+            .map(|line| {
+                line.split("dBm  ")
+                    .nth(1)
+                    .unwrap()
+                    .split("  ")
+                    .next()
+                    .unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn scan_shows_advertisements() {
+        let backend = SimulatedBackend::new().with_peripheral(
+            peripheral(1, "Thermometer", -48)
+                .with_service(SimulatedService::new(service::HEALTH_THERMOMETER.into()))
+                .with_manufacturer_data(0x004c, [0x02, 0x15]),
+        );
+        assert_eq!(
+            run_ok(&backend, &["scan"]),
+            "00000000-0000-0000-0000-000000000001   -48 dBm  Thermometer  \
+             services=health_thermometer  manufacturer=004c:0215\n"
+        );
+    }
+
+    #[test]
+    fn scan_filters_by_name_ignoring_case() {
+        let backend = SimulatedBackend::new()
+            .with_peripheral(peripheral(1, "Thermometer", -50))
+            .with_peripheral(peripheral(2, "Heart Sensor", -50))
+            .with_peripheral(peripheral(3, "Room thermostat", -50));
+        let output = run_ok(&backend, &["scan", "--name", "THERM"]);
+        assert_eq!(names(&output), ["Thermometer", "Room thermostat"]);
+    }
+
+    #[test]
+    fn scan_filters_by_rssi() {
+        let backend = SimulatedBackend::new()
+            .with_peripheral(peripheral(1, "Near", -40))
+            .with_peripheral(peripheral(2, "Edge", -70))
+            .with_peripheral(peripheral(3, "Far", -90));
+        let output = run_ok(&backend, &["scan", "--min-rssi", "-70"]);
+        assert_eq!(names(&output), ["Near", "Edge"]);
+    }
+
+    #[test]
+    fn scan_filters_by_service() {
+        let output = run_ok(&SimulatedBackend::demo(), &["scan", "--service", "battery"]);
+        assert_eq!(names(&output), ["Heart Sensor"]);
+        let output = run_ok(&SimulatedBackend::demo(), &["scan", "--service", "glucose"]);
+        assert_eq!(output, "");
+    }
+
+    #[test]
+    fn scan_only_shows_duplicates_when_asked() {
+        // The same peripheral advertising twice.
+        let backend = SimulatedBackend::new()
+            .with_peripheral(peripheral(1, "Beacon", -50))
+            .with_peripheral(peripheral(1, "Beacon", -55));
+        assert_eq!(names(&run_ok(&backend, &["scan"])), ["Beacon"]);
+        assert_eq!(
+            names(&run_ok(&backend, &["scan", "--duplicates"])),
+            ["Beacon", "Beacon"]
+        );
+    }
+
+    #[test]
+    fn gatt_lists_services_characteristics_and_descriptors() {
+        let backend = SimulatedBackend::demo();
+        assert_eq!(
+            run_ok(&backend, &["gatt", "Heart Sensor"]),
+            "service 0x180D (heart_rate)
+  characteristic 0x2A37 (heart_rate_measurement) [notify]
+    descriptor 0x2902 (client_characteristic_configuration)
+  characteristic 0x2A38 (body_sensor_location) [read]
+  characteristic 0x2A39 (heart_rate_control_point) [write]
+service 0x180F (battery)
+  characteristic 0x2A19 (battery_level) [read|notify]
+    descriptor 0x2902 (client_characteristic_configuration)
+service 0x180A (device_information)
+  characteristic 0x2A29 (manufacturer_name_string) [read]
+  characteristic 0x2A24 (model_number_string) [read]
+"
+        );
+        assert!(!backend.is_connected(HEART_SENSOR));
+    }
+
+    #[test]
+    fn written_values_can_be_read() {
+        let backend =
+            SimulatedBackend::new().with_peripheral(peripheral(1, "Lamp", -50).with_service(
+                SimulatedService::new(BluetoothUuid::from_u16(0xfff0)).with_characteristic(
+                    SimulatedCharacteristic::new(
+                        BluetoothUuid::from_u16(0xfff1),
+                        Properties::READ | Properties::WRITE,
+                    ),
+                ),
+            ));
+        run_ok(
+            &backend,
+            &["write", "Lamp", "fff1", "513,65535", "--format", "u16"],
+        );
+        assert_eq!(run_ok(&backend, &["read", "Lamp", "fff1"]), "01 02 ff ff\n");
+        run_ok(
+            &backend,
+            &["write", "Lamp", "fff1", "hi", "--format", "utf8"],
+        );
+        assert_eq!(
+            run_ok(&backend, &["read", "Lamp", "fff1", "--format", "utf8"]),
+            "\"hi\"\n"
+        );
+    }
+
+    #[test]
+    fn writes_without_response_need_the_property() {
+        let backend = SimulatedBackend::demo();
+        let args = [
+            "write",
+            "Heart Sensor",
+            "heart_rate_control_point",
+            "01",
+            "--without-response",
+        ];
+        let (res, _) = run_args(&backend, &args, b"");
+        let Err(Error::Bluetooth(error)) = res else {
+            panic!("unexpected result {res:?}");
+        };
+        assert_eq!(
+            error.kind(),
+            ErrorKind::ATT(corebluetooth_async::error::CBATTError::WriteNotPermitted)
+        );
+        assert!(!backend.is_connected(HEART_SENSOR));
+    }
+
+    #[test]
+    fn invalid_values_are_rejected_before_connecting() {
+        let backend = SimulatedBackend::demo();
+        let args = ["write", "Heart Sensor", "heart_rate_control_point", "0x1"];
+        let (res, _) = run_args(&backend, &args, b"");
+        assert!(matches!(res, Err(Error::InvalidValue(_))), "{res:?}");
+    }
+
+    #[test]
+    fn subscribe_stops_after_count() {
+        let backend = SimulatedBackend::demo();
+        let args = [
+            "subscribe",
+            "Heart Sensor",
+            "heart_rate_measurement",
+            "--count",
+            "2",
+            "--format",
+            "u8",
+        ];
+        assert_eq!(run_ok(&backend, &args), "0 72\n0 74\n");
+
+        let args = ["subscribe", "Heart Sensor", "heart_rate_measurement"];
+        assert_eq!(run_ok(&backend, &args), "00 48\n00 4a\n00 49\n");
+        assert!(!backend.is_connected(HEART_SENSOR));
+    }
+
+    #[test]
+    fn l2cap_pipes_input_through_the_channel() {
+        let backend = SimulatedBackend::demo();
+        let (res, output) = run_args(&backend, &["l2cap", "Heart Sensor", "0x80"], b"hello\n");
+        res.unwrap();
+        assert_eq!(output, "hello\n");
+
+        let (res, _) = run_args(&backend, &["l2cap", "Heart Sensor", "129"], b"");
+        assert!(matches!(res, Err(Error::Bluetooth(_))), "{res:?}");
+    }
+
+    #[test]
+    fn peripherals_are_resolved_by_name_or_identifier() {
+        let backend = SimulatedBackend::demo();
+        let args = ["read", "heart sensor", "battery_level", "--format", "u8"];
+        assert_eq!(run_ok(&backend, &args), "87\n");
+        let identifier = HEART_SENSOR.to_string();
+        let args = ["read", &identifier, "battery_level", "--format", "u8"];
+        assert_eq!(run_ok(&backend, &args), "87\n");
+    }
+
+    #[test]
+    fn unknown_peripherals_are_not_found() {
+        let backend = SimulatedBackend::demo();
+        // Names are matched exactly, not by prefix.
+        let (res, _) = run_args(&backend, &["read", "Heart", "battery_level"], b"");
+        assert!(
+            matches!(&res, Err(Error::PeripheralNotFound(name)) if name == "Heart"),
+            "{res:?}"
+        );
+    }
+
+    #[test]
+    fn unknown_characteristics_are_not_found() {
+        let backend = SimulatedBackend::demo();
+        let (res, _) = run_args(
+            &backend,
+            &["read", "Heart Sensor", "temperature_measurement"],
+            b"",
+        );
+        assert!(
+            matches!(&res, Err(Error::CharacteristicNotFound(name)) if name.contains("temperature_measurement")),
+            "{res:?}"
+        );
+        // The characteristic exists, but not in the given service.
+        let (res, _) = run_args(
+            &backend,
+            &[
+                "read",
+                "Heart Sensor",
+                "battery_level",
+                "--service",
+                "heart_rate",
+            ],
+            b"",
+        );
+        assert!(
+            matches!(res, Err(Error::CharacteristicNotFound(_))),
+            "{res:?}"
+        );
+        assert!(!backend.is_connected(HEART_SENSOR));
+    }
+}
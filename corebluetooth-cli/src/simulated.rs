@@ -0,0 +1,490 @@
+//! A [`Backend`] that simulates peripherals in memory.
+//!
+//! The simulated backend is used by `--simulate` and lets commands be exercised on platforms
+//! without CoreBluetooth. Peripherals are described with a builder-style API:
+//!
+//! ```
+//! use btuuid::{characteristic, service};
+//! use corebluetooth_cli::backend::Properties;
+//! use corebluetooth_cli::simulated::{
+//!     SimulatedBackend, SimulatedCharacteristic, SimulatedPeripheral, SimulatedService,
+//! };
+//! use uuid::Uuid;
+//!
+//! let backend = SimulatedBackend::new().with_peripheral(
+//!     SimulatedPeripheral::new(Uuid::from_u128(1), "Thermometer").with_service(
+//!         SimulatedService::new(service::BATTERY.into()).with_characteristic(
+//!             SimulatedCharacteristic::new(characteristic::BATTERY_LEVEL.into(), Properties::READ)
+//!                 .with_value([100]),
+//!         ),
+//!     ),
+//! );
+//! ```
+
+use std::cell::RefCell;
+use std::collections::{HashSet, VecDeque};
+use std::io;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
+
+use btuuid::{BluetoothUuid, characteristic, descriptors, service};
+use corebluetooth_async::error::{CBATTError, CBError, ErrorKind, Result};
+use futures_io::{AsyncRead, AsyncWrite};
+use uuid::Uuid;
+
+use crate::backend::{
+    Backend, CharacteristicRef, Discovery, GattCharacteristic, GattService, LocalStream, Properties,
+};
+
+/// A simulated characteristic.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimulatedCharacteristic {
+    uuid: BluetoothUuid,
+    properties: Properties,
+    descriptors: Vec<BluetoothUuid>,
+    value: Vec<u8>,
+    notifications: Vec<Vec<u8>>,
+}
+
+impl SimulatedCharacteristic {
+    /// Creates a characteristic with an empty value.
+    ///
+    /// Characteristics that can notify or indicate are given a client characteristic
+    /// configuration descriptor.
+    pub fn new(uuid: BluetoothUuid, properties: Properties) -> Self {
+        let descriptors = if properties.contains(Properties::NOTIFY)
+            || properties.contains(Properties::INDICATE)
+        {
+            vec![descriptors::CLIENT_CHARACTERISTIC_CONFIGURATION.into()]
+        } else {
+            Vec::new()
+        };
+        Self {
+            uuid,
+            properties,
+            descriptors,
+            value: Vec::new(),
+            notifications: Vec::new(),
+        }
+    }
+
+    /// Sets the value of the characteristic.
+    pub fn with_value(mut self, value: impl Into<Vec<u8>>) -> Self {
+        self.value = value.into();
+        self
+    }
+
+    /// Adds a value that is delivered to subscribers, in order, after they subscribe.
+    pub fn with_notification(mut self, value: impl Into<Vec<u8>>) -> Self {
+        self.notifications.push(value.into());
+        self
+    }
+
+    /// Adds a descriptor.
+    pub fn with_descriptor(mut self, uuid: BluetoothUuid) -> Self {
+        self.descriptors.push(uuid);
+        self
+    }
+}
+
+/// A simulated service.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimulatedService {
+    uuid: BluetoothUuid,
+    is_primary: bool,
+    characteristics: Vec<SimulatedCharacteristic>,
+}
+
+impl SimulatedService {
+    /// Creates a primary service with no characteristics.
+    pub fn new(uuid: BluetoothUuid) -> Self {
+        Self {
+            uuid,
+            is_primary: true,
+            characteristics: Vec::new(),
+        }
+    }
+
+    /// Adds a characteristic.
+    pub fn with_characteristic(mut self, characteristic: SimulatedCharacteristic) -> Self {
+        self.characteristics.push(characteristic);
+        self
+    }
+}
+
+/// A simulated peripheral.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimulatedPeripheral {
+    identifier: Uuid,
+    name: String,
+    rssi: i16,
+    manufacturer_data: Option<(u16, Vec<u8>)>,
+    services: Vec<SimulatedService>,
+    l2cap_psms: Vec<u16>,
+}
+
+impl SimulatedPeripheral {
+    /// Creates a peripheral with no services.
+    pub fn new(identifier: Uuid, name: impl Into<String>) -> Self {
+        Self {
+            identifier,
+            name: name.into(),
+            rssi: -60,
+            manufacturer_data: None,
+            services: Vec::new(),
+            l2cap_psms: Vec::new(),
+        }
+    }
+
+    /// Sets the RSSI reported when the peripheral is discovered.
+    pub fn with_rssi(mut self, rssi: i16) -> Self {
+        self.rssi = rssi;
+        self
+    }
+
+    /// Sets the manufacturer specific data included in the peripheral's advertisement.
+    pub fn with_manufacturer_data(mut self, company_id: u16, data: impl Into<Vec<u8>>) -> Self {
+        self.manufacturer_data = Some((company_id, data.into()));
+        self
+    }
+
+    /// Adds a service. Primary services are included in the peripheral's advertisement.
+    pub fn with_service(mut self, service: SimulatedService) -> Self {
+        self.services.push(service);
+        self
+    }
+
+    /// Accepts L2CAP connections on `psm`, echoing back everything written to the channel.
+    pub fn with_l2cap_echo(mut self, psm: u16) -> Self {
+        self.l2cap_psms.push(psm);
+        self
+    }
+
+    fn discovery(&self) -> Discovery {
+        Discovery {
+            identifier: self.identifier,
+            name: Some(self.name.clone()),
+            rssi: self.rssi,
+            local_name: Some(self.name.clone()),
+            service_uuids: self
+                .services
+                .iter()
+                .filter(|service| service.is_primary)
+                .map(|service| service.uuid)
+                .collect(),
+            manufacturer_data: self.manufacturer_data.clone(),
+            tx_power_level: None,
+            is_connectable: true,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    peripherals: Vec<SimulatedPeripheral>,
+    connected: HashSet<Uuid>,
+}
+
+impl State {
+    fn connected(&mut self, identifier: Uuid) -> Result<&mut SimulatedPeripheral> {
+        if !self.connected.contains(&identifier) {
+            return Err(ErrorKind::Bluetooth(CBError::NotConnected).into());
+        }
+        self.peripherals
+            .iter_mut()
+            .find(|peripheral| peripheral.identifier == identifier)
+            .ok_or_else(|| ErrorKind::NotFound.into())
+    }
+
+    fn characteristic(
+        &mut self,
+        characteristic: CharacteristicRef,
+    ) -> Result<&mut SimulatedCharacteristic> {
+        self.connected(characteristic.peripheral)?
+            .services
+            .iter_mut()
+            .filter(|service| {
+                characteristic
+                    .service
+                    .is_none_or(|uuid| uuid == service.uuid)
+            })
+            .flat_map(|service| &mut service.characteristics)
+            .find(|candidate| candidate.uuid == characteristic.characteristic)
+            .ok_or_else(|| ErrorKind::NotFound.into())
+    }
+}
+
+/// A [`Backend`] with simulated peripherals.
+///
+/// Clones share the same peripherals. Writes update the stored value of the characteristic, so
+/// they can be observed with a subsequent read.
+#[derive(Debug, Clone, Default)]
+pub struct SimulatedBackend {
+    state: Rc<RefCell<State>>,
+}
+
+impl SimulatedBackend {
+    /// Creates a backend with no peripherals.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a backend with a single peripheral named "Heart Sensor", which has heart rate,
+    /// battery and device information services and echoes L2CAP data on PSM `0x0080`.
+    pub fn demo() -> Self {
+        let heart_rate = SimulatedService::new(service::HEART_RATE.into())
+            .with_characteristic(
+                SimulatedCharacteristic::new(
+                    characteristic::HEART_RATE_MEASUREMENT.into(),
+                    Properties::NOTIFY,
+                )
+                .with_notification([0x00, 72])
+                .with_notification([0x00, 74])
+                .with_notification([0x00, 73]),
+            )
+            .with_characteristic(
+                SimulatedCharacteristic::new(
+                    characteristic::BODY_SENSOR_LOCATION.into(),
+                    Properties::READ,
+                )
+                .with_value([0x01]),
+            )
+            .with_characteristic(SimulatedCharacteristic::new(
+                characteristic::HEART_RATE_CONTROL_POINT.into(),
+                Properties::WRITE,
+            ));
+        let battery = SimulatedService::new(service::BATTERY.into()).with_characteristic(
+            SimulatedCharacteristic::new(
+                characteristic::BATTERY_LEVEL.into(),
+                Properties::READ | Properties::NOTIFY,
+            )
+            .with_value([87])
+            .with_notification([86]),
+        );
+        let device_information = SimulatedService::new(service::DEVICE_INFORMATION.into())
+            .with_characteristic(
+                SimulatedCharacteristic::new(
+                    characteristic::MANUFACTURER_NAME_STRING.into(),
+                    Properties::READ,
+                )
+                .with_value("Acme"),
+            )
+            .with_characteristic(
+                SimulatedCharacteristic::new(
+                    characteristic::MODEL_NUMBER_STRING.into(),
+                    Properties::READ,
+                )
+                .with_value("HRM-1"),
+            );
+
+        Self::new().with_peripheral(
+            SimulatedPeripheral::new(
+                Uuid::from_u128(0x6b0c_5c39_2f1d_4e6c_9a51_2d4f_3c2b_1a01),
+                "Heart Sensor",
+            )
+            .with_rssi(-52)
+            .with_service(heart_rate)
+            .with_service(battery)
+            .with_service(device_information)
+            .with_l2cap_echo(0x0080),
+        )
+    }
+
+    /// Adds a peripheral.
+    pub fn with_peripheral(self, peripheral: SimulatedPeripheral) -> Self {
+        self.state.borrow_mut().peripherals.push(peripheral);
+        self
+    }
+
+    /// Returns whether a peripheral is connected.
+    pub fn is_connected(&self, peripheral: Uuid) -> bool {
+        self.state.borrow().connected.contains(&peripheral)
+    }
+}
+
+impl Backend for SimulatedBackend {
+    type L2capStream = EchoStream;
+
+    async fn wait_until_ready(&self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn scan(
+        &self,
+        services: &[BluetoothUuid],
+        _allow_duplicates: bool,
+    ) -> Result<LocalStream<Discovery>> {
+        let discoveries: Vec<_> = self
+            .state
+            .borrow()
+            .peripherals
+            .iter()
+            .map(SimulatedPeripheral::discovery)
+            .filter(|discovery| {
+                services.is_empty()
+                    || discovery
+                        .service_uuids
+                        .iter()
+                        .any(|uuid| services.contains(uuid))
+            })
+            .collect();
+        Ok(Box::pin(futures_lite::stream::iter(discoveries)))
+    }
+
+    async fn connect(&self, peripheral: Uuid) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        if !state
+            .peripherals
+            .iter()
+            .any(|candidate| candidate.identifier == peripheral)
+        {
+            return Err(ErrorKind::Bluetooth(CBError::UnknownDevice).into());
+        }
+        state.connected.insert(peripheral);
+        Ok(())
+    }
+
+    async fn disconnect(&self, peripheral: Uuid) -> Result<()> {
+        self.state.borrow_mut().connected.remove(&peripheral);
+        Ok(())
+    }
+
+    fn is_connected(&self, peripheral: Uuid) -> bool {
+        SimulatedBackend::is_connected(self, peripheral)
+    }
+
+    async fn discover(&self, peripheral: Uuid) -> Result<Vec<GattService>> {
+        let mut state = self.state.borrow_mut();
+        let services = state
+            .connected(peripheral)?
+            .services
+            .iter()
+            .map(|service| GattService {
+                uuid: service.uuid,
+                is_primary: service.is_primary,
+                characteristics: service
+                    .characteristics
+                    .iter()
+                    .map(|characteristic| GattCharacteristic {
+                        uuid: characteristic.uuid,
+                        properties: characteristic.properties,
+                        descriptors: characteristic.descriptors.clone(),
+                    })
+                    .collect(),
+            })
+            .collect();
+        Ok(services)
+    }
+
+    async fn read(&self, characteristic: CharacteristicRef) -> Result<Vec<u8>> {
+        let mut state = self.state.borrow_mut();
+        let characteristic = state.characteristic(characteristic)?;
+        if !characteristic.properties.contains(Properties::READ) {
+            return Err(ErrorKind::ATT(CBATTError::ReadNotPermitted).into());
+        }
+        Ok(characteristic.value.clone())
+    }
+
+    async fn write(
+        &self,
+        characteristic: CharacteristicRef,
+        value: &[u8],
+        with_response: bool,
+    ) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        let characteristic = state.characteristic(characteristic)?;
+        let required = if with_response {
+            Properties::WRITE
+        } else {
+            Properties::WRITE_WITHOUT_RESPONSE
+        };
+        if !characteristic.properties.contains(required) {
+            return Err(ErrorKind::ATT(CBATTError::WriteNotPermitted).into());
+        }
+        characteristic.value = value.to_vec();
+        Ok(())
+    }
+
+    async fn subscribe(
+        &self,
+        characteristic: CharacteristicRef,
+    ) -> Result<LocalStream<Result<Vec<u8>>>> {
+        let mut state = self.state.borrow_mut();
+        let characteristic = state.characteristic(characteristic)?;
+        if !characteristic.properties.contains(Properties::NOTIFY)
+            && !characteristic.properties.contains(Properties::INDICATE)
+        {
+            return Err(ErrorKind::ATT(CBATTError::RequestNotSupported).into());
+        }
+        let notifications = characteristic.notifications.clone();
+        Ok(Box::pin(futures_lite::stream::iter(
+            notifications.into_iter().map(Ok),
+        )))
+    }
+
+    async fn open_l2cap(&self, peripheral: Uuid, psm: u16) -> Result<EchoStream> {
+        let mut state = self.state.borrow_mut();
+        if !state.connected(peripheral)?.l2cap_psms.contains(&psm) {
+            return Err(ErrorKind::Bluetooth(CBError::OperationNotSupported).into());
+        }
+        Ok(EchoStream::default())
+    }
+}
+
+/// A simulated L2CAP channel that echoes back everything written to it.
+///
+/// Reads return end of file once the stream has been closed and all written data has been read.
+#[derive(Debug, Default)]
+pub struct EchoStream {
+    buffer: VecDeque<u8>,
+    closed: bool,
+    reader: Option<Waker>,
+}
+
+impl AsyncRead for EchoStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        if self.buffer.is_empty() && !self.closed {
+            self.reader = Some(cx.waker().clone());
+            return Poll::Pending;
+        }
+        let len = buf.len().min(self.buffer.len());
+        for (dst, src) in buf.iter_mut().zip(self.buffer.drain(..len)) {
+            *dst = src;
+        }
+        Poll::Ready(Ok(len))
+    }
+}
+
+impl AsyncWrite for EchoStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        if self.closed {
+            return Poll::Ready(Err(io::ErrorKind::BrokenPipe.into()));
+        }
+        self.buffer.extend(buf);
+        if let Some(reader) = self.reader.take() {
+            reader.wake();
+        }
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.closed = true;
+        if let Some(reader) = self.reader.take() {
+            reader.wake();
+        }
+        Poll::Ready(Ok(()))
+    }
+}
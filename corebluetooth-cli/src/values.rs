@@ -0,0 +1,262 @@
+//! Decoding of the values of characteristics defined by the Bluetooth SIG.
+//!
+//! [`describe()`] turns the raw value of a well-known characteristic into a human-readable
+//! string, following the characteristic's definition in the GATT Specification Supplement.
+
+use btuuid::{BluetoothUuid, characteristic};
+
+use crate::format::Format;
+
+/// Describes the value of a characteristic, if the characteristic is one this module knows how
+/// to decode and the value is well formed.
+///
+/// ```
+/// use btuuid::characteristic;
+/// use corebluetooth_cli::values::describe;
+///
+/// assert_eq!(describe(characteristic::BATTERY_LEVEL.into(), &[87]).as_deref(), Some("87%"));
+/// assert_eq!(
+///     describe(characteristic::HEART_RATE_MEASUREMENT.into(), &[0x06, 72]).as_deref(),
+///     Some("72 bpm, contact detected"),
+/// );
+/// assert_eq!(describe(characteristic::BATTERY_LEVEL.into(), &[]), None);
+/// ```
+pub fn describe(uuid: BluetoothUuid, value: &[u8]) -> Option<String> {
+    let BluetoothUuid::Uuid16(uuid) = uuid else {
+        return None;
+    };
+    match uuid {
+        characteristic::BATTERY_LEVEL => match value {
+            [level] => Some(format!("{level}%")),
+            _ => None,
+        },
+        characteristic::HEART_RATE_MEASUREMENT => heart_rate_measurement(value),
+        characteristic::BODY_SENSOR_LOCATION => match value {
+            [location] => Some(body_sensor_location(*location).to_owned()),
+            _ => None,
+        },
+        characteristic::TEMPERATURE_MEASUREMENT => temperature_measurement(value),
+        characteristic::TX_POWER_LEVEL => match value {
+            [level] => Some(format!("{} dBm", *level as i8)),
+            _ => None,
+        },
+        characteristic::APPEARANCE => match value {
+            [low, high] => {
+                let appearance = u16::from_le_bytes([*low, *high]);
+                Some(format!(
+                    "category {}, subcategory {}",
+                    appearance >> 6,
+                    appearance & 0x3f
+                ))
+            }
+            _ => None,
+        },
+        characteristic::PNP_ID => match value {
+            [source, rest @ ..] if rest.len() == 6 => {
+                let field = |i: usize| u16::from_le_bytes([rest[i], rest[i + 1]]);
+                let source = match source {
+                    1 => "bluetooth",
+                    2 => "usb",
+                    _ => "unknown",
+                };
+                Some(format!(
+                    "vendor {:#06x} ({source}), product {:#06x}, version {:#06x}",
+                    field(0),
+                    field(2),
+                    field(4)
+                ))
+            }
+            _ => None,
+        },
+        characteristic::DEVICE_NAME
+        | characteristic::MANUFACTURER_NAME_STRING
+        | characteristic::MODEL_NUMBER_STRING
+        | characteristic::SERIAL_NUMBER_STRING
+        | characteristic::FIRMWARE_REVISION_STRING
+        | characteristic::HARDWARE_REVISION_STRING
+        | characteristic::SOFTWARE_REVISION_STRING => std::str::from_utf8(value)
+            .ok()
+            .map(|text| format!("{text:?}")),
+        _ => None,
+    }
+}
+
+/// Describes the value of a characteristic if possible, or formats it as hex otherwise.
+pub fn describe_or_hex(uuid: BluetoothUuid, value: &[u8]) -> String {
+    describe(uuid, value).unwrap_or_else(|| Format::Hex.format(value))
+}
+
+fn heart_rate_measurement(value: &[u8]) -> Option<String> {
+    let (&flags, mut rest) = value.split_first()?;
+    let heart_rate = if flags & 0x01 != 0 {
+        take_u16(&mut rest)?
+    } else {
+        let (&heart_rate, tail) = rest.split_first()?;
+        rest = tail;
+        heart_rate.into()
+    };
+    let mut description = format!("{heart_rate} bpm");
+    match flags & 0x06 {
+        0x06 => description.push_str(", contact detected"),
+        0x04 => description.push_str(", no contact"),
+        _ => {}
+    }
+    if flags & 0x08 != 0 {
+        let energy = take_u16(&mut rest)?;
+        description.push_str(&format!(", {energy} kJ"));
+    }
+    if flags & 0x10 != 0 {
+        let mut intervals = Vec::new();
+        while let Some(interval) = take_u16(&mut rest) {
+            intervals.push(format!("{:.3}", f64::from(interval) / 1024.0));
+        }
+        description.push_str(&format!(", rr {} s", intervals.join(" ")));
+    }
+    Some(description)
+}
+
+fn take_u16(rest: &mut &[u8]) -> Option<u16> {
+    let (bytes, tail) = rest.split_first_chunk::<2>()?;
+    *rest = tail;
+    Some(u16::from_le_bytes(*bytes))
+}
+
+fn body_sensor_location(location: u8) -> &'static str {
+    match location {
+        0 => "other",
+        1 => "chest",
+        2 => "wrist",
+        3 => "finger",
+        4 => "hand",
+        5 => "ear lobe",
+        6 => "foot",
+        _ => "unknown",
+    }
+}
+
+fn temperature_measurement(value: &[u8]) -> Option<String> {
+    let (&flags, rest) = value.split_first()?;
+    let (bytes, _) = rest.split_first_chunk::<4>()?;
+    let temperature = ieee_11073_float(u32::from_le_bytes(*bytes))?;
+    let unit = if flags & 0x01 != 0 { "°F" } else { "°C" };
+    Some(format!("{temperature} {unit}"))
+}
+
+/// Decodes an IEEE 11073-20601 32-bit FLOAT, returning `None` for the special values.
+fn ieee_11073_float(raw: u32) -> Option<f64> {
+    let mantissa = ((raw << 8) as i32) >> 8;
+    let exponent = (raw >> 24) as i8;
+    if (0x007f_fffe..=0x0080_0002).contains(&(raw & 0x00ff_ffff)) {
+        return None;
+    }
+    let value = f64::from(mantissa) * 10f64.powi(exponent.into());
+    // Round to the precision given by the exponent to avoid artifacts such as 36.599999.
+    let digits = (-i32::from(exponent)).clamp(0, 15);
+    let scale = 10f64.powi(digits);
+    Some((value * scale).round() / scale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn describe16(uuid: impl Into<BluetoothUuid>, value: &[u8]) -> Option<String> {
+        describe(uuid.into(), value)
+    }
+
+    #[test]
+    fn heart_rate_measurements() {
+        let uuid = characteristic::HEART_RATE_MEASUREMENT;
+        assert_eq!(describe16(uuid, &[0x00, 72]).as_deref(), Some("72 bpm"));
+        assert_eq!(
+            describe16(uuid, &[0x04, 72]).as_deref(),
+            Some("72 bpm, no contact")
+        );
+        assert_eq!(
+            describe16(uuid, &[0x01, 0x2c, 0x01]).as_deref(),
+            Some("300 bpm")
+        );
+        assert_eq!(
+            describe16(uuid, &[0x18, 60, 0x10, 0x00, 0x00, 0x04, 0x00, 0x02]).as_deref(),
+            Some("60 bpm, 16 kJ, rr 1.000 0.500 s")
+        );
+        // A 16-bit heart rate with only one byte, or a missing energy value.
+        assert_eq!(describe16(uuid, &[0x01, 72]), None);
+        assert_eq!(describe16(uuid, &[0x08, 72, 0x10]), None);
+        assert_eq!(describe16(uuid, &[]), None);
+    }
+
+    #[test]
+    fn temperature_measurements() {
+        let uuid = characteristic::TEMPERATURE_MEASUREMENT;
+        // 366 * 10^-1, with an optional timestamp that is ignored.
+        let celsius = [0x00, 0x6e, 0x01, 0x00, 0xff];
+        assert_eq!(describe16(uuid, &celsius).as_deref(), Some("36.6 °C"));
+        let fahrenheit = [0x01, 0x6e, 0x01, 0x00, 0xff, 0xe8, 0x07];
+        assert_eq!(describe16(uuid, &fahrenheit).as_deref(), Some("36.6 °F"));
+        let negative = [0x00, 0xf6, 0xff, 0xff, 0x00];
+        assert_eq!(describe16(uuid, &negative).as_deref(), Some("-10 °C"));
+        // NaN and a truncated value.
+        assert_eq!(describe16(uuid, &[0x00, 0xff, 0xff, 0x7f, 0x00]), None);
+        assert_eq!(describe16(uuid, &[0x00, 0x6e, 0x01, 0x00]), None);
+    }
+
+    #[test]
+    fn fixed_size_values() {
+        assert_eq!(
+            describe16(characteristic::BODY_SENSOR_LOCATION, &[2]).as_deref(),
+            Some("wrist")
+        );
+        assert_eq!(
+            describe16(characteristic::BODY_SENSOR_LOCATION, &[7]).as_deref(),
+            Some("unknown")
+        );
+        assert_eq!(
+            describe16(characteristic::TX_POWER_LEVEL, &[0xf4]).as_deref(),
+            Some("-12 dBm")
+        );
+        assert_eq!(
+            describe16(characteristic::APPEARANCE, &[0x41, 0x03]).as_deref(),
+            Some("category 13, subcategory 1")
+        );
+        assert_eq!(
+            describe16(
+                characteristic::PNP_ID,
+                &[0x02, 0xac, 0x05, 0x4d, 0x02, 0x10, 0x01]
+            )
+            .as_deref(),
+            Some("vendor 0x05ac (usb), product 0x024d, version 0x0110")
+        );
+        assert_eq!(describe16(characteristic::BATTERY_LEVEL, &[50, 0]), None);
+        assert_eq!(describe16(characteristic::APPEARANCE, &[0x41]), None);
+        assert_eq!(
+            describe16(characteristic::PNP_ID, &[0x01, 0xac, 0x05]),
+            None
+        );
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(
+            describe16(characteristic::MODEL_NUMBER_STRING, b"HRM-1").as_deref(),
+            Some("\"HRM-1\"")
+        );
+        assert_eq!(describe16(characteristic::DEVICE_NAME, &[0xff]), None);
+    }
+
+    #[test]
+    fn unknown_values_are_shown_in_hex() {
+        let vendor = BluetoothUuid::from_u128(0x6e40_0001_b5a3_f393_e0a9_e50e_24dc_ca9e);
+        assert_eq!(describe(vendor, &[87]), None);
+        assert_eq!(describe_or_hex(vendor, &[0x01, 0x02]), "01 02");
+        assert_eq!(describe16(BluetoothUuid::from_u16(0xfff1), &[87]), None);
+        assert_eq!(
+            describe_or_hex(characteristic::BATTERY_LEVEL.into(), &[87]),
+            "87%"
+        );
+        assert_eq!(
+            describe_or_hex(characteristic::BATTERY_LEVEL.into(), &[]),
+            ""
+        );
+    }
+}
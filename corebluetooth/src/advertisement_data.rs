@@ -13,13 +13,44 @@ use objc2_core_bluetooth::{
 use objc2_foundation::{NSArray, NSData, NSDictionary, NSNumber, NSString};
 
 /// Data included in a Bluetooth advertisement or scan reponse.
+///
+/// With the `serde` feature, service data is serialized as a sequence of `(uuid, data)` pairs, as
+/// many formats only support string map keys.
+///
+/// ```
+/// # #[cfg(feature = "serde")]
+/// # fn example() -> serde_json::Result<()> {
+/// use corebluetooth::advertisement_data::{AdvertisementData, ManufacturerData};
+///
+/// let data = AdvertisementData {
+///     local_name: Some("Thermometer".to_owned()),
+///     manufacturer_data: Some(ManufacturerData {
+///         company_id: 0x004c,
+///         data: vec![0x02, 0x15],
+///     }),
+///     service_data: [(btuuid::service::HEALTH_THERMOMETER.into(), vec![0x01])].into(),
+///     service_uuids: vec![btuuid::service::HEALTH_THERMOMETER.into()],
+///     overflow_service_uuids: Vec::new(),
+///     tx_power_level: Some(-8),
+///     is_connectable: true,
+///     solicited_service_uuids: Vec::new(),
+/// };
+/// let json = serde_json::to_string(&data)?;
+/// assert_eq!(serde_json::from_str::<AdvertisementData>(&json)?, data);
+/// # Ok(())
+/// # }
+/// # #[cfg(feature = "serde")]
+/// # example().unwrap();
+/// ```
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AdvertisementData {
     /// The (possibly shortened) local name of the device (CSS §A.1.2)
     pub local_name: Option<String>,
     /// Manufacturer specific data (CSS §A.1.4)
     pub manufacturer_data: Option<ManufacturerData>,
     /// Service associated data (CSS §A.1.11)
+    #[cfg_attr(feature = "serde", serde(with = "service_data"))]
     pub service_data: HashMap<BluetoothUuid, Vec<u8>>,
     /// Advertised GATT service UUIDs (CSS §A.1.1)
     pub service_uuids: Vec<BluetoothUuid>,
@@ -36,6 +67,7 @@ pub struct AdvertisementData {
 /// Manufacturer specific data included in Bluetooth advertisements. See the Bluetooth Core Specification Supplement
 /// §A.1.4 for details.
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ManufacturerData {
     /// Company identifier (defined [here](https://www.bluetooth.com/specifications/assigned-numbers/company-identifiers/))
     pub company_id: u16,
@@ -142,3 +174,27 @@ impl AdvertisementData {
         }
     }
 }
+
+#[cfg(feature = "serde")]
+mod service_data {
+    use std::collections::HashMap;
+
+    use btuuid::BluetoothUuid;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        value: &HashMap<BluetoothUuid, Vec<u8>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let mut entries: Vec<_> = value.iter().collect();
+        entries.sort();
+        serializer.collect_seq(entries)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<HashMap<BluetoothUuid, Vec<u8>>, D::Error> {
+        Vec::<(BluetoothUuid, Vec<u8>)>::deserialize(deserializer)
+            .map(|entries| entries.into_iter().collect())
+    }
+}
@@ -3,7 +3,7 @@
 use std::any::Any;
 
 use btuuid::BluetoothUuid;
-use dispatch_executor::{Executor, SyncClone, SyncDrop};
+use dispatch_executor::{Executor, SyncClone, SyncDrop, panic};
 use dispatch2::DispatchQueue;
 use objc2::rc::{Retained, RetainedFromIterator};
 use objc2::runtime::{AnyObject, ProtocolObject};
@@ -347,6 +347,9 @@ impl CentralManager {
 }
 
 /// A protocol that provides updates for the state of a [`CentralManager`].
+///
+/// Panics in delegate methods are caught before they reach CoreBluetooth and reported to the
+/// [panic handler][dispatch_executor::panic::PanicHandler].
 #[allow(unused_variables)]
 pub trait CentralManagerDelegate: Any {
     /// This method is called when a new peripheral delegate is needed.
@@ -435,9 +438,11 @@ define_class!(
     unsafe impl CBCentralManagerDelegate for CentralManagerDelegateBridge {
         #[unsafe(method(centralManagerDidUpdateState:))]
         fn centralManagerDidUpdateState(&self, central: &CBCentralManager) {
-            self.ivars()
-                .delegate
-                .did_update_state(CentralManager::new(central.retain()));
+            panic::catch("CentralManagerDelegate::did_update_state", || {
+                self.ivars()
+                    .delegate
+                    .did_update_state(CentralManager::new(central.retain()));
+            });
         }
 
         #[unsafe(method(centralManager:willRestoreState:))]
@@ -446,9 +451,11 @@ define_class!(
             central: &CBCentralManager,
             dict: &NSDictionary<NSString, AnyObject>,
         ) {
-            self.ivars()
-                .delegate
-                .will_restore_state(CentralManager::new(central.retain()), dict);
+            panic::catch("CentralManagerDelegate::will_restore_state", || {
+                self.ivars()
+                    .delegate
+                    .will_restore_state(CentralManager::new(central.retain()), dict);
+            });
         }
 
         #[unsafe(method(centralManager:didDiscoverPeripheral:advertisementData:RSSI:))]
@@ -459,18 +466,20 @@ define_class!(
             advertisement_data: &NSDictionary<NSString, AnyObject>,
             rssi: &NSNumber,
         ) {
-            let peripheral = Peripheral::init(peripheral.retain(), || {
-                self.ivars().delegate.new_peripheral_delegate()
+            panic::catch("CentralManagerDelegate::did_discover", || {
+                let peripheral = Peripheral::init(peripheral.retain(), || {
+                    self.ivars().delegate.new_peripheral_delegate()
+                });
+                let advertisement_data = AdvertisementData::from_nsdictionary(advertisement_data);
+                let rssi = rssi.shortValue();
+
+                self.ivars().delegate.did_discover(
+                    CentralManager::new(central.retain()),
+                    peripheral,
+                    advertisement_data,
+                    rssi,
+                );
             });
-            let advertisement_data = AdvertisementData::from_nsdictionary(advertisement_data);
-            let rssi = rssi.shortValue();
-
-            self.ivars().delegate.did_discover(
-                CentralManager::new(central.retain()),
-                peripheral,
-                advertisement_data,
-                rssi,
-            );
         }
 
         #[unsafe(method(centralManager:didConnectPeripheral:))]
@@ -479,10 +488,12 @@ define_class!(
             central: &CBCentralManager,
             peripheral: &CBPeripheral,
         ) {
-            self.ivars().delegate.did_connect(
-                CentralManager::new(central.retain()),
-                Peripheral::new(peripheral.retain()),
-            );
+            panic::catch("CentralManagerDelegate::did_connect", || {
+                self.ivars().delegate.did_connect(
+                    CentralManager::new(central.retain()),
+                    Peripheral::new(peripheral.retain()),
+                );
+            });
         }
 
         #[unsafe(method(centralManager:didFailToConnectPeripheral:error:))]
@@ -492,14 +503,18 @@ define_class!(
             peripheral: &CBPeripheral,
             error: Option<&NSError>,
         ) {
-            let error =
-                Error::from_nserror_or_kind(error, ErrorKind::Bluetooth(CBError::ConnectionFailed));
+            panic::catch("CentralManagerDelegate::did_fail_to_connect", || {
+                let error = Error::from_nserror_or_kind(
+                    error,
+                    ErrorKind::Bluetooth(CBError::ConnectionFailed),
+                );
 
-            self.ivars().delegate.did_fail_to_connect(
-                CentralManager::new(central.retain()),
-                Peripheral::new(peripheral.retain()),
-                error,
-            );
+                self.ivars().delegate.did_fail_to_connect(
+                    CentralManager::new(central.retain()),
+                    Peripheral::new(peripheral.retain()),
+                    error,
+                );
+            });
         }
 
         #[unsafe(method(centralManager:didDisconnectPeripheral:error:))]
@@ -509,14 +524,16 @@ define_class!(
             peripheral: &CBPeripheral,
             error: Option<&NSError>,
         ) {
-            let error = error.map(Error::from_nserror);
-            self.ivars().delegate.did_disconnect(
-                CentralManager::new(central.retain()),
-                Peripheral::new(peripheral.retain()),
-                None,
-                false,
-                error,
-            );
+            panic::catch("CentralManagerDelegate::did_disconnect", || {
+                let error = error.map(Error::from_nserror);
+                self.ivars().delegate.did_disconnect(
+                    CentralManager::new(central.retain()),
+                    Peripheral::new(peripheral.retain()),
+                    None,
+                    false,
+                    error,
+                );
+            });
         }
 
         #[unsafe(method(centralManager:didDisconnectPeripheral:timestamp:isReconnecting:error:))]
@@ -528,14 +545,16 @@ define_class!(
             is_reconnecting: bool,
             error: Option<&NSError>,
         ) {
-            let error = error.map(Error::from_nserror);
-            self.ivars().delegate.did_disconnect(
-                CentralManager::new(central.retain()),
-                Peripheral::new(peripheral.retain()),
-                to_system_time(timestamp),
-                is_reconnecting,
-                error,
-            );
+            panic::catch("CentralManagerDelegate::did_disconnect", || {
+                let error = error.map(Error::from_nserror);
+                self.ivars().delegate.did_disconnect(
+                    CentralManager::new(central.retain()),
+                    Peripheral::new(peripheral.retain()),
+                    to_system_time(timestamp),
+                    is_reconnecting,
+                    error,
+                );
+            });
         }
 
         #[unsafe(method(centralManager:connectionEventDidOccur:forPeripheral:))]
@@ -545,11 +564,13 @@ define_class!(
             event: CBConnectionEvent,
             peripheral: &CBPeripheral,
         ) {
-            self.ivars().delegate.on_connection_event(
-                CentralManager::new(central.retain()),
-                event,
-                Peripheral::new(peripheral.retain()),
-            );
+            panic::catch("CentralManagerDelegate::on_connection_event", || {
+                self.ivars().delegate.on_connection_event(
+                    CentralManager::new(central.retain()),
+                    event,
+                    Peripheral::new(peripheral.retain()),
+                );
+            });
         }
 
         #[unsafe(method(centralManager:didUpdateANCSAuthorizationForPeripheral:))]
@@ -558,9 +579,14 @@ define_class!(
             central: &CBCentralManager,
             peripheral: &CBPeripheral,
         ) {
-            self.ivars().delegate.did_update_ancs_authorization(
-                CentralManager::new(central.retain()),
-                Peripheral::new(peripheral.retain()),
+            panic::catch(
+                "CentralManagerDelegate::did_update_ancs_authorization",
+                || {
+                    self.ivars().delegate.did_update_ancs_authorization(
+                        CentralManager::new(central.retain()),
+                        Peripheral::new(peripheral.retain()),
+                    );
+                },
             );
         }
     }
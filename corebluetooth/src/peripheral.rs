@@ -4,7 +4,7 @@ use std::any::Any;
 use std::os::unix::net::UnixStream;
 
 use btuuid::BluetoothUuid;
-use dispatch_executor::{SyncClone, SyncDrop};
+use dispatch_executor::{SyncClone, SyncDrop, panic};
 use objc2::rc::{Retained, RetainedFromIterator};
 use objc2::runtime::ProtocolObject;
 use objc2::{AnyThread, DefinedClass, Message, define_class, msg_send};
@@ -291,6 +291,9 @@ impl Peripheral {
 }
 
 /// A protocol that provides updates for the state of a [`Peripheral`].
+///
+/// Panics in delegate methods are caught before they reach CoreBluetooth and reported to the
+/// [panic handler][dispatch_executor::panic::PanicHandler].
 #[allow(unused_variables)]
 pub trait PeripheralDelegate: Any {
     /// Called when the peripheral's name changes.
@@ -432,9 +435,11 @@ define_class!(
     unsafe impl CBPeripheralDelegate for PeripheralDelegateBridge {
         #[unsafe(method(peripheralDidUpdateName:))]
         unsafe fn peripheralDidUpdateName(&self, peripheral: &CBPeripheral) {
-            self.ivars()
-                .delegate
-                .did_update_name(Peripheral::new(peripheral.retain()));
+            panic::catch("PeripheralDelegate::did_update_name", || {
+                self.ivars()
+                    .delegate
+                    .did_update_name(Peripheral::new(peripheral.retain()));
+            });
         }
 
         #[unsafe(method(peripheral:didModifyServices:))]
@@ -443,10 +448,13 @@ define_class!(
             peripheral: &CBPeripheral,
             invalidated_services: &NSArray<CBService>,
         ) {
-            let invalidated_services = invalidated_services.iter().map(Service::new).collect();
-            self.ivars()
-                .delegate
-                .did_modify_services(Peripheral::new(peripheral.retain()), invalidated_services);
+            panic::catch("PeripheralDelegate::did_modify_services", || {
+                let invalidated_services = invalidated_services.iter().map(Service::new).collect();
+                self.ivars().delegate.did_modify_services(
+                    Peripheral::new(peripheral.retain()),
+                    invalidated_services,
+                );
+            });
         }
 
         #[unsafe(method(peripheral:didReadRSSI:error:))]
@@ -456,10 +464,12 @@ define_class!(
             rssi: &NSNumber,
             error: Option<&NSError>,
         ) {
-            self.ivars().delegate.did_read_rssi(
-                Peripheral::new(peripheral.retain()),
-                or_err(rssi.shortValue(), error),
-            );
+            panic::catch("PeripheralDelegate::did_read_rssi", || {
+                self.ivars().delegate.did_read_rssi(
+                    Peripheral::new(peripheral.retain()),
+                    or_err(rssi.shortValue(), error),
+                );
+            });
         }
 
         #[unsafe(method(peripheral:didDiscoverServices:))]
@@ -468,9 +478,11 @@ define_class!(
             peripheral: &CBPeripheral,
             error: Option<&NSError>,
         ) {
-            self.ivars()
-                .delegate
-                .did_discover_services(Peripheral::new(peripheral.retain()), or_err((), error));
+            panic::catch("PeripheralDelegate::did_discover_services", || {
+                self.ivars()
+                    .delegate
+                    .did_discover_services(Peripheral::new(peripheral.retain()), or_err((), error));
+            });
         }
 
         #[unsafe(method(peripheral:didDiscoverIncludedServicesForService:error:))]
@@ -480,11 +492,13 @@ define_class!(
             service: &CBService,
             error: Option<&NSError>,
         ) {
-            self.ivars().delegate.did_discover_included_services(
-                Peripheral::new(peripheral.retain()),
-                Service::new(service.retain()),
-                or_err((), error),
-            );
+            panic::catch("PeripheralDelegate::did_discover_included_services", || {
+                self.ivars().delegate.did_discover_included_services(
+                    Peripheral::new(peripheral.retain()),
+                    Service::new(service.retain()),
+                    or_err((), error),
+                );
+            });
         }
 
         #[unsafe(method(peripheral:didDiscoverCharacteristicsForService:error:))]
@@ -494,11 +508,13 @@ define_class!(
             service: &CBService,
             error: Option<&NSError>,
         ) {
-            self.ivars().delegate.did_discover_characteristics(
-                Peripheral::new(peripheral.retain()),
-                Service::new(service.retain()),
-                or_err((), error),
-            );
+            panic::catch("PeripheralDelegate::did_discover_characteristics", || {
+                self.ivars().delegate.did_discover_characteristics(
+                    Peripheral::new(peripheral.retain()),
+                    Service::new(service.retain()),
+                    or_err((), error),
+                );
+            });
         }
 
         #[unsafe(method(peripheral:didUpdateValueForCharacteristic:error:))]
@@ -508,10 +524,15 @@ define_class!(
             characteristic: &CBCharacteristic,
             error: Option<&NSError>,
         ) {
-            self.ivars().delegate.did_update_value_for_characteristic(
-                Peripheral::new(peripheral.retain()),
-                Characteristic::new(characteristic.retain()),
-                or_err((), error),
+            panic::catch(
+                "PeripheralDelegate::did_update_value_for_characteristic",
+                || {
+                    self.ivars().delegate.did_update_value_for_characteristic(
+                        Peripheral::new(peripheral.retain()),
+                        Characteristic::new(characteristic.retain()),
+                        or_err((), error),
+                    );
+                },
             );
         }
 
@@ -522,10 +543,15 @@ define_class!(
             characteristic: &CBCharacteristic,
             error: Option<&NSError>,
         ) {
-            self.ivars().delegate.did_write_value_for_characteristic(
-                Peripheral::new(peripheral.retain()),
-                Characteristic::new(characteristic.retain()),
-                or_err((), error),
+            panic::catch(
+                "PeripheralDelegate::did_write_value_for_characteristic",
+                || {
+                    self.ivars().delegate.did_write_value_for_characteristic(
+                        Peripheral::new(peripheral.retain()),
+                        Characteristic::new(characteristic.retain()),
+                        or_err((), error),
+                    );
+                },
             );
         }
 
@@ -536,13 +562,18 @@ define_class!(
             characteristic: &CBCharacteristic,
             error: Option<&NSError>,
         ) {
-            self.ivars()
-                .delegate
-                .did_update_notification_state_for_characteristic(
-                    Peripheral::new(peripheral.retain()),
-                    Characteristic::new(characteristic.retain()),
-                    or_err((), error),
-                );
+            panic::catch(
+                "PeripheralDelegate::did_update_notification_state_for_characteristic",
+                || {
+                    self.ivars()
+                        .delegate
+                        .did_update_notification_state_for_characteristic(
+                            Peripheral::new(peripheral.retain()),
+                            Characteristic::new(characteristic.retain()),
+                            or_err((), error),
+                        );
+                },
+            );
         }
 
         #[unsafe(method(peripheral:didDiscoverDescriptorsForCharacteristic:error:))]
@@ -552,13 +583,18 @@ define_class!(
             characteristic: &CBCharacteristic,
             error: Option<&NSError>,
         ) {
-            self.ivars()
-                .delegate
-                .did_discover_descriptors_for_characteristic(
-                    Peripheral::new(peripheral.retain()),
-                    Characteristic::new(characteristic.retain()),
-                    or_err((), error),
-                );
+            panic::catch(
+                "PeripheralDelegate::did_discover_descriptors_for_characteristic",
+                || {
+                    self.ivars()
+                        .delegate
+                        .did_discover_descriptors_for_characteristic(
+                            Peripheral::new(peripheral.retain()),
+                            Characteristic::new(characteristic.retain()),
+                            or_err((), error),
+                        );
+                },
+            );
         }
 
         #[unsafe(method(peripheral:didUpdateValueForDescriptor:error:))]
@@ -568,10 +604,15 @@ define_class!(
             descriptor: &CBDescriptor,
             error: Option<&NSError>,
         ) {
-            self.ivars().delegate.did_update_value_for_descriptor(
-                Peripheral::new(peripheral.retain()),
-                Descriptor::new(descriptor.retain()),
-                or_err((), error),
+            panic::catch(
+                "PeripheralDelegate::did_update_value_for_descriptor",
+                || {
+                    self.ivars().delegate.did_update_value_for_descriptor(
+                        Peripheral::new(peripheral.retain()),
+                        Descriptor::new(descriptor.retain()),
+                        or_err((), error),
+                    );
+                },
             );
         }
 
@@ -582,18 +623,27 @@ define_class!(
             descriptor: &CBDescriptor,
             error: Option<&NSError>,
         ) {
-            self.ivars().delegate.did_write_value_for_descriptor(
-                Peripheral::new(peripheral.retain()),
-                Descriptor::new(descriptor.retain()),
-                or_err((), error),
-            );
+            panic::catch("PeripheralDelegate::did_write_value_for_descriptor", || {
+                self.ivars().delegate.did_write_value_for_descriptor(
+                    Peripheral::new(peripheral.retain()),
+                    Descriptor::new(descriptor.retain()),
+                    or_err((), error),
+                );
+            });
         }
 
         #[unsafe(method(peripheralIsReadyToSendWriteWithoutResponse:))]
         unsafe fn peripheralIsReadyToSendWriteWithoutResponse(&self, peripheral: &CBPeripheral) {
-            self.ivars()
-                .delegate
-                .is_ready_to_send_write_without_response(Peripheral::new(peripheral.retain()));
+            panic::catch(
+                "PeripheralDelegate::is_ready_to_send_write_without_response",
+                || {
+                    self.ivars()
+                        .delegate
+                        .is_ready_to_send_write_without_response(Peripheral::new(
+                            peripheral.retain(),
+                        ));
+                },
+            );
         }
 
         #[unsafe(method(peripheral:didOpenL2CAPChannel:error:))]
@@ -603,15 +653,17 @@ define_class!(
             channel: Option<&CBL2CAPChannel>,
             error: Option<&NSError>,
         ) {
-            let result = match (channel, error) {
-                (Some(channel), None) => Ok(L2capChannel::<Peripheral>::new(channel.retain())),
-                (None, Some(error)) => Err(Error::from_nserror(error)),
-                _ => unreachable!(),
-            };
-
-            self.ivars()
-                .delegate
-                .did_open_l2cap_channel(Peripheral::new(peripheral.retain()), result);
+            panic::catch("PeripheralDelegate::did_open_l2cap_channel", || {
+                let result = match (channel, error) {
+                    (Some(channel), None) => Ok(L2capChannel::<Peripheral>::new(channel.retain())),
+                    (None, Some(error)) => Err(Error::from_nserror(error)),
+                    _ => unreachable!(),
+                };
+
+                self.ivars()
+                    .delegate
+                    .did_open_l2cap_channel(Peripheral::new(peripheral.retain()), result);
+            });
         }
     }
 );
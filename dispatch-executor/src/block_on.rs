@@ -0,0 +1,273 @@
+//! Support for driving futures to completion from synchronous code.
+
+use std::cell::Cell;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::Thread;
+
+use crate::DispatchQueue;
+
+thread_local! {
+    static CURRENT_QUEUE: Cell<Option<QueueKey>> = const { Cell::new(None) };
+}
+
+/// Identifies a dispatch queue by address, for tracking which queue the current thread is
+/// running work for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct QueueKey(usize);
+
+impl QueueKey {
+    pub(crate) fn of(queue: &DispatchQueue) -> Self {
+        Self(std::ptr::from_ref(queue) as usize)
+    }
+
+    /// Records that the current thread is running work for this queue until the guard is dropped.
+    pub(crate) fn enter(self) -> EnterGuard {
+        EnterGuard(CURRENT_QUEUE.replace(Some(self)))
+    }
+
+    /// Whether the current thread is running work for this queue that was entered with
+    /// [`enter()`][Self::enter].
+    pub(crate) fn is_current(self) -> bool {
+        CURRENT_QUEUE.get() == Some(self)
+    }
+}
+
+/// Whether the current thread is running work for `queue`.
+///
+/// Work run by this crate is recorded with [`QueueKey::enter()`]. Any other work on a queue
+/// prepared with [`register_queue()`], such as delegate callbacks or the closures passed to
+/// `Handle::lock()`, is identified by the queue itself.
+pub(crate) fn is_current_queue(queue: &DispatchQueue) -> bool {
+    QueueKey::of(queue).is_current() || is_running_on(queue)
+}
+
+#[cfg(target_vendor = "apple")]
+static SPECIFIC_KEY: u8 = 0;
+
+/// Prepares `queue` for [`is_current_queue()`] by setting queue-specific data that identifies it.
+///
+/// The main queue must not be registered: libdispatch treats the main thread as running on the
+/// main queue even outside of blocks submitted to it.
+#[cfg(target_vendor = "apple")]
+pub(crate) fn register_queue(queue: &DispatchQueue) {
+    // The queue's context is the address of the boxed destructor, so the destructor owns an
+    // allocation to make that address unique to the queue.
+    let marker = Box::new(0u8);
+    queue.set_specific(std::ptr::NonNull::from(&SPECIFIC_KEY).cast(), move || {
+        drop(marker)
+    });
+}
+
+/// Whether the current thread is running a block submitted to `queue` (or to a queue targeting
+/// it).
+#[cfg(target_vendor = "apple")]
+fn is_running_on(queue: &DispatchQueue) -> bool {
+    let key = std::ptr::NonNull::from(&SPECIFIC_KEY).cast();
+    // Safety: the key is only used for comparison.
+    let current = unsafe { dispatch2::dispatch_get_specific(key) };
+    !current.is_null() && current == unsafe { queue.specific(key) }
+}
+
+/// Portable queues track the queue that the current thread is running work for themselves.
+#[cfg(not(target_vendor = "apple"))]
+pub(crate) fn register_queue(_queue: &DispatchQueue) {}
+
+#[cfg(not(target_vendor = "apple"))]
+fn is_running_on(queue: &DispatchQueue) -> bool {
+    queue.is_current()
+}
+
+pub(crate) struct EnterGuard(Option<QueueKey>);
+
+impl Drop for EnterGuard {
+    fn drop(&mut self) {
+        CURRENT_QUEUE.set(self.0);
+    }
+}
+
+/// Asserts that a value is only accessed from the context of the dispatch queue it belongs to.
+pub(crate) struct AssertSend<T>(T);
+
+unsafe impl<T> Send for AssertSend<T> {}
+
+impl<T> AssertSend<T> {
+    /// # Safety
+    ///
+    /// `value` must only be accessed, including being dropped, from the context of a single
+    /// dispatch queue, or be `Send`.
+    pub(crate) unsafe fn new(value: T) -> Self {
+        Self(value)
+    }
+
+    pub(crate) fn get_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+
+    pub(crate) fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+/// Polls `future` on `queue` with `barrier_sync` until it completes, parking the current thread
+/// between polls.
+///
+/// The future is dropped on the queue as soon as it completes.
+///
+/// # Safety
+///
+/// `future` must be `Send`, or must have been created on `queue` so that any `!Send` values it
+/// holds are only accessed from the context of `queue`.
+pub(crate) unsafe fn block_on_queue<F>(queue: &DispatchQueue, future: Pin<Box<F>>) -> F::Output
+where
+    F: Future,
+    F::Output: Send,
+{
+    let key = QueueKey::of(queue);
+    let mut future = unsafe { AssertSend::new(Some(future)) };
+    let thread_waker = ThreadWaker::current();
+    let waker = Waker::from(thread_waker.clone());
+    loop {
+        let mut output = None;
+        queue.barrier_sync(|| {
+            let _enter = key.enter();
+            let slot = future.get_mut();
+            if let Some(fut) = slot
+                && let Poll::Ready(val) = fut.as_mut().poll(&mut Context::from_waker(&waker))
+            {
+                output = Some(val);
+                *slot = None;
+            }
+        });
+        if let Some(output) = output {
+            return output;
+        }
+        thread_waker.wait();
+    }
+}
+
+/// A waker that unparks a blocked thread.
+struct ThreadWaker {
+    thread: Thread,
+    notified: AtomicBool,
+}
+
+impl ThreadWaker {
+    fn current() -> Arc<Self> {
+        Arc::new(Self {
+            thread: std::thread::current(),
+            notified: AtomicBool::new(false),
+        })
+    }
+
+    /// Parks the current thread until the waker is woken.
+    fn wait(&self) {
+        while !self.notified.swap(false, Ordering::Acquire) {
+            std::thread::park();
+        }
+    }
+}
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.notified.store(true, Ordering::Release);
+        self.thread.unpark();
+    }
+}
+
+/// A waker that interrupts [`run_main_queue_once()`] by submitting an empty block to the main
+/// queue.
+#[derive(Default)]
+pub(crate) struct MainThreadWaker {
+    pending: Arc<AtomicBool>,
+}
+
+impl Wake for MainThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        // Any wake-up before the block runs is covered by the block that is already queued.
+        if !self.pending.swap(true, Ordering::AcqRel) {
+            let pending = self.pending.clone();
+            DispatchQueue::main().exec_async(move || pending.store(false, Ordering::Release));
+        }
+    }
+}
+
+/// Runs the main thread's run loop until at least one block on the main queue has run.
+#[cfg(target_vendor = "apple")]
+pub(crate) fn run_main_queue_once() {
+    use objc2_core_foundation::{CFRunLoop, kCFRunLoopDefaultMode};
+
+    // The main dispatch queue is serviced as a source of the main run loop's common modes, so
+    // the run loop returns after draining it.
+    CFRunLoop::run_in_mode(unsafe { kCFRunLoopDefaultMode }, 1.0e10, true);
+}
+
+/// Waits for a block to be submitted to the main queue and runs it.
+#[cfg(not(target_vendor = "apple"))]
+pub(crate) fn run_main_queue_once() {
+    crate::portable::run_main_queue_once();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use super::*;
+    use crate::{DispatchObject, DispatchQueueAttr};
+
+    fn queue(label: &str) -> crate::DispatchRetained<DispatchQueue> {
+        let queue = DispatchQueue::new(label, DispatchQueueAttr::SERIAL);
+        register_queue(&queue);
+        queue
+    }
+
+    #[test]
+    fn work_on_registered_queue_is_current() {
+        let queue = queue("a");
+        let other = queue.retain();
+        let (tx, rx) = mpsc::channel();
+        // Work submitted directly to the queue is not entered with a `QueueKey`.
+        queue.exec_async(move || tx.send(is_current_queue(&other)).unwrap());
+        assert!(rx.recv().unwrap());
+
+        let mut current = false;
+        queue.barrier_sync(|| current = is_current_queue(&queue));
+        assert!(current);
+        assert!(!is_current_queue(&queue));
+    }
+
+    #[test]
+    fn nested_work_on_other_queue_is_not_current() {
+        let (a, b) = (queue("a"), queue("b"));
+        let mut current = (false, false, false);
+        a.exec_sync(|| {
+            b.exec_sync(|| current.0 = is_current_queue(&a));
+            current.1 = is_current_queue(&a);
+            current.2 = is_current_queue(&b);
+        });
+        assert_eq!(current, (false, true, false));
+    }
+
+    #[test]
+    fn entered_key_is_current() {
+        let queue = queue("a");
+        let key = QueueKey::of(&queue);
+        assert!(!key.is_current());
+        {
+            let _enter = key.enter();
+            assert!(key.is_current());
+            assert!(is_current_queue(&queue));
+        }
+        assert!(!key.is_current());
+    }
+}
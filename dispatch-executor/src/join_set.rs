@@ -0,0 +1,341 @@
+//! Structured concurrency for tasks spawned on an [`Executor`].
+
+use std::any::Any;
+use std::collections::{HashMap, VecDeque};
+use std::panic::{AssertUnwindSafe, catch_unwind, resume_unwind};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+use futures_core::Stream;
+
+use crate::panic::ReportPanic;
+use crate::{Executor, Task};
+
+type TaskResult<T> = Result<T, Box<dyn Any + Send>>;
+
+struct Shared<T> {
+    completed: VecDeque<(u64, TaskResult<T>)>,
+    waker: Option<Waker>,
+}
+
+/// A collection of tasks spawned on one or more [`Executor`]s.
+///
+/// The set owns its tasks: dropping it cancels every task that has not completed. Results are
+/// returned by [`join_next()`][Self::join_next] in the order the tasks complete. If a task
+/// panics, the panic is reported to the [panic handler][crate::panic::PanicHandler] and then
+/// resumed in the caller of `join_next()`.
+///
+/// # Example
+///
+/// ```
+/// # use dispatch_executor::{Executor, JoinSet};
+/// let sum = Executor::run_background("example", None, |executor| async move {
+///     let mut set = JoinSet::new();
+///     for i in 0..4 {
+///         set.spawn(&executor, async move { i * 2 });
+///     }
+///
+///     let mut sum = 0;
+///     while let Some(value) = set.join_next().await {
+///         sum += value;
+///     }
+///     sum
+/// });
+/// assert_eq!(sum, 12);
+/// ```
+pub struct JoinSet<T> {
+    tasks: HashMap<u64, Task<()>>,
+    shared: Arc<Mutex<Shared<T>>>,
+    next_id: u64,
+}
+
+impl<T> std::fmt::Debug for JoinSet<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JoinSet")
+            .field("len", &self.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T> Default for JoinSet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> JoinSet<T> {
+    /// Creates an empty set.
+    pub fn new() -> Self {
+        Self {
+            tasks: HashMap::new(),
+            shared: Arc::new(Mutex::new(Shared {
+                completed: VecDeque::new(),
+                waker: None,
+            })),
+            next_id: 0,
+        }
+    }
+
+    /// The number of tasks in the set, including completed tasks whose results have not been
+    /// returned by [`join_next()`][Self::join_next].
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    /// Whether the set contains no tasks.
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    /// Spawns a task on `executor` and adds it to the set.
+    pub fn spawn<F>(&mut self, executor: &Executor, future: F)
+    where
+        F: Future<Output = T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let (id, future) = self.wrap(future);
+        let task = executor.spawn(future);
+        self.tasks.insert(id, task);
+    }
+
+    /// Spawns a `!Send` task on `executor` and adds it to the set.
+    ///
+    /// # Safety
+    ///
+    /// See [`Executor::spawn_local()`].
+    pub unsafe fn spawn_local<F>(&mut self, executor: &Executor, future: F)
+    where
+        F: Future<Output = T> + 'static,
+        T: 'static,
+    {
+        let (id, future) = self.wrap(future);
+        let task = unsafe { executor.spawn_local(future) };
+        self.tasks.insert(id, task);
+    }
+
+    fn wrap<F>(&mut self, future: F) -> (u64, impl Future<Output = ()> + use<F, T>)
+    where
+        F: Future<Output = T>,
+    {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let shared = self.shared.clone();
+        let future = async move {
+            let result = CatchUnwind(ReportPanic::new("task", future)).await;
+            let waker = {
+                let mut shared = shared.lock().unwrap();
+                shared.completed.push_back((id, result));
+                shared.waker.take()
+            };
+            if let Some(waker) = waker {
+                waker.wake();
+            }
+        };
+        (id, future)
+    }
+
+    /// Waits for the next task in the set to complete and returns its output.
+    ///
+    /// Returns `None` if the set is empty.
+    ///
+    /// # Panics
+    ///
+    /// If the task panicked, its panic is resumed in the caller.
+    pub async fn join_next(&mut self) -> Option<T> {
+        std::future::poll_fn(|cx| self.poll_join_next(cx)).await
+    }
+
+    /// Polls for the next task in the set to complete.
+    ///
+    /// # Panics
+    ///
+    /// If the task panicked, its panic is resumed in the caller.
+    pub fn poll_join_next(&mut self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let mut shared = self.shared.lock().unwrap();
+        if let Some((id, result)) = shared.completed.pop_front() {
+            drop(shared);
+            self.tasks.remove(&id);
+            match result {
+                Ok(output) => Poll::Ready(Some(output)),
+                Err(payload) => resume_unwind(payload),
+            }
+        } else if self.tasks.is_empty() {
+            Poll::Ready(None)
+        } else {
+            shared.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+
+    /// Cancels every task in the set and removes it.
+    pub fn cancel_all(&mut self) {
+        self.tasks.clear();
+        self.shared.lock().unwrap().completed.clear();
+    }
+}
+
+impl<T> Stream for JoinSet<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        self.get_mut().poll_join_next(cx)
+    }
+}
+
+/// A future that catches panics while polling the wrapped future.
+pub(crate) struct CatchUnwind<F>(pub(crate) F);
+
+impl<F: Future> Future for CatchUnwind<F> {
+    type Output = TaskResult<F::Output>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // Safety: the wrapped future is structurally pinned.
+        let future = unsafe { self.map_unchecked_mut(|this| &mut this.0) };
+        match catch_unwind(AssertUnwindSafe(|| future.poll(cx))) {
+            Ok(poll) => poll.map(Ok),
+            Err(payload) => Poll::Ready(Err(payload)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time::{Duration, Instant};
+
+    use super::*;
+    use crate::panic::capture_reports;
+
+    fn run<F, Fut>(entry: F) -> Fut::Output
+    where
+        F: FnOnce(Executor) -> Fut + Send,
+        Fut: Future,
+        Fut::Output: Send,
+    {
+        Executor::run_background("dispatch-executor.join-set-test", None, entry)
+    }
+
+    /// Sets a flag when dropped.
+    struct DropFlag(Arc<AtomicBool>);
+
+    impl Drop for DropFlag {
+        fn drop(&mut self) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    fn wait_for(flag: &AtomicBool) {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !flag.load(Ordering::SeqCst) {
+            assert!(Instant::now() < deadline, "timed out");
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn results_are_returned_in_completion_order() {
+        let order = run(|executor| async move {
+            let mut set = JoinSet::new();
+            for i in [3u64, 1, 2] {
+                let sleep = executor.sleep(Duration::from_millis(i * 20));
+                set.spawn(&executor, async move {
+                    sleep.await;
+                    i
+                });
+            }
+            assert_eq!(set.len(), 3);
+
+            let mut order = Vec::new();
+            while let Some(i) = set.join_next().await {
+                order.push(i);
+            }
+            assert!(set.is_empty());
+            order
+        });
+        assert_eq!(order, [1, 2, 3]);
+    }
+
+    #[test]
+    fn empty_set_returns_none() {
+        run(|_| async {
+            let mut set = JoinSet::<()>::new();
+            assert_eq!(set.join_next().await, None);
+        });
+    }
+
+    #[test]
+    fn drop_cancels_tasks() {
+        let dropped = Arc::new(AtomicBool::new(false));
+        run(|executor| {
+            let dropped = dropped.clone();
+            async move {
+                let mut set = JoinSet::new();
+                let flag = DropFlag(dropped);
+                set.spawn(&executor, async move {
+                    let _flag = flag;
+                    std::future::pending::<()>().await;
+                });
+                // Let the task start.
+                executor.sleep(Duration::from_millis(5)).await;
+            }
+        });
+        wait_for(&dropped);
+    }
+
+    #[test]
+    fn cancel_all() {
+        let dropped = Arc::new(AtomicBool::new(false));
+        run(|executor| {
+            let dropped = dropped.clone();
+            async move {
+                let mut set = JoinSet::new();
+                set.spawn(&executor, async { 1 });
+                let flag = DropFlag(dropped);
+                set.spawn(&executor, async move {
+                    let _flag = flag;
+                    std::future::pending().await
+                });
+                set.cancel_all();
+                assert!(set.is_empty());
+                assert_eq!(set.join_next().await, None);
+            }
+        });
+        wait_for(&dropped);
+    }
+
+    #[test]
+    fn spawn_local() {
+        let value = run(|executor| async move {
+            let mut set = JoinSet::new();
+            let local = std::rc::Rc::new(5);
+            unsafe { set.spawn_local(&executor, async move { *local }) };
+            set.join_next().await
+        });
+        assert_eq!(value, Some(5));
+    }
+
+    #[test]
+    fn panics_are_reported_and_resumed() {
+        let (result, reports) = capture_reports(|| {
+            run(|executor| async move {
+                let mut set = JoinSet::new();
+                set.spawn(&executor, async { panic!("join set task") });
+                std::future::poll_fn(|cx| {
+                    match catch_unwind(AssertUnwindSafe(|| set.poll_join_next(cx))) {
+                        Ok(Poll::Pending) => Poll::Pending,
+                        Ok(Poll::Ready(_)) => Poll::Ready(None),
+                        Err(payload) => Poll::Ready(payload.downcast::<&str>().ok()),
+                    }
+                })
+                .await
+                .map(|message| *message)
+            })
+        });
+        assert_eq!(result, Some("join set task"));
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].context(), "task");
+        assert_eq!(reports[0].message(), Some("join set task"));
+    }
+}
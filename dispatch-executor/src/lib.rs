@@ -6,6 +6,9 @@
 //! It also provides a [`Handle`] type that allows for sending `!Send` values
 //! between threads, as long as they are only accessed on the thread that owns them.
 //!
+//! On targets without Grand Central Dispatch, the same API is provided by a portable
+//! implementation of dispatch queues built on a pool of `std` threads.
+//!
 //! # Example
 //!
 //! ```no_run
@@ -26,14 +29,40 @@
 use std::hash::Hasher;
 use std::marker::PhantomData;
 use std::mem::MaybeUninit;
-use std::pin::Pin;
-use std::task::{Context, Poll};
+use std::pin::{Pin, pin};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
 
-use async_task::{Runnable, spawn, spawn_unchecked};
+use async_task::{Builder, Runnable};
+#[cfg(target_vendor = "apple")]
 use dispatch2::{DispatchObject, DispatchRetained};
-
-pub use dispatch2::{DispatchAutoReleaseFrequency, DispatchQoS, DispatchQueue, DispatchQueueAttr};
+#[cfg(not(target_vendor = "apple"))]
+use portable::DispatchObject;
+
+#[cfg(target_vendor = "apple")]
+pub use dispatch2::{
+    DispatchAutoReleaseFrequency, DispatchQoS, DispatchQueue, DispatchQueueAttr, dispatch_main,
+};
+pub use join_set::JoinSet;
+#[cfg(target_vendor = "apple")]
 pub use objc2::MainThreadMarker;
+#[cfg(not(target_vendor = "apple"))]
+pub use portable::{
+    DispatchAutoReleaseFrequency, DispatchQoS, DispatchQueue, DispatchQueueAttr, DispatchRetained,
+    MainThreadMarker, dispatch_main,
+};
+pub use timer::{Elapsed, Interval, Sleep, Timeout};
+
+use block_on::{AssertSend, MainThreadWaker, QueueKey};
+use panic::ReportPanic;
+
+mod block_on;
+mod join_set;
+pub mod panic;
+#[cfg(not(target_vendor = "apple"))]
+mod portable;
+mod timer;
 
 /// An executor that runs async tasks on a Grand Central Dispatch queue.
 #[derive(Clone)]
@@ -54,8 +83,10 @@ impl Executor {
         R: Send,
     {
         let queue = DispatchQueue::new(label, queue_attributes);
+        block_on::register_queue(&queue);
         let mut ret = MaybeUninit::uninit();
         queue.barrier_sync(|| {
+            let _enter = QueueKey::of(&queue).enter();
             let executor = Self {
                 queue: queue.retain(),
                 phantom: PhantomData,
@@ -65,6 +96,31 @@ impl Executor {
         unsafe { ret.assume_init() }
     }
 
+    /// Creates a new executor on a background dispatch queue and runs the future returned by the
+    /// provided entry point function to completion, blocking the current thread.
+    ///
+    /// `entry` is called on the new queue, and the future it returns is polled there with
+    /// `barrier_sync`. Between polls, the current thread sleeps until the future is woken, while
+    /// other tasks and callbacks on the queue continue to run.
+    pub fn run_background<F, Fut>(
+        label: &str,
+        queue_attributes: Option<&DispatchQueueAttr>,
+        entry: F,
+    ) -> Fut::Output
+    where
+        F: FnOnce(Self) -> Fut + Send,
+        Fut: Future,
+        Fut::Output: Send,
+    {
+        let (queue, future) = Self::background(label, queue_attributes, |executor| {
+            let future = Box::pin(entry(executor.clone()));
+            // Safety: `future` was created on `executor`'s queue.
+            (executor.queue, unsafe { AssertSend::new(future) })
+        });
+        // Safety: `future` was created on `queue` and is only accessed there.
+        unsafe { block_on::block_on_queue(&queue, future.into_inner()) }
+    }
+
     /// Returns an executor that runs tasks on the main dispatch queue.
     pub fn main_thread(_mtm: MainThreadMarker) -> Self {
         Self {
@@ -91,11 +147,40 @@ impl Executor {
         R: Send + 'static,
     {
         let queue = self.queue.clone();
-        let (runnable, task) = spawn(future, move |runnable: Runnable| {
-            queue.exec_async(|| {
-                runnable.run();
-            })
-        });
+        let key = QueueKey::of(&queue);
+        let (runnable, task) = Builder::new().propagate_panic(true).spawn(
+            |_| ReportPanic::new("task", future),
+            move |runnable: Runnable| {
+                queue.exec_async(move || {
+                    let _enter = key.enter();
+                    runnable.run();
+                })
+            },
+        );
+        runnable.schedule();
+        Task(TaskState::Spawned(task))
+    }
+
+    /// Runs a blocking function on a global concurrent dispatch queue with the given
+    /// quality-of-service class, returning a [`Task`] that can be used to await its result.
+    ///
+    /// Use this to keep long-running or blocking work, such as decoding large files or writing
+    /// logs, off this executor's queue. Dropping the `Task` before the function starts running
+    /// cancels it; once started, the function always runs to completion.
+    pub fn spawn_blocking<F, R>(&self, qos_class: DispatchQoS, func: F) -> Task<R>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let queue = blocking_queue(qos_class);
+        let (runnable, task) = Builder::new().propagate_panic(true).spawn(
+            |_| ReportPanic::new("blocking task", async move { func() }),
+            move |runnable: Runnable| {
+                queue.exec_async(move || {
+                    runnable.run();
+                })
+            },
+        );
         runnable.schedule();
         Task(TaskState::Spawned(task))
     }
@@ -114,28 +199,145 @@ impl Executor {
         R: 'static,
     {
         let queue = self.queue.clone();
+        let key = QueueKey::of(&queue);
         let (runnable, task) = unsafe {
             // Safety: Because `Executor` is `!Send` we know that any `!Send` values inside `future`
             // are accessible only within the context of our dispatch queue. Because `barrier_async`
             // synchronizes all access to the runnable exclusively within the dispatch queue, there
             // is no possibility of data races between the `runnable` and any other references to
             // values within the future.
-            spawn_unchecked(future, move |runnable: Runnable| {
-                queue.barrier_async(|| {
-                    runnable.run();
-                })
-            })
+            Builder::new().propagate_panic(true).spawn_unchecked(
+                |_| ReportPanic::new("task", future),
+                move |runnable: Runnable| {
+                    queue.barrier_async(move || {
+                        let _enter = key.enter();
+                        runnable.run();
+                    })
+                },
+            )
         };
         runnable.schedule();
         Task(TaskState::Spawned(task))
     }
 
+    /// Runs a future to completion on this executor, blocking the current thread until it
+    /// completes.
+    ///
+    /// The future is polled on the executor's dispatch queue with `barrier_sync`, so it is
+    /// synchronized with the executor's other tasks. Between polls, the current thread sleeps
+    /// until the future is woken.
+    ///
+    /// When called on the main thread for the main thread executor, this is equivalent to
+    /// [`run_main_thread()`][Self::run_main_thread].
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from code running on this executor's queue, such as one of its tasks or
+    /// the closure passed to [`Handle::lock()`], which would deadlock.
+    pub fn block_on<F>(&self, future: F) -> F::Output
+    where
+        F: Future + Send,
+        F::Output: Send,
+    {
+        if *self.queue == *DispatchQueue::main()
+            && let Some(mtm) = MainThreadMarker::new()
+        {
+            return Self::run_main_thread(mtm, future);
+        }
+
+        assert!(
+            !block_on::is_current_queue(&self.queue),
+            "`block_on` called from a task running on the same executor would deadlock"
+        );
+
+        // Safety: `future` is `Send`.
+        unsafe { block_on::block_on_queue(&self.queue, Box::pin(future)) }
+    }
+
+    /// Runs a future to completion on the main thread, running the main dispatch queue until it
+    /// completes.
+    ///
+    /// Tasks on the [main thread executor][Self::main_thread] make progress while this function
+    /// runs, so command-line tools can use the main thread executor without calling
+    /// [`dispatch_main()`] or running an application event loop.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a task running on the main thread executor, which would deadlock.
+    pub fn run_main_thread<F: Future>(_mtm: MainThreadMarker, future: F) -> F::Output {
+        let key = QueueKey::of(DispatchQueue::main());
+        assert!(
+            !block_on::is_current_queue(DispatchQueue::main()),
+            "`run_main_thread` called from a task running on the main thread executor would deadlock"
+        );
+
+        let mut future = pin!(future);
+        let waker = Waker::from(Arc::new(MainThreadWaker::default()));
+        loop {
+            let poll = {
+                let _enter = key.enter();
+                future.as_mut().poll(&mut Context::from_waker(&waker))
+            };
+            if let Poll::Ready(output) = poll {
+                return output;
+            }
+            block_on::run_main_queue_once();
+        }
+    }
+
+    /// Returns a future that completes after `duration` has elapsed.
+    ///
+    /// The timer is scheduled on this executor's dispatch queue with `dispatch_after`.
+    pub fn sleep(&self, duration: Duration) -> Sleep {
+        self.sleep_until(Instant::now() + duration)
+    }
+
+    /// Returns a future that completes at `deadline`.
+    pub fn sleep_until(&self, deadline: Instant) -> Sleep {
+        Sleep::new(self.queue.clone(), deadline)
+    }
+
+    /// Returns a stream that yields every `period`, starting immediately.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `period` is zero.
+    pub fn interval(&self, period: Duration) -> Interval {
+        Interval::new(self.queue.clone(), Instant::now(), period)
+    }
+
+    /// Requires `future` to complete within `duration`.
+    ///
+    /// If the future has not completed when `duration` elapses, it is dropped and the returned
+    /// future completes with [`Elapsed`].
+    pub fn timeout<F: Future>(&self, duration: Duration, future: F) -> Timeout<F> {
+        Timeout::new(future, self.sleep(duration))
+    }
+
     /// Returns a reference to the underlying [`DispatchQueue`].
     pub fn queue(&self) -> &DispatchQueue {
         &self.queue
     }
 }
 
+/// Returns the shared concurrent queue used by [`Executor::spawn_blocking()`] for `qos_class`.
+fn blocking_queue(qos_class: DispatchQoS) -> DispatchRetained<DispatchQueue> {
+    static QUEUES: Mutex<Vec<(DispatchQoS, DispatchRetained<DispatchQueue>)>> =
+        Mutex::new(Vec::new());
+
+    let mut queues = QUEUES.lock().unwrap();
+    if let Some((_, queue)) = queues.iter().find(|(qos, _)| *qos == qos_class) {
+        return queue.clone();
+    }
+
+    let attr = DispatchQueueAttrBuilder::concurrent()
+        .with_qos_class(qos_class, 0)
+        .build();
+    let queue = DispatchQueue::new("dispatch-executor.blocking", attr.as_deref());
+    queues.push((qos_class, queue.clone()));
+    queue
+}
+
 /// A marker trait for values whose `Drop` implementation is `Sync`.
 ///
 /// These values can be moved across threads even if they are `!Send`
@@ -222,11 +424,58 @@ impl<T> Handle<T> {
     {
         let mut ret = MaybeUninit::uninit();
         self.queue.barrier_sync(|| {
+            let _enter = QueueKey::of(&self.queue).enter();
             ret.write(func(&self.value, &self.executor()));
         });
         unsafe { ret.assume_init() }
     }
 
+    /// Acquires a lock on the value without blocking, running the provided function on the owning
+    /// executor's dispatch queue.
+    ///
+    /// Returns a [`Task`] that resolves to the result of `func`. This is the non-blocking
+    /// counterpart of [`lock()`][Self::lock], suitable for use from tasks on other executors or
+    /// runtimes. Dropping the `Task` before `func` runs cancels it.
+    pub fn lock_async<R>(&self, func: impl FnOnce(&T, &Executor) -> R + Send + 'static) -> Task<R>
+    where
+        T: SyncClone + SyncDrop + 'static,
+        R: Send + 'static,
+    {
+        let handle = self.clone();
+        let future = async move { func(&handle.value, &handle.executor()) };
+        // Safety: `future` is `Send`. `spawn_local` is used for its barrier semantics, which
+        // synchronize `func` with other accesses to the value in the same way as `lock()`.
+        unsafe { self.executor().spawn_local(future) }
+    }
+
+    /// Spawns a task on the owning executor, creating its future on the executor's dispatch queue.
+    ///
+    /// `func` is sent to the owning executor's queue and called there with a clone of the value
+    /// and the executor. The future it returns runs on the executor and does not need to be
+    /// `Send`, which allows code on other threads or runtimes to start work that uses `!Send`
+    /// values owned by the executor and await its result.
+    ///
+    /// # Safety
+    ///
+    /// The future returned by `func` must meet the requirements of
+    /// [`Executor::spawn_local()`].
+    pub unsafe fn spawn<F, Fut>(&self, func: F) -> Task<Fut::Output>
+    where
+        T: SyncClone + SyncDrop + 'static,
+        F: FnOnce(T, Executor) -> Fut + Send + 'static,
+        Fut: Future + 'static,
+        Fut::Output: Send + 'static,
+    {
+        let handle = self.clone();
+        let future = async move {
+            let executor = handle.executor();
+            func(handle.value, executor).await
+        };
+        // Safety: the `!Send` future returned by `func` is created on the owning executor's queue
+        // and the caller guarantees that it meets the requirements of `spawn_local()`.
+        unsafe { self.executor().spawn_local(future) }
+    }
+
     /// Zips two handles together, creating a new handle that provides access to both values.
     ///
     /// # Panics
@@ -258,6 +507,9 @@ enum TaskState<T> {
 ///
 /// Dropping a [`Task`] cancels it, which means its future won't be polled again. To drop the
 /// [`Task`] handle without canceling it, use [`detach()`][`Task::detach()`] instead.
+///
+/// If the task panics, the panic is reported to the [panic handler][panic::PanicHandler] and
+/// then resumed in the code awaiting the `Task`.
 pub struct Task<T>(TaskState<T>);
 
 impl<T> Task<T> {
@@ -329,3 +581,119 @@ impl DispatchQueueAttrBuilder {
         self.attr
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    fn executor(attr: DispatchQueueAttrBuilder) -> Executor {
+        let queue = DispatchQueue::new("dispatch-executor.test", attr.build().as_deref());
+        block_on::register_queue(&queue);
+        Executor {
+            queue,
+            phantom: PhantomData,
+        }
+    }
+
+    #[test]
+    fn block_on_runs_spawned_tasks() {
+        let executor = executor(DispatchQueueAttrBuilder::serial());
+        let tasks: Vec<_> = (0..10).map(|i| executor.spawn(async move { i })).collect();
+        let sum = executor.block_on(async {
+            let mut sum = 0;
+            for task in tasks {
+                sum += task.await;
+            }
+            sum
+        });
+        assert_eq!(sum, 45);
+    }
+
+    #[test]
+    fn spawn_local_is_exclusive_on_concurrent_queue() {
+        let executor = executor(DispatchQueueAttrBuilder::concurrent());
+        let running = Arc::new(AtomicUsize::new(0));
+        let spawned: Vec<_> = (0..10)
+            .map(|_| {
+                let running = running.clone();
+                executor.spawn(async move {
+                    running.fetch_add(1, Ordering::SeqCst);
+                    std::thread::sleep(Duration::from_micros(200));
+                    running.fetch_sub(1, Ordering::SeqCst);
+                })
+            })
+            .collect();
+        let local: Vec<_> = (0..10)
+            .map(|_| {
+                let running = running.clone();
+                // `Rc` is `!Send`; `spawn_local` runs the task as a barrier on the queue.
+                let count = Rc::new(Cell::new(0));
+                let future = async move {
+                    let others = running.fetch_add(1, Ordering::SeqCst);
+                    count.set(count.get() + 1);
+                    std::thread::sleep(Duration::from_micros(200));
+                    running.fetch_sub(1, Ordering::SeqCst);
+                    others
+                };
+                unsafe { executor.spawn_local(future) }
+            })
+            .collect();
+
+        let overlapped = executor.block_on(async move {
+            for task in spawned {
+                task.await;
+            }
+            let mut overlapped = 0;
+            for task in local {
+                overlapped += task.await;
+            }
+            overlapped
+        });
+        assert_eq!(overlapped, 0);
+    }
+
+    #[test]
+    fn handle_lock_is_synchronized() {
+        let executor = executor(DispatchQueueAttrBuilder::concurrent());
+        let counter = AtomicUsize::new(0);
+        let handle = executor.handle(&counter);
+        std::thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(|| {
+                    for _ in 0..50 {
+                        handle.lock(|counter, _| {
+                            // A non-atomic increment that only works if locks never overlap.
+                            let n = counter.load(Ordering::Relaxed);
+                            std::thread::yield_now();
+                            counter.store(n + 1, Ordering::Relaxed);
+                        });
+                    }
+                });
+            }
+        });
+        assert_eq!(counter.load(Ordering::Relaxed), 200);
+    }
+
+    #[test]
+    #[should_panic = "would deadlock"]
+    fn block_on_in_handle_lock_panics() {
+        static VALUE: usize = 7;
+        let handle = executor(DispatchQueueAttrBuilder::serial()).handle(&VALUE);
+        handle.lock(|_, executor| executor.block_on(async {}));
+    }
+
+    #[test]
+    fn handle_lock_async() {
+        static VALUE: usize = 7;
+        let handle = executor(DispatchQueueAttrBuilder::serial()).handle(&VALUE);
+        let other = executor(DispatchQueueAttrBuilder::serial());
+        assert_eq!(
+            other.block_on(handle.lock_async(|value, _| **value * 6)),
+            42
+        );
+    }
+}
@@ -0,0 +1,341 @@
+//! Catching and reporting panics at task and callback boundaries.
+//!
+//! Panics cannot unwind out of work submitted to Grand Central Dispatch or out of Objective-C
+//! callbacks. Tasks spawned on an [`Executor`][crate::Executor] and the delegate bridges in
+//! `corebluetooth` catch panics at these boundaries and pass a [`PanicReport`] to the process-wide
+//! [`PanicHandler`], which can abort the process, forward the report to a channel or, with the
+//! `tracing` feature, log it.
+//!
+//! The panic hook (see [`std::panic::set_hook()`]) runs before a panic is caught, so by default
+//! the panic is reported only by the hook and the report is discarded.
+//!
+//! # Example
+//!
+//! ```
+//! use dispatch_executor::panic::{self, PanicHandler};
+//!
+//! let (tx, rx) = std::sync::mpsc::channel();
+//! panic::set_handler(PanicHandler::forward(tx));
+//!
+//! let result = panic::catch("example", || -> u32 { panic!("oh no") });
+//! assert_eq!(result, None);
+//!
+//! let report = rx.recv().unwrap();
+//! assert_eq!(report.context(), "example");
+//! assert_eq!(report.message(), Some("oh no"));
+//! ```
+
+use std::any::Any;
+use std::fmt::Display;
+use std::panic::{AssertUnwindSafe, catch_unwind, resume_unwind};
+use std::pin::Pin;
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex, RwLock};
+use std::task::{Context, Poll};
+
+/// A description of a panic caught at a task or callback boundary.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PanicReport {
+    context: &'static str,
+    message: Option<String>,
+    thread: Option<String>,
+}
+
+impl PanicReport {
+    /// Creates a report for a panic with the given payload.
+    pub fn new(context: &'static str, payload: &(dyn Any + Send)) -> Self {
+        let message = if let Some(message) = payload.downcast_ref::<&'static str>() {
+            Some((*message).to_owned())
+        } else {
+            payload.downcast_ref::<String>().cloned()
+        };
+
+        Self {
+            context,
+            message,
+            thread: std::thread::current().name().map(ToOwned::to_owned),
+        }
+    }
+
+    /// Where the panic was caught, for example `"task"` or
+    /// `"CentralManagerDelegate::did_discover"`.
+    pub fn context(&self) -> &'static str {
+        self.context
+    }
+
+    /// The panic message, if the payload was a string.
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+
+    /// The name of the thread that panicked, if it has one.
+    pub fn thread(&self) -> Option<&str> {
+        self.thread.as_deref()
+    }
+}
+
+impl Display for PanicReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "panic in {}", self.context)?;
+        if let Some(thread) = &self.thread {
+            write!(f, " on thread '{thread}'")?;
+        }
+        if let Some(message) = &self.message {
+            write!(f, ": {message}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A function that is called with every [`PanicReport`].
+#[derive(Clone)]
+pub struct PanicHandler(Arc<dyn Fn(PanicReport) + Send + Sync>);
+
+impl std::fmt::Debug for PanicHandler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("PanicHandler { .. }")
+    }
+}
+
+impl Default for PanicHandler {
+    fn default() -> Self {
+        Self::ignore()
+    }
+}
+
+impl PanicHandler {
+    /// Creates a handler that calls `func`.
+    pub fn new(func: impl Fn(PanicReport) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(func))
+    }
+
+    /// A handler that discards reports and continues.
+    ///
+    /// This is the default handler.
+    pub fn ignore() -> Self {
+        Self::new(|_| ())
+    }
+
+    /// A handler that logs reports as errors with `tracing` and continues.
+    #[cfg(feature = "tracing")]
+    pub fn log() -> Self {
+        Self::new(|report| {
+            tracing::error!(
+                context = report.context(),
+                thread = report.thread(),
+                message = report.message(),
+                "caught panic"
+            )
+        })
+    }
+
+    /// A handler that aborts the process.
+    pub fn abort() -> Self {
+        Self::new(|_| std::process::abort())
+    }
+
+    /// A handler that sends reports to a channel.
+    ///
+    /// Reports are discarded if the receiver has been dropped.
+    pub fn forward(sender: Sender<PanicReport>) -> Self {
+        let sender = Mutex::new(sender);
+        Self::new(move |report| {
+            let _ = sender.lock().unwrap().send(report);
+        })
+    }
+
+    fn call(&self, report: PanicReport) {
+        // The handler runs at a boundary panics cannot cross, so a panicking handler must abort.
+        if catch_unwind(AssertUnwindSafe(|| (self.0)(report))).is_err() {
+            std::process::abort();
+        }
+    }
+}
+
+static HANDLER: RwLock<Option<PanicHandler>> = RwLock::new(None);
+
+/// Sets the process-wide panic handler, returning the previous handler.
+pub fn set_handler(handler: PanicHandler) -> PanicHandler {
+    let mut current = HANDLER.write().unwrap_or_else(|err| err.into_inner());
+    current.replace(handler).unwrap_or_default()
+}
+
+/// Returns the process-wide panic handler.
+pub fn handler() -> PanicHandler {
+    let current = HANDLER.read().unwrap_or_else(|err| err.into_inner());
+    current.clone().unwrap_or_default()
+}
+
+/// Passes a report to the process-wide panic handler.
+pub fn report(report: PanicReport) {
+    handler().call(report);
+}
+
+/// Calls `func`, catching and reporting any panic.
+///
+/// Returns `None` if `func` panicked.
+pub fn catch<R>(context: &'static str, func: impl FnOnce() -> R) -> Option<R> {
+    match catch_unwind(AssertUnwindSafe(func)) {
+        Ok(value) => Some(value),
+        Err(payload) => {
+            report(PanicReport::new(context, &*payload));
+            None
+        }
+    }
+}
+
+/// A future that reports panics while polling the wrapped future, then resumes them.
+pub(crate) struct ReportPanic<F> {
+    context: &'static str,
+    future: F,
+}
+
+impl<F> ReportPanic<F> {
+    pub(crate) fn new(context: &'static str, future: F) -> Self {
+        Self { context, future }
+    }
+}
+
+impl<F: Future> Future for ReportPanic<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // Safety: `future` is structurally pinned.
+        let this = unsafe { self.get_unchecked_mut() };
+        let future = unsafe { Pin::new_unchecked(&mut this.future) };
+        match catch_unwind(AssertUnwindSafe(|| future.poll(cx))) {
+            Ok(poll) => poll,
+            Err(payload) => {
+                report(PanicReport::new(this.context, &*payload));
+                resume_unwind(payload)
+            }
+        }
+    }
+}
+
+/// Runs `func` with a handler that collects reports, returning them with its result.
+///
+/// Tests that capture reports run one at a time, because the handler is process-wide.
+#[cfg(test)]
+pub(crate) fn capture_reports<R>(func: impl FnOnce() -> R) -> (R, Vec<PanicReport>) {
+    static LOCK: Mutex<()> = Mutex::new(());
+    let _lock = LOCK.lock().unwrap_or_else(|err| err.into_inner());
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let previous = set_handler(PanicHandler::forward(tx));
+    let result = catch_unwind(AssertUnwindSafe(func));
+    set_handler(previous);
+    match result {
+        Ok(value) => (value, rx.try_iter().collect()),
+        Err(payload) => resume_unwind(payload),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::task::Waker;
+
+    use super::*;
+
+    #[test]
+    fn report_from_str_payload() {
+        let report = PanicReport::new("ctx", &"message");
+        assert_eq!(report.context(), "ctx");
+        assert_eq!(report.message(), Some("message"));
+    }
+
+    #[test]
+    fn report_from_string_payload() {
+        let report = PanicReport::new("ctx", &String::from("formatted 42"));
+        assert_eq!(report.message(), Some("formatted 42"));
+    }
+
+    #[test]
+    fn report_from_other_payload() {
+        let report = PanicReport::new("ctx", &42u32);
+        assert_eq!(report.message(), None);
+    }
+
+    #[test]
+    fn report_records_thread_name() {
+        let report = std::thread::Builder::new()
+            .name("reporter".into())
+            .spawn(|| PanicReport::new("ctx", &"message"))
+            .unwrap()
+            .join()
+            .unwrap();
+        assert_eq!(report.thread(), Some("reporter"));
+        assert_eq!(
+            report.to_string(),
+            "panic in ctx on thread 'reporter': message"
+        );
+
+        let report = PanicReport {
+            context: "ctx",
+            message: None,
+            thread: None,
+        };
+        assert_eq!(report.to_string(), "panic in ctx");
+    }
+
+    #[test]
+    fn catch_returns_value() {
+        let (result, reports) = capture_reports(|| catch("ctx", || 7));
+        assert_eq!(result, Some(7));
+        assert!(reports.is_empty());
+    }
+
+    #[test]
+    fn catch_reports_panic() {
+        let (result, reports) = capture_reports(|| catch("ctx", || -> u32 { panic!("caught") }));
+        assert_eq!(result, None);
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].context(), "ctx");
+        assert_eq!(reports[0].message(), Some("caught"));
+    }
+
+    #[test]
+    fn report_panic_reports_and_resumes() {
+        let (result, reports) = capture_reports(|| {
+            let mut future = Box::pin(ReportPanic::new("future", async { panic!("polled") }));
+            let mut cx = Context::from_waker(Waker::noop());
+            catch_unwind(AssertUnwindSafe(|| future.as_mut().poll(&mut cx)))
+        });
+        let payload = result.unwrap_err();
+        assert_eq!(payload.downcast_ref::<&str>(), Some(&"polled"));
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].context(), "future");
+    }
+
+    #[test]
+    fn report_panic_passes_through_output() {
+        let mut future = Box::pin(ReportPanic::new("future", async { 3 }));
+        let mut cx = Context::from_waker(Waker::noop());
+        assert_eq!(future.as_mut().poll(&mut cx), Poll::Ready(3));
+    }
+
+    #[test]
+    fn set_handler_returns_previous() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        capture_reports(|| {
+            let counter = calls.clone();
+            let previous = set_handler(PanicHandler::new(move |_| {
+                counter.fetch_add(1, Ordering::SeqCst);
+            }));
+            report(PanicReport::new("ctx", &"first"));
+            // Restoring the previous handler forwards the next report to the test's channel.
+            set_handler(previous);
+            report(PanicReport::new("ctx", &"second"));
+        });
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn forward_discards_reports_without_receiver() {
+        let (tx, rx) = std::sync::mpsc::channel();
+        drop(rx);
+        PanicHandler::forward(tx).call(PanicReport::new("ctx", &"dropped"));
+        PanicHandler::ignore().call(PanicReport::new("ctx", &"ignored"));
+    }
+}
@@ -0,0 +1,799 @@
+//! A portable implementation of the Grand Central Dispatch primitives used by this crate.
+//!
+//! On targets without libdispatch, queues are implemented in pure Rust on top of a shared pool
+//! of `std` threads. The pool grows whenever work is submitted and no thread is idle, and idle
+//! threads exit after a few seconds. Queues preserve the GCD ordering guarantees that
+//! [`Executor`][crate::Executor] and [`Handle`][crate::Handle] rely on:
+//!
+//! - serial queues run one work item at a time, in FIFO order;
+//! - concurrent queues run any number of work items at once, except that a barrier waits for
+//!   all earlier items to finish and runs alone;
+//! - synchronous work runs on the calling thread once the queue would have started it.
+//!
+//! Work submitted asynchronously to the main queue only runs while the main thread is inside
+//! [`dispatch_main()`]. Quality-of-service classes and autorelease frequencies are recorded but
+//! have no effect.
+
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::ffi::{c_uint, c_ulong};
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::sync::{Arc, Condvar, Mutex, OnceLock, Weak};
+use std::time::Duration;
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+thread_local! {
+    static CURRENT_QUEUE: Cell<*const DispatchQueue> = const { Cell::new(std::ptr::null()) };
+}
+
+/// Records that the current thread is running work for a queue until it is dropped.
+struct CurrentGuard(*const DispatchQueue);
+
+impl CurrentGuard {
+    fn enter(queue: &DispatchQueue) -> Self {
+        Self(CURRENT_QUEUE.replace(queue))
+    }
+}
+
+impl Drop for CurrentGuard {
+    fn drop(&mut self) {
+        CURRENT_QUEUE.set(self.0);
+    }
+}
+
+/// How long an idle pool thread waits for work before exiting.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A reference-counted pointer to a dispatch object.
+pub struct DispatchRetained<T: ?Sized>(Arc<T>);
+
+impl<T: ?Sized> Clone for DispatchRetained<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T: ?Sized> Deref for DispatchRetained<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: ?Sized + PartialEq> PartialEq for DispatchRetained<T> {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl<T: ?Sized + Eq> Eq for DispatchRetained<T> {}
+
+impl<T: ?Sized + std::hash::Hash> std::hash::Hash for DispatchRetained<T> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        (**self).hash(state)
+    }
+}
+
+impl<T: ?Sized + std::fmt::Debug> std::fmt::Debug for DispatchRetained<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+/// Types that are dispatch objects.
+pub trait DispatchObject {
+    /// Returns a new reference to the object.
+    fn retain(&self) -> DispatchRetained<Self>;
+}
+
+/// Quality-of-service classes that specify the priorities for executing tasks.
+#[repr(transparent)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct DispatchQoS(pub c_uint);
+
+#[allow(non_upper_case_globals)]
+impl DispatchQoS {
+    /// Quality of service for user-interactive tasks.
+    pub const UserInteractive: Self = Self(0x21);
+    /// Quality of service for tasks that prevent the user from actively using your app.
+    pub const UserInitiated: Self = Self(0x19);
+    /// Default Quality of service.
+    pub const Default: Self = Self(0x15);
+    /// Quality of service for tasks that the user does not track actively.
+    pub const Utility: Self = Self(0x11);
+    /// Quality of service for maintenance or cleanup tasks.
+    pub const Background: Self = Self(0x09);
+    /// The absence of a Quality of service.
+    pub const Unspecified: Self = Self(0x00);
+}
+
+/// The frequency with which a dispatch queue drains autorelease pools.
+#[repr(transparent)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct DispatchAutoReleaseFrequency(pub c_ulong);
+
+impl DispatchAutoReleaseFrequency {
+    /// Inherit the behavior of the target queue.
+    pub const INHERIT: Self = Self(0);
+    /// Drain an autorelease pool around each work item.
+    pub const WORK_ITEM: Self = Self(1);
+    /// Never drain an autorelease pool.
+    pub const NEVER: Self = Self(2);
+}
+
+/// Dispatch queue attributes.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DispatchQueueAttr {
+    concurrent: bool,
+    autorelease_frequency: DispatchAutoReleaseFrequency,
+    qos_class: DispatchQoS,
+    relative_priority: i32,
+}
+
+impl DispatchQueueAttr {
+    /// A dispatch queue that executes blocks serially in FIFO order.
+    pub const SERIAL: Option<&Self> = None;
+
+    const DEFAULT: Self = Self {
+        concurrent: false,
+        autorelease_frequency: DispatchAutoReleaseFrequency::INHERIT,
+        qos_class: DispatchQoS::Unspecified,
+        relative_priority: 0,
+    };
+
+    /// A dispatch queue that executes blocks concurrently.
+    pub fn concurrent() -> Option<&'static Self> {
+        static CONCURRENT: DispatchQueueAttr = DispatchQueueAttr {
+            concurrent: true,
+            ..DispatchQueueAttr::DEFAULT
+        };
+        Some(&CONCURRENT)
+    }
+
+    /// Returns attributes based on `attr` with the given autorelease frequency.
+    pub fn with_autorelease_frequency(
+        attr: Option<&DispatchQueueAttr>,
+        frequency: DispatchAutoReleaseFrequency,
+    ) -> DispatchRetained<DispatchQueueAttr> {
+        DispatchRetained(Arc::new(Self {
+            autorelease_frequency: frequency,
+            ..attr.cloned().unwrap_or(Self::DEFAULT)
+        }))
+    }
+
+    /// Returns attributes based on `attr` with the given quality-of-service class.
+    pub fn with_qos_class(
+        attr: Option<&DispatchQueueAttr>,
+        qos_class: DispatchQoS,
+        relative_priority: i32,
+    ) -> DispatchRetained<DispatchQueueAttr> {
+        DispatchRetained(Arc::new(Self {
+            qos_class,
+            relative_priority,
+            ..attr.cloned().unwrap_or(Self::DEFAULT)
+        }))
+    }
+}
+
+impl DispatchObject for DispatchQueueAttr {
+    fn retain(&self) -> DispatchRetained<Self> {
+        DispatchRetained(Arc::new(self.clone()))
+    }
+}
+
+enum Work {
+    Async(Job),
+    Sync(Arc<SyncSlot>),
+}
+
+struct Item {
+    work: Work,
+    barrier: bool,
+}
+
+#[derive(Default)]
+struct QueueState {
+    items: VecDeque<Item>,
+    running: usize,
+    barrier_running: bool,
+}
+
+/// A dispatch queue.
+pub struct DispatchQueue {
+    label: String,
+    concurrent: bool,
+    main: bool,
+    attr: DispatchQueueAttr,
+    state: Mutex<QueueState>,
+    this: Weak<DispatchQueue>,
+}
+
+impl PartialEq for DispatchQueue {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl Eq for DispatchQueue {}
+
+impl std::hash::Hash for DispatchQueue {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        std::ptr::hash(self, state)
+    }
+}
+
+impl std::fmt::Debug for DispatchQueue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DispatchQueue")
+            .field("label", &self.label)
+            .field("concurrent", &self.concurrent)
+            .field("qos_class", &self.attr.qos_class)
+            .finish_non_exhaustive()
+    }
+}
+
+impl DispatchObject for DispatchQueue {
+    fn retain(&self) -> DispatchRetained<Self> {
+        DispatchRetained(
+            self.this
+                .upgrade()
+                .expect("dispatch queue has been released"),
+        )
+    }
+}
+
+impl DispatchQueue {
+    fn create(label: &str, attr: DispatchQueueAttr, main: bool) -> DispatchRetained<Self> {
+        DispatchRetained(Arc::new_cyclic(|this| Self {
+            label: label.to_owned(),
+            concurrent: attr.concurrent,
+            main,
+            attr,
+            state: Mutex::default(),
+            this: this.clone(),
+        }))
+    }
+
+    /// Creates a new dispatch queue.
+    pub fn new(label: &str, queue_attribute: Option<&DispatchQueueAttr>) -> DispatchRetained<Self> {
+        let attr = queue_attribute
+            .cloned()
+            .unwrap_or(DispatchQueueAttr::DEFAULT);
+        Self::create(label, attr, false)
+    }
+
+    /// Returns the serial dispatch queue associated with the application's main thread.
+    pub fn main() -> &'static Self {
+        static MAIN: OnceLock<DispatchRetained<DispatchQueue>> = OnceLock::new();
+        MAIN.get_or_init(|| Self::create("com.apple.main-thread", DispatchQueueAttr::DEFAULT, true))
+    }
+
+    /// The label of the queue.
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// Whether the current thread is running a work item submitted to this queue.
+    pub(crate) fn is_current(&self) -> bool {
+        std::ptr::eq(CURRENT_QUEUE.get(), self)
+    }
+
+    /// Submits `work` for asynchronous execution and returns immediately.
+    pub fn exec_async<F>(&self, work: F)
+    where
+        F: Send + FnOnce() + 'static,
+    {
+        self.enqueue(Work::Async(Box::new(work)), false);
+    }
+
+    /// Submits `work` for synchronous execution and waits until it completes.
+    pub fn exec_sync<F>(&self, work: F)
+    where
+        F: Send + FnOnce(),
+    {
+        self.sync(work, false);
+    }
+
+    /// Submits a barrier for asynchronous execution and returns immediately.
+    pub fn barrier_async<F>(&self, work: F)
+    where
+        F: Send + FnOnce() + 'static,
+    {
+        self.enqueue(Work::Async(Box::new(work)), true);
+    }
+
+    /// Submits a barrier for synchronous execution and waits until it completes.
+    pub fn barrier_sync<F>(&self, work: F)
+    where
+        F: Send + FnOnce(),
+    {
+        self.sync(work, true);
+    }
+
+    fn sync(&self, work: impl FnOnce(), barrier: bool) {
+        let barrier = self.is_barrier(barrier);
+        let slot = Arc::new(SyncSlot::default());
+        self.enqueue(Work::Sync(slot.clone()), barrier);
+        slot.wait();
+
+        // Completes the work item even if `work` panics, so the queue is not left blocked.
+        let _guard = CompleteGuard {
+            queue: self,
+            barrier,
+        };
+        let _current = CurrentGuard::enter(self);
+        work();
+    }
+
+    /// Barriers on a serial queue are indistinguishable from ordinary work items.
+    fn is_barrier(&self, barrier: bool) -> bool {
+        barrier && self.concurrent
+    }
+
+    fn enqueue(&self, work: Work, barrier: bool) {
+        let barrier = self.is_barrier(barrier);
+        let mut state = self.state.lock().unwrap();
+        state.items.push_back(Item { work, barrier });
+        let ready = self.take_ready(&mut state);
+        drop(state);
+        self.start(ready);
+    }
+
+    fn complete(&self, barrier: bool) {
+        let mut state = self.state.lock().unwrap();
+        if barrier {
+            state.barrier_running = false;
+        } else {
+            state.running -= 1;
+        }
+        let ready = self.take_ready(&mut state);
+        drop(state);
+        self.start(ready);
+    }
+
+    /// Removes the work items that may start now from the front of the queue.
+    fn take_ready(&self, state: &mut QueueState) -> Vec<Item> {
+        let width = if self.concurrent { usize::MAX } else { 1 };
+        let mut ready = Vec::new();
+        while let Some(item) = state.items.front() {
+            if state.barrier_running {
+                break;
+            } else if item.barrier {
+                if state.running == 0 {
+                    state.barrier_running = true;
+                    ready.extend(state.items.pop_front());
+                }
+                break;
+            } else if state.running < width {
+                state.running += 1;
+                ready.extend(state.items.pop_front());
+            } else {
+                break;
+            }
+        }
+        ready
+    }
+
+    fn start(&self, ready: Vec<Item>) {
+        for Item { work, barrier } in ready {
+            match work {
+                Work::Sync(slot) => slot.signal(),
+                Work::Async(job) => {
+                    let queue = self.retain();
+                    let job: Job = Box::new(move || {
+                        let _guard = CompleteGuard {
+                            queue: &queue,
+                            barrier,
+                        };
+                        let _current = CurrentGuard::enter(&queue);
+                        run_job(job);
+                    });
+                    if self.main {
+                        MainQueue::get().submit(job);
+                    } else {
+                        Pool::get().submit(job);
+                    }
+                }
+            }
+        }
+    }
+}
+
+struct CompleteGuard<'a> {
+    queue: &'a DispatchQueue,
+    barrier: bool,
+}
+
+impl Drop for CompleteGuard<'_> {
+    fn drop(&mut self) {
+        self.queue.complete(self.barrier);
+    }
+}
+
+/// Runs an asynchronous work item, aborting if it panics.
+///
+/// Panics cannot unwind out of work items submitted to libdispatch, so they abort the process on
+/// both backends.
+fn run_job(job: Job) {
+    if std::panic::catch_unwind(std::panic::AssertUnwindSafe(job)).is_err() {
+        std::process::abort();
+    }
+}
+
+#[derive(Default)]
+struct SyncSlot {
+    ready: Mutex<bool>,
+    condvar: Condvar,
+}
+
+impl SyncSlot {
+    fn signal(&self) {
+        *self.ready.lock().unwrap() = true;
+        self.condvar.notify_one();
+    }
+
+    fn wait(&self) {
+        let ready = self.ready.lock().unwrap();
+        let _ready = self.condvar.wait_while(ready, |ready| !*ready).unwrap();
+    }
+}
+
+#[derive(Default)]
+struct PoolState {
+    jobs: VecDeque<Job>,
+    idle: usize,
+}
+
+struct Pool {
+    state: Mutex<PoolState>,
+    condvar: Condvar,
+}
+
+impl Pool {
+    fn get() -> &'static Pool {
+        static POOL: Pool = Pool {
+            state: Mutex::new(PoolState {
+                jobs: VecDeque::new(),
+                idle: 0,
+            }),
+            condvar: Condvar::new(),
+        };
+        &POOL
+    }
+
+    fn submit(&'static self, job: Job) {
+        let mut state = self.state.lock().unwrap();
+        state.jobs.push_back(job);
+        if state.idle >= state.jobs.len() {
+            self.condvar.notify_one();
+        } else {
+            drop(state);
+            std::thread::Builder::new()
+                .name("dispatch-executor-worker".into())
+                .spawn(move || self.run())
+                .expect("failed to spawn worker thread");
+        }
+    }
+
+    fn run(&self) {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if let Some(job) = state.jobs.pop_front() {
+                drop(state);
+                job();
+                state = self.state.lock().unwrap();
+                continue;
+            }
+
+            state.idle += 1;
+            let (next, timeout) = self.condvar.wait_timeout(state, IDLE_TIMEOUT).unwrap();
+            state = next;
+            state.idle -= 1;
+            if timeout.timed_out() && state.jobs.is_empty() {
+                return;
+            }
+        }
+    }
+}
+
+struct MainQueue {
+    jobs: Mutex<VecDeque<Job>>,
+    condvar: Condvar,
+}
+
+impl MainQueue {
+    fn get() -> &'static MainQueue {
+        static MAIN_QUEUE: MainQueue = MainQueue {
+            jobs: Mutex::new(VecDeque::new()),
+            condvar: Condvar::new(),
+        };
+        &MAIN_QUEUE
+    }
+
+    fn submit(&self, job: Job) {
+        self.jobs.lock().unwrap().push_back(job);
+        self.condvar.notify_one();
+    }
+
+    fn run_once(&self) {
+        let jobs = self.jobs.lock().unwrap();
+        let mut jobs = self
+            .condvar
+            .wait_while(jobs, |jobs| jobs.is_empty())
+            .unwrap();
+        let job = jobs.pop_front().unwrap();
+        drop(jobs);
+        job();
+    }
+}
+
+/// Waits for a block to be submitted to the main queue and runs it.
+pub(crate) fn run_main_queue_once() {
+    MainQueue::get().run_once();
+}
+
+/// Executes blocks submitted to the main queue.
+///
+/// # Panics
+///
+/// Panics if not called on the main thread.
+pub fn dispatch_main() -> ! {
+    assert!(
+        MainThreadMarker::new().is_some(),
+        "dispatch_main must be called on the main thread"
+    );
+    loop {
+        MainQueue::get().run_once();
+    }
+}
+
+/// Whether the current thread is the process's initial thread, which runs `fn main()`.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn is_main_thread() -> bool {
+    unsafe extern "C" {
+        fn gettid() -> i32;
+    }
+    // The initial thread's id is the process id.
+    // Safety: `gettid` has no preconditions.
+    u32::try_from(unsafe { gettid() }) == Ok(std::process::id())
+}
+
+/// Whether the current thread is the process's initial thread, which runs `fn main()`.
+#[cfg(any(target_os = "freebsd", target_os = "openbsd", target_os = "dragonfly"))]
+fn is_main_thread() -> bool {
+    unsafe extern "C" {
+        fn pthread_main_np() -> std::ffi::c_int;
+    }
+    // Safety: `pthread_main_np` has no preconditions.
+    unsafe { pthread_main_np() == 1 }
+}
+
+/// The main thread cannot be identified on this target.
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "freebsd",
+    target_os = "openbsd",
+    target_os = "dragonfly"
+)))]
+fn is_main_thread() -> bool {
+    false
+}
+
+/// A marker type for functionality only available on the main thread.
+///
+/// The main thread is the process's initial thread, which runs `fn main()`. It is identified with
+/// `gettid()` on Linux and Android and `pthread_main_np()` on the BSDs. On other targets,
+/// [`new()`][Self::new] always returns `None`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MainThreadMarker {
+    phantom: PhantomData<*mut ()>,
+}
+
+impl MainThreadMarker {
+    /// Constructs a new `MainThreadMarker` if the current thread is the main thread.
+    pub fn new() -> Option<Self> {
+        if is_main_thread() {
+            Some(Self {
+                phantom: PhantomData,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Constructs a new `MainThreadMarker` without first checking whether the current thread is
+    /// the main one.
+    ///
+    /// # Safety
+    ///
+    /// The current thread must be the main thread.
+    pub unsafe fn new_unchecked() -> Self {
+        Self {
+            phantom: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::mpsc;
+
+    use super::*;
+    use crate::DispatchQueueAttrBuilder;
+
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    /// Tracks how many work items are running at once.
+    #[derive(Default)]
+    struct Occupancy {
+        running: AtomicUsize,
+        max: AtomicUsize,
+    }
+
+    impl Occupancy {
+        fn run(&self, work: impl FnOnce()) {
+            let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max.fetch_max(running, Ordering::SeqCst);
+            work();
+            self.running.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+
+    fn concurrent_queue() -> DispatchRetained<DispatchQueue> {
+        let attr = DispatchQueueAttrBuilder::concurrent().build();
+        DispatchQueue::new("concurrent", attr.as_deref())
+    }
+
+    #[test]
+    fn marker_is_not_created_on_other_threads() {
+        assert!(MainThreadMarker::new().is_none());
+        let named_main = std::thread::Builder::new()
+            .name("main".into())
+            .spawn(|| MainThreadMarker::new().is_none())
+            .unwrap();
+        assert!(named_main.join().unwrap());
+    }
+
+    #[test]
+    fn serial_queue_runs_items_in_order_one_at_a_time() {
+        let queue = DispatchQueue::new("serial", DispatchQueueAttr::SERIAL);
+        let occupancy = Arc::new(Occupancy::default());
+        let (tx, rx) = mpsc::channel();
+        for i in 0..50 {
+            let occupancy = occupancy.clone();
+            let tx = tx.clone();
+            queue.exec_async(move || {
+                occupancy.run(|| std::thread::sleep(Duration::from_micros(200)));
+                tx.send(i).unwrap();
+            });
+        }
+        let order: Vec<_> = (0..50).map(|_| rx.recv_timeout(TIMEOUT).unwrap()).collect();
+        assert_eq!(order, (0..50).collect::<Vec<_>>());
+        assert_eq!(occupancy.max.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn serial_queue_sync_waits_for_earlier_items() {
+        let queue = DispatchQueue::new("serial", DispatchQueueAttr::SERIAL);
+        let done = Arc::new(AtomicUsize::new(0));
+        for _ in 0..10 {
+            let done = done.clone();
+            queue.exec_async(move || {
+                std::thread::sleep(Duration::from_millis(1));
+                done.fetch_add(1, Ordering::SeqCst);
+            });
+        }
+        let caller = std::thread::current().id();
+        let mut ran_on = None;
+        queue.exec_sync(|| ran_on = Some(std::thread::current().id()));
+        assert_eq!(done.load(Ordering::SeqCst), 10);
+        assert_eq!(ran_on, Some(caller));
+    }
+
+    #[test]
+    fn concurrent_queue_runs_items_at_once() {
+        let queue = concurrent_queue();
+        let barrier = Arc::new(std::sync::Barrier::new(3));
+        let (tx, rx) = mpsc::channel();
+        for _ in 0..3 {
+            let barrier = barrier.clone();
+            let tx = tx.clone();
+            // Each item waits for the others, so they only finish if they run concurrently.
+            queue.exec_async(move || {
+                barrier.wait();
+                tx.send(()).unwrap();
+            });
+        }
+        for _ in 0..3 {
+            rx.recv_timeout(TIMEOUT).unwrap();
+        }
+    }
+
+    #[test]
+    fn barrier_runs_alone() {
+        let queue = concurrent_queue();
+        let occupancy = Arc::new(Occupancy::default());
+        let before = Arc::new(AtomicUsize::new(0));
+        let barrier_done = Arc::new(AtomicUsize::new(0));
+        let (tx, rx) = mpsc::channel();
+
+        for _ in 0..5 {
+            let (occupancy, before) = (occupancy.clone(), before.clone());
+            queue.exec_async(move || {
+                occupancy.run(|| std::thread::sleep(Duration::from_millis(5)));
+                before.fetch_add(1, Ordering::SeqCst);
+            });
+        }
+        {
+            let (occupancy, before, barrier_done) =
+                (occupancy.clone(), before.clone(), barrier_done.clone());
+            let tx = tx.clone();
+            queue.barrier_async(move || {
+                occupancy.run(|| {
+                    std::thread::sleep(Duration::from_millis(5));
+                    let running = occupancy.running.load(Ordering::SeqCst);
+                    tx.send((before.load(Ordering::SeqCst), running)).unwrap();
+                });
+                barrier_done.store(1, Ordering::SeqCst);
+            });
+        }
+        for _ in 0..5 {
+            let (barrier_done, tx) = (barrier_done.clone(), tx.clone());
+            queue.exec_async(move || {
+                tx.send((barrier_done.load(Ordering::SeqCst), 0)).unwrap();
+            });
+        }
+
+        // Every earlier item finished before the barrier started, and nothing ran alongside it.
+        assert_eq!(rx.recv_timeout(TIMEOUT).unwrap(), (5, 1));
+        // Later items did not start until the barrier finished.
+        for _ in 0..5 {
+            assert_eq!(rx.recv_timeout(TIMEOUT).unwrap(), (1, 0));
+        }
+        assert!(occupancy.max.load(Ordering::SeqCst) > 1);
+    }
+
+    #[test]
+    fn barrier_sync_waits_for_earlier_items() {
+        let queue = concurrent_queue();
+        let done = Arc::new(AtomicUsize::new(0));
+        for _ in 0..5 {
+            let done = done.clone();
+            queue.exec_async(move || {
+                std::thread::sleep(Duration::from_millis(5));
+                done.fetch_add(1, Ordering::SeqCst);
+            });
+        }
+        queue.barrier_sync(|| assert_eq!(done.load(Ordering::SeqCst), 5));
+    }
+
+    #[test]
+    fn panic_in_sync_work_does_not_block_queue() {
+        let queue = DispatchQueue::new("serial", DispatchQueueAttr::SERIAL);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            queue.barrier_sync(|| panic!("oops"));
+        }));
+        assert!(result.is_err());
+
+        let mut ran = false;
+        queue.exec_sync(|| ran = true);
+        assert!(ran);
+    }
+
+    #[test]
+    fn retain_returns_same_queue() {
+        let queue = DispatchQueue::new("serial", DispatchQueueAttr::SERIAL);
+        let retained = queue.retain();
+        assert_eq!(retained, queue);
+        assert_ne!(
+            retained,
+            DispatchQueue::new("serial", DispatchQueueAttr::SERIAL)
+        );
+        assert_eq!(retained.label(), "serial");
+    }
+}
@@ -0,0 +1,500 @@
+//! Timers for tasks running on an [`Executor`][crate::Executor].
+
+use std::fmt::Display;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+
+use futures_core::Stream;
+
+use crate::{DispatchQueue, DispatchRetained};
+
+#[derive(Default)]
+struct TimerState {
+    fired: AtomicBool,
+    waker: Mutex<Option<Waker>>,
+}
+
+impl TimerState {
+    fn fire(&self) {
+        self.fired.store(true, Ordering::Release);
+        if let Some(waker) = self.waker.lock().unwrap().take() {
+            waker.wake();
+        }
+    }
+
+    fn poll(&self, cx: &mut Context<'_>) -> Poll<()> {
+        if self.fired.load(Ordering::Acquire) {
+            return Poll::Ready(());
+        }
+
+        *self.waker.lock().unwrap() = Some(cx.waker().clone());
+
+        // Check again in case the timer fired before the waker was registered.
+        if self.fired.load(Ordering::Acquire) {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+/// Arranges for `state` to be fired on `queue` at `deadline`.
+#[cfg(target_vendor = "apple")]
+fn schedule(queue: &DispatchQueue, deadline: Instant, state: &Arc<TimerState>) {
+    use dispatch2::DispatchTime;
+
+    let delay = deadline.saturating_duration_since(Instant::now());
+    if delay.is_zero() {
+        state.fire();
+        return;
+    }
+
+    // A delay too large to represent as a dispatch time never elapses.
+    if let Ok(when) = DispatchTime::try_from(delay) {
+        // Held weakly so that dropped timers do not keep their state (and waker) alive until the
+        // block runs.
+        let state = Arc::downgrade(state);
+        let _ = queue.after(when, move || {
+            if let Some(state) = state.upgrade() {
+                state.fire();
+            }
+        });
+    }
+}
+
+/// Arranges for `state` to be fired at `deadline`.
+///
+/// Without Grand Central Dispatch, timers are kept in a binary heap serviced by a single
+/// background thread.
+#[cfg(not(target_vendor = "apple"))]
+fn schedule(_queue: &DispatchQueue, deadline: Instant, state: &Arc<TimerState>) {
+    if deadline <= Instant::now() {
+        state.fire();
+    } else {
+        fallback::TimerThread::get().insert(deadline, state.clone());
+    }
+}
+
+#[cfg(not(target_vendor = "apple"))]
+pub(crate) mod fallback {
+    use std::cmp::{Ordering, Reverse};
+    use std::collections::BinaryHeap;
+    use std::sync::{Arc, Condvar, Mutex, OnceLock, Weak};
+    use std::time::Instant;
+
+    use super::TimerState;
+
+    struct Entry {
+        deadline: Instant,
+        seq: u64,
+        // Held weakly so that dropped timers do not keep their state alive.
+        state: Weak<TimerState>,
+    }
+
+    impl PartialEq for Entry {
+        fn eq(&self, other: &Self) -> bool {
+            self.cmp(other) == Ordering::Equal
+        }
+    }
+
+    impl Eq for Entry {}
+
+    impl PartialOrd for Entry {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Entry {
+        fn cmp(&self, other: &Self) -> Ordering {
+            (self.deadline, self.seq).cmp(&(other.deadline, other.seq))
+        }
+    }
+
+    #[derive(Default)]
+    struct Timers {
+        heap: BinaryHeap<Reverse<Entry>>,
+        next_seq: u64,
+    }
+
+    pub(crate) struct TimerThread {
+        timers: Mutex<Timers>,
+        condvar: Condvar,
+    }
+
+    impl TimerThread {
+        pub(crate) fn get() -> &'static TimerThread {
+            static TIMER_THREAD: OnceLock<TimerThread> = OnceLock::new();
+            TIMER_THREAD.get_or_init(|| {
+                std::thread::Builder::new()
+                    .name("dispatch-executor-timer".into())
+                    .spawn(|| TimerThread::get().run())
+                    .expect("failed to spawn timer thread");
+
+                TimerThread {
+                    timers: Mutex::default(),
+                    condvar: Condvar::new(),
+                }
+            })
+        }
+
+        pub(super) fn insert(&self, deadline: Instant, state: Arc<TimerState>) {
+            let mut timers = self.timers.lock().unwrap();
+            let seq = timers.next_seq;
+            timers.next_seq += 1;
+            timers.heap.push(Reverse(Entry {
+                deadline,
+                seq,
+                state: Arc::downgrade(&state),
+            }));
+            self.condvar.notify_one();
+        }
+
+        fn run(&self) {
+            let mut timers = self.timers.lock().unwrap();
+            loop {
+                let now = Instant::now();
+                match timers.heap.peek() {
+                    Some(Reverse(entry)) if entry.deadline <= now => {
+                        let Reverse(entry) = timers.heap.pop().unwrap();
+                        drop(timers);
+                        if let Some(state) = entry.state.upgrade() {
+                            state.fire();
+                        }
+                        timers = self.timers.lock().unwrap();
+                    }
+                    Some(Reverse(entry)) => {
+                        let timeout = entry.deadline - now;
+                        timers = self.condvar.wait_timeout(timers, timeout).unwrap().0;
+                    }
+                    None => {
+                        timers = self.condvar.wait(timers).unwrap();
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A future that completes at a specific point in time.
+///
+/// Created by [`Executor::sleep()`][crate::Executor::sleep] and
+/// [`Executor::sleep_until()`][crate::Executor::sleep_until].
+pub struct Sleep {
+    queue: DispatchRetained<DispatchQueue>,
+    deadline: Instant,
+    state: Arc<TimerState>,
+}
+
+impl std::fmt::Debug for Sleep {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Sleep")
+            .field("deadline", &self.deadline)
+            .finish()
+    }
+}
+
+impl Sleep {
+    pub(crate) fn new(queue: DispatchRetained<DispatchQueue>, deadline: Instant) -> Self {
+        let state = Arc::new(TimerState::default());
+        schedule(&queue, deadline, &state);
+        Self {
+            queue,
+            deadline,
+            state,
+        }
+    }
+
+    /// The instant at which this future completes.
+    pub fn deadline(&self) -> Instant {
+        self.deadline
+    }
+
+    /// Whether the deadline has been reached.
+    pub fn is_elapsed(&self) -> bool {
+        self.state.fired.load(Ordering::Acquire)
+    }
+
+    /// Resets the future to complete at `deadline`.
+    pub fn reset(&mut self, deadline: Instant) {
+        self.deadline = deadline;
+        self.state = Arc::new(TimerState::default());
+        schedule(&self.queue, deadline, &self.state);
+    }
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        self.state.poll(cx)
+    }
+}
+
+/// A stream that yields at a fixed period.
+///
+/// The first tick completes immediately. If a tick is delayed (for example, because the task
+/// was busy), subsequent ticks are scheduled relative to the original schedule, skipping any
+/// ticks that were missed entirely.
+///
+/// Created by [`Executor::interval()`][crate::Executor::interval].
+#[derive(Debug)]
+pub struct Interval {
+    sleep: Sleep,
+    period: Duration,
+}
+
+impl Interval {
+    pub(crate) fn new(
+        queue: DispatchRetained<DispatchQueue>,
+        start: Instant,
+        period: Duration,
+    ) -> Self {
+        assert!(!period.is_zero(), "interval period must be non-zero");
+        Self {
+            sleep: Sleep::new(queue, start),
+            period,
+        }
+    }
+
+    /// The period of the interval.
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    /// Waits for the next tick, returning the instant at which it was scheduled.
+    pub async fn tick(&mut self) -> Instant {
+        std::future::poll_fn(|cx| self.poll_tick(cx)).await
+    }
+
+    /// Polls for the next tick, returning the instant at which it was scheduled.
+    pub fn poll_tick(&mut self, cx: &mut Context<'_>) -> Poll<Instant> {
+        match Pin::new(&mut self.sleep).poll(cx) {
+            Poll::Ready(()) => {
+                let scheduled = self.sleep.deadline();
+                self.sleep
+                    .reset(next_tick(scheduled, Instant::now(), self.period));
+                Poll::Ready(scheduled)
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// Returns the first tick after `now` on the schedule of a tick at `scheduled`.
+///
+/// If the number of missed ticks is too large to skip at once, the returned tick may not be after
+/// `now`; the remaining ticks are then skipped when it completes.
+fn next_tick(scheduled: Instant, now: Instant, period: Duration) -> Instant {
+    let next = scheduled + period;
+    if next > now {
+        return next;
+    }
+    let missed = (now - scheduled).as_nanos() / period.as_nanos();
+    let ticks = u32::try_from(missed).unwrap_or(u32::MAX).saturating_add(1);
+    period
+        .checked_mul(ticks)
+        .and_then(|skipped| scheduled.checked_add(skipped))
+        .unwrap_or(now)
+}
+
+impl Stream for Interval {
+    type Item = Instant;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Instant>> {
+        self.get_mut().poll_tick(cx).map(Some)
+    }
+}
+
+/// The error returned when a [`Timeout`] elapses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Elapsed(());
+
+impl Display for Elapsed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("deadline has elapsed")
+    }
+}
+
+impl std::error::Error for Elapsed {}
+
+/// A future that completes with the output of another future, or an error if it does not
+/// complete before a deadline.
+///
+/// Created by [`Executor::timeout()`][crate::Executor::timeout].
+#[derive(Debug)]
+pub struct Timeout<F> {
+    future: F,
+    sleep: Sleep,
+}
+
+impl<F> Timeout<F> {
+    pub(crate) fn new(future: F, sleep: Sleep) -> Self {
+        Self { future, sleep }
+    }
+
+    /// Returns the wrapped future.
+    pub fn into_inner(self) -> F {
+        self.future
+    }
+}
+
+impl<F: Future> Future for Timeout<F> {
+    type Output = Result<F::Output, Elapsed>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // Safety: `future` is structurally pinned and is never moved out of a pinned `Timeout`.
+        let this = unsafe { self.get_unchecked_mut() };
+        let future = unsafe { Pin::new_unchecked(&mut this.future) };
+
+        if let Poll::Ready(output) = future.poll(cx) {
+            return Poll::Ready(Ok(output));
+        }
+
+        match Pin::new(&mut this.sleep).poll(cx) {
+            Poll::Ready(()) => Poll::Ready(Err(Elapsed(()))),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::pending;
+
+    use super::*;
+    use crate::Executor;
+
+    const SHORT: Duration = Duration::from_millis(20);
+
+    fn run<F, Fut>(entry: F) -> Fut::Output
+    where
+        F: FnOnce(Executor) -> Fut + Send,
+        Fut: Future,
+        Fut::Output: Send,
+    {
+        Executor::run_background("dispatch-executor.timer-test", None, entry)
+    }
+
+    #[test]
+    fn sleep_completes_after_duration() {
+        let elapsed = run(|executor| async move {
+            let start = Instant::now();
+            executor.sleep(SHORT).await;
+            start.elapsed()
+        });
+        assert!(elapsed >= SHORT, "{elapsed:?}");
+    }
+
+    #[test]
+    fn sleep_in_the_past_is_elapsed() {
+        run(|executor| async move {
+            let sleep = executor.sleep_until(Instant::now() - SHORT);
+            assert!(sleep.is_elapsed());
+            sleep.await;
+        });
+    }
+
+    #[test]
+    fn reset_sleep() {
+        run(|executor| async move {
+            let start = Instant::now();
+            let mut sleep = executor.sleep(Duration::from_secs(3600));
+            sleep.reset(start + SHORT);
+            assert_eq!(sleep.deadline(), start + SHORT);
+            (&mut sleep).await;
+            assert!(sleep.is_elapsed());
+            assert!(start.elapsed() >= SHORT);
+        });
+    }
+
+    #[test]
+    fn dropped_sleep_releases_its_state() {
+        run(|executor| async move {
+            let sleep = executor.sleep(Duration::from_secs(3600));
+            let state = Arc::downgrade(&sleep.state);
+            drop(sleep);
+            assert!(state.upgrade().is_none());
+        });
+    }
+
+    #[test]
+    fn timeout() {
+        run(|executor| async move {
+            let ok = executor.timeout(Duration::from_secs(3600), async { 7 });
+            assert_eq!(ok.await, Ok(7));
+
+            let start = Instant::now();
+            let err = executor.timeout(SHORT, pending::<()>());
+            assert_eq!(err.await, Err(Elapsed(())));
+            assert!(start.elapsed() >= SHORT);
+        });
+    }
+
+    #[test]
+    fn interval_ticks_on_schedule() {
+        run(|executor| async move {
+            let mut interval = executor.interval(SHORT);
+            let first = interval.tick().await;
+            assert!(first.elapsed() < SHORT);
+            let mut previous = first;
+            for _ in 0..3 {
+                let tick = interval.tick().await;
+                assert!(Instant::now() >= tick);
+                // Ticks stay on the original schedule, even if one is skipped on a busy machine.
+                assert!(tick > previous);
+                assert_eq!((tick - first).as_nanos() % SHORT.as_nanos(), 0);
+                previous = tick;
+            }
+        });
+    }
+
+    #[test]
+    fn interval_skips_missed_ticks() {
+        run(|executor| async move {
+            let mut interval = executor.interval(SHORT);
+            let first = interval.tick().await;
+            std::thread::sleep(SHORT * 3 + SHORT / 2);
+            // The delayed tick completes immediately, then the schedule resumes.
+            assert_eq!(interval.tick().await, first + SHORT);
+            assert_eq!(interval.tick().await, first + SHORT * 4);
+        });
+    }
+
+    #[test]
+    fn next_tick_catches_up() {
+        let start = Instant::now();
+        let period = Duration::from_millis(10);
+        assert_eq!(next_tick(start, start, period), start + period);
+        assert_eq!(
+            next_tick(start, start + Duration::from_millis(35), period),
+            start + Duration::from_millis(40)
+        );
+        assert_eq!(
+            next_tick(start, start + Duration::from_millis(40), period),
+            start + Duration::from_millis(50)
+        );
+    }
+
+    #[test]
+    fn next_tick_saturates() {
+        let start = Instant::now();
+        let period = Duration::from_nanos(1);
+        let now = start + Duration::from_secs(10);
+
+        // More than `u32::MAX` ticks were missed, so they are skipped over several ticks.
+        let mut tick = start;
+        let mut steps = 0;
+        while tick <= now {
+            let next = next_tick(tick, now, period);
+            assert!(next > tick);
+            tick = next;
+            steps += 1;
+        }
+        assert_eq!(tick, now + period);
+        assert!(steps > 1);
+    }
+}
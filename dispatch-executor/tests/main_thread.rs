@@ -0,0 +1,15 @@
+//! Checks that [`MainThreadMarker`] identifies the main thread.
+//!
+//! This test runs without the libtest harness, which runs tests on other threads.
+
+use dispatch_executor::MainThreadMarker;
+
+fn main() {
+    assert!(MainThreadMarker::new().is_some());
+
+    let named_main = std::thread::Builder::new()
+        .name("main".into())
+        .spawn(|| MainThreadMarker::new().is_none())
+        .unwrap();
+    assert!(named_main.join().unwrap());
+}